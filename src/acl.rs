@@ -0,0 +1,333 @@
+//! Users and the commands they are allowed to run.
+//!
+//! Every connection runs its commands as a user. New connections are logged
+//! in as the `default` user, unless that user requires a password. In that
+//! case, every command but `AUTH` and `HELLO ... AUTH` is refused until the
+//! connection authenticates. The `default` user allows every command and requires no
+//! password, unless `ServerConfig::requirepass` is set.
+//!
+//! Users are managed with `ACL SETUSER`, which supports a subset of the Redis
+//! ACL rules:
+//!
+//! * `on`, `off`: enable or disable the user. Disabled users cannot
+//!   authenticate.
+//! * `>password`, `<password`: add or remove a password.
+//! * `#hash`, `!hash`: add or remove a password by its SHA-256 hash, in
+//!   hexadecimal.
+//! * `nopass`: accept any password. `resetpass` removes every password, as
+//!   well as `nopass`.
+//! * `+command`, `-command`: allow or deny a command.
+//! * `+@all` (or `allcommands`), `-@all` (or `nocommands`): allow or deny
+//!   every command.
+//! * `reset`: disable the user and remove its passwords and commands.
+//!
+//! Key and channel patterns are not supported: users may access every key and
+//! channel. The `allkeys`, `~*`, `allchannels` and `&*` rules are accepted for
+//! compatibility.
+//!
+//! As in Redis, only the SHA-256 hashes of the passwords are stored. `ACL
+//! LIST` and `ACL GETUSER` show the hashes instead of the passwords.
+
+use crate::Frame;
+
+use ring::digest;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Name of the user new connections are logged in as.
+pub(crate) const DEFAULT_USER: &str = "default";
+
+/// Replaces passwords wherever commands are logged.
+pub(crate) const REDACTED: &str = "(redacted)";
+
+/// The users known to the server, by name.
+#[derive(Debug)]
+pub(crate) struct Acl {
+    users: BTreeMap<String, User>,
+}
+
+/// A user and its permissions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct User {
+    /// Disabled users cannot authenticate.
+    enabled: bool,
+
+    /// Any password is accepted.
+    nopass: bool,
+
+    /// SHA-256 hashes of the passwords accepted by `AUTH`, in lowercase
+    /// hexadecimal.
+    passwords: BTreeSet<String>,
+
+    /// Whether commands are allowed unless listed in `exceptions`, or denied
+    /// unless listed.
+    all_commands: bool,
+
+    /// Commands that are the exception to `all_commands`.
+    exceptions: BTreeSet<String>,
+}
+
+impl Acl {
+    /// Create the ACL table, holding the `default` user only. If
+    /// `requirepass` is set, the `default` user requires that password.
+    pub(crate) fn new(requirepass: Option<&str>) -> Acl {
+        let default = User {
+            enabled: true,
+            all_commands: true,
+            ..User::default()
+        };
+
+        let mut users = BTreeMap::new();
+        users.insert(DEFAULT_USER.to_string(), default);
+
+        let mut acl = Acl { users };
+        acl.set_requirepass(requirepass);
+        acl
+    }
+
+    /// Replace the passwords of the `default` user with `requirepass`. If
+    /// `None`, the user requires no password.
+    pub(crate) fn set_requirepass(&mut self, requirepass: Option<&str>) {
+        let default = match self.users.get_mut(DEFAULT_USER) {
+            Some(default) => default,
+            None => return,
+        };
+
+        default.passwords.clear();
+
+        match requirepass {
+            Some(password) => {
+                default.passwords.insert(hash(password));
+                default.nopass = false;
+            }
+            None => default.nopass = true,
+        }
+    }
+
+    /// Returns the user new connections are logged in as, or `None` if they
+    /// need to authenticate first.
+    pub(crate) fn default_login(&self) -> Option<String> {
+        match self.users.get(DEFAULT_USER) {
+            Some(user) if user.enabled && user.nopass => Some(DEFAULT_USER.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `password` authenticates `username`.
+    pub(crate) fn authenticate(&self, username: &str, password: &str) -> bool {
+        match self.users.get(username) {
+            Some(user) => user.enabled && (user.nopass || user.passwords.contains(&hash(password))),
+            None => false,
+        }
+    }
+
+    /// Returns `true` if `username` may run `command`. The command name must
+    /// be lowercase.
+    pub(crate) fn is_permitted(&self, username: &str, command: &str) -> bool {
+        match self.users.get(username) {
+            Some(user) => user.all_commands != user.exceptions.contains(command),
+            None => false,
+        }
+    }
+
+    /// Create or modify `username` by applying `rules`, in order. Users are
+    /// created disabled, without passwords nor commands.
+    ///
+    /// If a rule is invalid, the user is left unchanged and the rule is
+    /// returned.
+    pub(crate) fn set_user(&mut self, username: &str, rules: &[String]) -> Result<(), String> {
+        let mut user = self.users.get(username).cloned().unwrap_or_default();
+
+        for rule in rules {
+            if !user.apply(rule) {
+                return Err(rule.clone());
+            }
+        }
+
+        self.users.insert(username.to_string(), user);
+        Ok(())
+    }
+
+    /// Remove `username`. Returns `false` if there is no such user.
+    pub(crate) fn del_user(&mut self, username: &str) -> bool {
+        self.users.remove(username).is_some()
+    }
+
+    /// Returns the user named `username`.
+    pub(crate) fn user(&self, username: &str) -> Option<&User> {
+        self.users.get(username)
+    }
+
+    /// Returns every user along with its name, sorted by name.
+    pub(crate) fn users(&self) -> impl Iterator<Item = (&str, &User)> {
+        self.users.iter().map(|(name, user)| (&name[..], user))
+    }
+}
+
+/// Returns the SHA-256 hash of `password`, in lowercase hexadecimal.
+fn hash(password: &str) -> String {
+    let digest = digest::digest(&digest::SHA256, password.as_bytes());
+
+    let mut hash = String::with_capacity(64);
+    for byte in digest.as_ref() {
+        write!(hash, "{:02x}", byte).unwrap();
+    }
+
+    hash
+}
+
+/// Returns the hash of an `ACL SETUSER` `#hash` or `!hash` rule, in
+/// lowercase, or `None` if it is not a valid SHA-256 hash.
+fn parse_hash(hash: &str) -> Option<String> {
+    if hash.len() == 64 && hash.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        Some(hash.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Returns `true` if the `ACL SETUSER` rule holds a password, such as
+/// `>password`.
+pub(crate) fn is_password_rule(rule: &str) -> bool {
+    rule.starts_with('>') || rule.starts_with('<')
+}
+
+/// Returns `true` if the argument at `index` of the command `args` holds a
+/// password, such as the password of `AUTH`. `MONITOR` and `SLOWLOG` show
+/// `REDACTED` in its place.
+pub(crate) fn is_secret(args: &[Frame], index: usize) -> bool {
+    let is = |index: usize, name: &str| match args.get(index) {
+        Some(Frame::Bulk(data)) => data.eq_ignore_ascii_case(name.as_bytes()),
+        _ => false,
+    };
+
+    if is(0, "auth") {
+        index > 0
+    } else if is(0, "hello") {
+        // HELLO protover AUTH username password
+        index >= 4 && is(index - 2, "auth")
+    } else if is(0, "acl") && is(1, "setuser") && index >= 3 {
+        match &args[index] {
+            Frame::Bulk(data) => is_password_rule(&String::from_utf8_lossy(data)),
+            _ => false,
+        }
+    } else {
+        false
+    }
+}
+
+impl User {
+    /// Apply a single rule. Returns `false` if the rule is not valid.
+    fn apply(&mut self, rule: &str) -> bool {
+        match &rule.to_lowercase()[..] {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allcommands" | "+@all" => {
+                self.all_commands = true;
+                self.exceptions.clear();
+            }
+            "nocommands" | "-@all" => {
+                self.all_commands = false;
+                self.exceptions.clear();
+            }
+            "allkeys" | "~*" | "allchannels" | "&*" => {}
+            "reset" => *self = User::default(),
+            lowercase => {
+                if let Some(password) = rule.strip_prefix('>') {
+                    self.nopass = false;
+                    self.passwords.insert(hash(password));
+                } else if let Some(password) = rule.strip_prefix('<') {
+                    self.passwords.remove(&hash(password));
+                } else if let Some(hash) = lowercase.strip_prefix('#') {
+                    match parse_hash(hash) {
+                        Some(hash) => {
+                            self.nopass = false;
+                            self.passwords.insert(hash);
+                        }
+                        None => return false,
+                    }
+                } else if let Some(hash) = lowercase.strip_prefix('!') {
+                    match parse_hash(hash) {
+                        Some(hash) => {
+                            self.passwords.remove(&hash);
+                        }
+                        None => return false,
+                    }
+                } else if let Some(command) = lowercase.strip_prefix('+') {
+                    self.set_permitted(command, true);
+                } else if let Some(command) = lowercase.strip_prefix('-') {
+                    self.set_permitted(command, false);
+                } else {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn set_permitted(&mut self, command: &str, permitted: bool) {
+        if permitted == self.all_commands {
+            self.exceptions.remove(command);
+        } else {
+            self.exceptions.insert(command.to_string());
+        }
+    }
+
+    /// Returns the flags of the user, as reported by `ACL GETUSER`.
+    pub(crate) fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+
+        if self.nopass {
+            flags.push("nopass");
+        }
+
+        flags
+    }
+
+    /// Returns the hashes of the passwords of the user.
+    pub(crate) fn passwords(&self) -> impl Iterator<Item = &str> {
+        self.passwords.iter().map(|password| &password[..])
+    }
+
+    /// Returns the rules describing the commands the user may run, such as
+    /// `+@all -flushdb`.
+    pub(crate) fn commands(&self) -> String {
+        let (all, sign) = if self.all_commands {
+            ("+@all", '-')
+        } else {
+            ("-@all", '+')
+        };
+
+        let mut commands = all.to_string();
+        for command in &self.exceptions {
+            commands.push(' ');
+            commands.push(sign);
+            commands.push_str(command);
+        }
+
+        commands
+    }
+
+    /// Returns the rules recreating the user, as listed by `ACL LIST`.
+    pub(crate) fn rules(&self) -> String {
+        let mut rules = self.flags().join(" ");
+
+        for hash in &self.passwords {
+            rules.push_str(" #");
+            rules.push_str(hash);
+        }
+
+        rules.push_str(" ~* &* ");
+        rules.push_str(&self.commands());
+        rules
+    }
+}
@@ -0,0 +1,344 @@
+//! Append only file persistence.
+//!
+//! Every write applied to the key-value store is appended to a file as a RESP
+//! command. On startup, the commands are replayed to rebuild the data.
+//!
+//! Writes are received from the `Db` through a channel and written to disk by
+//! a dedicated task, so connections never wait on the file system. How often
+//! the file is synced is controlled by `AppendFsync`, which may be changed at
+//! runtime with `CONFIG SET appendfsync`.
+//!
+//! Writes are group committed: the writes received while the previous batch
+//! was written are appended with a single write and, under `always`, synced
+//! once. Concurrent connections share the cost of each sync. Under `always`,
+//! connections only reply to a write once the batch holding it is synced, see
+//! `Db::aof_synced`.
+//!
+//! If a write fails, the part of it that reached the file is truncated, so the
+//! file does not end with an incomplete command. The commands are written
+//! again every second until it succeeds, and writes are refused meanwhile. See
+//! `Db::aof_write_ok`.
+//!
+//! The file only grows. `BGREWRITEAOF` replaces it with the shortest sequence
+//! of commands rebuilding the current data. The writer task asks the `Db` for a
+//! snapshot, which is ordered with respect to the writes, so no write is lost
+//! or applied twice.
+
+use crate::config::AppendFsync;
+use crate::db::{select_command, Write};
+use crate::replay::Replay;
+use crate::{Db, Frame, Shutdown};
+
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{self, Duration};
+use tracing::{debug, error, info, warn};
+
+/// Maximum number of writes appended as a single batch.
+const MAX_BATCH: usize = 1024;
+
+/// Writes the commands received from the `Db` to the append only file.
+#[derive(Debug)]
+struct Writer {
+    /// Path of the append only file.
+    path: PathBuf,
+
+    /// The append only file, opened for appending.
+    file: File,
+
+    /// Length of the file once the commands written so far are. Where the
+    /// file is truncated back to when a write fails.
+    len: u64,
+
+    /// Encoded commands not written yet. Kept when writing them fails, to be
+    /// written again.
+    pending: Vec<u8>,
+
+    /// When the file is synced. Refreshed from the settings of the `Db` before
+    /// handling each event.
+    fsync: AppendFsync,
+
+    /// Logical database the commands written last apply to. A `SELECT` is
+    /// written whenever it changes.
+    selected: Option<usize>,
+
+    /// Connections waiting for the writes received so far to be synced.
+    waiting: Vec<oneshot::Sender<()>>,
+}
+
+/// Rebuild the data stored in `db` by replaying the append only file at `path`.
+///
+/// A missing file is treated as an empty one. If the file ends with an
+/// incomplete command, as happens when the server stops in the middle of a
+/// write, the command is ignored.
+pub(crate) async fn load(db: &Db, path: &Path) -> crate::Result<()> {
+    let data = match fs::read(path).await {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut replay = Replay::new(db);
+    let mut buf = Cursor::new(&data[..]);
+    let mut count = 0;
+
+    while (buf.position() as usize) < data.len() {
+        let start = buf.position();
+
+        match Frame::check(&mut buf) {
+            Ok(()) => {}
+            Err(crate::frame::Error::Incomplete) => {
+                warn!(
+                    offset = start,
+                    "append only file ends with an incomplete command, ignoring it"
+                );
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        buf.set_position(start);
+        replay.apply(Frame::parse(&mut buf)?).await?;
+        count += 1;
+    }
+
+    info!(commands = count, path = %path.display(), "loaded append only file");
+    Ok(())
+}
+
+/// Start appending the writes applied to `db` to the append only file at
+/// `path`, in a background task.
+///
+/// The task also handles `BGREWRITEAOF` requests. It runs until `shutdown` is
+/// signalled, at which point pending writes are flushed and the file synced.
+pub(crate) async fn start(db: &Db, path: &Path, shutdown: Shutdown) -> crate::Result<()> {
+    let file = open(path).await?;
+    let len = file.metadata().await?.len();
+
+    let writer = Writer {
+        path: path.to_path_buf(),
+        file,
+        len,
+        pending: vec![],
+        fsync: db.config().appendfsync,
+        selected: None,
+        waiting: vec![],
+    };
+
+    // Subscribe before returning, so no write applied from now on is missed.
+    let writes = db.subscribe_aof(false);
+
+    tokio::spawn(writer.run(db.clone(), writes, shutdown));
+    Ok(())
+}
+
+impl Writer {
+    async fn run(
+        mut self,
+        db: Db,
+        mut writes: mpsc::UnboundedReceiver<Write>,
+        mut shutdown: Shutdown,
+    ) {
+        let rewrites = db.aof_rewrites();
+        let mut everysec = time::interval(Duration::from_secs(1));
+
+        loop {
+            self.fsync = db.config().appendfsync;
+
+            let res = tokio::select! {
+                Some(write) = writes.recv() => {
+                    let mut batch = vec![write];
+
+                    while batch.len() < MAX_BATCH {
+                        match writes.try_recv() {
+                            Ok(write) => batch.push(write),
+                            Err(_) => break,
+                        }
+                    }
+
+                    self.write(batch).await
+                }
+                // Also writes the commands left behind by a failed write.
+                _ = everysec.tick(), if self.fsync == AppendFsync::EverySec || !self.pending.is_empty() => {
+                    match self.append().await {
+                        Ok(()) => self.file.sync_data().await,
+                        Err(err) => Err(err),
+                    }
+                }
+                _ = rewrites.notified() => {
+                    // The new receiver starts with a snapshot of the data,
+                    // which includes the effect of every write still pending in
+                    // the previous receiver. These are dropped along with it.
+                    let mut previous = std::mem::replace(&mut writes, db.subscribe_aof(true));
+
+                    // The connections waiting on them are answered once the
+                    // snapshot is synced.
+                    while let Ok(write) = previous.try_recv() {
+                        if let Write::Sync(tx) = write {
+                            self.waiting.push(tx);
+                        }
+                    }
+
+                    Ok(())
+                }
+                _ = shutdown.recv() => break,
+            };
+
+            match res {
+                Ok(()) if !db.aof_write_ok() && self.pending.is_empty() => {
+                    info!("append only file written again, accepting writes");
+                    db.set_aof_write_ok(true);
+                }
+                Ok(()) => {}
+                Err(err) => {
+                    error!(cause = %err, "failed to write to the append only file");
+                    db.set_aof_write_ok(false);
+
+                    // The writes may not be on disk: the waiting connections
+                    // report an error.
+                    self.waiting.clear();
+                }
+            }
+        }
+
+        // Write what was applied before the shutdown signal.
+        let mut batch = vec![];
+        while let Ok(write) = writes.try_recv() {
+            batch.push(write);
+        }
+
+        if let Err(err) = self.write(batch).await {
+            error!(cause = %err, "failed to write to the append only file");
+        }
+
+        match self.file.sync_all().await {
+            Ok(()) => self.notify_synced(),
+            Err(err) => error!(cause = %err, "failed to sync the append only file"),
+        }
+
+        debug!("append only file writer shut down");
+    }
+
+    /// Write a batch of `Write`s to the file with as few writes as possible,
+    /// then sync it once if required or if a connection waits for it.
+    async fn write(&mut self, batch: Vec<Write>) -> io::Result<()> {
+        let mut res = Ok(());
+
+        for write in batch {
+            match write {
+                Write::Command { db, frame } => {
+                    encode(&mut self.selected, db, &frame, &mut self.pending)
+                }
+                Write::Snapshot(commands) => match self.rewrite(commands).await {
+                    // The snapshot includes the effect of the pending
+                    // commands.
+                    Ok(()) => self.pending.clear(),
+                    // The commands are still appended to the previous file.
+                    Err(err) => res = Err(err),
+                },
+                Write::Sync(tx) => self.waiting.push(tx),
+            }
+        }
+
+        res?;
+        self.append().await?;
+
+        if self.fsync == AppendFsync::Always || !self.waiting.is_empty() {
+            self.file.sync_data().await?;
+            self.notify_synced();
+        }
+
+        Ok(())
+    }
+
+    /// Let the waiting connections know that their writes are synced.
+    fn notify_synced(&mut self) {
+        for tx in self.waiting.drain(..) {
+            // The connection may have been closed meanwhile.
+            let _ = tx.send(());
+        }
+    }
+
+    /// Append the pending commands to the file.
+    ///
+    /// On failure, the file is truncated to its previous length, and the
+    /// commands are kept pending.
+    async fn append(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let res = match self.file.write_all(&self.pending).await {
+            Ok(()) => self.file.flush().await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = res {
+            if let Err(err) = self.file.set_len(self.len).await {
+                error!(cause = %err, "failed to truncate the append only file");
+            }
+
+            return Err(err);
+        }
+
+        self.len += self.pending.len() as u64;
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    /// Replace the file with `commands`.
+    ///
+    /// The commands are written to a temporary file, which is then renamed
+    /// over the append only file. A crash in the middle of a rewrite leaves
+    /// the previous file untouched.
+    async fn rewrite(&mut self, commands: Vec<(usize, Frame)>) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".rewrite");
+        let tmp = PathBuf::from(tmp);
+
+        let mut buf = vec![];
+        let mut selected = None;
+
+        for (db, frame) in &commands {
+            encode(&mut selected, *db, frame, &mut buf);
+        }
+
+        let mut file = File::create(&tmp).await?;
+        file.write_all(&buf).await?;
+        file.sync_all().await?;
+        drop(file);
+
+        fs::rename(&tmp, &self.path).await?;
+        self.file = open(&self.path).await?;
+        self.len = buf.len() as u64;
+        self.selected = selected;
+
+        info!(commands = commands.len(), "rewrote append only file");
+        Ok(())
+    }
+}
+
+/// Encode the command `frame` applying to the logical database `db`, preceded
+/// by a `SELECT` if `selected` is another database.
+fn encode(selected: &mut Option<usize>, db: usize, frame: &Frame, dst: &mut Vec<u8>) {
+    if *selected != Some(db) {
+        select_command(db).write_to(dst, 2);
+
+        *selected = Some(db);
+    }
+
+    frame.write_to(dst, 2);
+}
+
+/// Open the append only file for appending, creating it if needed.
+async fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
@@ -1,8 +1,9 @@
-use mini_redis::{client, DEFAULT_PORT};
+use mini_redis::{client, TlsOptions, DEFAULT_PORT};
 
 use bytes::Bytes;
 use clap::{Parser, Subcommand};
 use std::num::ParseIntError;
+use std::path::PathBuf;
 use std::str;
 use std::time::Duration;
 
@@ -15,13 +16,36 @@ use std::time::Duration;
 )]
 struct Cli {
     #[clap(subcommand)]
-    command: Command,
+    command: Option<Command>,
+
+    /// Print the most frequently accessed keys instead of issuing a command.
+    #[clap(long)]
+    hotkeys: bool,
 
     #[clap(name = "hostname", long, default_value = "127.0.0.1")]
     host: String,
 
     #[clap(long, default_value_t = DEFAULT_PORT)]
     port: u16,
+
+    /// Connect to the unix domain socket at this path instead of the
+    /// hostname and port.
+    #[clap(short = 's', long, conflicts_with = "tls")]
+    socket: Option<PathBuf>,
+
+    /// Connect using TLS.
+    #[clap(long)]
+    tls: bool,
+
+    /// PEM file holding the certificate authorities to trust, instead of the
+    /// ones of the operating system.
+    #[clap(long, requires = "tls")]
+    cacert: Option<PathBuf>,
+
+    /// Name the server certificate must be valid for. Defaults to the
+    /// hostname.
+    #[clap(long, requires = "tls")]
+    sni: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -62,8 +86,16 @@ enum Command {
         /// Specific channel or channels
         channels: Vec<String>,
     },
+    /// Subscribe a client to the channels matching a pattern or patterns.
+    Psubscribe {
+        /// Glob-style pattern or patterns
+        patterns: Vec<String>,
+    },
 }
 
+/// Number of keys printed in `--hotkeys` mode.
+const HOTKEYS_COUNT: usize = 16;
+
 /// Entry point for CLI tool.
 ///
 /// The `[tokio::main]` annotation signals that the Tokio runtime should be
@@ -81,14 +113,48 @@ async fn main() -> mini_redis::Result<()> {
     // Parse command line arguments
     let cli = Cli::parse();
 
+    // Clap cannot declare a conflict between an argument and the subcommand.
+    if cli.hotkeys && cli.command.is_some() {
+        return Err("`--hotkeys` cannot be used with a command".into());
+    }
+
     // Get the remote address to connect to
     let addr = format!("{}:{}", cli.host, cli.port);
 
     // Establish a connection
-    let mut client = client::connect(&addr).await?;
+    let mut client = match cli.socket {
+        #[cfg(unix)]
+        Some(path) => client::connect_unix(path).await?,
+        _ if cli.tls => {
+            let mut options = TlsOptions::new(cli.sni.unwrap_or(cli.host));
+            options.ca_file = cli.cacert;
+
+            client::connect_tls(&addr, options).await?
+        }
+        _ => client::connect(&addr).await?,
+    };
+
+    if cli.hotkeys {
+        let hotkeys = client.hotkeys(HOTKEYS_COUNT).await?;
+
+        if hotkeys.is_empty() {
+            println!("(no hot keys)");
+        }
+
+        for (i, (key, ops_per_sec)) in hotkeys.iter().enumerate() {
+            println!("{}) \"{}\" {:.2} ops/sec", i + 1, key, ops_per_sec);
+        }
+
+        return Ok(());
+    }
+
+    let command = match cli.command {
+        Some(command) => command,
+        None => return Err("a command must be provided".into()),
+    };
 
     // Process the requested command
-    match cli.command {
+    match command {
         Command::Ping { msg } => {
             let value = client.ping(msg).await?;
             if let Ok(string) = str::from_utf8(&value) {
@@ -125,7 +191,7 @@ async fn main() -> mini_redis::Result<()> {
             println!("OK");
         }
         Command::Publish { channel, message } => {
-            client.publish(&channel, message.into()).await?;
+            client.publish(&channel, message).await?;
             println!("Publish OK");
         }
         Command::Subscribe { channels } => {
@@ -142,6 +208,21 @@ async fn main() -> mini_redis::Result<()> {
                 );
             }
         }
+        Command::Psubscribe { patterns } => {
+            if patterns.is_empty() {
+                return Err("pattern(s) must be provided".into());
+            }
+            let mut subscriber = client.psubscribe(patterns).await?;
+
+            while let Some(msg) = subscriber.next_message().await? {
+                println!(
+                    "got message from the channel: {}; pattern = {}; message = {:?}",
+                    msg.channel,
+                    msg.pattern.unwrap_or_default(),
+                    msg.content
+                );
+            }
+        }
     }
 
     Ok(())
@@ -6,9 +6,13 @@
 //!
 //! The `clap` crate is used for parsing arguments.
 
-use mini_redis::{server, DEFAULT_PORT};
+use mini_redis::{
+    server, AppendFsync, KeyspaceEvents, MaxMemoryPolicy, SavePoints, ServerConfig,
+    ShutdownController, TlsConfig, DEFAULT_PORT,
+};
 
 use clap::Parser;
+use std::path::PathBuf;
 use tokio::net::TcpListener;
 use tokio::signal;
 
@@ -35,12 +39,60 @@ pub async fn main() -> mini_redis::Result<()> {
     let cli = Cli::parse();
     let port = cli.port.unwrap_or(DEFAULT_PORT);
 
+    let mut config = ServerConfig::default();
+    if let Some(databases) = cli.databases {
+        config.databases = databases;
+    }
+    if let Some(dbfilename) = cli.dbfilename {
+        config.dbfilename = dbfilename;
+    }
+    config.appendonly = cli.appendonly;
+    if let Some(appendfilename) = cli.appendfilename {
+        config.appendfilename = appendfilename;
+    }
+    if let Some(appendfsync) = cli.appendfsync {
+        config.appendfsync = appendfsync;
+    }
+    config.requirepass = cli.requirepass;
+    config.unixsocket = cli.unixsocket;
+    if let Some(events) = cli.notify_keyspace_events {
+        config.notify_keyspace_events = events;
+    }
+    if let Some(maxmemory) = cli.maxmemory {
+        config.maxmemory = maxmemory;
+    }
+    if let Some(policy) = cli.maxmemory_policy {
+        config.maxmemory_policy = policy;
+    }
+    if let Some(threshold) = cli.slowlog_log_slower_than {
+        config.slowlog_log_slower_than = threshold;
+    }
+    if let Some(len) = cli.slowlog_max_len {
+        config.slowlog_max_len = len;
+    }
+    config.save_on_shutdown = cli.save_on_shutdown;
+    if let Some(save) = cli.save {
+        config.save = save;
+    }
+    if let (Some(cert_file), Some(key_file)) = (cli.tls_cert_file, cli.tls_key_file) {
+        config.tls = Some(TlsConfig {
+            cert_file,
+            key_file,
+        });
+    }
+
     // Bind a TCP listener
     let listener = TcpListener::bind(&format!("127.0.0.1:{}", port)).await?;
 
-    server::run(listener, signal::ctrl_c()).await;
+    // Trigger a graceful shutdown on SIGINT, as `SHUTDOWN` does.
+    let controller = ShutdownController::new();
+    let ctrl_c = controller.clone();
+    tokio::spawn(async move {
+        let _ = signal::ctrl_c().await;
+        ctrl_c.trigger();
+    });
 
-    Ok(())
+    server::run_with_config(listener, config, controller).await
 }
 
 #[derive(Parser, Debug)]
@@ -48,6 +100,79 @@ pub async fn main() -> mini_redis::Result<()> {
 struct Cli {
     #[clap(long)]
     port: Option<u16>,
+
+    /// Number of logical databases, at least 1
+    #[clap(long, parse(try_from_str = databases_from_str))]
+    databases: Option<usize>,
+
+    /// Path of the snapshot file
+    #[clap(long)]
+    dbfilename: Option<PathBuf>,
+
+    /// Log writes to the append only file and replay it on startup
+    #[clap(long)]
+    appendonly: bool,
+
+    /// Path of the append only file
+    #[clap(long)]
+    appendfilename: Option<PathBuf>,
+
+    /// When to sync the append only file: always, everysec or no
+    #[clap(long)]
+    appendfsync: Option<AppendFsync>,
+
+    /// Require clients to authenticate with this password
+    #[clap(long)]
+    requirepass: Option<String>,
+
+    /// Accept TLS connections, presenting the certificate in this PEM file
+    #[clap(long, requires = "tls-key-file")]
+    tls_cert_file: Option<PathBuf>,
+
+    /// PEM file holding the private key of the TLS certificate
+    #[clap(long, requires = "tls-cert-file")]
+    tls_key_file: Option<PathBuf>,
+
+    /// Also accept connections on the unix domain socket at this path
+    #[clap(long)]
+    unixsocket: Option<PathBuf>,
+
+    /// Classes of keyspace events to publish, such as KEA
+    #[clap(long)]
+    notify_keyspace_events: Option<KeyspaceEvents>,
+
+    /// Evict keys once the data uses this many bytes. 0 means no limit
+    #[clap(long)]
+    maxmemory: Option<usize>,
+
+    /// How keys are picked for eviction, such as allkeys-lru
+    #[clap(long)]
+    maxmemory_policy: Option<MaxMemoryPolicy>,
+
+    /// Log commands slower than this many microseconds. Negative disables it
+    #[clap(long, allow_hyphen_values = true)]
+    slowlog_log_slower_than: Option<i64>,
+
+    /// Maximum number of entries of the slow log
+    #[clap(long)]
+    slowlog_max_len: Option<usize>,
+
+    /// Save a snapshot when the server stops, unless stopped by SHUTDOWN NOSAVE
+    #[clap(long)]
+    save_on_shutdown: bool,
+
+    /// Save a snapshot once both the seconds and the number of writes of one
+    /// of the pairs are reached, such as "3600 1 300 100"
+    #[clap(long)]
+    save: Option<SavePoints>,
+}
+
+fn databases_from_str(src: &str) -> Result<usize, String> {
+    match src.parse::<usize>() {
+        Ok(0) => Err("there must be at least one database".to_string()),
+        Ok(databases) => Ok(databases),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
 #[cfg(not(feature = "otel"))]
@@ -2,7 +2,11 @@
 //!
 //! Provides a blocking connect and methods for issuing the supported commands.
 
+use crate::TlsOptions;
+
 use bytes::Bytes;
+#[cfg(unix)]
+use std::path::Path;
 use std::time::Duration;
 use tokio::net::ToSocketAddrs;
 use tokio::runtime::Runtime;
@@ -79,6 +83,36 @@ pub fn connect<T: ToSocketAddrs>(addr: T) -> crate::Result<BlockingClient> {
     Ok(BlockingClient { inner, rt })
 }
 
+/// Establish a TLS connection with the Redis server located at `addr`.
+///
+/// See [`client::connect_tls`](crate::client::connect_tls) for how the server
+/// certificate is verified.
+pub fn connect_tls<T: ToSocketAddrs>(
+    addr: T,
+    options: TlsOptions,
+) -> crate::Result<BlockingClient> {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let inner = rt.block_on(crate::client::connect_tls(addr, options))?;
+
+    Ok(BlockingClient { inner, rt })
+}
+
+/// Establish a connection with the Redis server listening on the unix domain
+/// socket at `path`.
+#[cfg(unix)]
+pub fn connect_unix(path: impl AsRef<Path>) -> crate::Result<BlockingClient> {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let inner = rt.block_on(crate::client::connect_unix(path))?;
+
+    Ok(BlockingClient { inner, rt })
+}
+
 impl BlockingClient {
     /// Get the value of key.
     ///
@@ -219,6 +253,19 @@ impl BlockingClient {
             rt: self.rt,
         })
     }
+
+    /// Subscribes the client to the channels matching the specified
+    /// glob-style patterns.
+    ///
+    /// As with `subscribe`, the function consumes `self` and returns a
+    /// `BlockingSubscriber`.
+    pub fn psubscribe(self, patterns: Vec<String>) -> crate::Result<BlockingSubscriber> {
+        let subscriber = self.rt.block_on(self.inner.psubscribe(patterns))?;
+        Ok(BlockingSubscriber {
+            inner: subscriber,
+            rt: self.rt,
+        })
+    }
 }
 
 impl BlockingSubscriber {
@@ -227,6 +274,11 @@ impl BlockingSubscriber {
         self.inner.get_subscribed()
     }
 
+    /// Returns the set of patterns currently subscribed to.
+    pub fn get_subscribed_patterns(&self) -> &[String] {
+        self.inner.get_subscribed_patterns()
+    }
+
     /// Receive the next message published on a subscribed channel, waiting if
     /// necessary.
     ///
@@ -237,6 +289,7 @@ impl BlockingSubscriber {
 
     /// Convert the subscriber into an `Iterator` yielding new messages published
     /// on subscribed channels.
+    #[allow(clippy::should_implement_trait)]
     pub fn into_iter(self) -> impl Iterator<Item = crate::Result<Message>> {
         SubscriberIterator {
             inner: self.inner,
@@ -253,6 +306,16 @@ impl BlockingSubscriber {
     pub fn unsubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         self.rt.block_on(self.inner.unsubscribe(channels))
     }
+
+    /// Subscribe to a list of new patterns
+    pub fn psubscribe(&mut self, patterns: &[String]) -> crate::Result<()> {
+        self.rt.block_on(self.inner.psubscribe(patterns))
+    }
+
+    /// Unsubscribe to a list of patterns
+    pub fn punsubscribe(&mut self, patterns: &[String]) -> crate::Result<()> {
+        self.rt.block_on(self.inner.punsubscribe(patterns))
+    }
 }
 
 impl Iterator for SubscriberIterator {
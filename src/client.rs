@@ -2,22 +2,36 @@
 //!
 //! Provides an async connect and methods for issuing the supported commands.
 
-use crate::cmd::{Get, Ping, Publish, Set, Subscribe, Unsubscribe};
-use crate::{Connection, Frame};
+use crate::acl::REDACTED;
+use crate::cmd::{
+    Auth, Get, Hello, HotKeys, PSubscribe, PUnsubscribe, Ping, Publish, Select, Set, Subscribe,
+    Unsubscribe,
+};
+use crate::{tls, Connection, Frame, TlsOptions};
 
 use async_stream::try_stream;
 use bytes::Bytes;
+use std::fmt;
 use std::io::{Error, ErrorKind};
+use std::ops::{Deref, DerefMut};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time;
 use tokio_stream::Stream;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
 /// Established connection with a Redis server.
 ///
 /// Backed by a single `TcpStream`, `Client` provides basic network client
-/// functionality (no pooling, retrying, ...). Connections are established using
-/// the [`connect`](fn@connect) function.
+/// functionality (no retrying, ...). Connections are established using the
+/// [`connect`](fn@connect) function. A [`Pool`] shares several connections
+/// between tasks.
 ///
 /// Requests are issued using the various methods of `Client`.
 pub struct Client {
@@ -29,6 +43,14 @@ pub struct Client {
     /// `Connection` allows the handler to operate at the "frame" level and keep
     /// the byte level protocol parsing details encapsulated in `Connection`.
     connection: Connection,
+
+    /// Set while a reply is expected. A request cancelled before its reply is
+    /// read leaves it set, as the reply is still pending on the connection.
+    in_flight: bool,
+
+    /// Set once a command may have changed the state of the connection, such
+    /// as the selected database, the protocol or the authenticated user.
+    modified: bool,
 }
 
 /// A client that has entered pub/sub mode.
@@ -42,6 +64,96 @@ pub struct Subscriber {
 
     /// The set of channels to which the `Subscriber` is currently subscribed.
     subscribed_channels: Vec<String>,
+
+    /// The set of patterns to which the `Subscriber` is currently subscribed.
+    subscribed_patterns: Vec<String>,
+}
+
+/// A batch of commands sent to the server at once.
+///
+/// Commands are queued on the `Pipeline`, then `execute` writes all of them
+/// with a single flush and reads back the replies, in order. This saves a
+/// round trip per command compared to issuing each command with `Client`,
+/// which makes loading a large number of keys much faster.
+///
+/// Created by [`Client::pipeline`].
+pub struct Pipeline<'a> {
+    /// The client the commands are sent with.
+    client: &'a mut Client,
+
+    /// The queued commands.
+    frames: Vec<Frame>,
+}
+
+/// A pool of connections to a Redis server, shared by tasks.
+///
+/// The pool holds up to `size` connections, established when first needed.
+/// [`get`](Pool::get) checks a connection out of the pool. It is returned to
+/// the pool once the `PooledClient` handle drops, so it can be reused by
+/// another task. When every connection is checked out, `get` waits for one to
+/// be returned.
+///
+/// Connections are checked with a `PING` before being handed out. Broken ones
+/// are dropped and re-established, retrying with an exponential backoff while
+/// the server is unreachable. A connection whose state was changed, by
+/// `select` for instance, or whose request was cancelled before its reply was
+/// read, is closed rather than returned to the pool.
+///
+/// `Pool` is cheap to clone. Clones share the same connections.
+#[derive(Clone)]
+pub struct Pool {
+    shared: Arc<PoolShared>,
+}
+
+/// Options for establishing the connections of a [`Pool`].
+#[derive(Clone, Default)]
+pub struct PoolOptions {
+    /// Encrypt the connections, verifying the server certificate according to
+    /// these options.
+    pub tls: Option<TlsOptions>,
+
+    /// Connect to the unix domain socket at this path rather than to the
+    /// address of the pool.
+    #[cfg(unix)]
+    pub unixsocket: Option<PathBuf>,
+
+    /// Authenticate the connections as this user, or as the `default` user if
+    /// `None`. Only used along with `password`.
+    pub username: Option<String>,
+
+    /// Authenticate the connections with this password once established.
+    pub password: Option<String>,
+}
+
+/// State shared by the clones of a `Pool`.
+struct PoolShared {
+    /// Address of the server.
+    addr: String,
+
+    /// How connections are established.
+    options: PoolOptions,
+
+    /// Connections not currently checked out.
+    idle: Mutex<Vec<Client>>,
+
+    /// Limits the number of connections checked out at once to the size of
+    /// the pool. A connection is checked out along with a permit.
+    permits: Arc<Semaphore>,
+}
+
+/// A connection checked out of a `Pool`.
+///
+/// Dereferences to `Client`, which issues the commands. The connection is
+/// returned to the pool when the handle drops.
+pub struct PooledClient {
+    /// Always `Some`, until the client is returned to the pool on drop.
+    client: Option<Client>,
+
+    /// The pool to return the client to.
+    shared: Arc<PoolShared>,
+
+    /// Lets another connection be checked out once dropped.
+    _permit: OwnedSemaphorePermit,
 }
 
 /// A message received on a subscribed channel.
@@ -49,6 +161,10 @@ pub struct Subscriber {
 pub struct Message {
     pub channel: String,
     pub content: Bytes,
+
+    /// The subscribed pattern the channel matched, if the message was received
+    /// through a pattern subscription.
+    pub pattern: Option<String>,
 }
 
 /// Establish a connection with the Redis server located at `addr`.
@@ -83,10 +199,74 @@ pub async fn connect<T: ToSocketAddrs>(addr: T) -> crate::Result<Client> {
     // perform redis protocol frame parsing.
     let connection = Connection::new(socket);
 
-    Ok(Client { connection })
+    Ok(Client::new(connection))
+}
+
+/// Establish a TLS connection with the Redis server located at `addr`.
+///
+/// This is the same as [`connect`](fn@connect), except that the connection is
+/// encrypted. The server certificate is verified according to `options`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mini_redis::{client, TlsOptions};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let options = TlsOptions::new("redis.example.com");
+///
+///     let client = match client::connect_tls("redis.example.com:6379", options).await {
+///         Ok(client) => client,
+///         Err(_) => panic!("failed to establish connection"),
+///     };
+/// # drop(client);
+/// }
+/// ```
+pub async fn connect_tls<T: ToSocketAddrs>(addr: T, options: TlsOptions) -> crate::Result<Client> {
+    let socket = TcpStream::connect(addr).await?;
+
+    // Perform the TLS handshake before any frame is exchanged.
+    let stream = tls::connect(socket, &options).await?;
+    let connection = Connection::from_stream(stream);
+
+    Ok(Client::new(connection))
+}
+
+/// Establish a connection with the Redis server listening on the unix domain
+/// socket at `path`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mini_redis::client;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let client = match client::connect_unix("/tmp/mini-redis.sock").await {
+///         Ok(client) => client,
+///         Err(_) => panic!("failed to establish connection"),
+///     };
+/// # drop(client);
+/// }
+/// ```
+#[cfg(unix)]
+pub async fn connect_unix(path: impl AsRef<Path>) -> crate::Result<Client> {
+    let socket = UnixStream::connect(path).await?;
+    let connection = Connection::from_stream(socket);
+
+    Ok(Client::new(connection))
 }
 
 impl Client {
+    fn new(connection: Connection) -> Client {
+        Client {
+            connection,
+            in_flight: false,
+            modified: false,
+        }
+    }
+
     /// Ping to the server.
     ///
     /// Returns PONG if no argument is provided, otherwise
@@ -114,7 +294,7 @@ impl Client {
         let frame = Ping::new(msg).into_frame();
         debug!(request = ?frame);
 
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
 
         match self.read_response().await? {
             Frame::Simple(value) => Ok(value.into()),
@@ -123,6 +303,80 @@ impl Client {
         }
     }
 
+    /// Negotiate the protocol version used by the connection.
+    ///
+    /// Passing `3` switches the connection to RESP3, after which the server
+    /// may reply with the additional RESP3 frame types, such as maps and
+    /// doubles. Returns the server information sent in response, as a map.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let info = client.hello(3).await.unwrap();
+    ///     println!("server = {:?}", info);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn hello(&mut self, protover: u64) -> crate::Result<Frame> {
+        let frame = Hello::new(Some(protover)).into_frame();
+        debug!(request = ?frame);
+
+        // The connection no longer matches a new one, so a `Pool` does not
+        // hand it out again. The same goes for `auth` and `select`.
+        self.modified = true;
+        self.send(&frame).await?;
+
+        // The server switches protocol before replying, so a successful reply
+        // is already encoded using the requested version.
+        match self.read_response().await? {
+            frame @ Frame::Map(_) | frame @ Frame::Array(_) => {
+                self.connection.set_protocol(protover as u8);
+                Ok(frame)
+            }
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Authenticate the connection as `username`, or as the `default` user if
+    /// `None`.
+    ///
+    /// Servers started with a password refuse every other command until the
+    /// connection is authenticated.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.auth(None, "secret").await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self, password))]
+    pub async fn auth(&mut self, username: Option<&str>, password: &str) -> crate::Result<()> {
+        let frame = Auth::new(username.map(str::to_string), password).into_frame();
+
+        // The frame holds the password, so it is not logged.
+        self.modified = true;
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
     /// Get the value of key.
     ///
     /// If the key does not exist the special value `None` is returned.
@@ -151,7 +405,7 @@ impl Client {
 
         // Write the frame to the socket. This writes the full frame to the
         // socket, waiting if necessary.
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
 
         // Wait for the response from the server
         //
@@ -261,7 +515,7 @@ impl Client {
 
         // Write the frame to the socket. This writes the full frame to the
         // socket, waiting if necessary.
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
 
         // Wait for the response from the server. On success, the server
         // responds simply with `OK`. Any other response indicates an error.
@@ -271,6 +525,38 @@ impl Client {
         }
     }
 
+    /// Select the logical database subsequent commands operate on.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     client.select(1).await.unwrap();
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn select(&mut self, index: u64) -> crate::Result<()> {
+        let frame = Select::new(index).into_frame();
+
+        debug!(request = ?frame);
+
+        self.modified = true;
+        self.send(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
     /// Posts `message` to the given `channel`.
     ///
     /// Returns the number of subscribers currently listening on the channel.
@@ -300,15 +586,96 @@ impl Client {
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
 
         // Read the response
         match self.read_response().await? {
-            Frame::Integer(response) => Ok(response),
+            Frame::Integer(response) => Ok(response as u64),
             frame => Err(frame.to_error()),
         }
     }
 
+    /// Returns up to `count` of the most frequently accessed keys on the
+    /// server, hottest first, along with an estimate of their accesses per
+    /// second.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     for (key, ops_per_sec) in client.hotkeys(10).await.unwrap() {
+    ///         println!("{}: {:.2} ops/sec", key, ops_per_sec);
+    ///     }
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn hotkeys(&mut self, count: usize) -> crate::Result<Vec<(String, f64)>> {
+        let frame = HotKeys::new(count).into_frame();
+
+        debug!(request = ?frame);
+
+        self.send(&frame).await?;
+
+        // The server responds with an array alternating between key names and
+        // their estimated ops/sec.
+        match self.read_response().await? {
+            Frame::Array(entries) => entries
+                .chunks(2)
+                .map(|pair| match pair {
+                    [key, rate] => {
+                        let rate = rate
+                            .to_string()
+                            .parse::<f64>()
+                            .map_err(|_| rate.to_error())?;
+                        Ok((key.to_string(), rate))
+                    }
+                    _ => Err("protocol error; odd number of hotkeys entries".into()),
+                })
+                .collect(),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Start a pipeline of commands.
+    ///
+    /// The commands queued on the returned `Pipeline` are only sent when it is
+    /// executed.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let replies = client
+    ///         .pipeline()
+    ///         .set("foo", "bar".into())
+    ///         .get("foo")
+    ///         .execute()
+    ///         .await
+    ///         .unwrap();
+    ///     println!("Got = {:?}", replies);
+    /// }
+    /// ```
+    pub fn pipeline(&mut self) -> Pipeline<'_> {
+        Pipeline {
+            client: self,
+            frames: vec![],
+        }
+    }
+
     /// Subscribes the client to the specified channels.
     ///
     /// Once a client issues a subscribe command, it may no longer issue any
@@ -321,24 +688,47 @@ impl Client {
         // Issue the subscribe command to the server and wait for confirmation.
         // The client will then have been transitioned into the "subscriber"
         // state and may only issue pub/sub commands from that point on.
-        self.subscribe_cmd(&channels).await?;
+        let frame = Subscribe::new(&channels).into_frame();
+        self.subscribe_cmd(frame, "subscribe", &channels).await?;
 
         // Return the `Subscriber` type
         Ok(Subscriber {
             client: self,
             subscribed_channels: channels,
+            subscribed_patterns: vec![],
         })
     }
 
-    /// The core `SUBSCRIBE` logic, used by misc subscribe fns
-    async fn subscribe_cmd(&mut self, channels: &[String]) -> crate::Result<()> {
-        // Convert the `Subscribe` command into a frame
-        let frame = Subscribe::new(&channels).into_frame();
+    /// Subscribes the client to the channels matching the specified
+    /// glob-style patterns.
+    ///
+    /// As with `subscribe`, the function consumes `self` and returns a
+    /// `Subscriber`.
+    #[instrument(skip(self))]
+    pub async fn psubscribe(mut self, patterns: Vec<String>) -> crate::Result<Subscriber> {
+        let frame = PSubscribe::new(&patterns).into_frame();
+        self.subscribe_cmd(frame, "psubscribe", &patterns).await?;
 
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: vec![],
+            subscribed_patterns: patterns,
+        })
+    }
+
+    /// The core `SUBSCRIBE` and `PSUBSCRIBE` logic, used by misc subscribe
+    /// fns. `kind` is the name of the command sent in `frame`, which the
+    /// server repeats when confirming each subscription.
+    async fn subscribe_cmd(
+        &mut self,
+        frame: Frame,
+        kind: &str,
+        channels: &[String],
+    ) -> crate::Result<()> {
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.connection.write_frame(&frame).await?;
+        self.send(&frame).await?;
 
         // For each channel being subscribed to, the server responds with a
         // message confirming subscription to that channel.
@@ -348,7 +738,8 @@ impl Client {
 
             // Verify it is confirmation of subscription.
             match response {
-                Frame::Array(ref frame) => match frame.as_slice() {
+                // In RESP3, the confirmation is a push frame instead.
+                Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
                     // The server responds with an array frame in the form of:
                     //
                     // ```
@@ -359,7 +750,7 @@ impl Client {
                     // num-subscribed is the number of channels that the client
                     // is currently subscribed to.
                     [subscribe, schannel, ..]
-                        if *subscribe == "subscribe" && *schannel == channel => {}
+                        if *subscribe == kind && *schannel == &channel[..] => {}
                     _ => return Err(response.to_error()),
                 },
                 frame => return Err(frame.to_error()),
@@ -369,11 +760,19 @@ impl Client {
         Ok(())
     }
 
+    /// Writes a request frame to the socket, expecting a reply.
+    async fn send(&mut self, frame: &Frame) -> crate::Result<()> {
+        self.in_flight = true;
+        self.connection.write_frame(frame).await?;
+        Ok(())
+    }
+
     /// Reads a response frame from the socket.
     ///
     /// If an `Error` frame is received, it is converted to `Err`.
     async fn read_response(&mut self) -> crate::Result<Frame> {
         let response = self.connection.read_frame().await?;
+        self.in_flight = false;
 
         debug!(?response);
 
@@ -393,12 +792,130 @@ impl Client {
     }
 }
 
+impl Pipeline<'_> {
+    /// Queue a `PING`.
+    pub fn ping(&mut self, msg: Option<String>) -> &mut Self {
+        self.command(Ping::new(msg).into_frame())
+    }
+
+    /// Queue a `GET` of `key`.
+    pub fn get(&mut self, key: &str) -> &mut Self {
+        self.command(Get::new(key).into_frame())
+    }
+
+    /// Queue a `SET` of `key` to `value`.
+    pub fn set(&mut self, key: &str, value: Bytes) -> &mut Self {
+        self.command(Set::new(key, value, None).into_frame())
+    }
+
+    /// Queue a `SET` of `key` to `value`, expiring after `expiration`.
+    pub fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> &mut Self {
+        self.command(Set::new(key, value, Some(expiration)).into_frame())
+    }
+
+    /// Queue a `PUBLISH` of `message` to `channel`.
+    pub fn publish(&mut self, channel: &str, message: Bytes) -> &mut Self {
+        self.command(Publish::new(channel, message).into_frame())
+    }
+
+    /// Queue any command, given as the frame to send.
+    ///
+    /// The frame is usually an array of bulk strings, the name of the command
+    /// followed by its arguments.
+    pub fn command(&mut self, frame: Frame) -> &mut Self {
+        self.frames.push(frame);
+        self
+    }
+
+    /// Returns the number of queued commands.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if no command is queued.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Send the queued commands and return their replies, in the order the
+    /// commands were queued. The queue is emptied, so the pipeline can be
+    /// reused.
+    ///
+    /// A command failing does not fail the others: its reply is an `Error`
+    /// frame. `Err` is only returned if the connection fails.
+    #[instrument(skip(self))]
+    pub async fn execute(&mut self) -> crate::Result<Vec<Frame>> {
+        if self.frames.iter().any(changes_state) {
+            self.client.modified = true;
+        }
+
+        self.client.in_flight = true;
+        let connection = &mut self.client.connection;
+
+        // All the commands are encoded before anything is written, so they
+        // are sent with as few writes as possible.
+        for frame in &self.frames {
+            debug!(request = ?frame);
+            connection.buffer_frame(frame);
+        }
+
+        connection.flush().await?;
+
+        let mut replies = Vec::with_capacity(self.frames.len());
+
+        for _ in self.frames.drain(..) {
+            match connection.read_frame().await? {
+                Some(reply) => {
+                    debug!(?reply);
+                    replies.push(reply);
+                }
+                None => {
+                    let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+                    return Err(err.into());
+                }
+            }
+        }
+
+        self.client.in_flight = false;
+
+        Ok(replies)
+    }
+}
+
+/// Returns `true` if the command sent in `frame` may change the state of the
+/// connection, rather than only the data held by the server.
+fn changes_state(frame: &Frame) -> bool {
+    let parts = match frame {
+        Frame::Array(parts) => parts,
+        _ => return false,
+    };
+
+    // Lowercase name of the command, or of its subcommand.
+    let name = |index: usize| match parts.get(index) {
+        Some(Frame::Bulk(name)) => String::from_utf8_lossy(name).to_lowercase(),
+        Some(Frame::Simple(name)) => name.to_lowercase(),
+        _ => String::new(),
+    };
+
+    match &name(0)[..] {
+        "auth" | "hello" | "monitor" | "multi" | "psubscribe" | "psync" | "select"
+        | "subscribe" | "watch" => true,
+        "client" => name(1) == "setname",
+        _ => false,
+    }
+}
+
 impl Subscriber {
     /// Returns the set of channels currently subscribed to.
     pub fn get_subscribed(&self) -> &[String] {
         &self.subscribed_channels
     }
 
+    /// Returns the set of patterns currently subscribed to.
+    pub fn get_subscribed_patterns(&self) -> &[String] {
+        &self.subscribed_patterns
+    }
+
     /// Receive the next message published on a subscribed channel, waiting if
     /// necessary.
     ///
@@ -409,11 +926,19 @@ impl Subscriber {
                 debug!(?mframe);
 
                 match mframe {
-                    Frame::Array(ref frame) => match frame.as_slice() {
+                    Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
                         [message, channel, content] if *message == "message" => Ok(Some(Message {
                             channel: channel.to_string(),
                             content: Bytes::from(content.to_string()),
+                            pattern: None,
                         })),
+                        [message, pattern, channel, content] if *message == "pmessage" => {
+                            Ok(Some(Message {
+                                channel: channel.to_string(),
+                                content: Bytes::from(content.to_string()),
+                                pattern: Some(pattern.to_string()),
+                            }))
+                        }
                         _ => Err(mframe.to_error()),
                     },
                     frame => Err(frame.to_error()),
@@ -447,7 +972,10 @@ impl Subscriber {
     #[instrument(skip(self))]
     pub async fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         // Issue the subscribe command
-        self.client.subscribe_cmd(channels).await?;
+        let frame = Subscribe::new(channels).into_frame();
+        self.client
+            .subscribe_cmd(frame, "subscribe", channels)
+            .await?;
 
         // Update the set of subscribed channels.
         self.subscribed_channels
@@ -456,54 +984,300 @@ impl Subscriber {
         Ok(())
     }
 
+    /// Subscribe to a list of new patterns
+    #[instrument(skip(self))]
+    pub async fn psubscribe(&mut self, patterns: &[String]) -> crate::Result<()> {
+        let frame = PSubscribe::new(patterns).into_frame();
+        self.client
+            .subscribe_cmd(frame, "psubscribe", patterns)
+            .await?;
+
+        self.subscribed_patterns
+            .extend(patterns.iter().map(Clone::clone));
+
+        Ok(())
+    }
+
     /// Unsubscribe to a list of new channels
     #[instrument(skip(self))]
     pub async fn unsubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
-        let frame = Unsubscribe::new(&channels).into_frame();
+        let frame = Unsubscribe::new(channels).into_frame();
+        unsubscribe_cmd(
+            &mut self.client,
+            frame,
+            "unsubscribe",
+            channels,
+            &mut self.subscribed_channels,
+        )
+        .await
+    }
 
-        debug!(request = ?frame);
+    /// Unsubscribe to a list of patterns
+    #[instrument(skip(self))]
+    pub async fn punsubscribe(&mut self, patterns: &[String]) -> crate::Result<()> {
+        let frame = PUnsubscribe::new(patterns).into_frame();
+        unsubscribe_cmd(
+            &mut self.client,
+            frame,
+            "punsubscribe",
+            patterns,
+            &mut self.subscribed_patterns,
+        )
+        .await
+    }
+}
 
-        // Write the frame to the socket
-        self.client.connection.write_frame(&frame).await?;
-
-        // if the input channel list is empty, server acknowledges as unsubscribing
-        // from all subscribed channels, so we assert that the unsubscribe list received
-        // matches the client subscribed one
-        let num = if channels.is_empty() {
-            self.subscribed_channels.len()
-        } else {
-            channels.len()
-        };
+/// The core `UNSUBSCRIBE` and `PUNSUBSCRIBE` logic. `kind` is the name of the
+/// command sent in `frame`, and `subscribed` the channels or patterns it
+/// unsubscribes from.
+async fn unsubscribe_cmd(
+    client: &mut Client,
+    frame: Frame,
+    kind: &str,
+    channels: &[String],
+    subscribed: &mut Vec<String>,
+) -> crate::Result<()> {
+    debug!(request = ?frame);
 
-        // Read the response
-        for _ in 0..num {
-            let response = self.client.read_response().await?;
+    // Write the frame to the socket
+    client.connection.write_frame(&frame).await?;
 
-            match response {
-                Frame::Array(ref frame) => match frame.as_slice() {
-                    [unsubscribe, channel, ..] if *unsubscribe == "unsubscribe" => {
-                        let len = self.subscribed_channels.len();
+    // if the input channel list is empty, server acknowledges as unsubscribing
+    // from all subscribed channels, so we assert that the unsubscribe list received
+    // matches the client subscribed one
+    let num = if channels.is_empty() {
+        subscribed.len()
+    } else {
+        channels.len()
+    };
 
-                        if len == 0 {
-                            // There must be at least one channel
-                            return Err(response.to_error());
-                        }
+    // Read the response
+    for _ in 0..num {
+        let response = client.read_response().await?;
 
-                        // unsubscribed channel should exist in the subscribed list at this point
-                        self.subscribed_channels.retain(|c| *channel != &c[..]);
+        match response {
+            Frame::Array(ref frame) | Frame::Push(ref frame) => match frame.as_slice() {
+                [unsubscribe, channel, ..] if *unsubscribe == kind => {
+                    let len = subscribed.len();
 
-                        // Only a single channel should be removed from the
-                        // list of subscribed channels.
-                        if self.subscribed_channels.len() != len - 1 {
-                            return Err(response.to_error());
-                        }
+                    if len == 0 {
+                        // There must be at least one channel
+                        return Err(response.to_error());
                     }
-                    _ => return Err(response.to_error()),
-                },
-                frame => return Err(frame.to_error()),
-            };
+
+                    // unsubscribed channel should exist in the subscribed list at this point
+                    subscribed.retain(|c| *channel != &c[..]);
+
+                    // Only a single channel should be removed from the
+                    // list of subscribed channels.
+                    if subscribed.len() != len - 1 {
+                        return Err(response.to_error());
+                    }
+                }
+                _ => return Err(response.to_error()),
+            },
+            frame => return Err(frame.to_error()),
+        };
+    }
+
+    Ok(())
+}
+
+/// Delay before the first attempt to establish a connection of a `Pool` is
+/// retried. The delay doubles after each failed attempt.
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Number of attempts to establish a connection of a `Pool` before giving up.
+const RECONNECT_ATTEMPTS: u32 = 6;
+
+impl Pool {
+    /// Create a pool of up to `size` connections to the Redis server located
+    /// at `addr`.
+    ///
+    /// No connection is established until one is checked out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mini_redis::client::Pool;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let pool = Pool::new("localhost:6379", 8);
+    ///
+    ///     let mut client = pool.get().await.unwrap();
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    /// }
+    /// ```
+    pub fn new(addr: impl ToString, size: usize) -> Pool {
+        Pool::with_options(addr, size, PoolOptions::default())
+    }
+
+    /// Create a pool of up to `size` connections to the Redis server located
+    /// at `addr`, established according to `options`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mini_redis::client::{Pool, PoolOptions};
+    /// use mini_redis::TlsOptions;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let options = PoolOptions {
+    ///         tls: Some(TlsOptions::new("redis.example.com")),
+    ///         password: Some("secret".to_string()),
+    ///         ..PoolOptions::default()
+    ///     };
+    ///     let pool = Pool::with_options("redis.example.com:6379", 8, options);
+    ///
+    ///     let mut client = pool.get().await.unwrap();
+    ///     client.set("foo", "bar".into()).await.unwrap();
+    /// }
+    /// ```
+    pub fn with_options(addr: impl ToString, size: usize, options: PoolOptions) -> Pool {
+        assert!(size > 0, "a pool holds at least one connection");
+
+        Pool {
+            shared: Arc::new(PoolShared {
+                addr: addr.to_string(),
+                options,
+                idle: Mutex::new(Vec::with_capacity(size)),
+                permits: Arc::new(Semaphore::new(size)),
+            }),
         }
+    }
 
-        Ok(())
+    /// Check a connection out of the pool, waiting for one to be returned if
+    /// all of them are checked out.
+    ///
+    /// An idle connection is reused if it still answers `PING`. Otherwise, a
+    /// new connection is established. `Err` is returned if the server cannot
+    /// be reached after several attempts.
+    #[instrument(skip(self))]
+    pub async fn get(&self) -> crate::Result<PooledClient> {
+        // The semaphore is never closed, so `unwrap()` is safe.
+        let permit = self.shared.permits.clone().acquire_owned().await.unwrap();
+
+        let idle = self.shared.idle.lock().unwrap().pop();
+
+        let client = match idle {
+            Some(mut client) => match client.ping(None).await {
+                Ok(_) => client,
+                Err(err) => {
+                    debug!(cause = %err, "dropping broken connection");
+                    self.establish().await?
+                }
+            },
+            None => self.establish().await?,
+        };
+
+        Ok(PooledClient {
+            client: Some(client),
+            shared: self.shared.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Establish a new connection, retrying with an exponential backoff, then
+    /// authenticate it if the pool has a password.
+    async fn establish(&self) -> crate::Result<Client> {
+        let mut backoff = RECONNECT_BACKOFF;
+        let mut attempt = 1;
+
+        let mut client = loop {
+            match self.connect().await {
+                Ok(client) => break client,
+                Err(err) if attempt == RECONNECT_ATTEMPTS => return Err(err),
+                Err(err) => warn!(cause = %err, attempt, "failed to connect, retrying"),
+            }
+
+            time::sleep(backoff).await;
+
+            backoff *= 2;
+            attempt += 1;
+        };
+
+        let options = &self.shared.options;
+
+        // A wrong password is not retried, as it would fail again.
+        if let Some(password) = &options.password {
+            client.auth(options.username.as_deref(), password).await?;
+
+            // Every connection of the pool is authenticated, so this is the
+            // state connections are returned in.
+            client.modified = false;
+        }
+
+        Ok(client)
+    }
+
+    /// Establish a single connection, using the transport of the options.
+    async fn connect(&self) -> crate::Result<Client> {
+        let options = &self.shared.options;
+        let addr = &self.shared.addr[..];
+
+        #[cfg(unix)]
+        if let Some(path) = &options.unixsocket {
+            return connect_unix(path).await;
+        }
+
+        match &options.tls {
+            Some(tls) => connect_tls(addr, tls.clone()).await,
+            None => connect(addr).await,
+        }
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        // The connection may have broken while checked out. This is detected
+        // by the `PING` sent when it is checked out again.
+        if let Some(client) = self.client.take() {
+            // The next task expects a connection in its initial state, with no
+            // reply left to read. Other connections are closed, and replaced
+            // by new ones when needed.
+            if client.in_flight || client.modified {
+                debug!("dropping modified connection");
+                return;
+            }
+
+            self.shared.idle.lock().unwrap().push(client);
+        }
+    }
+}
+
+impl fmt::Debug for PoolOptions {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = fmt.debug_struct("PoolOptions");
+        debug.field("tls", &self.tls);
+        #[cfg(unix)]
+        debug.field("unixsocket", &self.unixsocket);
+        debug
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| REDACTED))
+            .finish()
     }
 }
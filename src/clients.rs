@@ -0,0 +1,219 @@
+//! Registry of the connections to the server, as listed by `CLIENT LIST`.
+//!
+//! Each connection is registered when accepted, under an identifier that is
+//! never reused, and unregistered once its task completes. The registry keeps
+//! the handle of the task, so `CLIENT KILL` can abort it.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// The connected clients, by identifier.
+#[derive(Debug)]
+pub(crate) struct Clients {
+    /// Identifier of the next client to register. Identifiers start at 1.
+    next_id: u64,
+
+    /// The registered clients. A `BTreeMap` lists them in the order they
+    /// connected.
+    clients: BTreeMap<u64, Client>,
+}
+
+/// A connected client.
+#[derive(Debug)]
+struct Client {
+    /// Address of the peer, as `host:port`.
+    addr: String,
+
+    /// Name set with `CLIENT SETNAME`.
+    name: Option<String>,
+
+    /// When the connection was accepted.
+    created_at: Instant,
+
+    /// When the last command was received.
+    active_at: Instant,
+
+    /// Index of the logical database selected by the client.
+    db: usize,
+
+    /// Name of the last command received, lowercase.
+    last_command: String,
+
+    /// The task processing the connection. `None` until the task is spawned.
+    task: Option<JoinHandle<()>>,
+
+    /// Set when the client is killed. If its task is not known yet, the task
+    /// is aborted as soon as it is.
+    killed: bool,
+}
+
+/// Selects the clients to kill, as given to `CLIENT KILL`.
+#[derive(Debug, Default)]
+pub(crate) struct KillFilter {
+    /// Only kill the client with this identifier.
+    pub(crate) id: Option<u64>,
+
+    /// Only kill the client connected from this address.
+    pub(crate) addr: Option<String>,
+
+    /// Never kill this client, which is the one running the command.
+    pub(crate) skip: Option<u64>,
+}
+
+impl Clients {
+    pub(crate) fn new() -> Clients {
+        Clients {
+            next_id: 1,
+            clients: BTreeMap::new(),
+        }
+    }
+
+    /// Register a client connected from `addr`. Returns its identifier.
+    pub(crate) fn register(&mut self, addr: String) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let now = Instant::now();
+        self.clients.insert(
+            id,
+            Client {
+                addr,
+                name: None,
+                created_at: now,
+                active_at: now,
+                db: 0,
+                last_command: "NULL".to_string(),
+                task: None,
+                killed: false,
+            },
+        );
+
+        id
+    }
+
+    /// Set the task processing the connection of the client.
+    ///
+    /// The task is aborted right away if the client was killed meanwhile. The
+    /// task is detached if the client already disconnected.
+    pub(crate) fn set_task(&mut self, id: u64, task: JoinHandle<()>) {
+        match self.clients.get_mut(&id) {
+            Some(client) if client.killed => task.abort(),
+            Some(client) => client.task = Some(task),
+            None => {}
+        }
+    }
+
+    pub(crate) fn unregister(&mut self, id: u64) {
+        self.clients.remove(&id);
+    }
+
+    /// Record that the client received `command` while using the logical
+    /// database `db`.
+    pub(crate) fn record_command(&mut self, id: u64, db: usize, command: &str) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.active_at = Instant::now();
+            client.db = db;
+            client.last_command = command.to_string();
+        }
+    }
+
+    /// Returns the address of the client.
+    pub(crate) fn addr(&self, id: u64) -> Option<String> {
+        self.clients.get(&id).map(|client| client.addr.clone())
+    }
+
+    pub(crate) fn name(&self, id: u64) -> Option<String> {
+        self.clients.get(&id).and_then(|client| client.name.clone())
+    }
+
+    /// Set the name of the client. An empty name removes it.
+    pub(crate) fn set_name(&mut self, id: u64, name: String) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.name = if name.is_empty() { None } else { Some(name) };
+        }
+    }
+
+    /// Kill the clients matching `filter`, aborting their task. Returns the
+    /// number of clients killed.
+    ///
+    /// Killed clients are unregistered once their task drops.
+    pub(crate) fn kill(&mut self, filter: &KillFilter) -> usize {
+        let mut killed = 0;
+
+        for (id, client) in &mut self.clients {
+            let matches = filter.id.map(|wanted| wanted == *id).unwrap_or(true)
+                && filter
+                    .addr
+                    .as_ref()
+                    .map(|wanted| *wanted == client.addr)
+                    .unwrap_or(true)
+                && filter.skip != Some(*id);
+
+            if !matches || client.killed {
+                continue;
+            }
+
+            client.killed = true;
+
+            if let Some(task) = &client.task {
+                task.abort();
+            }
+
+            killed += 1;
+        }
+
+        killed
+    }
+
+    /// Returns the description of every client, one line each, as listed by
+    /// `CLIENT LIST`.
+    pub(crate) fn list(&self) -> String {
+        let now = Instant::now();
+
+        self.clients
+            .iter()
+            .filter(|(_, client)| !client.killed)
+            .map(|(id, client)| {
+                format!(
+                    "{}\n",
+                    ClientLine {
+                        id: *id,
+                        client,
+                        now
+                    }
+                )
+            })
+            .collect()
+    }
+}
+
+/// Formats a client as a line of `CLIENT LIST`.
+struct ClientLine<'a> {
+    id: u64,
+    client: &'a Client,
+    now: Instant,
+}
+
+impl fmt::Display for ClientLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let client = self.client;
+
+        write!(
+            f,
+            "id={} addr={} name={} age={} idle={} db={} cmd={}",
+            self.id,
+            client.addr,
+            client.name.as_deref().unwrap_or(""),
+            self.now
+                .saturating_duration_since(client.created_at)
+                .as_secs(),
+            self.now
+                .saturating_duration_since(client.active_at)
+                .as_secs(),
+            client.db,
+            client.last_command,
+        )
+    }
+}
@@ -0,0 +1,207 @@
+use crate::acl::{self, User, DEFAULT_USER};
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use std::fmt;
+use tracing::{debug, instrument};
+
+/// Manage the users and the commands they may run.
+///
+/// See the `acl` module for the supported rules.
+#[derive(Debug)]
+pub struct Acl {
+    subcommand: Subcommand,
+}
+
+enum Subcommand {
+    /// Create or modify a user.
+    SetUser {
+        username: String,
+        rules: Vec<String>,
+    },
+
+    /// Describe a user.
+    GetUser { username: String },
+
+    /// Remove users.
+    DelUser { usernames: Vec<String> },
+
+    /// Describe every user as the rules recreating it.
+    List,
+
+    /// Return the user the connection is authenticated as.
+    WhoAmI,
+
+    /// A subcommand that is not supported.
+    Unknown(String),
+}
+
+impl Acl {
+    /// Parse an `Acl` instance from a received frame.
+    ///
+    /// The `ACL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `ACL`, the subcommand and its
+    /// arguments.
+    ///
+    /// ```text
+    /// ACL SETUSER username [rule [rule ...]]
+    /// ACL GETUSER username
+    /// ACL DELUSER username [username ...]
+    /// ACL LIST
+    /// ACL WHOAMI
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Acl> {
+        let name = parse.next_string()?;
+
+        let subcommand = match &name.to_lowercase()[..] {
+            "setuser" => Subcommand::SetUser {
+                username: parse.next_string()?,
+                rules: rest(parse)?,
+            },
+            "getuser" => Subcommand::GetUser {
+                username: parse.next_string()?,
+            },
+            "deluser" => {
+                let mut usernames = vec![parse.next_string()?];
+                usernames.extend(rest(parse)?);
+                Subcommand::DelUser { usernames }
+            }
+            "list" => Subcommand::List,
+            "whoami" => Subcommand::WhoAmI,
+            _ => {
+                // The arguments of unknown subcommands are skipped.
+                rest(parse)?;
+                Subcommand::Unknown(name)
+            }
+        };
+
+        Ok(Acl { subcommand })
+    }
+
+    /// Apply the `Acl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::SetUser { username, rules } => match db.acl_set_user(&username, &rules) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(rule) => Frame::Error(format!(
+                    "ERR Error in ACL SETUSER modifier '{}': Syntax error",
+                    rule
+                )),
+            },
+            Subcommand::GetUser { username } => match db.acl_user(&username) {
+                Some(user) => describe(&user),
+                None => Frame::Null,
+            },
+            Subcommand::DelUser { usernames } => {
+                if usernames.iter().any(|username| username == DEFAULT_USER) {
+                    Frame::Error("ERR The 'default' user cannot be removed".to_string())
+                } else {
+                    let removed = usernames
+                        .iter()
+                        .filter(|username| db.acl_del_user(username))
+                        .count();
+                    Frame::Integer(removed as i64)
+                }
+            }
+            Subcommand::List => {
+                let mut response = Frame::array();
+                for (username, user) in db.acl_users() {
+                    let rules = format!("user {} {}", username, user.rules());
+                    response.push_bulk(Bytes::from(rules));
+                }
+                response
+            }
+            Subcommand::WhoAmI => match dst.user() {
+                Some(username) => Frame::Bulk(Bytes::from(username.to_string())),
+                None => Frame::Null,
+            },
+            Subcommand::Unknown(name) => {
+                Frame::Error(format!("ERR unknown subcommand '{}'. Try ACL HELP.", name))
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+// The rules holding passwords are not logged.
+impl fmt::Debug for Subcommand {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Subcommand::SetUser { username, rules } => {
+                let rules: Vec<&str> = rules
+                    .iter()
+                    .map(|rule| match &rule[..] {
+                        rule if acl::is_password_rule(rule) => acl::REDACTED,
+                        rule => rule,
+                    })
+                    .collect();
+
+                fmt.debug_struct("SetUser")
+                    .field("username", username)
+                    .field("rules", &rules)
+                    .finish()
+            }
+            Subcommand::GetUser { username } => fmt
+                .debug_struct("GetUser")
+                .field("username", username)
+                .finish(),
+            Subcommand::DelUser { usernames } => fmt
+                .debug_struct("DelUser")
+                .field("usernames", usernames)
+                .finish(),
+            Subcommand::List => fmt.write_str("List"),
+            Subcommand::WhoAmI => fmt.write_str("WhoAmI"),
+            Subcommand::Unknown(name) => fmt.debug_tuple("Unknown").field(name).finish(),
+        }
+    }
+}
+
+/// Read the remaining arguments.
+fn rest(parse: &mut Parse) -> crate::Result<Vec<String>> {
+    let mut args = vec![];
+
+    loop {
+        match parse.next_string() {
+            Ok(arg) => args.push(arg),
+            Err(ParseError::EndOfStream) => return Ok(args),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Describe `user` as `ACL GETUSER` does.
+fn describe(user: &User) -> Frame {
+    let mut flags = Frame::array();
+    for flag in user.flags() {
+        flags.push_bulk(Bytes::from_static(flag.as_bytes()));
+    }
+
+    let mut passwords = Frame::array();
+    for password in user.passwords() {
+        passwords.push_bulk(Bytes::from(password.to_string()));
+    }
+
+    Frame::Map(vec![
+        (bulk("flags"), flags),
+        (bulk("passwords"), passwords),
+        (bulk("commands"), Frame::Bulk(Bytes::from(user.commands()))),
+        (bulk("keys"), bulk("~*")),
+        (bulk("channels"), bulk("&*")),
+    ])
+}
+
+fn bulk(value: &'static str) -> Frame {
+    Frame::Bulk(Bytes::from_static(value.as_bytes()))
+}
@@ -0,0 +1,125 @@
+use crate::acl::{DEFAULT_USER, REDACTED};
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use std::fmt;
+use tracing::{debug, instrument};
+
+/// Authenticate the connection.
+///
+/// Without a username, the connection authenticates as the `default` user.
+/// Once authenticated, the commands of the connection run with the
+/// permissions of the user.
+pub struct Auth {
+    /// User to authenticate as, the `default` user if `None`.
+    username: Option<String>,
+
+    /// Password of the user.
+    password: String,
+}
+
+impl Auth {
+    /// Create a new `Auth` command authenticating as `username`, or as the
+    /// `default` user if `None`.
+    pub fn new(username: Option<String>, password: impl ToString) -> Auth {
+        Auth {
+            username,
+            password: password.to_string(),
+        }
+    }
+
+    /// Parse an `Auth` instance from a received frame.
+    ///
+    /// The `AUTH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// AUTH [username] password
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Auth> {
+        let first = parse.next_string()?;
+
+        match parse.next_string() {
+            Ok(password) => Ok(Auth::new(Some(first), password)),
+            Err(ParseError::EndOfStream) => Ok(Auth::new(None, first)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Apply the `Auth` command to the connection.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match login(db, dst, self.username, &self.password) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(response) => response,
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Auth` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("auth".as_bytes()));
+        if let Some(username) = self.username {
+            frame.push_bulk(Bytes::from(username.into_bytes()));
+        }
+        frame.push_bulk(Bytes::from(self.password.into_bytes()));
+        frame
+    }
+}
+
+/// Authenticate the connection as `username`, or as the `default` user if
+/// `None`. Returns the error to reply with if the password is refused.
+///
+/// This is shared by `AUTH` and `HELLO ... AUTH`.
+pub(super) fn login(
+    db: &Db,
+    dst: &mut Connection,
+    username: Option<String>,
+    password: &str,
+) -> Result<(), Frame> {
+    match username {
+        // Like Redis, point out a likely configuration mistake.
+        None if db.default_login().is_some() => Err(Frame::Error(
+            "ERR AUTH <password> called without any password configured for the default user. \
+             Are you sure your configuration is correct?"
+                .to_string(),
+        )),
+        username => {
+            let username = username.unwrap_or_else(|| DEFAULT_USER.to_string());
+
+            if db.authenticate(&username, password) {
+                dst.set_user(Some(username));
+                Ok(())
+            } else {
+                Err(Frame::Error(
+                    "WRONGPASS invalid username-password pair or user is disabled.".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+// The password is not logged.
+impl fmt::Debug for Auth {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Auth")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Rewrite the append only file in the background.
+///
+/// The file is replaced with the shortest sequence of commands rebuilding the
+/// current data. The reply is sent right away, before the rewrite completes.
+#[derive(Debug, Default)]
+pub struct BgRewriteAof {}
+
+impl BgRewriteAof {
+    /// Create a new `BgRewriteAof` command.
+    pub fn new() -> BgRewriteAof {
+        BgRewriteAof {}
+    }
+
+    /// Parse a `BgRewriteAof` instance from a received frame.
+    ///
+    /// The `BGREWRITEAOF` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// BGREWRITEAOF
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<BgRewriteAof> {
+        Ok(BgRewriteAof {})
+    }
+
+    /// Apply the `BgRewriteAof` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if db.rewrite_aof() {
+            Frame::Simple("Background append only file rewriting started".to_string())
+        } else {
+            Frame::Error("ERR Append only file is disabled".to_string())
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
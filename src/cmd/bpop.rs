@@ -0,0 +1,163 @@
+use crate::cmd::{Parse, ParseError};
+use crate::db::{timer_deadline, End};
+use crate::{Connection, Db, Frame, Shutdown};
+
+use bytes::Bytes;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Blocking variant of `LPOP` (`BLPOP`) and `RPOP` (`BRPOP`).
+///
+/// Pops an element from the first non-empty list among the given keys. If all
+/// the lists are empty, the connection blocks until another client pushes to
+/// one of them or the timeout elapses.
+///
+/// The response is a two element array holding the key and the popped
+/// element, or nil once the timeout elapses.
+#[derive(Debug)]
+pub struct BPop {
+    /// Keys of the lists to pop from, checked in order.
+    keys: Vec<String>,
+
+    /// How long to block for. `None` blocks indefinitely.
+    timeout: Option<Duration>,
+
+    /// End of the list to pop from.
+    end: End,
+}
+
+impl BPop {
+    /// Parse a `BPop` instance from a received frame.
+    ///
+    /// The `BLPOP` or `BRPOP` string has already been consumed and is
+    /// reflected by `end`.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries. The timeout
+    /// is in seconds and may have a fractional part. A timeout of `0` blocks
+    /// indefinitely.
+    ///
+    /// ```text
+    /// BLPOP key [key ...] timeout
+    /// BRPOP key [key ...] timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, end: End) -> crate::Result<BPop> {
+        let mut keys = vec![parse.next_string()?];
+
+        // The timeout comes last, so it can only be told apart from the keys
+        // once the frame is exhausted.
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let timeout = keys.pop().unwrap();
+
+        if keys.is_empty() {
+            return Err("protocol error; missing timeout".into());
+        }
+
+        let timeout = match timeout.parse::<f64>() {
+            Ok(0.0) => None,
+            // A timeout too large to be represented is rejected when the
+            // command is applied.
+            Ok(secs) if secs > 0.0 && secs.is_finite() => {
+                Some(Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX))
+            }
+            _ => return Err("protocol error; invalid timeout".into()),
+        };
+
+        Ok(BPop { keys, timeout, end })
+    }
+
+    /// Returns the command name.
+    pub(crate) fn get_name(&self) -> &str {
+        match self.end {
+            End::Left => "blpop",
+            End::Right => "brpop",
+        }
+    }
+
+    /// Apply the `BPop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let deadline = match self
+            .timeout
+            .map(|timeout| Instant::now().checked_add(timeout))
+        {
+            Some(None) => {
+                let response = Frame::Error("ERR timeout is out of range".to_string());
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+            deadline => deadline.flatten(),
+        };
+        let waiter = Arc::new(Notify::new());
+
+        let response = loop {
+            // Inside `EXEC`, the transaction already holds the lock exclusively.
+            let in_transaction = dst.is_capturing();
+
+            let guard = match in_transaction {
+                true => None,
+                false => Some(db.lock_shared().await),
+            };
+
+            match db.blocking_pop(&self.keys, self.end, &waiter) {
+                Ok(Some((key, value))) => {
+                    break Frame::Array(vec![Frame::Bulk(Bytes::from(key)), Frame::Bulk(value)])
+                }
+                Ok(None) => {}
+                Err(err) => break Frame::Error(err.to_string()),
+            }
+
+            drop(guard);
+
+            // Blocking would stall the whole server while a transaction runs.
+            // Behave as if the timeout elapsed instead.
+            if in_transaction {
+                break Frame::Null;
+            }
+
+            // Wait for a push to one of the lists, then try again.
+            tokio::select! {
+                _ = waiter.notified() => {}
+                _ = sleep_until(deadline) => break Frame::Null,
+                _ = shutdown.recv() => {
+                    db.unblock(&self.keys, &waiter);
+                    return Ok(());
+                }
+            }
+        };
+
+        db.unblock(&self.keys, &waiter);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Sleep until `deadline`, or forever if there is none.
+pub(super) async fn sleep_until(deadline: Option<Instant>) {
+    match deadline.and_then(timer_deadline) {
+        Some(deadline) => time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,192 @@
+use crate::clients::KillFilter;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspect and manage the connections to the server.
+///
+/// Every connection is registered under an identifier when accepted.
+/// `CLIENT LIST` describes the registered connections, and `CLIENT KILL`
+/// aborts some of them.
+#[derive(Debug)]
+pub struct Client {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    /// Return the identifier of the connection.
+    Id,
+
+    /// Name the connection. An empty name removes it.
+    SetName { name: String },
+
+    /// Return the name of the connection.
+    GetName,
+
+    /// Describe every connection.
+    List,
+
+    /// Kill the connection from `addr`, as done by the old form of
+    /// `CLIENT KILL`.
+    KillAddr { addr: String },
+
+    /// Kill the connections matching the filters. Unless `skipme` is false,
+    /// the connection running the command is never killed.
+    Kill {
+        id: Option<u64>,
+        addr: Option<String>,
+        skipme: bool,
+    },
+
+    /// A subcommand that is not supported.
+    Unknown(String),
+}
+
+impl Client {
+    /// Parse a `Client` instance from a received frame.
+    ///
+    /// The `CLIENT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `CLIENT`, the subcommand and its
+    /// arguments.
+    ///
+    /// ```text
+    /// CLIENT ID
+    /// CLIENT SETNAME name
+    /// CLIENT GETNAME
+    /// CLIENT LIST
+    /// CLIENT KILL addr
+    /// CLIENT KILL [ID id] [ADDR addr] [SKIPME yes|no]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Client> {
+        let name = parse.next_string()?;
+
+        let subcommand = match &name.to_lowercase()[..] {
+            "id" => Subcommand::Id,
+            "setname" => Subcommand::SetName {
+                name: parse.next_string()?,
+            },
+            "getname" => Subcommand::GetName,
+            "list" => Subcommand::List,
+            "kill" if parse.remaining() == 1 => Subcommand::KillAddr {
+                addr: parse.next_string()?,
+            },
+            "kill" => {
+                let mut id = None;
+                let mut addr = None;
+                let mut skipme = true;
+
+                loop {
+                    let filter = match parse.next_string() {
+                        Ok(filter) => filter,
+                        Err(ParseError::EndOfStream) if id.is_some() || addr.is_some() => break,
+                        Err(err) => return Err(err.into()),
+                    };
+
+                    match &filter.to_uppercase()[..] {
+                        "ID" => id = Some(parse.next_int()?),
+                        "ADDR" => addr = Some(parse.next_string()?),
+                        "SKIPME" => {
+                            skipme = match &parse.next_string()?.to_lowercase()[..] {
+                                "yes" => true,
+                                "no" => false,
+                                _ => return Err("protocol error; invalid `SKIPME` value".into()),
+                            }
+                        }
+                        _ => {
+                            return Err(format!(
+                                "protocol error; unknown `CLIENT KILL` filter `{}`",
+                                filter
+                            )
+                            .into())
+                        }
+                    }
+                }
+
+                Subcommand::Kill { id, addr, skipme }
+            }
+            _ => {
+                // The arguments of unknown subcommands are skipped.
+                loop {
+                    match parse.next_string() {
+                        Ok(_) => {}
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Subcommand::Unknown(name)
+            }
+        };
+
+        Ok(Client { subcommand })
+    }
+
+    /// Apply the `Client` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let id = dst.client_id();
+
+        let response = match self.subcommand {
+            Subcommand::Id => Frame::Integer(id as i64),
+            Subcommand::SetName { name } => {
+                // Names are listed by `CLIENT LIST`, separated by spaces.
+                if name.chars().all(|c| ('!'..='~').contains(&c)) {
+                    db.set_client_name(id, name);
+                    Frame::Simple("OK".to_string())
+                } else {
+                    Frame::Error(
+                        "ERR Client names cannot contain spaces, newlines or special characters."
+                            .to_string(),
+                    )
+                }
+            }
+            Subcommand::GetName => match db.client_name(id) {
+                Some(name) => Frame::Bulk(Bytes::from(name)),
+                None => Frame::Null,
+            },
+            Subcommand::List => Frame::Bulk(Bytes::from(db.client_list())),
+            Subcommand::KillAddr { addr } => {
+                let filter = KillFilter {
+                    addr: Some(addr),
+                    ..KillFilter::default()
+                };
+
+                if db.kill_clients(&filter) > 0 {
+                    Frame::Simple("OK".to_string())
+                } else {
+                    Frame::Error("ERR No such client".to_string())
+                }
+            }
+            Subcommand::Kill {
+                id: target,
+                addr,
+                skipme,
+            } => {
+                let filter = KillFilter {
+                    id: target,
+                    addr,
+                    skip: if skipme { Some(id) } else { None },
+                };
+
+                Frame::Integer(db.kill_clients(&filter) as i64)
+            }
+            Subcommand::Unknown(name) => Frame::Error(format!(
+                "ERR unknown subcommand '{}'. Try CLIENT HELP.",
+                name
+            )),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -1,25 +1,122 @@
-use crate::{Connection, Frame, Parse};
-use tracing::instrument;
+use crate::{Connection, Db, Frame, Parse, ParseError};
 
-#[derive(Debug, Default)]
-pub struct Config {}
+use bytes::Bytes;
+use tracing::{debug, instrument};
 
-impl Config {
-    pub fn new() -> Config {
-        Config {}
-    }
+/// Read or change the settings of the server at runtime.
+///
+/// `CONFIG GET` returns the parameters matching any of the given glob-style
+/// patterns, along with their value. `CONFIG SET` changes one or more
+/// parameters at once: if any of them is unknown, immutable or given an
+/// invalid value, none is changed.
+///
+/// See `ServerConfig` for the parameters.
+#[derive(Debug)]
+pub struct Config {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    /// Return the parameters matching any of the patterns.
+    Get { patterns: Vec<String> },
+
+    /// Set parameters to the paired values.
+    Set { parameters: Vec<(String, String)> },
 
-    /// CONFIG GET parameter [parameter ...]
-    /// TODO: This is just a stub implementation
+    /// A subcommand that is not supported.
+    Unknown(String),
+}
+
+impl Config {
+    /// Parse a `Config` instance from a received frame.
+    ///
+    /// The `CONFIG` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `CONFIG`, the subcommand and its
+    /// arguments.
+    ///
+    /// ```text
+    /// CONFIG GET pattern [pattern ...]
+    /// CONFIG SET parameter value [parameter value ...]
+    /// ```
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Config> {
-        while parse.next_string().is_ok() {}
+        let name = parse.next_string()?;
+
+        let subcommand = match &name.to_lowercase()[..] {
+            "get" => Subcommand::Get {
+                patterns: parse.next_strings()?,
+            },
+            "set" => {
+                let mut parameters = vec![];
 
-        Ok(Config {})
+                loop {
+                    parameters.push((parse.next_string()?, parse.next_string()?));
+
+                    if parse.remaining() == 0 {
+                        break;
+                    }
+                }
+
+                Subcommand::Set { parameters }
+            }
+            _ => {
+                // The arguments of unknown subcommands are skipped.
+                loop {
+                    match parse.next_string() {
+                        Ok(_) => {}
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Subcommand::Unknown(name)
+            }
+        };
+
+        Ok(Config { subcommand })
     }
 
-    #[instrument(skip(self, dst))]
-    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
-        dst.write_frame(&Frame::Simple("OK".to_string())).await?;
+    /// Apply the `Config` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Get { patterns } => {
+                let config = db.config();
+                let mut response = Frame::array();
+                let mut seen = vec![];
+
+                // A parameter matching several patterns is only listed once.
+                for pattern in &patterns {
+                    for (name, value) in config.get_parameters(pattern) {
+                        if !seen.contains(&name) {
+                            seen.push(name);
+                            response.push_bulk(Bytes::from_static(name.as_bytes()));
+                            response.push_bulk(Bytes::from(value));
+                        }
+                    }
+                }
+
+                response
+            }
+            Subcommand::Set { parameters } => match db.set_config(&parameters) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err),
+            },
+            Subcommand::Unknown(name) => Frame::Error(format!(
+                "ERR unknown subcommand '{}'. Try CONFIG HELP.",
+                name
+            )),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
         Ok(())
     }
 }
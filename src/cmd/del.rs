@@ -0,0 +1,65 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Remove the specified keys.
+///
+/// Keys that do not exist are ignored. The response is the number of keys
+/// that were removed.
+///
+/// The same command type backs `DEL` and `UNLINK`. Values are always freed
+/// right away, so `UNLINK` behaves exactly like `DEL`.
+#[derive(Debug)]
+pub struct Del {
+    /// Names of the keys to remove.
+    keys: Vec<String>,
+
+    /// Name of the command, either `del` or `unlink`.
+    name: &'static str,
+}
+
+impl Del {
+    /// Parse a `Del` instance from a received frame.
+    ///
+    /// The command name has already been consumed and is passed as `name`, in
+    /// lowercase.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// DEL key [key ...]
+    /// UNLINK key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, name: &str) -> crate::Result<Del> {
+        let keys = parse.next_strings()?;
+
+        let name = match name {
+            "del" => "del",
+            "unlink" => "unlink",
+            _ => return Err(format!("protocol error; unexpected command `{}`", name).into()),
+        };
+
+        Ok(Del { keys, name })
+    }
+
+    /// Apply the `Del` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.del(&self.keys) as i64);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Returns the name of the command, either `del` or `unlink`.
+    pub(crate) fn get_name(&self) -> &'static str {
+        self.name
+    }
+}
@@ -0,0 +1,55 @@
+use crate::cmd::Transaction;
+use crate::{Connection, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Drops all commands queued since `MULTI` and ends the transaction.
+///
+/// Watched keys are forgotten as well.
+#[derive(Debug, Default)]
+pub struct Discard {}
+
+impl Discard {
+    /// Create a new `Discard` command.
+    pub fn new() -> Discard {
+        Discard {}
+    }
+
+    /// Parse a `Discard` instance from a received frame.
+    ///
+    /// The `DISCARD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// DISCARD
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Discard> {
+        Ok(Discard {})
+    }
+
+    /// Apply the `Discard` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst, transaction))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        transaction: &mut Transaction,
+    ) -> crate::Result<()> {
+        let response = match transaction.finish() {
+            Some(_) => Frame::Simple("OK".to_string()),
+            None => Frame::Error("ERR DISCARD without MULTI".to_string()),
+        };
+
+        transaction.unwatch();
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use crate::cmd::{self, Transaction};
+use crate::{Command, Connection, Db, Frame, Parse, Shutdown};
+
+use tracing::{debug, instrument};
+
+/// Executes all commands queued since `MULTI`.
+///
+/// The commands are executed atomically: no command from another connection
+/// runs in between. The response is an array holding the reply of each
+/// command.
+///
+/// If a key watched with `WATCH` was modified before `EXEC`, the transaction
+/// is not executed and the response is nil.
+#[derive(Debug, Default)]
+pub struct Exec {}
+
+impl Exec {
+    /// Create a new `Exec` command.
+    pub fn new() -> Exec {
+        Exec {}
+    }
+
+    /// Parse an `Exec` instance from a received frame.
+    ///
+    /// The `EXEC` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// EXEC
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Exec> {
+        Ok(Exec {})
+    }
+
+    /// Apply the `Exec` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown, transaction))]
+    pub(crate) async fn apply(
+        self,
+        db: &mut Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+        transaction: &mut Transaction,
+    ) -> crate::Result<()> {
+        let mut write = false;
+
+        let mut response = match transaction.finish() {
+            None => Frame::Error("ERR EXEC without MULTI".to_string()),
+            Some((_, true)) => Frame::Error(
+                "EXECABORT Transaction discarded because of previous errors.".to_string(),
+            ),
+            Some((commands, false)) => {
+                write = commands.iter().any(Command::is_write);
+
+                // Wait for in-flight commands of other connections to
+                // complete, and keep new ones from starting.
+                let _guard = db.lock_exclusive().await;
+
+                if transaction.is_dirty() {
+                    Frame::Null
+                } else {
+                    // Each command writes its reply to `dst`. Collect them in
+                    // order to send a single array.
+                    dst.begin_capture();
+
+                    for cmd in commands {
+                        if let Err(err) = cmd.execute(db, dst, shutdown).await {
+                            dst.end_capture();
+                            return Err(err);
+                        }
+                    }
+
+                    Frame::Array(dst.end_capture())
+                }
+            }
+        };
+
+        transaction.unwatch();
+
+        if write {
+            if let Some(error) = cmd::wait_for_aof(db).await {
+                response = error;
+            }
+        }
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the number of the specified keys that exist.
+///
+/// A key given several times is counted as many times.
+#[derive(Debug)]
+pub struct Exists {
+    /// Names of the keys to check.
+    keys: Vec<String>,
+}
+
+impl Exists {
+    /// Parse an `Exists` instance from a received frame.
+    ///
+    /// The `EXISTS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// EXISTS key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Exists> {
+        let keys = parse.next_strings()?;
+
+        Ok(Exists { keys })
+    }
+
+    /// Apply the `Exists` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.exists(&self.keys) as i64);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::db::{expiration_after, expiration_at};
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Set a timeout on a key.
+///
+/// After the timeout has expired, the key is deleted. A timeout that is not in
+/// the future deletes the key immediately. The response is `1` if the timeout
+/// was set and `0` if the key does not exist.
+///
+/// The same command type backs `EXPIRE`, `PEXPIRE`, `EXPIREAT` and
+/// `PEXPIREAT`. They differ in the unit of the argument and in whether it is
+/// relative to now or an absolute Unix timestamp.
+#[derive(Debug)]
+pub struct Expire {
+    /// Name of the key to set the timeout on.
+    key: String,
+
+    /// When the key expires.
+    when: When,
+
+    /// Name of the command, such as `expire` or `pexpireat`.
+    name: &'static str,
+}
+
+#[derive(Debug)]
+enum When {
+    /// Milliseconds from now. May be negative.
+    After(i64),
+
+    /// Unix time, in milliseconds. May be in the past.
+    At(i64),
+
+    /// Seconds too large to be represented in milliseconds.
+    Overflow,
+}
+
+impl Expire {
+    /// Parse an `Expire` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The command name has already been consumed and is passed as `name`, in
+    /// lowercase.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Expire` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// EXPIRE key seconds
+    /// PEXPIRE key milliseconds
+    /// EXPIREAT key unix-time-seconds
+    /// PEXPIREAT key unix-time-milliseconds
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, name: &str) -> crate::Result<Expire> {
+        let key = parse.next_string()?;
+        let value = parse.next_signed_int()?;
+
+        // Seconds are converted to milliseconds. A value too large to be
+        // represented is rejected when the command is applied.
+        let millis = |secs: i64, when: fn(i64) -> When| match secs.checked_mul(1000) {
+            Some(millis) => when(millis),
+            None => When::Overflow,
+        };
+
+        let (when, name) = match name {
+            "expire" => (millis(value, When::After), "expire"),
+            "pexpire" => (When::After(value), "pexpire"),
+            "expireat" => (millis(value, When::At), "expireat"),
+            "pexpireat" => (When::At(value), "pexpireat"),
+            _ => return Err(format!("protocol error; unexpected command `{}`", name).into()),
+        };
+
+        Ok(Expire { key, when, name })
+    }
+
+    /// Apply the `Expire` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Expirations are tracked with the monotonic clock. Absolute
+        // timestamps are converted using the system clock.
+        let when = match self.when {
+            When::After(millis) => expiration_after(millis),
+            When::At(timestamp) => expiration_at(timestamp),
+            When::Overflow => None,
+        };
+
+        let response = match when {
+            Some(when) => Frame::Integer(db.expire(&self.key, when) as i64),
+            None => Frame::Error(format!("ERR invalid expire time in '{}'", self.name)),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Returns the name of the command, such as `expire` or `pexpireat`.
+    pub(crate) fn get_name(&self) -> &'static str {
+        self.name
+    }
+}
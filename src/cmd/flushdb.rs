@@ -0,0 +1,44 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Delete all the keys of the currently selected database.
+#[derive(Debug, Default)]
+pub struct FlushDb {}
+
+impl FlushDb {
+    /// Create a new `FlushDb` command.
+    pub fn new() -> FlushDb {
+        FlushDb {}
+    }
+
+    /// Parse a `FlushDb` instance from a received frame.
+    ///
+    /// The `FLUSHDB` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// FLUSHDB
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<FlushDb> {
+        Ok(FlushDb {})
+    }
+
+    /// Apply the `FlushDb` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.flush();
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Remove fields from the hash stored at key.
+///
+/// Fields that do not exist are ignored. Once the last field is removed, the
+/// key is deleted. The response is the number of fields that were removed.
+#[derive(Debug)]
+pub struct HDel {
+    /// Name of the key holding the hash.
+    key: String,
+
+    /// Fields to remove.
+    fields: Vec<String>,
+}
+
+impl HDel {
+    /// Parse a `HDel` instance from a received frame.
+    ///
+    /// The `HDEL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// HDEL key field [field ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HDel> {
+        let key = parse.next_string()?;
+        let mut fields = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(field) => fields.push(field),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HDel { key, fields })
+    }
+
+    /// Apply the `HDel` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hdel(&self.key, &self.fields) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use crate::acl::REDACTED;
+use crate::cmd::auth;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::fmt;
+use tracing::{debug, instrument};
+
+/// Switch the connection's protocol version and return server information.
+///
+/// Without an argument, the protocol is left unchanged. The reply is a map
+/// describing the server. It is sent as a RESP3 map or, when the connection
+/// uses RESP2, as a flat array.
+///
+/// With the `AUTH` option, the connection also authenticates, as with the
+/// `AUTH` command. Connections that are not authenticated yet may only send
+/// `HELLO` with this option.
+#[derive(Default)]
+pub struct Hello {
+    /// Requested protocol version.
+    protover: Option<u64>,
+
+    /// Username and password to authenticate with.
+    auth: Option<(String, String)>,
+}
+
+impl Hello {
+    /// Create a new `Hello` command requesting the protocol version
+    /// `protover`.
+    pub fn new(protover: Option<u64>) -> Hello {
+        Hello {
+            protover,
+            auth: None,
+        }
+    }
+
+    /// Parse a `Hello` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HELLO` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Hello` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `HELLO`, an optional protocol
+    /// version and its options.
+    ///
+    /// ```text
+    /// HELLO [protover [AUTH username password]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hello> {
+        let mut hello = match parse.next_int() {
+            Ok(protover) => Hello::new(Some(protover)),
+            Err(ParseError::EndOfStream) => return Ok(Hello::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        loop {
+            match parse.next_string() {
+                Ok(option) if option.eq_ignore_ascii_case("auth") => {
+                    let username = parse.next_string()?;
+                    let password = parse.next_string()?;
+                    hello.auth = Some((username, password));
+                }
+                Ok(option) => {
+                    return Err(
+                        format!("protocol error; unknown `HELLO` option `{}`", option).into(),
+                    )
+                }
+                Err(ParseError::EndOfStream) => return Ok(hello),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Apply the `Hello` command to the connection.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // As in Redis, the protocol is checked first and is only switched
+        // once the connection is authenticated.
+        let error = match (self.protover, self.auth) {
+            (Some(protover), _) if protover != 2 && protover != 3 => Some(Frame::Error(
+                "NOPROTO unsupported protocol version".to_string(),
+            )),
+            (_, Some((username, password))) => {
+                auth::login(db, dst, Some(username), &password).err()
+            }
+            (_, None) if dst.user().is_none() => Some(Frame::Error(
+                "NOAUTH HELLO must be called with the client already authenticated, otherwise \
+                 the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the \
+                 client and select the RESP protocol version at the same time"
+                    .to_string(),
+            )),
+            (_, None) => None,
+        };
+
+        if let Some(response) = error {
+            debug!(?response);
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        if let Some(protover) = self.protover {
+            dst.set_protocol(protover as u8);
+        }
+
+        let role = match db.primary() {
+            Some(_) => "replica",
+            None => "master",
+        };
+
+        // The reply is written using the newly negotiated protocol.
+        let response = Frame::Map(vec![
+            (bulk("server"), bulk("redis")),
+            (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+            (bulk("proto"), Frame::Integer(dst.protocol() as i64)),
+            (bulk("mode"), bulk("standalone")),
+            (bulk("role"), bulk(role)),
+            (bulk("modules"), Frame::array()),
+        ]);
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Hello` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hello".as_bytes()));
+        if let Some(protover) = self.protover {
+            frame.push_int(protover as i64);
+        }
+        if let Some((username, password)) = self.auth {
+            frame.push_bulk(Bytes::from("auth".as_bytes()));
+            frame.push_bulk(Bytes::from(username.into_bytes()));
+            frame.push_bulk(Bytes::from(password.into_bytes()));
+        }
+        frame
+    }
+}
+
+// The password is not logged.
+impl fmt::Debug for Hello {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let auth = self.auth.as_ref().map(|(username, _)| (username, REDACTED));
+
+        fmt.debug_struct("Hello")
+            .field("protover", &self.protover)
+            .field("auth", &auth)
+            .finish()
+    }
+}
+
+fn bulk(value: &'static str) -> Frame {
+    Frame::Bulk(Bytes::from_static(value.as_bytes()))
+}
@@ -0,0 +1,52 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns if field is an existing field of the hash stored at key.
+///
+/// The response is `1` if the field exists and `0` otherwise.
+#[derive(Debug)]
+pub struct HExists {
+    /// Name of the key holding the hash.
+    key: String,
+
+    /// Field to look up.
+    field: String,
+}
+
+impl HExists {
+    /// Parse a `HExists` instance from a received frame.
+    ///
+    /// The `HEXISTS` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// HEXISTS key field
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HExists> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+
+        Ok(HExists { key, field })
+    }
+
+    /// Apply the `HExists` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hexists(&self.key, &self.field) {
+            Ok(exists) => Frame::Integer(exists as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Get the value of a field of the hash stored at key.
+///
+/// If the key or the field does not exist, the special value nil is returned.
+#[derive(Debug)]
+pub struct HGet {
+    /// Name of the key holding the hash.
+    key: String,
+
+    /// Field to get.
+    field: String,
+}
+
+impl HGet {
+    /// Parse a `HGet` instance from a received frame.
+    ///
+    /// The `HGET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// HGET key field
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGet> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+
+        Ok(HGet { key, field })
+    }
+
+    /// Apply the `HGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hget(&self.key, &self.field) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns all fields and values of the hash stored at key.
+///
+/// The response is a map of fields to values. RESP2 clients receive it as an
+/// array alternating between fields and values. A missing key is an empty
+/// hash.
+#[derive(Debug)]
+pub struct HGetAll {
+    /// Name of the key holding the hash.
+    key: String,
+}
+
+impl HGetAll {
+    /// Parse a `HGetAll` instance from a received frame.
+    ///
+    /// The `HGETALL` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HGETALL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HGetAll> {
+        let key = parse.next_string()?;
+
+        Ok(HGetAll { key })
+    }
+
+    /// Apply the `HGetAll` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hgetall(&self.key) {
+            Ok(fields) => Frame::Map(
+                fields
+                    .into_iter()
+                    .map(|(field, value)| (Frame::Bulk(Bytes::from(field)), Frame::Bulk(value)))
+                    .collect(),
+            ),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
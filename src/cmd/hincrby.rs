@@ -0,0 +1,61 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Increment the number stored at field in the hash stored at key.
+///
+/// A missing key or field is treated as `0`. The response is the value after
+/// the increment. The increment may be negative.
+#[derive(Debug)]
+pub struct HIncrBy {
+    /// Name of the key holding the hash.
+    key: String,
+
+    /// Field to increment.
+    field: String,
+
+    /// Amount to add.
+    increment: i64,
+}
+
+impl HIncrBy {
+    /// Parse a `HIncrBy` instance from a received frame.
+    ///
+    /// The `HINCRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// HINCRBY key field increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HIncrBy> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+        let increment = parse.next_signed_int()?;
+
+        Ok(HIncrBy {
+            key,
+            field,
+            increment,
+        })
+    }
+
+    /// Apply the `HIncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hincrby(&self.key, &self.field, self.increment) {
+            Ok(value) => Frame::Integer(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the number of fields contained in the hash stored at key.
+///
+/// A missing key is an empty hash.
+#[derive(Debug)]
+pub struct HLen {
+    /// Name of the key holding the hash.
+    key: String,
+}
+
+impl HLen {
+    /// Parse a `HLen` instance from a received frame.
+    ///
+    /// The `HLEN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// HLEN key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HLen> {
+        let key = parse.next_string()?;
+
+        Ok(HLen { key })
+    }
+
+    /// Apply the `HLen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hlen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
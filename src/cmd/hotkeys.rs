@@ -0,0 +1,89 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the most frequently accessed keys.
+///
+/// Key accesses are sampled into an approximate counter, so the reported
+/// frequencies are estimates. The response is an array alternating between
+/// key names and their estimated accesses per second, hottest key first.
+#[derive(Debug)]
+pub struct HotKeys {
+    /// Maximum number of keys to report.
+    count: usize,
+}
+
+/// Number of keys reported when `COUNT` is not specified.
+pub(super) const DEFAULT_COUNT: usize = 10;
+
+impl HotKeys {
+    /// Create a new `HotKeys` command reporting at most `count` keys.
+    pub fn new(count: usize) -> HotKeys {
+        HotKeys { count }
+    }
+
+    /// Parse a `HotKeys` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `HOTKEYS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `HotKeys` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `HOTKEYS` and an optional count.
+    ///
+    /// ```text
+    /// HOTKEYS [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HotKeys> {
+        match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "COUNT" => {
+                let count = parse.next_int()? as usize;
+                Ok(HotKeys::new(count))
+            }
+            Ok(_) => Err("currently `HOTKEYS` only supports the count option".into()),
+            Err(ParseError::EndOfStream) => Ok(HotKeys::new(DEFAULT_COUNT)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Apply the `HotKeys` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+
+        for (key, ops_per_sec) in db.hot_keys(self.count) {
+            response.push_bulk(Bytes::from(key));
+            response.push_bulk(Bytes::from(format!("{:.2}", ops_per_sec)));
+        }
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `HotKeys` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("hotkeys".as_bytes()));
+        frame.push_bulk(Bytes::from("count".as_bytes()));
+        frame.push_int(self.count as i64);
+        frame
+    }
+}
@@ -0,0 +1,74 @@
+use crate::cmd::scan::{page_frame, ScanOptions};
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Iterate over the fields of the hash stored at key.
+///
+/// Follows the same cursor semantics as `SCAN`. The items of each page
+/// alternate between fields and their value. A missing key is an empty hash.
+#[derive(Debug)]
+pub struct HScan {
+    /// Name of the key holding the hash.
+    key: String,
+
+    /// Position to resume the iteration from.
+    cursor: u64,
+
+    /// Only return the fields matching the pattern.
+    options: ScanOptions,
+}
+
+impl HScan {
+    /// Parse a `HScan` instance from a received frame.
+    ///
+    /// The `HSCAN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// HSCAN key cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HScan> {
+        let key = parse.next_string()?;
+        let cursor = parse.next_int()?;
+        let options = ScanOptions::parse(parse, |_, _| Ok(false))?;
+
+        Ok(HScan {
+            key,
+            cursor,
+            options,
+        })
+    }
+
+    /// Apply the `HScan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hscan(&self.key, self.cursor, self.options.count) {
+            Ok((cursor, fields)) => {
+                let mut items = vec![];
+
+                for (field, value) in fields {
+                    if self.options.matches(&field) {
+                        items.push(Frame::Bulk(Bytes::from(field)));
+                        items.push(Frame::Bulk(value));
+                    }
+                }
+
+                page_frame(cursor, items)
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Set fields of the hash stored at key.
+///
+/// If the key does not exist, a new hash is created. Fields that already exist
+/// in the hash are overwritten. The response is the number of fields that were
+/// added.
+#[derive(Debug)]
+pub struct HSet {
+    /// Name of the key holding the hash.
+    key: String,
+
+    /// Fields to set, along with their values.
+    fields: Vec<(String, Bytes)>,
+}
+
+impl HSet {
+    /// Parse a `HSet` instance from a received frame.
+    ///
+    /// The `HSET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// HSET key field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<HSet> {
+        let key = parse.next_string()?;
+        let mut fields = vec![(parse.next_string()?, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_string() {
+                Ok(field) => fields.push((field, parse.next_bytes()?)),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(HSet { key, fields })
+    }
+
+    /// Apply the `HSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.hset(&self.key, self.fields) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Increment the integer stored at key.
+///
+/// A missing key is treated as `0`. The response is the value after the
+/// increment. The expiration of the key, if any, is kept.
+///
+/// The same command type backs `INCR`, `DECR`, `INCRBY` and `DECRBY`. They
+/// differ in whether the amount is given and in its sign.
+#[derive(Debug)]
+pub struct IncrBy {
+    /// Name of the key holding the integer.
+    key: String,
+
+    /// Amount to add. Negative for `DECR` and `DECRBY`.
+    increment: i64,
+
+    /// Name of the command, in lowercase.
+    name: &'static str,
+}
+
+impl IncrBy {
+    /// Parse an `IncrBy` instance from a received frame.
+    ///
+    /// The command name has already been consumed and is passed as `name`, in
+    /// lowercase.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// INCR key
+    /// DECR key
+    /// INCRBY key increment
+    /// DECRBY key decrement
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, name: &str) -> crate::Result<IncrBy> {
+        let key = parse.next_string()?;
+
+        let (name, increment) = match name {
+            "incr" => ("incr", 1),
+            "decr" => ("decr", -1),
+            "incrby" => ("incrby", parse.next_signed_int()?),
+            // `i64::MIN` cannot be negated.
+            "decrby" => match parse.next_signed_int()?.checked_neg() {
+                Some(increment) => ("decrby", increment),
+                None => return Err("protocol error; invalid decrement".into()),
+            },
+            _ => return Err(format!("protocol error; unexpected command `{}`", name).into()),
+        };
+
+        Ok(IncrBy {
+            key,
+            increment,
+            name,
+        })
+    }
+
+    /// Returns the command name.
+    pub(crate) fn get_name(&self) -> &str {
+        self.name
+    }
+
+    /// Apply the `IncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.incr_by(&self.key, self.increment) {
+            Ok(value) => Frame::Integer(value),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Increment the floating point number stored at key.
+///
+/// A missing key is treated as `0`. The response is the value after the
+/// increment, as a bulk string. The increment may be negative. The expiration
+/// of the key, if any, is kept.
+#[derive(Debug)]
+pub struct IncrByFloat {
+    /// Name of the key holding the number.
+    key: String,
+
+    /// Amount to add.
+    increment: f64,
+}
+
+impl IncrByFloat {
+    /// Parse an `IncrByFloat` instance from a received frame.
+    ///
+    /// The `INCRBYFLOAT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// INCRBYFLOAT key increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<IncrByFloat> {
+        let key = parse.next_string()?;
+        let increment = parse.next_float()?;
+
+        Ok(IncrByFloat { key, increment })
+    }
+
+    /// Apply the `IncrByFloat` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.incr_by_float(&self.key, self.increment) {
+            Ok(value) => Frame::Bulk(Bytes::from(value.to_string())),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use crate::cmd::hotkeys::DEFAULT_COUNT;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use std::fmt::{self, Write as _};
+use tracing::{debug, instrument};
+
+/// Returns information and statistics about the server.
+///
+/// The response is a bulk string made of sections, each starting with a
+/// `# Name` header followed by `field:value` lines. Sections not requested are
+/// omitted, as are unknown ones.
+#[derive(Debug)]
+pub struct Info {
+    /// Names of the requested sections, lowercase. Empty to request the
+    /// default sections.
+    sections: Vec<String>,
+}
+
+/// Sections reported by `INFO`, in order.
+const SECTIONS: &[&str] = &[
+    "server",
+    "clients",
+    "memory",
+    "persistence",
+    "stats",
+    "replication",
+    "keyspace",
+    "hotkeys",
+];
+
+impl Info {
+    /// Parse an `Info` instance from a received frame.
+    ///
+    /// The `INFO` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `INFO` and optional section names.
+    /// `default`, `all` and `everything` request every section.
+    ///
+    /// ```text
+    /// INFO [section [section ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Info> {
+        let sections = match parse.next_strings() {
+            Ok(sections) => sections,
+            Err(ParseError::EndOfStream) => vec![],
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Info {
+            sections: sections.iter().map(|name| name.to_lowercase()).collect(),
+        })
+    }
+
+    /// Apply the `Info` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let mut info = String::new();
+
+        for &section in SECTIONS {
+            if !self.includes(section) {
+                continue;
+            }
+
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+
+            write_section(&mut info, section, db);
+        }
+
+        let response = Frame::Bulk(Bytes::from(info));
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Returns `true` if `section` was requested.
+    fn includes(&self, section: &str) -> bool {
+        self.sections.is_empty()
+            || self.sections.iter().any(|name| {
+                name == section || name == "default" || name == "all" || name == "everything"
+            })
+    }
+}
+
+/// Append `section`, along with its header, to `info`.
+fn write_section(info: &mut String, section: &str, db: &Db) {
+    let stats = db.stats();
+
+    let (header, fields) = match section {
+        "server" => {
+            let uptime = stats.uptime().as_secs();
+
+            (
+                "Server",
+                vec![
+                    field("redis_version", env!("CARGO_PKG_VERSION")),
+                    field("process_id", std::process::id()),
+                    field("uptime_in_seconds", uptime),
+                    field("uptime_in_days", uptime / 86400),
+                ],
+            )
+        }
+        "clients" => (
+            "Clients",
+            vec![field("connected_clients", stats.connected_clients())],
+        ),
+        "memory" => {
+            let config = db.config();
+
+            (
+                "Memory",
+                vec![
+                    field("used_memory", db.used_memory()),
+                    field("maxmemory", config.maxmemory),
+                    field("maxmemory_policy", config.maxmemory_policy),
+                ],
+            )
+        }
+        "persistence" => {
+            let status = if db.aof_write_ok() { "ok" } else { "err" };
+
+            (
+                "Persistence",
+                vec![
+                    field("aof_enabled", db.config().appendonly as u8),
+                    field("aof_last_write_status", status),
+                ],
+            )
+        }
+        "stats" => (
+            "Stats",
+            stats
+                .counters()
+                .iter()
+                .map(|(name, value)| field(name, value))
+                .collect(),
+        ),
+        "replication" => {
+            let mut fields = vec![];
+
+            match db.primary() {
+                Some(addr) => {
+                    let (host, port) = match addr.rfind(':') {
+                        Some(at) => (&addr[..at], &addr[at + 1..]),
+                        None => (&addr[..], ""),
+                    };
+
+                    fields.push(field("role", "slave"));
+                    fields.push(field("master_host", host));
+                    fields.push(field("master_port", port));
+                }
+                None => fields.push(field("role", "master")),
+            }
+
+            fields.push(field("master_replid", db.replid()));
+            ("Replication", fields)
+        }
+        "keyspace" => (
+            "Keyspace",
+            // Only databases holding keys are listed.
+            db.key_counts()
+                .into_iter()
+                .enumerate()
+                .filter(|(_, (keys, _))| *keys > 0)
+                .map(|(index, (keys, expires))| {
+                    let value = format!("keys={},expires={}", keys, expires);
+                    field(&format!("db{}", index), value)
+                })
+                .collect(),
+        ),
+        _ => (
+            "Hotkeys",
+            // The same keys as reported by `HOTKEYS`, hottest first.
+            db.hot_keys(DEFAULT_COUNT)
+                .into_iter()
+                .enumerate()
+                .map(|(rank, (key, ops_per_sec))| {
+                    let value = format!("key={},ops_per_sec={:.2}", key, ops_per_sec);
+                    field(&format!("hotkey{}", rank), value)
+                })
+                .collect(),
+        ),
+    };
+
+    // Writing to a `String` never fails.
+    let _ = write!(info, "# {}\r\n", header);
+
+    for (name, value) in fields {
+        let _ = write!(info, "{}:{}\r\n", name, value);
+    }
+}
+
+fn field(name: &str, value: impl fmt::Display) -> (String, String) {
+    (name.to_string(), value.to_string())
+}
@@ -0,0 +1,48 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the length of the list stored at key.
+///
+/// A missing key is an empty list.
+#[derive(Debug)]
+pub struct LLen {
+    /// Name of the key holding the list.
+    key: String,
+}
+
+impl LLen {
+    /// Parse a `LLen` instance from a received frame.
+    ///
+    /// The `LLEN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// LLEN key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LLen> {
+        let key = parse.next_string()?;
+
+        Ok(LLen { key })
+    }
+
+    /// Apply the `LLen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.llen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the specified elements of the list stored at key.
+///
+/// `start` and `stop` are zero-based offsets, both inclusive. Negative offsets
+/// count from the end of the list: `-1` is the last element. Out of range
+/// offsets are clamped to the list.
+#[derive(Debug)]
+pub struct LRange {
+    /// Name of the key holding the list.
+    key: String,
+
+    /// Offset of the first element to return.
+    start: i64,
+
+    /// Offset of the last element to return.
+    stop: i64,
+}
+
+impl LRange {
+    /// Parse a `LRange` instance from a received frame.
+    ///
+    /// The `LRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// LRANGE key start stop
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<LRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_signed_int()?;
+        let stop = parse.next_signed_int()?;
+
+        Ok(LRange { key, start, stop })
+    }
+
+    /// Apply the `LRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.lrange(&self.key, self.start, self.stop) {
+            Ok(values) => Frame::Array(values.into_iter().map(Frame::Bulk).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
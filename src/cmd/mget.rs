@@ -0,0 +1,54 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Get the values of all the specified keys.
+///
+/// The response is an array with one entry per key. Keys that do not exist or
+/// hold a value that is not a string are returned as nil, so the command never
+/// fails.
+#[derive(Debug)]
+pub struct MGet {
+    /// Names of the keys to get.
+    keys: Vec<String>,
+}
+
+impl MGet {
+    /// Parse a `MGet` instance from a received frame.
+    ///
+    /// The `MGET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// MGET key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MGet> {
+        let keys = parse.next_strings()?;
+
+        Ok(MGet { keys })
+    }
+
+    /// Apply the `MGet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let values = db.mget(&self.keys);
+
+        let response = Frame::Array(
+            values
+                .into_iter()
+                .map(|value| value.map(Frame::Bulk).unwrap_or(Frame::Null))
+                .collect(),
+        );
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ mod set;
 pub use set::Set;
 
 mod subscribe;
-pub use subscribe::{Subscribe, Unsubscribe};
+pub use subscribe::{PSubscribe, PUnsubscribe, Subscribe, Unsubscribe};
 
 mod ping;
 pub use ping::Ping;
@@ -16,11 +16,183 @@ pub use ping::Ping;
 mod config;
 pub use config::Config;
 
+mod hotkeys;
+pub use hotkeys::HotKeys;
+
+mod info;
+pub use info::Info;
+
+mod client;
+pub use client::Client;
+
+mod monitor;
+pub use monitor::Monitor;
+
+mod slowlog;
+pub use slowlog::SlowLog;
+
+mod hello;
+pub use hello::Hello;
+
+mod select;
+pub use select::Select;
+
+mod flushdb;
+pub use flushdb::FlushDb;
+
+mod swapdb;
+pub use swapdb::SwapDb;
+
+mod mget;
+pub use mget::MGet;
+
+mod mset;
+pub use mset::MSet;
+
+mod del;
+pub use del::Del;
+
+mod exists;
+pub use exists::Exists;
+
+mod expire;
+pub use expire::Expire;
+
+mod ttl;
+pub use ttl::Ttl;
+
+mod persist;
+pub use persist::Persist;
+
+mod object;
+pub use object::Object;
+
+mod incrby;
+pub use incrby::IncrBy;
+
+mod incrbyfloat;
+pub use incrbyfloat::IncrByFloat;
+
+mod hset;
+pub use hset::HSet;
+
+mod hget;
+pub use hget::HGet;
+
+mod hdel;
+pub use hdel::HDel;
+
+mod hgetall;
+pub use hgetall::HGetAll;
+
+mod hexists;
+pub use hexists::HExists;
+
+mod hlen;
+pub use hlen::HLen;
+
+mod hincrby;
+pub use hincrby::HIncrBy;
+
+mod push;
+pub use push::Push;
+
+mod pop;
+pub use pop::Pop;
+
+mod lrange;
+pub use lrange::LRange;
+
+mod llen;
+pub use llen::LLen;
+
+mod bpop;
+pub use bpop::BPop;
+
+mod zadd;
+pub use zadd::ZAdd;
+
+mod zrem;
+pub use zrem::ZRem;
+
+mod zscore;
+pub use zscore::ZScore;
+
+mod zincrby;
+pub use zincrby::ZIncrBy;
+
+mod zrange;
+pub use zrange::ZRange;
+
+mod zrangebyscore;
+pub use zrangebyscore::ZRangeByScore;
+
+mod zcard;
+pub use zcard::ZCard;
+
+mod xadd;
+pub use xadd::XAdd;
+
+mod xlen;
+pub use xlen::XLen;
+
+mod xrange;
+pub use xrange::XRange;
+
+mod xread;
+pub use xread::XRead;
+
+mod scan;
+pub use scan::Scan;
+
+mod hscan;
+pub use hscan::HScan;
+
+mod zscan;
+pub use zscan::ZScan;
+
+mod multi;
+pub use multi::Multi;
+pub(crate) use multi::Transaction;
+
+mod exec;
+pub use exec::Exec;
+
+mod discard;
+pub use discard::Discard;
+
+mod watch;
+pub use watch::{Unwatch, Watch};
+
+mod bgrewriteaof;
+pub use bgrewriteaof::BgRewriteAof;
+
+mod save;
+pub use save::{BgSave, Save};
+
+mod shutdown;
+pub use shutdown::ShutdownServer;
+
+mod psync;
+pub use psync::PSync;
+
+mod replicaof;
+pub use replicaof::ReplicaOf;
+
+mod auth;
+pub use auth::Auth;
+
+mod acl;
+pub use acl::Acl;
+
 mod unknown;
 pub use unknown::Unknown;
 
+use crate::db::End;
 use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
 
+use tracing::debug;
+
 /// Enumeration of supported Redis commands.
 ///
 /// Methods called on `Command` are delegated to the command implementation.
@@ -31,9 +203,69 @@ pub enum Command {
     Set(Set),
     Subscribe(Subscribe),
     Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
     Ping(Ping),
     Unknown(Unknown),
     Config(Config),
+    HotKeys(HotKeys),
+    Info(Info),
+    Client(Client),
+    Monitor(Monitor),
+    SlowLog(SlowLog),
+    Hello(Hello),
+    Select(Select),
+    FlushDb(FlushDb),
+    SwapDb(SwapDb),
+    MGet(MGet),
+    MSet(MSet),
+    Del(Del),
+    Exists(Exists),
+    Expire(Expire),
+    Ttl(Ttl),
+    Persist(Persist),
+    Object(Object),
+    IncrBy(IncrBy),
+    IncrByFloat(IncrByFloat),
+    HSet(HSet),
+    HGet(HGet),
+    HDel(HDel),
+    HGetAll(HGetAll),
+    HExists(HExists),
+    HLen(HLen),
+    HIncrBy(HIncrBy),
+    Push(Push),
+    Pop(Pop),
+    LRange(LRange),
+    LLen(LLen),
+    BPop(BPop),
+    ZAdd(ZAdd),
+    ZRem(ZRem),
+    ZScore(ZScore),
+    ZIncrBy(ZIncrBy),
+    ZRange(ZRange),
+    ZRangeByScore(ZRangeByScore),
+    ZCard(ZCard),
+    XAdd(XAdd),
+    XLen(XLen),
+    XRange(XRange),
+    XRead(XRead),
+    Scan(Scan),
+    HScan(HScan),
+    ZScan(ZScan),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    BgRewriteAof(BgRewriteAof),
+    Save(Save),
+    BgSave(BgSave),
+    Shutdown(ShutdownServer),
+    PSync(PSync),
+    ReplicaOf(ReplicaOf),
+    Auth(Auth),
+    Acl(Acl),
 }
 
 impl Command {
@@ -66,8 +298,78 @@ impl Command {
             "set" => Command::Set(Set::parse_frames(&mut parse)?),
             "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
             "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
+            "psubscribe" => Command::PSubscribe(PSubscribe::parse_frames(&mut parse)?),
+            "punsubscribe" => Command::PUnsubscribe(PUnsubscribe::parse_frames(&mut parse)?),
             "ping" => Command::Ping(Ping::parse_frames(&mut parse)?),
             "config" => Command::Config(Config::parse_frames(&mut parse)?),
+            "hotkeys" => Command::HotKeys(HotKeys::parse_frames(&mut parse)?),
+            "info" => Command::Info(Info::parse_frames(&mut parse)?),
+            "client" => Command::Client(Client::parse_frames(&mut parse)?),
+            "monitor" => Command::Monitor(Monitor::parse_frames(&mut parse)?),
+            "slowlog" => Command::SlowLog(SlowLog::parse_frames(&mut parse)?),
+            "hello" => Command::Hello(Hello::parse_frames(&mut parse)?),
+            "select" => Command::Select(Select::parse_frames(&mut parse)?),
+            "flushdb" => Command::FlushDb(FlushDb::parse_frames(&mut parse)?),
+            "swapdb" => Command::SwapDb(SwapDb::parse_frames(&mut parse)?),
+            "mget" => Command::MGet(MGet::parse_frames(&mut parse)?),
+            "mset" => Command::MSet(MSet::parse_frames(&mut parse)?),
+            "del" | "unlink" => Command::Del(Del::parse_frames(&mut parse, &command_name)?),
+            "exists" => Command::Exists(Exists::parse_frames(&mut parse)?),
+            "expire" | "pexpire" | "expireat" | "pexpireat" => {
+                Command::Expire(Expire::parse_frames(&mut parse, &command_name)?)
+            }
+            "ttl" => Command::Ttl(Ttl::parse_frames(&mut parse, false)?),
+            "pttl" => Command::Ttl(Ttl::parse_frames(&mut parse, true)?),
+            "persist" => Command::Persist(Persist::parse_frames(&mut parse)?),
+            "object" => Command::Object(Object::parse_frames(&mut parse)?),
+            "incr" | "decr" | "incrby" | "decrby" => {
+                Command::IncrBy(IncrBy::parse_frames(&mut parse, &command_name)?)
+            }
+            "incrbyfloat" => Command::IncrByFloat(IncrByFloat::parse_frames(&mut parse)?),
+            "hset" => Command::HSet(HSet::parse_frames(&mut parse)?),
+            "hget" => Command::HGet(HGet::parse_frames(&mut parse)?),
+            "hdel" => Command::HDel(HDel::parse_frames(&mut parse)?),
+            "hgetall" => Command::HGetAll(HGetAll::parse_frames(&mut parse)?),
+            "hexists" => Command::HExists(HExists::parse_frames(&mut parse)?),
+            "hlen" => Command::HLen(HLen::parse_frames(&mut parse)?),
+            "hincrby" => Command::HIncrBy(HIncrBy::parse_frames(&mut parse)?),
+            "lpush" => Command::Push(Push::parse_frames(&mut parse, End::Left)?),
+            "rpush" => Command::Push(Push::parse_frames(&mut parse, End::Right)?),
+            "lpop" => Command::Pop(Pop::parse_frames(&mut parse, End::Left)?),
+            "rpop" => Command::Pop(Pop::parse_frames(&mut parse, End::Right)?),
+            "lrange" => Command::LRange(LRange::parse_frames(&mut parse)?),
+            "llen" => Command::LLen(LLen::parse_frames(&mut parse)?),
+            "blpop" => Command::BPop(BPop::parse_frames(&mut parse, End::Left)?),
+            "brpop" => Command::BPop(BPop::parse_frames(&mut parse, End::Right)?),
+            "zadd" => Command::ZAdd(ZAdd::parse_frames(&mut parse)?),
+            "zrem" => Command::ZRem(ZRem::parse_frames(&mut parse)?),
+            "zscore" => Command::ZScore(ZScore::parse_frames(&mut parse)?),
+            "zincrby" => Command::ZIncrBy(ZIncrBy::parse_frames(&mut parse)?),
+            "zrange" => Command::ZRange(ZRange::parse_frames(&mut parse)?),
+            "zrangebyscore" => Command::ZRangeByScore(ZRangeByScore::parse_frames(&mut parse)?),
+            "zcard" => Command::ZCard(ZCard::parse_frames(&mut parse)?),
+            "xadd" => Command::XAdd(XAdd::parse_frames(&mut parse)?),
+            "xlen" => Command::XLen(XLen::parse_frames(&mut parse)?),
+            "xrange" => Command::XRange(XRange::parse_frames(&mut parse)?),
+            "xread" => Command::XRead(XRead::parse_frames(&mut parse)?),
+            "scan" => Command::Scan(Scan::parse_frames(&mut parse)?),
+            "hscan" => Command::HScan(HScan::parse_frames(&mut parse)?),
+            "zscan" => Command::ZScan(ZScan::parse_frames(&mut parse)?),
+            "multi" => Command::Multi(Multi::parse_frames(&mut parse)?),
+            "exec" => Command::Exec(Exec::parse_frames(&mut parse)?),
+            "discard" => Command::Discard(Discard::parse_frames(&mut parse)?),
+            "watch" => Command::Watch(Watch::parse_frames(&mut parse)?),
+            "unwatch" => Command::Unwatch(Unwatch::parse_frames(&mut parse)?),
+            "bgrewriteaof" => Command::BgRewriteAof(BgRewriteAof::parse_frames(&mut parse)?),
+            "save" => Command::Save(Save::parse_frames(&mut parse)?),
+            "bgsave" => Command::BgSave(BgSave::parse_frames(&mut parse)?),
+            "shutdown" => Command::Shutdown(ShutdownServer::parse_frames(&mut parse)?),
+            "psync" => Command::PSync(PSync::parse_frames(&mut parse)?),
+            "replicaof" | "slaveof" => {
+                Command::ReplicaOf(ReplicaOf::parse_frames(&mut parse, &command_name)?)
+            }
+            "auth" => Command::Auth(Auth::parse_frames(&mut parse)?),
+            "acl" => Command::Acl(Acl::parse_frames(&mut parse)?),
             _ => {
                 // The command is not recognized and an Unknown command is
                 // returned.
@@ -92,9 +394,137 @@ impl Command {
     ///
     /// The response is written to `dst`. This is called by the server in order
     /// to execute a received command.
+    ///
+    /// `db` is the connection's handle. Commands such as `SELECT` rebind it to
+    /// another logical database. `transaction` is the connection's transaction
+    /// state. While a transaction is active, commands are queued instead of
+    /// being executed.
+    ///
+    /// Commands the connection's user is not allowed to run are refused.
     pub(crate) async fn apply(
         self,
-        db: &Db,
+        db: &mut Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+        transaction: &mut Transaction,
+    ) -> crate::Result<()> {
+        use Command::*;
+
+        if let Some(response) = self.check_permission(db, dst) {
+            // Like any other error, this fails the transaction.
+            if transaction.is_active() {
+                transaction.abort();
+            }
+
+            debug!(?response);
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        match self {
+            // Replicas only receive writes from their primary.
+            cmd if cmd.is_write() && db.primary().is_some() => {
+                if transaction.is_active() {
+                    transaction.abort();
+                }
+
+                let response = Frame::Error(
+                    "READONLY You can't write against a read only replica.".to_string(),
+                );
+                dst.write_frame(&response).await?;
+                Ok(())
+            }
+            // Writes that cannot be persisted are refused, as Redis does.
+            cmd if cmd.is_write() && !db.aof_write_ok() => {
+                if transaction.is_active() {
+                    transaction.abort();
+                }
+
+                let response = Frame::Error("MISCONF Errors writing to the AOF file".to_string());
+                dst.write_frame(&response).await?;
+                Ok(())
+            }
+            // Under `maxmemory`, keys are evicted before storing more data.
+            cmd if cmd.uses_memory() && !db.evict() => {
+                if transaction.is_active() {
+                    transaction.abort();
+                }
+
+                let response = Frame::Error(
+                    "OOM command not allowed when used memory > 'maxmemory'.".to_string(),
+                );
+                dst.write_frame(&response).await?;
+                Ok(())
+            }
+            Multi(cmd) => cmd.apply(dst, transaction).await,
+            Exec(cmd) => cmd.apply(db, dst, shutdown, transaction).await,
+            Discard(cmd) => cmd.apply(dst, transaction).await,
+            Watch(cmd) => cmd.apply(db, dst, transaction).await,
+            cmd if transaction.is_active() => {
+                let response = match cmd {
+                    // Unknown commands and commands that take over the
+                    // connection cannot be queued. This fails the whole
+                    // transaction.
+                    Unknown(cmd) => {
+                        transaction.abort();
+                        return cmd.apply(dst).await;
+                    }
+                    Subscribe(_) | Unsubscribe(_) | PSubscribe(_) | PUnsubscribe(_) | PSync(_)
+                    | Monitor(_) => {
+                        transaction.abort();
+                        Frame::Error("ERR Command not allowed inside a transaction".to_string())
+                    }
+                    cmd => {
+                        transaction.queue(cmd);
+                        Frame::Simple("QUEUED".to_string())
+                    }
+                };
+
+                dst.write_frame(&response).await?;
+                Ok(())
+            }
+            Unwatch(cmd) => cmd.apply(dst, transaction).await,
+            // These commands are not atomic as a whole. `BPop` and `XRead`
+            // hold the transaction lock while they read.
+            cmd if cmd.is_blocking() => cmd.execute(db, dst, shutdown).await,
+            cmd => {
+                let write = cmd.is_write();
+
+                // Keep transactions of other connections from running while
+                // the command executes. The reply is captured and only written
+                // once the guard is released, so a peer slow to read does not
+                // hold back transactions.
+                dst.begin_capture();
+
+                let res = {
+                    let _guard = db.lock_shared().await;
+                    cmd.execute(db, dst, shutdown).await
+                };
+
+                let mut replies = dst.end_capture();
+                res?;
+
+                if write {
+                    if let Some(error) = wait_for_aof(db).await {
+                        replies = vec![error];
+                    }
+                }
+
+                for reply in &replies {
+                    dst.buffer_frame(reply);
+                }
+                dst.flush().await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Execute the command, regardless of the transaction state.
+    ///
+    /// This is called by `apply`, as well as by `EXEC` for each queued command.
+    pub(crate) async fn execute(
+        self,
+        db: &mut Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
     ) -> crate::Result<()> {
@@ -105,26 +535,246 @@ impl Command {
             Publish(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
             Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            PSubscribe(cmd) => cmd.apply(db, dst, shutdown).await,
             Ping(cmd) => cmd.apply(dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
-            Config(cmd) => cmd.apply(dst).await,
+            Config(cmd) => cmd.apply(db, dst).await,
+            HotKeys(cmd) => cmd.apply(db, dst).await,
+            Info(cmd) => cmd.apply(db, dst).await,
+            Client(cmd) => cmd.apply(db, dst).await,
+            Monitor(cmd) => cmd.apply(db, dst, shutdown).await,
+            SlowLog(cmd) => cmd.apply(db, dst).await,
+            Hello(cmd) => cmd.apply(db, dst).await,
+            Select(cmd) => cmd.apply(db, dst).await,
+            FlushDb(cmd) => cmd.apply(db, dst).await,
+            SwapDb(cmd) => cmd.apply(db, dst).await,
+            MGet(cmd) => cmd.apply(db, dst).await,
+            MSet(cmd) => cmd.apply(db, dst).await,
+            Del(cmd) => cmd.apply(db, dst).await,
+            Exists(cmd) => cmd.apply(db, dst).await,
+            Expire(cmd) => cmd.apply(db, dst).await,
+            Ttl(cmd) => cmd.apply(db, dst).await,
+            Persist(cmd) => cmd.apply(db, dst).await,
+            Object(cmd) => cmd.apply(db, dst).await,
+            IncrBy(cmd) => cmd.apply(db, dst).await,
+            IncrByFloat(cmd) => cmd.apply(db, dst).await,
+            HSet(cmd) => cmd.apply(db, dst).await,
+            HGet(cmd) => cmd.apply(db, dst).await,
+            HDel(cmd) => cmd.apply(db, dst).await,
+            HGetAll(cmd) => cmd.apply(db, dst).await,
+            HExists(cmd) => cmd.apply(db, dst).await,
+            HLen(cmd) => cmd.apply(db, dst).await,
+            HIncrBy(cmd) => cmd.apply(db, dst).await,
+            Push(cmd) => cmd.apply(db, dst).await,
+            Pop(cmd) => cmd.apply(db, dst).await,
+            LRange(cmd) => cmd.apply(db, dst).await,
+            LLen(cmd) => cmd.apply(db, dst).await,
+            BPop(cmd) => cmd.apply(db, dst, shutdown).await,
+            ZAdd(cmd) => cmd.apply(db, dst).await,
+            ZRem(cmd) => cmd.apply(db, dst).await,
+            ZScore(cmd) => cmd.apply(db, dst).await,
+            ZIncrBy(cmd) => cmd.apply(db, dst).await,
+            ZRange(cmd) => cmd.apply(db, dst).await,
+            ZRangeByScore(cmd) => cmd.apply(db, dst).await,
+            ZCard(cmd) => cmd.apply(db, dst).await,
+            XAdd(cmd) => cmd.apply(db, dst).await,
+            XLen(cmd) => cmd.apply(db, dst).await,
+            XRange(cmd) => cmd.apply(db, dst).await,
+            XRead(cmd) => cmd.apply(db, dst, shutdown).await,
+            Scan(cmd) => cmd.apply(db, dst).await,
+            HScan(cmd) => cmd.apply(db, dst).await,
+            ZScan(cmd) => cmd.apply(db, dst).await,
+            BgRewriteAof(cmd) => cmd.apply(db, dst).await,
+            Save(cmd) => cmd.apply(db, dst).await,
+            BgSave(cmd) => cmd.apply(db, dst).await,
+            Shutdown(cmd) => cmd.apply(db, dst).await,
+            PSync(cmd) => cmd.apply(db, dst, shutdown).await,
+            ReplicaOf(cmd) => cmd.apply(db, dst).await,
+            Auth(cmd) => cmd.apply(db, dst).await,
+            Acl(cmd) => cmd.apply(db, dst).await,
             // `Unsubscribe` cannot be applied. It may only be received from the
             // context of a `Subscribe` command.
             Unsubscribe(_) => Err("`Unsubscribe` is unsupported in this context".into()),
+            PUnsubscribe(_) => Err("`PUnsubscribe` is unsupported in this context".into()),
+            // Transaction commands are handled by `apply` and never queued.
+            Multi(_) | Exec(_) | Discard(_) | Watch(_) | Unwatch(_) => {
+                Err("transaction commands are unsupported in this context".into())
+            }
+        }
+    }
+
+    /// Check that the user the connection is authenticated as may run the
+    /// command. Returns the error to reply with if not.
+    fn check_permission(&self, db: &Db, dst: &Connection) -> Option<Frame> {
+        match (self, dst.user()) {
+            // Authenticating is always allowed. `HELLO` checks by itself
+            // that the connection is authenticated.
+            (Command::Auth(_) | Command::Hello(_), _) => None,
+            (_, None) => Some(Frame::Error("NOAUTH Authentication required.".to_string())),
+            // Unknown commands are refused regardless.
+            (Command::Unknown(_), Some(_)) => None,
+            (cmd, Some(user)) if db.is_permitted(user, cmd.get_name()) => None,
+            (cmd, Some(user)) => Some(Frame::Error(format!(
+                "NOPERM User {} has no permissions to run the '{}' command",
+                user,
+                cmd.get_name()
+            ))),
         }
     }
 
+    /// Returns `true` if the command may modify the data.
+    ///
+    /// Replicas reject these commands, as their data only changes through
+    /// replication.
+    pub(crate) fn is_write(&self) -> bool {
+        use Command::*;
+
+        matches!(
+            self,
+            Set(_)
+                | FlushDb(_)
+                | SwapDb(_)
+                | MSet(_)
+                | Del(_)
+                | Expire(_)
+                | Persist(_)
+                | IncrBy(_)
+                | IncrByFloat(_)
+                | HSet(_)
+                | HDel(_)
+                | HIncrBy(_)
+                | Push(_)
+                | Pop(_)
+                | BPop(_)
+                | ZAdd(_)
+                | ZRem(_)
+                | ZIncrBy(_)
+                | XAdd(_)
+        )
+    }
+
+    /// Returns `true` if the command may wait for as long as the connection is
+    /// open. Its execution time says nothing about the server, so it is never
+    /// recorded in the slow log.
+    pub(crate) fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            Command::Subscribe(_)
+                | Command::PSubscribe(_)
+                | Command::BPop(_)
+                | Command::XRead(_)
+                | Command::PSync(_)
+                | Command::Monitor(_)
+        )
+    }
+
+    /// Returns `true` if the command may store more data.
+    ///
+    /// Once `maxmemory` is reached, these are refused unless keys can be
+    /// evicted.
+    pub(crate) fn uses_memory(&self) -> bool {
+        use Command::*;
+
+        matches!(
+            self,
+            Set(_)
+                | MSet(_)
+                | IncrBy(_)
+                | IncrByFloat(_)
+                | HSet(_)
+                | HIncrBy(_)
+                | Push(_)
+                | ZAdd(_)
+                | ZIncrBy(_)
+                | XAdd(_)
+        )
+    }
+
     /// Returns the command name
     pub(crate) fn get_name(&self) -> &str {
         match self {
             Command::Get(_) => "get",
-            Command::Publish(_) => "pub",
+            Command::Publish(_) => "publish",
             Command::Set(_) => "set",
             Command::Subscribe(_) => "subscribe",
             Command::Unsubscribe(_) => "unsubscribe",
+            Command::PSubscribe(_) => "psubscribe",
+            Command::PUnsubscribe(_) => "punsubscribe",
             Command::Ping(_) => "ping",
             Command::Config(_) => "config",
+            Command::HotKeys(_) => "hotkeys",
+            Command::Info(_) => "info",
+            Command::Client(_) => "client",
+            Command::Monitor(_) => "monitor",
+            Command::SlowLog(_) => "slowlog",
+            Command::Hello(_) => "hello",
+            Command::Select(_) => "select",
+            Command::FlushDb(_) => "flushdb",
+            Command::SwapDb(_) => "swapdb",
+            Command::MGet(_) => "mget",
+            Command::MSet(_) => "mset",
+            Command::Del(cmd) => cmd.get_name(),
+            Command::Exists(_) => "exists",
+            Command::Expire(cmd) => cmd.get_name(),
+            Command::Ttl(cmd) => cmd.get_name(),
+            Command::Persist(_) => "persist",
+            Command::Object(_) => "object",
+            Command::IncrBy(cmd) => cmd.get_name(),
+            Command::IncrByFloat(_) => "incrbyfloat",
+            Command::HSet(_) => "hset",
+            Command::HGet(_) => "hget",
+            Command::HDel(_) => "hdel",
+            Command::HGetAll(_) => "hgetall",
+            Command::HExists(_) => "hexists",
+            Command::HLen(_) => "hlen",
+            Command::HIncrBy(_) => "hincrby",
+            Command::Push(cmd) => cmd.get_name(),
+            Command::Pop(cmd) => cmd.get_name(),
+            Command::LRange(_) => "lrange",
+            Command::LLen(_) => "llen",
+            Command::BPop(cmd) => cmd.get_name(),
+            Command::ZAdd(_) => "zadd",
+            Command::ZRem(_) => "zrem",
+            Command::ZScore(_) => "zscore",
+            Command::ZIncrBy(_) => "zincrby",
+            Command::ZRange(_) => "zrange",
+            Command::ZRangeByScore(_) => "zrangebyscore",
+            Command::ZCard(_) => "zcard",
+            Command::XAdd(_) => "xadd",
+            Command::XLen(_) => "xlen",
+            Command::XRange(_) => "xrange",
+            Command::XRead(_) => "xread",
+            Command::Scan(_) => "scan",
+            Command::HScan(_) => "hscan",
+            Command::ZScan(_) => "zscan",
+            Command::Multi(_) => "multi",
+            Command::Exec(_) => "exec",
+            Command::Discard(_) => "discard",
+            Command::Watch(_) => "watch",
+            Command::Unwatch(_) => "unwatch",
+            Command::BgRewriteAof(_) => "bgrewriteaof",
+            Command::Save(_) => "save",
+            Command::BgSave(_) => "bgsave",
+            Command::Shutdown(_) => "shutdown",
+            Command::PSync(_) => "psync",
+            Command::ReplicaOf(cmd) => cmd.get_name(),
+            Command::Auth(_) => "auth",
+            Command::Acl(_) => "acl",
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
 }
+
+/// Wait for the writes applied so far to be synced to the append only file,
+/// under `appendfsync always`. Returns the error to reply with instead if they
+/// could not be written.
+///
+/// Blocking commands do not wait, as they write their reply by themselves.
+async fn wait_for_aof(db: &Db) -> Option<Frame> {
+    match db.aof_synced()?.await {
+        Ok(()) => None,
+        Err(_) => Some(Frame::Error(
+            "MISCONF Errors writing to the AOF file".to_string(),
+        )),
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{Connection, Db, Frame, Parse, Shutdown};
+
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, instrument};
+
+/// Streams a line describing every command received by the server.
+///
+/// Once the client runs `MONITOR`, the connection only receives these lines,
+/// as simple strings, until it disconnects. Commands sent by the client
+/// afterwards are ignored.
+#[derive(Debug)]
+pub struct Monitor {}
+
+impl Monitor {
+    /// Parse a `Monitor` instance from a received frame.
+    ///
+    /// The `MONITOR` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// MONITOR
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Monitor> {
+        Ok(Monitor {})
+    }
+
+    /// Apply the `Monitor` command to the specified `Db` instance.
+    ///
+    /// Runs until the client disconnects or the server shuts down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        // Subscribe before replying, so no command received after the reply
+        // is missed.
+        let mut lines = db.monitor();
+
+        dst.write_frame(&Frame::Simple("OK".to_string())).await?;
+
+        loop {
+            select! {
+                res = lines.recv() => match res {
+                    Ok(line) => dst.write_frame(&Frame::Simple(line)).await?,
+                    // Lines missed by a slow connection are skipped.
+                    Err(RecvError::Lagged(skipped)) => debug!(skipped, "monitor lagging"),
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                res = dst.read_frame() => {
+                    // This happens if the remote client has disconnected.
+                    if res?.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+    }
+}
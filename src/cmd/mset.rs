@@ -0,0 +1,58 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Set the given keys to their respective values.
+///
+/// Existing values are overwritten, regardless of their type, and their time
+/// to live is discarded. All the keys are set at once. The response is always
+/// `OK`.
+#[derive(Debug)]
+pub struct MSet {
+    /// Keys to set, along with their value.
+    pairs: Vec<(String, Bytes)>,
+}
+
+impl MSet {
+    /// Parse a `MSet` instance from a received frame.
+    ///
+    /// The `MSET` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing an odd number of entries, at least
+    /// three.
+    ///
+    /// ```text
+    /// MSET key value [key value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MSet> {
+        let mut pairs = vec![];
+
+        loop {
+            pairs.push((parse.next_string()?, parse.next_bytes()?));
+
+            if parse.remaining() == 0 {
+                break;
+            }
+        }
+
+        Ok(MSet { pairs })
+    }
+
+    /// Apply the `MSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.mset(self.pairs);
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
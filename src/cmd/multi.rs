@@ -0,0 +1,121 @@
+use crate::{Command, Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Marks the start of a transaction.
+///
+/// Subsequent commands are queued instead of being executed, and are executed
+/// atomically by `EXEC`. `DISCARD` drops the queued commands instead.
+#[derive(Debug, Default)]
+pub struct Multi {}
+
+/// Transaction state of a connection.
+///
+/// This tracks both the commands queued since `MULTI` and the keys watched by
+/// `WATCH`.
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    /// Commands queued since `MULTI`. `None` outside of a transaction.
+    queued: Option<Vec<Command>>,
+
+    /// Set when a command could not be queued. `EXEC` then discards the
+    /// transaction instead of executing it.
+    aborted: bool,
+
+    /// Keys watched by `WATCH`, along with the key version observed at that
+    /// time. The handle identifies the logical database the key is in.
+    watched: Vec<(Db, String, Option<u64>)>,
+}
+
+impl Multi {
+    /// Create a new `Multi` command.
+    pub fn new() -> Multi {
+        Multi {}
+    }
+
+    /// Parse a `Multi` instance from a received frame.
+    ///
+    /// The `MULTI` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// MULTI
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Multi> {
+        Ok(Multi {})
+    }
+
+    /// Apply the `Multi` command, starting a transaction.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst, transaction))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        transaction: &mut Transaction,
+    ) -> crate::Result<()> {
+        let response = if transaction.is_active() {
+            Frame::Error("ERR MULTI calls can not be nested".to_string())
+        } else {
+            transaction.queued = Some(vec![]);
+            Frame::Simple("OK".to_string())
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl Transaction {
+    /// Returns `true` between `MULTI` and `EXEC` or `DISCARD`.
+    pub(crate) fn is_active(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// Queue a command for execution by `EXEC`.
+    pub(crate) fn queue(&mut self, cmd: Command) {
+        if let Some(queued) = &mut self.queued {
+            queued.push(cmd);
+        }
+    }
+
+    /// Mark the transaction as failed. It is discarded by `EXEC`.
+    pub(crate) fn abort(&mut self) {
+        self.aborted = true;
+    }
+
+    /// Watch `key` in the logical database `db` is bound to.
+    pub(crate) fn watch(&mut self, db: &Db, key: String) {
+        let version = db.version(&key);
+        self.watched.push((db.clone(), key, version));
+    }
+
+    /// Returns `true` if any of the watched keys was modified since it was
+    /// watched.
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.watched
+            .iter()
+            .any(|(db, key, version)| db.version(key) != *version)
+    }
+
+    /// Forget all watched keys.
+    pub(crate) fn unwatch(&mut self) {
+        self.watched.clear();
+    }
+
+    /// End the transaction, returning the queued commands and whether the
+    /// transaction was aborted. Watched keys are forgotten.
+    ///
+    /// Returns `None` if no transaction is active.
+    pub(crate) fn finish(&mut self) -> Option<(Vec<Command>, bool)> {
+        let queued = self.queued.take()?;
+        let aborted = std::mem::replace(&mut self.aborted, false);
+        Some((queued, aborted))
+    }
+}
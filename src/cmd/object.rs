@@ -0,0 +1,84 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspect the value stored at a key.
+///
+/// Only `OBJECT ENCODING` is supported. It returns the encoding Redis would
+/// store the value with, which changes once the value grows past the
+/// thresholds of parameters such as `hash-max-listpack-entries`.
+#[derive(Debug)]
+pub struct Object {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    /// Return the encoding of the value stored at `key`.
+    Encoding { key: String },
+
+    /// A subcommand that is not supported.
+    Unknown(String),
+}
+
+impl Object {
+    /// Parse an `Object` instance from a received frame.
+    ///
+    /// The `OBJECT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `OBJECT`, the subcommand and its
+    /// arguments.
+    ///
+    /// ```text
+    /// OBJECT ENCODING key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Object> {
+        let name = parse.next_string()?;
+
+        let subcommand = match &name.to_lowercase()[..] {
+            "encoding" => Subcommand::Encoding {
+                key: parse.next_string()?,
+            },
+            _ => {
+                // The arguments of unknown subcommands are skipped.
+                loop {
+                    match parse.next_string() {
+                        Ok(_) => {}
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Subcommand::Unknown(name)
+            }
+        };
+
+        Ok(Object { subcommand })
+    }
+
+    /// Apply the `Object` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            Subcommand::Encoding { key } => match db.encoding(&key) {
+                Some(encoding) => Frame::Bulk(Bytes::from(encoding)),
+                None => Frame::Null,
+            },
+            Subcommand::Unknown(name) => Frame::Error(format!(
+                "ERR unknown subcommand '{}'. Try OBJECT HELP.",
+                name
+            )),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Remove the existing timeout on a key.
+///
+/// The response is `1` if the timeout was removed and `0` if the key does not
+/// exist or has no associated timeout.
+#[derive(Debug)]
+pub struct Persist {
+    /// Name of the key to persist.
+    key: String,
+}
+
+impl Persist {
+    /// Parse a `Persist` instance from a received frame.
+    ///
+    /// The `PERSIST` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// PERSIST key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Persist> {
+        let key = parse.next_string()?;
+
+        Ok(Persist { key })
+    }
+
+    /// Apply the `Persist` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.persist(&self.key) as i64);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use crate::cmd::{Parse, ParseError};
+use crate::db::End;
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Remove and return elements from the head (`LPOP`) or the tail (`RPOP`) of
+/// the list stored at key.
+///
+/// Without a count, the response is the popped element, or nil if the list is
+/// empty. With a count, the response is an array of up to `count` elements,
+/// or nil if the list is empty.
+#[derive(Debug)]
+pub struct Pop {
+    /// Name of the key holding the list.
+    key: String,
+
+    /// Number of elements to pop, if specified.
+    count: Option<u64>,
+
+    /// End of the list to pop from.
+    end: End,
+}
+
+impl Pop {
+    /// Parse a `Pop` instance from a received frame.
+    ///
+    /// The `LPOP` or `RPOP` string has already been consumed and is reflected
+    /// by `end`.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or three entries.
+    ///
+    /// ```text
+    /// LPOP key [count]
+    /// RPOP key [count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, end: End) -> crate::Result<Pop> {
+        let key = parse.next_string()?;
+
+        let count = match parse.next_int() {
+            Ok(count) => Some(count),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Pop { key, count, end })
+    }
+
+    /// Returns the command name.
+    pub(crate) fn get_name(&self) -> &str {
+        match self.end {
+            End::Left => "lpop",
+            End::Right => "rpop",
+        }
+    }
+
+    /// Apply the `Pop` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let count = self.count.unwrap_or(1) as usize;
+
+        let response = match db.pop(&self.key, self.end, count) {
+            Ok(values) if values.is_empty() => Frame::Null,
+            Ok(values) => match self.count {
+                Some(_) => Frame::Array(values.into_iter().map(Frame::Bulk).collect()),
+                None => Frame::Bulk(values.into_iter().next().unwrap()),
+            },
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{replication, Connection, Db, Parse, Shutdown};
+
+use tracing::{info, instrument};
+
+/// Synchronize a replica with this server.
+///
+/// This is sent by replicas, not by regular clients. The connection is then
+/// used to stream writes to the replica. See the `replication` module.
+///
+/// Partial resynchronization is not supported. The replication ID and offset
+/// sent by the replica are ignored and a full resynchronization is always
+/// performed.
+#[derive(Debug)]
+pub struct PSync {
+    /// Replication ID of the data set the replica holds, `?` if none.
+    replid: String,
+
+    /// Replication offset the replica reached, `-1` if none.
+    offset: i64,
+}
+
+impl PSync {
+    /// Parse a `PSync` instance from a received frame.
+    ///
+    /// The `PSYNC` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// PSYNC replicationid offset
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSync> {
+        let replid = parse.next_string()?;
+        let offset = parse.next_signed_int()?;
+
+        Ok(PSync { replid, offset })
+    }
+
+    /// Apply the `PSync` command to the specified `Db` instance.
+    ///
+    /// Writes are sent to `dst` until the replica disconnects or the server
+    /// shuts down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        info!(replid = %self.replid, offset = self.offset, "replica requested synchronization");
+        replication::feed(db, dst, shutdown).await
+    }
+}
@@ -0,0 +1,79 @@
+use crate::cmd::{Parse, ParseError};
+use crate::db::End;
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Insert values at the head (`LPUSH`) or the tail (`RPUSH`) of the list
+/// stored at key.
+///
+/// If the key does not exist, a new list is created. Values are inserted one
+/// after the other, so `LPUSH key a b c` results in the list `c b a`. The
+/// response is the length of the list after the push.
+#[derive(Debug)]
+pub struct Push {
+    /// Name of the key holding the list.
+    key: String,
+
+    /// Values to insert.
+    values: Vec<Bytes>,
+
+    /// End of the list to insert at.
+    end: End,
+}
+
+impl Push {
+    /// Parse a `Push` instance from a received frame.
+    ///
+    /// The `LPUSH` or `RPUSH` string has already been consumed and is
+    /// reflected by `end`.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LPUSH key value [value ...]
+    /// RPUSH key value [value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, end: End) -> crate::Result<Push> {
+        let key = parse.next_string()?;
+        let mut values = vec![parse.next_bytes()?];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(value) => values.push(value),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Push { key, values, end })
+    }
+
+    /// Returns the command name.
+    pub(crate) fn get_name(&self) -> &str {
+        match self.end {
+            End::Left => "lpush",
+            End::Right => "rpush",
+        }
+    }
+
+    /// Apply the `Push` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.push(&self.key, self.values, self.end) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
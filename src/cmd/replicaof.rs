@@ -0,0 +1,101 @@
+use crate::{replication, Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Make the server a replica of another server, or turn it back into a
+/// primary.
+///
+/// A replica discards its data, loads the data of its primary and then
+/// applies every write the primary receives. Replicas are read only: write
+/// commands sent by clients are rejected. `REPLICAOF NO ONE` stops the
+/// replication and keeps the data replicated so far.
+///
+/// `SLAVEOF` is accepted as an alias.
+#[derive(Debug)]
+pub struct ReplicaOf {
+    /// Host and port of the primary, or `None` to stop replicating.
+    primary: Option<(String, u16)>,
+
+    /// Name of the command, either `replicaof` or `slaveof`.
+    name: &'static str,
+}
+
+impl ReplicaOf {
+    /// Create a new `ReplicaOf` command replicating the server at
+    /// `host:port`, or stopping the replication if `primary` is `None`.
+    pub fn new(primary: Option<(String, u16)>) -> ReplicaOf {
+        ReplicaOf {
+            primary,
+            name: "replicaof",
+        }
+    }
+
+    /// Parse a `ReplicaOf` instance from a received frame.
+    ///
+    /// The command name has already been consumed and is passed as `name`, in
+    /// lowercase.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// REPLICAOF host port
+    /// REPLICAOF NO ONE
+    /// SLAVEOF host port
+    /// SLAVEOF NO ONE
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, name: &str) -> crate::Result<ReplicaOf> {
+        let host = parse.next_string()?;
+        let port = parse.next_string()?;
+
+        let name = match name {
+            "replicaof" => "replicaof",
+            "slaveof" => "slaveof",
+            _ => return Err(format!("protocol error; unexpected command `{}`", name).into()),
+        };
+
+        if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+            return Ok(ReplicaOf {
+                primary: None,
+                name,
+            });
+        }
+
+        let port = port
+            .parse()
+            .map_err(|_| "protocol error; invalid primary port")?;
+
+        Ok(ReplicaOf {
+            primary: Some((host, port)),
+            name,
+        })
+    }
+
+    /// Apply the `ReplicaOf` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        match self.primary {
+            Some((host, port)) => {
+                let addr = format!("{}:{}", host, port);
+                let task = replication::start(db, host, port);
+                db.set_primary(Some((addr, task)));
+            }
+            None => db.set_primary(None),
+        }
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Returns the name of the command, either `replicaof` or `slaveof`.
+    pub(crate) fn get_name(&self) -> &'static str {
+        self.name
+    }
+}
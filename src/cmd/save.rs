@@ -0,0 +1,98 @@
+use crate::{rdb, Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Save a snapshot of the data to disk, replying once it is written.
+///
+/// The snapshot is written to the file set by `ServerConfig::dbfilename`.
+#[derive(Debug, Default)]
+pub struct Save {}
+
+/// Save a snapshot of the data to disk in the background.
+///
+/// The snapshot reflects the data at the time the command is received. The
+/// reply is sent right away, before the snapshot is written.
+#[derive(Debug, Default)]
+pub struct BgSave {}
+
+impl Save {
+    /// Create a new `Save` command.
+    pub fn new() -> Save {
+        Save {}
+    }
+
+    /// Parse a `Save` instance from a received frame.
+    ///
+    /// The `SAVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// SAVE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Save> {
+        Ok(Save {})
+    }
+
+    /// Apply the `Save` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.try_lock_save() {
+            Some(guard) => match rdb::snapshot(db, guard).await {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(format!("ERR {}", err)),
+            },
+            None => Frame::Error("ERR Background save already in progress".to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl BgSave {
+    /// Create a new `BgSave` command.
+    pub fn new() -> BgSave {
+        BgSave {}
+    }
+
+    /// Parse a `BgSave` instance from a received frame.
+    ///
+    /// The `BGSAVE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// BGSAVE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<BgSave> {
+        Ok(BgSave {})
+    }
+
+    /// Apply the `BgSave` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if rdb::bgsave(db) {
+            Frame::Simple("Background saving started".to_string())
+        } else {
+            Frame::Error("ERR Background save already in progress".to_string())
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{glob, Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Number of keys examined per call when `COUNT` is not given.
+const DEFAULT_COUNT: usize = 10;
+
+/// Iterate over the keys of the selected database.
+///
+/// Each call returns a page of keys along with the cursor to pass to the next
+/// call. Iteration starts with cursor `0` and is complete once the returned
+/// cursor is `0` again. Keys present during the whole iteration are returned
+/// exactly once. Keys added or removed meanwhile may or may not be returned.
+#[derive(Debug)]
+pub struct Scan {
+    /// Position to resume the iteration from.
+    cursor: u64,
+
+    /// Only return the keys matching the pattern.
+    options: ScanOptions,
+
+    /// Only return the keys holding a value of this type, such as `string`
+    /// or `hash`.
+    kind: Option<String>,
+}
+
+/// Options shared by the `SCAN` family of commands.
+#[derive(Debug)]
+pub(super) struct ScanOptions {
+    /// Glob-style pattern the returned names must match.
+    pub(super) pattern: Option<String>,
+
+    /// Number of names to examine. Fewer may be returned, as names not
+    /// matching the pattern are filtered out after being examined.
+    pub(super) count: usize,
+}
+
+impl Scan {
+    /// Parse a `Scan` instance from a received frame.
+    ///
+    /// The `SCAN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Scan> {
+        let cursor = parse.next_int()?;
+        let mut kind = None;
+
+        let options = ScanOptions::parse(parse, |option, parse| {
+            if option == "TYPE" {
+                kind = Some(parse.next_string()?.to_lowercase());
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        })?;
+
+        Ok(Scan {
+            cursor,
+            options,
+            kind,
+        })
+    }
+
+    /// Apply the `Scan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let (cursor, keys) = db.scan(self.cursor, self.options.count);
+
+        let keys = keys
+            .into_iter()
+            .filter(|(key, kind)| {
+                self.options.matches(key) && self.kind.as_deref().is_none_or(|k| k == *kind)
+            })
+            .map(|(key, _)| Frame::Bulk(Bytes::from(key)))
+            .collect();
+
+        let response = page_frame(cursor, keys);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl ScanOptions {
+    /// Parse the `MATCH` and `COUNT` options until the end of the frame.
+    ///
+    /// Other options are passed to `other`, which returns `false` if it does
+    /// not support them either.
+    pub(super) fn parse(
+        parse: &mut Parse,
+        mut other: impl FnMut(&str, &mut Parse) -> crate::Result<bool>,
+    ) -> crate::Result<ScanOptions> {
+        let mut options = ScanOptions {
+            pattern: None,
+            count: DEFAULT_COUNT,
+        };
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "MATCH" => options.pattern = Some(parse.next_string()?),
+                "COUNT" => match parse.next_int()? {
+                    0 => return Err("protocol error; COUNT must be positive".into()),
+                    count => options.count = count as usize,
+                },
+                option => {
+                    if !other(option, parse)? {
+                        return Err(
+                            format!("protocol error; unsupported option `{}`", option).into()
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Returns `true` if `name` matches the `MATCH` pattern, if any.
+    pub(super) fn matches(&self, name: &str) -> bool {
+        match &self.pattern {
+            Some(pattern) => glob::matches(pattern.as_bytes(), name.as_bytes()),
+            None => true,
+        }
+    }
+}
+
+/// Returns the response to a command of the `SCAN` family: the cursor of the
+/// next page, followed by the items of this page.
+pub(super) fn page_frame(cursor: u64, items: Vec<Frame>) -> Frame {
+    Frame::Array(vec![
+        Frame::Bulk(Bytes::from(cursor.to_string())),
+        Frame::Array(items),
+    ])
+}
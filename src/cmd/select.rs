@@ -0,0 +1,78 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Select the logical database to use for the current connection.
+///
+/// New connections always start with database `0`.
+#[derive(Debug)]
+pub struct Select {
+    /// Index of the database to switch to.
+    index: u64,
+}
+
+impl Select {
+    /// Create a new `Select` command which switches to database `index`.
+    pub fn new(index: u64) -> Select {
+        Select { index }
+    }
+
+    /// Parse a `Select` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SELECT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Select` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SELECT index
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Select> {
+        let index = parse.next_int()?;
+
+        Ok(Select { index })
+    }
+
+    /// Apply the `Select` command by rebinding the connection's `Db` handle.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &mut Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.select(self.index as usize) {
+            Some(selected) => {
+                *db = selected;
+                Frame::Simple("OK".to_string())
+            }
+            None => Frame::Error("ERR DB index is out of range".to_string()),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Select` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("select".as_bytes()));
+        frame.push_int(self.index as i64);
+        frame
+    }
+}
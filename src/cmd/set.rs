@@ -1,22 +1,41 @@
 use crate::cmd::{Parse, ParseError};
+use crate::db::{expiration_after, expiration_at, SetCondition, SetOptions};
 use crate::{Connection, Db, Frame};
 
 use bytes::Bytes;
-use std::time::Duration;
+use std::convert::TryFrom;
+use tokio::time::Duration;
 use tracing::{debug, instrument};
 
 /// Set `key` to hold the string `value`.
 ///
 /// If `key` already holds a value, it is overwritten, regardless of its type.
 /// Any previous time to live associated with the key is discarded on successful
-/// SET operation.
+/// SET operation, unless `KEEPTTL` is given.
+///
+/// The response is `OK` if the key was set, and a null value if it was not
+/// because of `NX` or `XX`. With `GET`, the response is instead the string
+/// previously stored at the key, or a null value if there was none.
 ///
 /// # Options
 ///
-/// Currently, the following options are supported:
+/// The following options are supported:
 ///
 /// * EX `seconds` -- Set the specified expire time, in seconds.
 /// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp` -- Set the specified Unix time at which the key will
+///   expire, in seconds.
+/// * PXAT `timestamp` -- Set the specified Unix time at which the key will
+///   expire, in milliseconds.
+/// * NX -- Only set the key if it does not already exist.
+/// * XX -- Only set the key if it already exists.
+/// * KEEPTTL -- Retain the time to live associated with the key.
+/// * GET -- Return the old string stored at key, or nil if key did not exist.
+///   An error is returned and SET aborted if the value stored at key is not a
+///   string.
+///
+/// Only one of EX, PX, EXAT, PXAT and KEEPTTL may be given, and only one of
+/// NX and XX.
 #[derive(Debug)]
 pub struct Set {
     /// the lookup key
@@ -27,6 +46,18 @@ pub struct Set {
 
     /// When to expire the key
     expire: Option<Duration>,
+
+    /// Unix time at which to expire the key, in milliseconds
+    expire_at: Option<u64>,
+
+    /// Retain the time to live of the previous value
+    keep_ttl: bool,
+
+    /// Only set the key if this condition holds
+    condition: Option<SetCondition>,
+
+    /// Respond with the previous value
+    get: bool,
 }
 
 impl Set {
@@ -39,6 +70,10 @@ impl Set {
             key: key.to_string(),
             value,
             expire,
+            expire_at: None,
+            keep_ttl: false,
+            condition: None,
+            get: false,
         }
     }
 
@@ -75,7 +110,8 @@ impl Set {
     /// Expects an array frame containing at least 3 entries.
     ///
     /// ```text
-    /// SET key value [EX seconds|PX milliseconds]
+    /// SET key value [NX|XX] [GET]
+    ///     [EX seconds|PX milliseconds|EXAT timestamp|PXAT timestamp|KEEPTTL]
     /// ```
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Set> {
         use ParseError::EndOfStream;
@@ -86,38 +122,74 @@ impl Set {
         // Read the value to set. This is a required field.
         let value = parse.next_bytes()?;
 
-        // The expiration is optional. If nothing else follows, then it is
-        // `None`.
-        let mut expire = None;
-
-        // Attempt to parse another string.
-        match parse.next_string() {
-            Ok(s) if s.to_uppercase() == "EX" => {
-                // An expiration is specified in seconds. The next value is an
-                // integer.
-                let secs = parse.next_int()?;
-                expire = Some(Duration::from_secs(secs));
-            }
-            Ok(s) if s.to_uppercase() == "PX" => {
-                // An expiration is specified in milliseconds. The next value is
-                // an integer.
-                let ms = parse.next_int()?;
-                expire = Some(Duration::from_millis(ms));
+        let mut set = Set::new(key, value, None);
+
+        // Whether one of the expiration options was already given. They are
+        // mutually exclusive.
+        let mut has_expiration = false;
+
+        // Options may be given in any order, until the end of the frame.
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_uppercase(),
+                // The `EndOfStream` error indicates there is no further data to
+                // parse. In this case, it is a normal run time situation and
+                // indicates there are no more `SET` options.
+                Err(EndOfStream) => break,
+                // All other errors are bubbled up, resulting in the connection
+                // being terminated.
+                Err(err) => return Err(err.into()),
+            };
+
+            match &option[..] {
+                "EX" | "PX" | "EXAT" | "PXAT" | "KEEPTTL" if has_expiration => {
+                    return Err("protocol error; conflicting expiration options in `SET`".into())
+                }
+                "NX" | "XX" if set.condition.is_some() => {
+                    return Err(
+                        "protocol error; `NX` and `XX` are mutually exclusive in `SET`".into(),
+                    )
+                }
+                "GET" if set.get => return Err("protocol error; duplicate `GET` option".into()),
+                "EX" | "PX" | "EXAT" | "PXAT" => {
+                    let value = parse.next_int()?;
+
+                    // Redis rejects expirations that are not positive.
+                    if value == 0 {
+                        return Err("protocol error; invalid expire time in `SET`".into());
+                    }
+
+                    // Seconds are converted to milliseconds. A value too large
+                    // to be represented is rejected when the command is
+                    // applied.
+                    let millis = match &option[..] {
+                        "EX" | "EXAT" => value.saturating_mul(1000),
+                        _ => value,
+                    };
+
+                    match &option[..] {
+                        "EX" | "PX" => set.expire = Some(Duration::from_millis(millis)),
+                        _ => set.expire_at = Some(millis),
+                    }
+
+                    has_expiration = true;
+                }
+                "KEEPTTL" => {
+                    set.keep_ttl = true;
+                    has_expiration = true;
+                }
+                "NX" => set.condition = Some(SetCondition::NotExists),
+                "XX" => set.condition = Some(SetCondition::Exists),
+                "GET" => set.get = true,
+                // An error here results in the connection being terminated.
+                // Other connections will continue to operate normally.
+                _ => {
+                    return Err(format!("protocol error; unknown `SET` option `{}`", option).into())
+                }
             }
-            // Currently, mini-redis does not support any of the other SET
-            // options. An error here results in the connection being
-            // terminated. Other connections will continue to operate normally.
-            Ok(_) => return Err("currently `SET` only supports the expiration option".into()),
-            // The `EndOfStream` error indicates there is no further data to
-            // parse. In this case, it is a normal run time situation and
-            // indicates there are no specified `SET` options.
-            Err(EndOfStream) => {}
-            // All other errors are bubbled up, resulting in the connection
-            // being terminated.
-            Err(err) => return Err(err.into()),
         }
 
-        Ok(Set { key, value, expire })
+        Ok(set)
     }
 
     /// Apply the `Set` command to the specified `Db` instance.
@@ -126,11 +198,48 @@ impl Set {
     /// to execute a received command.
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // Expirations are tracked with the monotonic clock. Absolute
+        // timestamps are converted using the system clock. A timestamp in the
+        // past expires the key right away.
+        let expires_at = match (self.expire, self.expire_at) {
+            (Some(duration), _) => i64::try_from(duration.as_millis())
+                .ok()
+                .and_then(expiration_after)
+                .map(Some),
+            (None, Some(timestamp)) => i64::try_from(timestamp)
+                .ok()
+                .and_then(expiration_at)
+                .map(Some),
+            (None, None) => Some(None),
+        };
+
+        // The expiration cannot be represented, as Redis checks.
+        let expires_at = match expires_at {
+            Some(expires_at) => expires_at,
+            None => {
+                let response = Frame::Error("ERR invalid expire time in 'set'".to_string());
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let options = SetOptions {
+            expires_at,
+            keep_ttl: self.keep_ttl,
+            condition: self.condition,
+            get: self.get,
+        };
+
         // Set the value in the shared database state.
-        db.set(self.key, self.value, self.expire);
+        let response = match db.set(self.key, self.value, options) {
+            Ok((_, Some(previous))) if self.get => Frame::Bulk(previous),
+            Ok((_, None)) if self.get => Frame::Null,
+            Ok((true, _)) => Frame::Simple("OK".to_string()),
+            Ok((false, _)) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
 
-        // Create a success response and write it to `dst`.
-        let response = Frame::Simple("OK".to_string());
         debug!(?response);
         dst.write_frame(&response).await?;
 
@@ -154,7 +263,22 @@ impl Set {
             // src/bin/cli.rs parses the expiration argument as milliseconds
             // in duration_from_ms_str()
             frame.push_bulk(Bytes::from("px".as_bytes()));
-            frame.push_int(ms.as_millis() as u64);
+            frame.push_int(ms.as_millis() as i64);
+        }
+        if let Some(timestamp) = self.expire_at {
+            frame.push_bulk(Bytes::from("pxat".as_bytes()));
+            frame.push_int(timestamp as i64);
+        }
+        if self.keep_ttl {
+            frame.push_bulk(Bytes::from("keepttl".as_bytes()));
+        }
+        match self.condition {
+            Some(SetCondition::NotExists) => frame.push_bulk(Bytes::from("nx".as_bytes())),
+            Some(SetCondition::Exists) => frame.push_bulk(Bytes::from("xx".as_bytes())),
+            None => {}
+        }
+        if self.get {
+            frame.push_bulk(Bytes::from("get".as_bytes()));
         }
         frame
     }
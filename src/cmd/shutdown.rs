@@ -0,0 +1,84 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{rdb, Connection, Db, Frame};
+
+use tracing::{debug, error, instrument};
+
+/// Stop the server.
+///
+/// The server stops accepting connections, and connections are closed once
+/// they finish the command they are running. A snapshot of the data is then
+/// saved, if requested. No reply is sent, the connection is closed instead.
+///
+/// As in Redis, if the snapshot cannot be saved, an error is returned and the
+/// server keeps running.
+#[derive(Debug, Default)]
+pub struct ShutdownServer {
+    /// Whether to save a snapshot, overriding `ServerConfig::save_on_shutdown`.
+    save: Option<bool>,
+}
+
+impl ShutdownServer {
+    /// Create a new `ShutdownServer` command. `save` overrides whether a
+    /// snapshot is saved.
+    pub fn new(save: Option<bool>) -> ShutdownServer {
+        ShutdownServer { save }
+    }
+
+    /// Parse a `ShutdownServer` instance from a received frame.
+    ///
+    /// The `SHUTDOWN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// SHUTDOWN [NOSAVE|SAVE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ShutdownServer> {
+        let save = match parse.next_string() {
+            Ok(s) => match &s.to_uppercase()[..] {
+                "SAVE" => Some(true),
+                "NOSAVE" => Some(false),
+                option => {
+                    return Err(
+                        format!("protocol error; unknown `SHUTDOWN` option `{}`", option).into(),
+                    )
+                }
+            },
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ShutdownServer { save })
+    }
+
+    /// Apply the `ShutdownServer` command to the specified `Db` instance.
+    ///
+    /// This is called by the server in order to execute a received command.
+    /// Nothing is written to `dst`, unless the snapshot cannot be saved.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        debug!(save = ?self.save, "shutdown requested");
+
+        // The snapshot is saved right away to find out whether it can be.
+        // It is saved again once every connection is closed, so it also
+        // holds the writes completed meanwhile.
+        if self.save.unwrap_or_else(|| db.save_on_shutdown()) {
+            if let Err(err) = rdb::save_now(db).await {
+                error!(cause = %err, "failed to save the snapshot, not shutting down");
+
+                let response =
+                    Frame::Error("ERR Errors trying to SHUTDOWN. Check logs.".to_string());
+                debug!(?response);
+                dst.write_frame(&response).await?;
+
+                return Ok(());
+            }
+        }
+
+        db.shutdown_server(self.save);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Read or reset the slow log.
+///
+/// Commands taking longer than `slowlog-log-slower-than` microseconds to
+/// execute are recorded in the slow log, which holds up to `slowlog-max-len`
+/// entries.
+#[derive(Debug)]
+pub struct SlowLog {
+    subcommand: Subcommand,
+}
+
+#[derive(Debug)]
+enum Subcommand {
+    /// Return up to `count` entries, newest first. All of them if `count` is
+    /// `None`.
+    Get { count: Option<usize> },
+
+    /// Return the number of entries.
+    Len,
+
+    /// Remove every entry.
+    Reset,
+
+    /// A subcommand that is not supported.
+    Unknown(String),
+}
+
+/// Number of entries returned by `SLOWLOG GET` when no count is given.
+const DEFAULT_COUNT: usize = 10;
+
+impl SlowLog {
+    /// Parse a `SlowLog` instance from a received frame.
+    ///
+    /// The `SLOWLOG` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `SLOWLOG`, the subcommand and its
+    /// arguments. A negative count returns every entry.
+    ///
+    /// ```text
+    /// SLOWLOG GET [count]
+    /// SLOWLOG LEN
+    /// SLOWLOG RESET
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SlowLog> {
+        let name = parse.next_string()?;
+
+        let subcommand = match &name.to_lowercase()[..] {
+            "get" => match parse.next_signed_int() {
+                Ok(count) if count < 0 => Subcommand::Get { count: None },
+                Ok(count) => Subcommand::Get {
+                    count: Some(count as usize),
+                },
+                Err(ParseError::EndOfStream) => Subcommand::Get {
+                    count: Some(DEFAULT_COUNT),
+                },
+                Err(err) => return Err(err.into()),
+            },
+            "len" => Subcommand::Len,
+            "reset" => Subcommand::Reset,
+            _ => {
+                // The arguments of unknown subcommands are skipped.
+                loop {
+                    match parse.next_string() {
+                        Ok(_) => {}
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Subcommand::Unknown(name)
+            }
+        };
+
+        Ok(SlowLog { subcommand })
+    }
+
+    /// Apply the `SlowLog` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self.subcommand {
+            // Each entry is an array holding its identifier, the Unix time it
+            // was recorded at, the execution time in microseconds, the
+            // arguments of the command and the address and name of the client.
+            Subcommand::Get { count } => Frame::Array(
+                db.slowlog(count)
+                    .into_iter()
+                    .map(|entry| {
+                        Frame::Array(vec![
+                            Frame::Integer(entry.id as i64),
+                            Frame::Integer(entry.timestamp as i64),
+                            Frame::Integer(entry.duration.as_micros() as i64),
+                            Frame::Array(entry.args.into_iter().map(Frame::Bulk).collect()),
+                            Frame::Bulk(Bytes::from(entry.addr)),
+                            Frame::Bulk(Bytes::from(entry.name)),
+                        ])
+                    })
+                    .collect(),
+            ),
+            Subcommand::Len => Frame::Integer(db.slowlog_len() as i64),
+            Subcommand::Reset => {
+                db.reset_slowlog();
+                Frame::Simple("OK".to_string())
+            }
+            Subcommand::Unknown(name) => Frame::Error(format!(
+                "ERR unknown subcommand '{}'. Try SLOWLOG HELP.",
+                name
+            )),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
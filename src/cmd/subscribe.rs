@@ -26,12 +26,41 @@ pub struct Unsubscribe {
     channels: Vec<String>,
 }
 
+/// Subscribes the client to one or more channel patterns.
+///
+/// Patterns are glob-style, as accepted by `SCAN ... MATCH`. Messages published
+/// on a channel matching a pattern are delivered as `pmessage` frames, which
+/// include the pattern along with the channel.
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<String>,
+}
+
+/// Unsubscribes the client from one or more channel patterns.
+///
+/// When no patterns are specified, the client is unsubscribed from all the
+/// previously subscribed patterns.
+#[derive(Clone, Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<String>,
+}
+
 /// Stream of messages. The stream receives messages from the
 /// `broadcast::Receiver`. We use `stream!` to create a `Stream` that consumes
 /// messages. Because `stream!` values cannot be named, we box the stream using
 /// a trait object.
 type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
 
+/// Stream of messages published on the channels matching a pattern, along with
+/// the name of the channel.
+type PatternMessages = Pin<Box<dyn Stream<Item = (String, Bytes)> + Send>>;
+
+/// The subscriptions of a client, to channels and to patterns.
+struct Subscriptions {
+    channels: StreamMap<String, Messages>,
+    patterns: StreamMap<String, PatternMessages>,
+}
+
 impl Subscribe {
     /// Creates a new `Subscribe` command to listen on the specified channels.
     pub(crate) fn new(channels: &[String]) -> Subscribe {
@@ -100,59 +129,12 @@ impl Subscribe {
     ///
     /// [here]: https://redis.io/topics/pubsub
     pub(crate) async fn apply(
-        mut self,
+        self,
         db: &Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
     ) -> crate::Result<()> {
-        // Each individual channel subscription is handled using a
-        // `sync::broadcast` channel. Messages are then fanned out to all
-        // clients currently subscribed to the channels.
-        //
-        // An individual client may subscribe to multiple channels and may
-        // dynamically add and remove channels from its subscription set. To
-        // handle this, a `StreamMap` is used to track active subscriptions. The
-        // `StreamMap` merges messages from individual broadcast channels as
-        // they are received.
-        let mut subscriptions = StreamMap::new();
-
-        loop {
-            // `self.channels` is used to track additional channels to subscribe
-            // to. When new `SUBSCRIBE` commands are received during the
-            // execution of `apply`, the new channels are pushed onto this vec.
-            for channel_name in self.channels.drain(..) {
-                subscribe_to_channel(channel_name, &mut subscriptions, db, dst).await?;
-            }
-
-            // Wait for one of the following to happen:
-            //
-            // - Receive a message from one of the subscribed channels.
-            // - Receive a subscribe or unsubscribe command from the client.
-            // - A server shutdown signal.
-            select! {
-                // Receive messages from subscribed channels
-                Some((channel_name, msg)) = subscriptions.next() => {
-                    dst.write_frame(&make_message_frame(channel_name, msg)).await?;
-                }
-                res = dst.read_frame() => {
-                    let frame = match res? {
-                        Some(frame) => frame,
-                        // This happens if the remote client has disconnected.
-                        None => return Ok(())
-                    };
-
-                    handle_command(
-                        frame,
-                        &mut self.channels,
-                        &mut subscriptions,
-                        dst,
-                    ).await?;
-                }
-                _ = shutdown.recv() => {
-                    return Ok(());
-                }
-            };
-        }
+        run(self.channels, vec![], db, dst, shutdown).await
     }
 
     /// Converts the command into an equivalent `Frame`.
@@ -169,9 +151,83 @@ impl Subscribe {
     }
 }
 
+/// Run the subscribed state of a connection until the client disconnects or
+/// the server shuts down, starting with subscriptions to `channels` and
+/// `patterns`.
+async fn run(
+    mut channels: Vec<String>,
+    mut patterns: Vec<String>,
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> crate::Result<()> {
+    // Each individual channel subscription is handled using a
+    // `sync::broadcast` channel. Messages are then fanned out to all
+    // clients currently subscribed to the channels.
+    //
+    // An individual client may subscribe to multiple channels and may
+    // dynamically add and remove channels from its subscription set. To
+    // handle this, a `StreamMap` is used to track active subscriptions. The
+    // `StreamMap` merges messages from individual broadcast channels as
+    // they are received. Pattern subscriptions are tracked the same way, in a
+    // `StreamMap` of their own.
+    let mut subscriptions = Subscriptions {
+        channels: StreamMap::new(),
+        patterns: StreamMap::new(),
+    };
+
+    loop {
+        // `channels` and `patterns` are used to track additional subscriptions.
+        // When new `SUBSCRIBE` or `PSUBSCRIBE` commands are received during
+        // the execution of `run`, the new names are pushed onto these vecs.
+        for channel_name in channels.drain(..) {
+            subscribe_to_channel(channel_name, &mut subscriptions, db, dst).await?;
+        }
+
+        for pattern in patterns.drain(..) {
+            subscribe_to_pattern(pattern, &mut subscriptions, db, dst).await?;
+        }
+
+        // Wait for one of the following to happen:
+        //
+        // - Receive a message from one of the subscribed channels.
+        // - Receive a message from a channel matching a subscribed pattern.
+        // - Receive a subscribe or unsubscribe command from the client.
+        // - A server shutdown signal.
+        select! {
+            // Receive messages from subscribed channels
+            Some((channel_name, msg)) = subscriptions.channels.next() => {
+                write_push(dst, make_message_frame(channel_name, msg)).await?;
+            }
+            // Receive messages from channels matching subscribed patterns
+            Some((pattern, (channel_name, msg))) = subscriptions.patterns.next() => {
+                write_push(dst, make_pmessage_frame(pattern, channel_name, msg)).await?;
+            }
+            res = dst.read_frame() => {
+                let frame = match res? {
+                    Some(frame) => frame,
+                    // This happens if the remote client has disconnected.
+                    None => return Ok(())
+                };
+
+                handle_command(
+                    frame,
+                    &mut channels,
+                    &mut patterns,
+                    &mut subscriptions,
+                    dst,
+                ).await?;
+            }
+            _ = shutdown.recv() => {
+                return Ok(());
+            }
+        };
+    }
+}
+
 async fn subscribe_to_channel(
     channel_name: String,
-    subscriptions: &mut StreamMap<String, Messages>,
+    subscriptions: &mut Subscriptions,
     db: &Db,
     dst: &mut Connection,
 ) -> crate::Result<()> {
@@ -190,35 +246,66 @@ async fn subscribe_to_channel(
     });
 
     // Track subscription in this client's subscription set.
-    subscriptions.insert(channel_name.clone(), rx);
+    subscriptions.channels.insert(channel_name.clone(), rx);
 
     // Respond with the successful subscription
     let response = make_subscribe_frame(channel_name, subscriptions.len());
-    dst.write_frame(&response).await?;
+    write_push(dst, response).await?;
 
     Ok(())
 }
 
-/// Handle a command received while inside `Subscribe::apply`. Only subscribe
-/// and unsubscribe commands are permitted in this context.
+async fn subscribe_to_pattern(
+    pattern: String,
+    subscriptions: &mut Subscriptions,
+    db: &Db,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    let mut rx = db.psubscribe(pattern.clone());
+
+    let rx = Box::pin(async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(msg) => yield msg,
+                // If we lagged in consuming messages, just resume.
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+    });
+
+    subscriptions.patterns.insert(pattern.clone(), rx);
+
+    let response = make_psubscribe_frame(pattern, subscriptions.len());
+    write_push(dst, response).await?;
+
+    Ok(())
+}
+
+/// Handle a command received while inside `run`. Only subscribe and
+/// unsubscribe commands are permitted in this context.
 ///
-/// Any new subscriptions are appended to `subscribe_to` instead of modifying
-/// `subscriptions`.
+/// Any new subscriptions are appended to `subscribe_to` or `psubscribe_to`
+/// instead of modifying `subscriptions`.
 async fn handle_command(
     frame: Frame,
     subscribe_to: &mut Vec<String>,
-    subscriptions: &mut StreamMap<String, Messages>,
+    psubscribe_to: &mut Vec<String>,
+    subscriptions: &mut Subscriptions,
     dst: &mut Connection,
 ) -> crate::Result<()> {
     // A command has been received from the client.
     //
-    // Only `SUBSCRIBE` and `UNSUBSCRIBE` commands are permitted
-    // in this context.
+    // Only `SUBSCRIBE`, `UNSUBSCRIBE`, `PSUBSCRIBE` and `PUNSUBSCRIBE`
+    // commands are permitted in this context.
     match Command::from_frame(frame)? {
         Command::Subscribe(subscribe) => {
-            // The `apply` method will subscribe to the channels we add to this
+            // The `run` function will subscribe to the channels we add to this
             // vector.
-            subscribe_to.extend(subscribe.channels.into_iter());
+            subscribe_to.extend(subscribe.channels);
+        }
+        Command::PSubscribe(psubscribe) => {
+            psubscribe_to.extend(psubscribe.patterns);
         }
         Command::Unsubscribe(mut unsubscribe) => {
             // If no channels are specified, this requests unsubscribing from
@@ -227,16 +314,34 @@ async fn handle_command(
             // to.
             if unsubscribe.channels.is_empty() {
                 unsubscribe.channels = subscriptions
+                    .channels
                     .keys()
                     .map(|channel_name| channel_name.to_string())
                     .collect();
             }
 
             for channel_name in unsubscribe.channels {
-                subscriptions.remove(&channel_name);
+                subscriptions.channels.remove(&channel_name);
 
                 let response = make_unsubscribe_frame(channel_name, subscriptions.len());
-                dst.write_frame(&response).await?;
+                write_push(dst, response).await?;
+            }
+        }
+        Command::PUnsubscribe(mut punsubscribe) => {
+            // As with `UNSUBSCRIBE`, no patterns means all patterns.
+            if punsubscribe.patterns.is_empty() {
+                punsubscribe.patterns = subscriptions
+                    .patterns
+                    .keys()
+                    .map(|pattern| pattern.to_string())
+                    .collect();
+            }
+
+            for pattern in punsubscribe.patterns {
+                subscriptions.patterns.remove(&pattern);
+
+                let response = make_punsubscribe_frame(pattern, subscriptions.len());
+                write_push(dst, response).await?;
             }
         }
         command => {
@@ -247,6 +352,20 @@ async fn handle_command(
     Ok(())
 }
 
+/// Writes a pub/sub frame built by one of the `make_*_frame` functions.
+///
+/// In RESP3, pub/sub messages are out of band data and are sent as push
+/// frames. In RESP2 they are plain arrays.
+async fn write_push(dst: &mut Connection, frame: Frame) -> crate::Result<()> {
+    let frame = match frame {
+        Frame::Array(entries) if dst.protocol() >= 3 => Frame::Push(entries),
+        frame => frame,
+    };
+
+    dst.write_frame(&frame).await?;
+    Ok(())
+}
+
 /// Creates the response to a subcribe request.
 ///
 /// All of these functions take the `channel_name` as a `String` instead of
@@ -257,7 +376,7 @@ fn make_subscribe_frame(channel_name: String, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"subscribe"));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
     response
 }
 
@@ -266,7 +385,25 @@ fn make_unsubscribe_frame(channel_name: String, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"unsubscribe"));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
+    response
+}
+
+/// Creates the response to a psubscribe request.
+fn make_psubscribe_frame(pattern: String, num_subs: usize) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"psubscribe"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_int(num_subs as i64);
+    response
+}
+
+/// Creates the response to a punsubscribe request.
+fn make_punsubscribe_frame(pattern: String, num_subs: usize) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"punsubscribe"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_int(num_subs as i64);
     response
 }
 
@@ -280,6 +417,24 @@ fn make_message_frame(channel_name: String, msg: Bytes) -> Frame {
     response
 }
 
+/// Creates a message informing the client about a new message on a channel
+/// matching a pattern that the client subscribes to.
+fn make_pmessage_frame(pattern: String, channel_name: String, msg: Bytes) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"pmessage"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_bulk(Bytes::from(channel_name));
+    response.push_bulk(msg);
+    response
+}
+
+impl Subscriptions {
+    /// Returns the number of channels and patterns subscribed to.
+    fn len(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+}
+
 impl Unsubscribe {
     /// Create a new `Unsubscribe` command with the given `channels`.
     pub(crate) fn new(channels: &[String]) -> Unsubscribe {
@@ -349,3 +504,117 @@ impl Unsubscribe {
         frame
     }
 }
+
+impl PSubscribe {
+    /// Creates a new `PSubscribe` command to listen on the channels matching
+    /// the specified patterns.
+    pub(crate) fn new(patterns: &[String]) -> PSubscribe {
+        PSubscribe {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    /// Parse a `PSubscribe` instance from a received frame.
+    ///
+    /// The `PSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// PSUBSCRIBE pattern [pattern ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSubscribe> {
+        use ParseError::EndOfStream;
+
+        let mut patterns = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PSubscribe { patterns })
+    }
+
+    /// Apply the `PSubscribe` command to the specified `Db` instance.
+    ///
+    /// As with `Subscribe::apply`, the connection remains subscribed until the
+    /// client disconnects, and may update its subscriptions meanwhile.
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        run(vec![], self.patterns, db, dst, shutdown).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PSubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("psubscribe".as_bytes()));
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+        frame
+    }
+}
+
+impl PUnsubscribe {
+    /// Create a new `PUnsubscribe` command with the given `patterns`.
+    pub(crate) fn new(patterns: &[String]) -> PUnsubscribe {
+        PUnsubscribe {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    /// Parse a `PUnsubscribe` instance from a received frame.
+    ///
+    /// The `PUNSUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// PUNSUBSCRIBE [pattern [pattern ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<PUnsubscribe, ParseError> {
+        use ParseError::EndOfStream;
+
+        let mut patterns = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(PUnsubscribe { patterns })
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `PUnsubscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("punsubscribe".as_bytes()));
+
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+
+        frame
+    }
+}
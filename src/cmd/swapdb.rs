@@ -0,0 +1,59 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Swap two logical databases.
+///
+/// Connections bound to either database immediately see the data of the other
+/// database.
+#[derive(Debug)]
+pub struct SwapDb {
+    /// First database to swap.
+    a: u64,
+
+    /// Second database to swap.
+    b: u64,
+}
+
+impl SwapDb {
+    /// Create a new `SwapDb` command which swaps databases `a` and `b`.
+    pub fn new(a: u64, b: u64) -> SwapDb {
+        SwapDb { a, b }
+    }
+
+    /// Parse a `SwapDb` instance from a received frame.
+    ///
+    /// The `SWAPDB` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SWAPDB index1 index2
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SwapDb> {
+        let a = parse.next_int()?;
+        let b = parse.next_int()?;
+
+        Ok(SwapDb { a, b })
+    }
+
+    /// Apply the `SwapDb` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if db.swap(self.a as usize, self.b as usize) {
+            Frame::Simple("OK".to_string())
+        } else {
+            Frame::Error("ERR invalid DB index".to_string())
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
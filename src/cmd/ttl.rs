@@ -0,0 +1,69 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the remaining time to live of a key.
+///
+/// `TTL` replies in seconds and `PTTL` in milliseconds. The reply is `-2` if
+/// the key does not exist and `-1` if the key exists but has no associated
+/// expiration.
+#[derive(Debug)]
+pub struct Ttl {
+    /// Name of the key to inspect.
+    key: String,
+
+    /// Reply in milliseconds instead of seconds.
+    millis: bool,
+}
+
+impl Ttl {
+    /// Parse a `Ttl` instance from a received frame.
+    ///
+    /// The `TTL` or `PTTL` string has already been consumed. `millis` is set
+    /// for `PTTL`.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// TTL key
+    /// PTTL key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse, millis: bool) -> crate::Result<Ttl> {
+        let key = parse.next_string()?;
+
+        Ok(Ttl { key, millis })
+    }
+
+    /// Apply the `Ttl` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let ttl = match db.ttl(&self.key) {
+            None => -2,
+            Some(None) => -1,
+            Some(Some(left)) if self.millis => left.as_millis() as i64,
+            // Rounded to the nearest second.
+            Some(Some(left)) => ((left.as_millis() + 500) / 1000) as i64,
+        };
+
+        let response = Frame::Integer(ttl);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Returns the name of the command, either `ttl` or `pttl`.
+    pub(crate) fn get_name(&self) -> &'static str {
+        if self.millis {
+            "pttl"
+        } else {
+            "ttl"
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::cmd::{Parse, ParseError, Transaction};
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Marks keys to be watched for conditional execution of a transaction.
+///
+/// If any of the keys is modified before `EXEC`, the transaction is not
+/// executed.
+#[derive(Debug)]
+pub struct Watch {
+    keys: Vec<String>,
+}
+
+/// Forgets all keys watched by the connection.
+#[derive(Debug, Default)]
+pub struct Unwatch {}
+
+impl Watch {
+    /// Parse a `Watch` instance from a received frame.
+    ///
+    /// The `WATCH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least two entries.
+    ///
+    /// ```text
+    /// WATCH key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Watch> {
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Watch { keys })
+    }
+
+    /// Apply the `Watch` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, transaction))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        transaction: &mut Transaction,
+    ) -> crate::Result<()> {
+        let response = if transaction.is_active() {
+            Frame::Error("ERR WATCH inside MULTI is not allowed".to_string())
+        } else {
+            for key in self.keys {
+                transaction.watch(db, key);
+            }
+
+            Frame::Simple("OK".to_string())
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl Unwatch {
+    /// Create a new `Unwatch` command.
+    pub fn new() -> Unwatch {
+        Unwatch {}
+    }
+
+    /// Parse an `Unwatch` instance from a received frame.
+    ///
+    /// The `UNWATCH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// UNWATCH
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Unwatch> {
+        Ok(Unwatch {})
+    }
+
+    /// Apply the `Unwatch` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst, transaction))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        transaction: &mut Transaction,
+    ) -> crate::Result<()> {
+        transaction.unwatch();
+
+        let response = Frame::Simple("OK".to_string());
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
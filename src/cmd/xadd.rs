@@ -0,0 +1,83 @@
+use crate::cmd::{Parse, ParseError};
+use crate::stream::{Fields, NewId, StreamId};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Add an entry to the stream stored at key.
+///
+/// If the key does not exist, a new stream is created. The response is the
+/// identifier of the entry.
+#[derive(Debug)]
+pub struct XAdd {
+    /// Name of the key holding the stream.
+    key: String,
+
+    /// Identifier of the entry.
+    id: NewId,
+
+    /// Fields of the entry, along with their values.
+    fields: Fields,
+}
+
+impl XAdd {
+    /// Parse a `XAdd` instance from a received frame.
+    ///
+    /// The `XADD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least five entries. The identifier
+    /// is `*` to generate it from the current time, `ms-*` to only generate
+    /// the sequence number, or `ms-seq`.
+    ///
+    /// ```text
+    /// XADD key id field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAdd> {
+        let key = parse.next_string()?;
+        let id = parse_new_id(&parse.next_string()?)?;
+        let mut fields = vec![(parse.next_bytes()?, parse.next_bytes()?)];
+
+        loop {
+            match parse.next_bytes() {
+                Ok(field) => fields.push((field, parse.next_bytes()?)),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(XAdd { key, id, fields })
+    }
+
+    /// Apply the `XAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.xadd(&self.key, self.id, self.fields) {
+            Ok(id) => Frame::Bulk(Bytes::from(id.to_string())),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+fn parse_new_id(src: &str) -> crate::Result<NewId> {
+    if src == "*" {
+        return Ok(NewId::Auto);
+    }
+
+    let id = match src.strip_suffix("-*") {
+        Some(ms) => ms.parse().ok().map(NewId::Ms),
+        None => StreamId::parse(src, 0).map(NewId::Explicit),
+    };
+
+    id.ok_or_else(|| "protocol error; invalid stream ID".into())
+}
@@ -0,0 +1,48 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the number of entries of the stream stored at key.
+///
+/// A missing key is an empty stream.
+#[derive(Debug)]
+pub struct XLen {
+    /// Name of the key holding the stream.
+    key: String,
+}
+
+impl XLen {
+    /// Parse a `XLen` instance from a received frame.
+    ///
+    /// The `XLEN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// XLEN key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XLen> {
+        let key = parse.next_string()?;
+
+        Ok(XLen { key })
+    }
+
+    /// Apply the `XLen` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.xlen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
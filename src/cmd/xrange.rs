@@ -0,0 +1,112 @@
+use crate::cmd::{Parse, ParseError};
+use crate::stream::{StreamEntries, StreamId};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the entries of the stream stored at key with an identifier between
+/// start and end.
+///
+/// Both bounds are inclusive. `-` and `+` stand for the smallest and greatest
+/// identifiers. Entries are ordered by identifier.
+#[derive(Debug)]
+pub struct XRange {
+    /// Name of the key holding the stream.
+    key: String,
+
+    /// Identifier of the first entry to return.
+    start: StreamId,
+
+    /// Identifier of the last entry to return.
+    end: StreamId,
+
+    /// Maximum number of entries to return.
+    count: usize,
+}
+
+impl XRange {
+    /// Parse a `XRange` instance from a received frame.
+    ///
+    /// The `XRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or six entries. The sequence
+    /// number of `start` defaults to the smallest one, and the one of `end`
+    /// to the greatest.
+    ///
+    /// ```text
+    /// XRANGE key start end [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XRange> {
+        let key = parse.next_string()?;
+
+        let start = match &parse.next_string()?[..] {
+            "-" => Some(StreamId::MIN),
+            start => StreamId::parse(start, 0),
+        };
+
+        let end = match &parse.next_string()?[..] {
+            "+" => Some(StreamId::MAX),
+            end => StreamId::parse(end, u64::MAX),
+        };
+
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Err("protocol error; invalid stream ID".into()),
+        };
+
+        let count = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "COUNT" => parse.next_int()? as usize,
+            Ok(_) => return Err("currently `XRANGE` only supports the COUNT option".into()),
+            Err(ParseError::EndOfStream) => usize::MAX,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(XRange {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+
+    /// Apply the `XRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.xrange(&self.key, self.start, self.end, self.count) {
+            Ok(entries) => entries_frame(entries),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Returns stream entries as sent to clients: an array holding, for each
+/// entry, its identifier and an array of its fields and values.
+pub(super) fn entries_frame(entries: StreamEntries) -> Frame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| {
+            let fields = fields
+                .into_iter()
+                .flat_map(|(field, value)| vec![Frame::Bulk(field), Frame::Bulk(value)])
+                .collect();
+
+            Frame::Array(vec![
+                Frame::Bulk(Bytes::from(id.to_string())),
+                Frame::Array(fields),
+            ])
+        })
+        .collect();
+
+    Frame::Array(entries)
+}
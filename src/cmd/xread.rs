@@ -0,0 +1,176 @@
+use crate::cmd::bpop::sleep_until;
+use crate::cmd::xrange::entries_frame;
+use crate::cmd::Parse;
+use crate::stream::{StreamEntries, StreamId};
+use crate::{Connection, Db, Frame, Shutdown};
+
+use bytes::Bytes;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Returns the entries of the given streams added after the given identifiers.
+///
+/// With `BLOCK`, if none of the streams has such entries, the connection
+/// blocks until another client adds to one of them or the timeout elapses.
+/// The identifier `$` stands for the last entry of the stream, so only entries
+/// added while blocking are returned.
+///
+/// The response holds, for each stream with entries to return, its key and
+/// its entries, or nil if there are none.
+#[derive(Debug)]
+pub struct XRead {
+    /// Keys of the streams to read, along with the identifier to return
+    /// entries after. `None` stands for `$`.
+    streams: Vec<(String, Option<StreamId>)>,
+
+    /// Maximum number of entries to return for each stream.
+    count: usize,
+
+    /// How long to block for. `None` does not block, and a zero duration
+    /// blocks indefinitely.
+    block: Option<Duration>,
+}
+
+impl XRead {
+    /// Parse a `XRead` instance from a received frame.
+    ///
+    /// The `XREAD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries, with as many
+    /// identifiers as keys. The timeout is in milliseconds.
+    ///
+    /// ```text
+    /// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XRead> {
+        let mut count = usize::MAX;
+        let mut block = None;
+
+        loop {
+            match &parse.next_string()?.to_uppercase()[..] {
+                "COUNT" => count = parse.next_int()? as usize,
+                "BLOCK" => block = Some(Duration::from_millis(parse.next_int()?)),
+                "STREAMS" => break,
+                option => {
+                    return Err(
+                        format!("protocol error; unknown `XREAD` option `{}`", option).into(),
+                    )
+                }
+            }
+        }
+
+        let args = parse.next_strings()?;
+
+        if args.len() % 2 != 0 {
+            return Err("protocol error; each stream needs an identifier".into());
+        }
+
+        let (keys, ids) = args.split_at(args.len() / 2);
+        let mut streams = vec![];
+
+        for (key, id) in keys.iter().zip(ids) {
+            let id = match &id[..] {
+                "$" => None,
+                id => match StreamId::parse(id, 0) {
+                    Some(id) => Some(id),
+                    None => return Err("protocol error; invalid stream ID".into()),
+                },
+            };
+
+            streams.push((key.clone(), id));
+        }
+
+        Ok(XRead {
+            streams,
+            count,
+            block,
+        })
+    }
+
+    /// Apply the `XRead` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        mut self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        // A timeout too large to be represented blocks forever, as `0` does.
+        let deadline = match self.block {
+            Some(timeout) if timeout > Duration::ZERO => Instant::now().checked_add(timeout),
+            _ => None,
+        };
+
+        let keys: Vec<String> = self.streams.iter().map(|(key, _)| key.clone()).collect();
+        let waiter = Arc::new(Notify::new());
+
+        // Inside `EXEC`, the transaction already holds the lock exclusively.
+        // Blocking would stall the whole server while a transaction runs.
+        let in_transaction = dst.is_capturing();
+        let blocking = self.block.is_some() && !in_transaction;
+
+        let response = loop {
+            let guard = match in_transaction {
+                true => None,
+                false => Some(db.lock_shared().await),
+            };
+
+            let waiting = if blocking { Some(&waiter) } else { None };
+
+            match db.xread(&mut self.streams, self.count, waiting) {
+                Ok(found) if !found.is_empty() => break streams_frame(found, dst.protocol()),
+                Ok(_) => {}
+                Err(err) => break Frame::Error(err.to_string()),
+            }
+
+            drop(guard);
+
+            if !blocking {
+                break Frame::Null;
+            }
+
+            // Wait for an entry to be added to one of the streams, then try
+            // again.
+            tokio::select! {
+                _ = waiter.notified() => {}
+                _ = sleep_until(deadline) => break Frame::Null,
+                _ = shutdown.recv() => {
+                    db.unblock(&keys, &waiter);
+                    return Ok(());
+                }
+            }
+        };
+
+        db.unblock(&keys, &waiter);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the entries read from each stream. RESP3 clients receive a map of
+/// keys to entries.
+fn streams_frame(streams: Vec<(String, StreamEntries)>, protocol: u8) -> Frame {
+    let streams = streams
+        .into_iter()
+        .map(|(key, entries)| (Frame::Bulk(Bytes::from(key)), entries_frame(entries)));
+
+    if protocol >= 3 {
+        Frame::Map(streams.collect())
+    } else {
+        Frame::Array(
+            streams
+                .map(|(key, entries)| Frame::Array(vec![key, entries]))
+                .collect(),
+        )
+    }
+}
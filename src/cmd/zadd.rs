@@ -0,0 +1,63 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Add members with the given scores to the sorted set stored at key.
+///
+/// If the key does not exist, a new sorted set is created. The score of
+/// members that already exist is updated. The response is the number of
+/// members that were added.
+#[derive(Debug)]
+pub struct ZAdd {
+    /// Name of the key holding the sorted set.
+    key: String,
+
+    /// Members to add, along with their scores.
+    members: Vec<(f64, String)>,
+}
+
+impl ZAdd {
+    /// Parse a `ZAdd` instance from a received frame.
+    ///
+    /// The `ZADD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// ZADD key score member [score member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZAdd> {
+        let key = parse.next_string()?;
+        let mut members = vec![(parse.next_float()?, parse.next_string()?)];
+
+        loop {
+            match parse.next_float() {
+                Ok(score) => members.push((score, parse.next_string()?)),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ZAdd { key, members })
+    }
+
+    /// Apply the `ZAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zadd(&self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
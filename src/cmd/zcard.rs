@@ -0,0 +1,48 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the number of members of the sorted set stored at key.
+///
+/// A missing key is an empty sorted set.
+#[derive(Debug)]
+pub struct ZCard {
+    /// Name of the key holding the sorted set.
+    key: String,
+}
+
+impl ZCard {
+    /// Parse a `ZCard` instance from a received frame.
+    ///
+    /// The `ZCARD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// ZCARD key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZCard> {
+        let key = parse.next_string()?;
+
+        Ok(ZCard { key })
+    }
+
+    /// Apply the `ZCard` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zcard(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
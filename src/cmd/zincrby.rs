@@ -0,0 +1,61 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Increment the score of member in the sorted set stored at key.
+///
+/// A missing key or member is treated as a score of `0`. The response is the
+/// new score. The increment may be negative.
+#[derive(Debug)]
+pub struct ZIncrBy {
+    /// Name of the key holding the sorted set.
+    key: String,
+
+    /// Amount to add to the score.
+    increment: f64,
+
+    /// Member whose score is incremented.
+    member: String,
+}
+
+impl ZIncrBy {
+    /// Parse a `ZIncrBy` instance from a received frame.
+    ///
+    /// The `ZINCRBY` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// ZINCRBY key increment member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZIncrBy> {
+        let key = parse.next_string()?;
+        let increment = parse.next_float()?;
+        let member = parse.next_string()?;
+
+        Ok(ZIncrBy {
+            key,
+            increment,
+            member,
+        })
+    }
+
+    /// Apply the `ZIncrBy` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zincrby(&self.key, &self.member, self.increment) {
+            Ok(score) => Frame::Double(score),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
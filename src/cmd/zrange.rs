@@ -0,0 +1,99 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the specified range of members of the sorted set stored at key.
+///
+/// Members are ordered from the lowest to the highest score. `start` and
+/// `stop` are zero-based ranks, both inclusive. Negative ranks count from the
+/// highest score: `-1` is the last member.
+#[derive(Debug)]
+pub struct ZRange {
+    /// Name of the key holding the sorted set.
+    key: String,
+
+    /// Rank of the first member to return.
+    start: i64,
+
+    /// Rank of the last member to return.
+    stop: i64,
+
+    /// Include the scores in the response.
+    with_scores: bool,
+}
+
+impl ZRange {
+    /// Parse a `ZRange` instance from a received frame.
+    ///
+    /// The `ZRANGE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or five entries.
+    ///
+    /// ```text
+    /// ZRANGE key start stop [WITHSCORES]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_signed_int()?;
+        let stop = parse.next_signed_int()?;
+
+        let with_scores = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "WITHSCORES" => true,
+            Ok(_) => return Err("currently `ZRANGE` only supports the WITHSCORES option".into()),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ZRange {
+            key,
+            start,
+            stop,
+            with_scores,
+        })
+    }
+
+    /// Apply the `ZRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrange(&self.key, self.start, self.stop) {
+            Ok(members) => members_frame(members, self.with_scores, dst.protocol()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Build the response to a sorted set range query.
+///
+/// With scores, RESP3 clients receive a `[member, score]` pair per member,
+/// while RESP2 clients receive a flat array alternating between members and
+/// scores.
+pub(super) fn members_frame(members: Vec<(String, f64)>, with_scores: bool, protocol: u8) -> Frame {
+    let mut response = vec![];
+
+    for (member, score) in members {
+        let member = Frame::Bulk(Bytes::from(member));
+
+        if !with_scores {
+            response.push(member);
+        } else if protocol >= 3 {
+            response.push(Frame::Array(vec![member, Frame::Double(score)]));
+        } else {
+            response.push(member);
+            response.push(Frame::Double(score));
+        }
+    }
+
+    Frame::Array(response)
+}
@@ -0,0 +1,124 @@
+use crate::cmd::zrange::members_frame;
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use std::convert::TryFrom;
+use std::ops::Bound;
+use tracing::{debug, instrument};
+
+/// Returns the members of the sorted set stored at key with a score between
+/// min and max.
+///
+/// Bounds are inclusive unless prefixed with `(`. `-inf` and `+inf` may be
+/// used for unbounded ranges. Members are ordered from the lowest to the
+/// highest score.
+#[derive(Debug)]
+pub struct ZRangeByScore {
+    /// Name of the key holding the sorted set.
+    key: String,
+
+    /// Lowest score to return.
+    min: Bound<f64>,
+
+    /// Highest score to return.
+    max: Bound<f64>,
+
+    /// Include the scores in the response.
+    with_scores: bool,
+
+    /// Number of matching members to skip.
+    offset: usize,
+
+    /// Maximum number of members to return.
+    count: usize,
+}
+
+impl ZRangeByScore {
+    /// Parse a `ZRangeByScore` instance from a received frame.
+    ///
+    /// The `ZRANGEBYSCORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries. A negative
+    /// `count` returns all members after `offset`.
+    ///
+    /// ```text
+    /// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRangeByScore> {
+        let key = parse.next_string()?;
+        let min = parse_bound(&parse.next_string()?)?;
+        let max = parse_bound(&parse.next_string()?)?;
+
+        let mut with_scores = false;
+        let mut offset = 0;
+        let mut count = usize::MAX;
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "WITHSCORES" => with_scores = true,
+                Ok(s) if s.to_uppercase() == "LIMIT" => {
+                    offset = parse.next_int()? as usize;
+                    count = usize::try_from(parse.next_signed_int()?).unwrap_or(usize::MAX);
+                }
+                Ok(_) => {
+                    return Err(
+                        "currently `ZRANGEBYSCORE` only supports the WITHSCORES and LIMIT options"
+                            .into(),
+                    )
+                }
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ZRangeByScore {
+            key,
+            min,
+            max,
+            with_scores,
+            offset,
+            count,
+        })
+    }
+
+    /// Apply the `ZRangeByScore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let members = db.zrangebyscore(&self.key, self.min, self.max, self.offset, self.count);
+
+        let response = match members {
+            Ok(members) => members_frame(members, self.with_scores, dst.protocol()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Parse a score range bound: a number, optionally prefixed with `(` to make
+/// it exclusive.
+fn parse_bound(src: &str) -> crate::Result<Bound<f64>> {
+    let (src, exclusive) = match src.strip_prefix('(') {
+        Some(src) => (src, true),
+        None => (src, false),
+    };
+
+    let score = match src.parse::<f64>() {
+        Ok(score) if !score.is_nan() => score,
+        _ => return Err("protocol error; min or max is not a float".into()),
+    };
+
+    Ok(if exclusive {
+        Bound::Excluded(score)
+    } else {
+        Bound::Included(score)
+    })
+}
@@ -0,0 +1,62 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Remove members from the sorted set stored at key.
+///
+/// Members that do not exist are ignored. Once the last member is removed, the
+/// key is deleted. The response is the number of members that were removed.
+#[derive(Debug)]
+pub struct ZRem {
+    /// Name of the key holding the sorted set.
+    key: String,
+
+    /// Members to remove.
+    members: Vec<String>,
+}
+
+impl ZRem {
+    /// Parse a `ZRem` instance from a received frame.
+    ///
+    /// The `ZREM` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// ZREM key member [member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRem> {
+        let key = parse.next_string()?;
+        let mut members = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(member) => members.push(member),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ZRem { key, members })
+    }
+
+    /// Apply the `ZRem` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrem(&self.key, &self.members) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
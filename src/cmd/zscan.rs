@@ -0,0 +1,76 @@
+use crate::cmd::scan::{page_frame, ScanOptions};
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Iterate over the members of the sorted set stored at key.
+///
+/// Follows the same cursor semantics as `SCAN`. The items of each page
+/// alternate between members and their score. A missing key is an empty
+/// sorted set.
+#[derive(Debug)]
+pub struct ZScan {
+    /// Name of the key holding the sorted set.
+    key: String,
+
+    /// Position to resume the iteration from.
+    cursor: u64,
+
+    /// Only return the members matching the pattern.
+    options: ScanOptions,
+}
+
+impl ZScan {
+    /// Parse a `ZScan` instance from a received frame.
+    ///
+    /// The `ZSCAN` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// ZSCAN key cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZScan> {
+        let key = parse.next_string()?;
+        let cursor = parse.next_int()?;
+        let options = ScanOptions::parse(parse, |_, _| Ok(false))?;
+
+        Ok(ZScan {
+            key,
+            cursor,
+            options,
+        })
+    }
+
+    /// Apply the `ZScan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zscan(&self.key, self.cursor, self.options.count) {
+            Ok((cursor, members)) => {
+                let mut items = vec![];
+
+                for (member, score) in members {
+                    if self.options.matches(&member) {
+                        items.push(Frame::Bulk(Bytes::from(member)));
+                        // Scores are sent as strings, as Redis does.
+                        items.push(Frame::Bulk(Bytes::from(score.to_string())));
+                    }
+                }
+
+                page_frame(cursor, items)
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
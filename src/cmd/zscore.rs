@@ -0,0 +1,54 @@
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the score of member in the sorted set stored at key.
+///
+/// If the key or the member does not exist, the special value nil is
+/// returned.
+#[derive(Debug)]
+pub struct ZScore {
+    /// Name of the key holding the sorted set.
+    key: String,
+
+    /// Member to look up.
+    member: String,
+}
+
+impl ZScore {
+    /// Parse a `ZScore` instance from a received frame.
+    ///
+    /// The `ZSCORE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// ZSCORE key member
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZScore> {
+        let key = parse.next_string()?;
+        let member = parse.next_string()?;
+
+        Ok(ZScore { key, member })
+    }
+
+    /// Apply the `ZScore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zscore(&self.key, &self.member) {
+            Ok(Some(score)) => Frame::Double(score),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
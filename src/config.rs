@@ -0,0 +1,633 @@
+//! Server configuration.
+//!
+//! `ServerConfig` gathers the settings that shape how the server runs. It is
+//! passed to [`server::run_with_config`](crate::server::run_with_config).
+//!
+//! Once the server runs, the settings are read with `CONFIG GET` and some of
+//! them are changed with `CONFIG SET`. Parameters are named as in `redis.conf`.
+
+use crate::glob;
+
+use std::fmt;
+use std::ops::BitOr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Default number of logical databases.
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// Default path of the snapshot file.
+pub const DEFAULT_DBFILENAME: &str = "dump.rdb";
+
+/// Default path of the append only file.
+pub const DEFAULT_APPENDFILENAME: &str = "appendonly.aof";
+
+/// Default threshold of the slow log, in microseconds.
+pub const DEFAULT_SLOWLOG_LOG_SLOWER_THAN: i64 = 10_000;
+
+/// Default maximum number of entries of the slow log.
+pub const DEFAULT_SLOWLOG_MAX_LEN: usize = 128;
+
+/// Settings for a mini-redis server.
+///
+/// Use `ServerConfig::default()` and override the fields of interest.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Number of logical databases available through `SELECT`.
+    pub databases: usize,
+
+    /// Path of the snapshot file written by `SAVE` and `BGSAVE`. Unless the
+    /// append only file is enabled, the snapshot is loaded on startup.
+    pub dbfilename: PathBuf,
+
+    /// Log every write to the append only file, and replay the file on
+    /// startup.
+    pub appendonly: bool,
+
+    /// Path of the append only file.
+    pub appendfilename: PathBuf,
+
+    /// When the append only file is flushed to disk.
+    pub appendfsync: AppendFsync,
+
+    /// Password of the `default` user. When set, clients must authenticate
+    /// with `AUTH` before running any other command.
+    pub requirepass: Option<String>,
+
+    /// Certificate and private key used to accept TLS connections. When
+    /// `None`, connections are not encrypted.
+    pub tls: Option<TlsConfig>,
+
+    /// Path of a unix domain socket to accept connections on, in addition to
+    /// the TCP listener. An existing file at this path is replaced.
+    pub unixsocket: Option<PathBuf>,
+
+    /// Classes of keyspace events published over pub/sub. None by default.
+    /// May be changed at runtime with `CONFIG SET notify-keyspace-events`.
+    pub notify_keyspace_events: KeyspaceEvents,
+
+    /// Approximate amount of memory the data may use, in bytes. Once it is
+    /// reached, keys are evicted as directed by `maxmemory_policy`. `0`, the
+    /// default, means there is no limit.
+    pub maxmemory: usize,
+
+    /// How keys are picked for eviction once `maxmemory` is reached.
+    pub maxmemory_policy: MaxMemoryPolicy,
+
+    /// Commands taking longer than this many microseconds to execute are
+    /// recorded in the slow log. `0` records every command, and a negative
+    /// value disables the slow log.
+    pub slowlog_log_slower_than: i64,
+
+    /// Maximum number of entries of the slow log. Once it is full, the oldest
+    /// entry is dropped for each new one.
+    pub slowlog_max_len: usize,
+
+    /// Save a snapshot once the server stopped, such as on Ctrl-C. `SHUTDOWN
+    /// SAVE` and `SHUTDOWN NOSAVE` override it for a single shutdown.
+    pub save_on_shutdown: bool,
+
+    /// When snapshots are saved in the background while the server runs. None
+    /// by default. May be changed at runtime with `CONFIG SET save`.
+    pub save: SavePoints,
+
+    /// Port of the TCP listener, as reported by `CONFIG GET port`. It is set
+    /// by `run_with_config` from the address of the listener.
+    pub port: u16,
+
+    /// Hashes with more fields than this are converted from the `listpack`
+    /// encoding to `hashtable`. `128` by default.
+    pub hash_max_listpack_entries: usize,
+
+    /// Hashes with a field or value longer than this, in bytes, are converted
+    /// from the `listpack` encoding to `hashtable`. `64` by default.
+    pub hash_max_listpack_value: usize,
+
+    /// Lists larger than this are converted from the `listpack` encoding to
+    /// `quicklist`. A positive value is a number of elements. A negative one,
+    /// from `-1` to `-5`, is a size of 4, 8, 16, 32 or 64 KiB. `-2` by
+    /// default.
+    pub list_max_listpack_size: i64,
+
+    /// Sets with more members than this use the `hashtable` encoding instead
+    /// of `intset`. `512` by default. mini-redis has no sets, the parameter
+    /// is only stored.
+    pub set_max_intset_entries: usize,
+
+    /// Sorted sets with more members than this are converted from the
+    /// `listpack` encoding to `skiplist`. `128` by default.
+    pub zset_max_listpack_entries: usize,
+
+    /// Sorted sets with a member longer than this, in bytes, are converted
+    /// from the `listpack` encoding to `skiplist`. `64` by default.
+    pub zset_max_listpack_value: usize,
+}
+
+/// Certificate and private key presented by a server accepting TLS
+/// connections.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Path of a PEM file holding the certificate chain, starting with the
+    /// server certificate.
+    pub cert_file: PathBuf,
+
+    /// Path of a PEM file holding the private key of the server certificate.
+    pub key_file: PathBuf,
+}
+
+/// Policy for flushing the append only file to disk.
+///
+/// Writes are always handed to the operating system right away. The policy
+/// controls how often the file is synced, trading durability for throughput.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppendFsync {
+    /// Sync after every write. A crash loses no acknowledged write.
+    Always,
+
+    /// Sync once per second. A crash loses at most a second of writes.
+    EverySec,
+
+    /// Never sync, leaving it to the operating system.
+    No,
+}
+
+/// How keys are picked for eviction once `maxmemory` is reached, as set by
+/// `maxmemory-policy`.
+///
+/// Like Redis, mini-redis does not look for the best key to evict among all of
+/// them. It samples a few keys from each database and evicts the best one
+/// among those.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxMemoryPolicy {
+    /// Evict nothing. Commands that would store more data are refused.
+    NoEviction,
+
+    /// Evict the least recently used keys.
+    AllKeysLru,
+
+    /// Evict the least frequently used keys.
+    AllKeysLfu,
+
+    /// Evict random keys.
+    AllKeysRandom,
+
+    /// Evict the least recently used keys among those with an expiration.
+    VolatileLru,
+
+    /// Evict the least frequently used keys among those with an expiration.
+    VolatileLfu,
+
+    /// Evict random keys among those with an expiration.
+    VolatileRandom,
+
+    /// Evict the keys with an expiration that expire first.
+    VolatileTtl,
+}
+
+/// When snapshots are saved in the background, as set by `save`.
+///
+/// Written as in Redis, as pairs of numbers such as `3600 1 300 100`: a
+/// snapshot is saved once both `seconds` elapsed and `changes` writes were
+/// applied since the last save, for any of the `seconds changes` pairs. Empty
+/// when snapshots are only saved on demand.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavePoints(Vec<(u64, u64)>);
+
+/// Classes of keyspace events published over pub/sub, as set by
+/// `notify-keyspace-events`.
+///
+/// Events are written as in Redis, one character per class. `K` publishes to
+/// `__keyspace@<db>__:<key>` channels with the event as message, and `E` to
+/// `__keyevent@<db>__:<event>` channels with the key as message. The event
+/// classes are `g` for generic commands such as `EXPIRE`, `$` for strings, `l`
+/// for lists, `h` for hashes, `z` for sorted sets, `t` for streams, `x` for
+/// expired keys and `e` for evicted keys. `A` stands for all of them.
+///
+/// Nothing is published unless `K` or `E` is set along with an event class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyspaceEvents(u16);
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            databases: DEFAULT_DATABASES,
+            dbfilename: PathBuf::from(DEFAULT_DBFILENAME),
+            appendonly: false,
+            appendfilename: PathBuf::from(DEFAULT_APPENDFILENAME),
+            appendfsync: AppendFsync::EverySec,
+            requirepass: None,
+            tls: None,
+            unixsocket: None,
+            notify_keyspace_events: KeyspaceEvents::default(),
+            maxmemory: 0,
+            maxmemory_policy: MaxMemoryPolicy::NoEviction,
+            slowlog_log_slower_than: DEFAULT_SLOWLOG_LOG_SLOWER_THAN,
+            slowlog_max_len: DEFAULT_SLOWLOG_MAX_LEN,
+            save_on_shutdown: false,
+            save: SavePoints::default(),
+            port: crate::DEFAULT_PORT,
+            hash_max_listpack_entries: 128,
+            hash_max_listpack_value: 64,
+            list_max_listpack_size: -2,
+            set_max_intset_entries: 512,
+            zset_max_listpack_entries: 128,
+            zset_max_listpack_value: 64,
+        }
+    }
+}
+
+/// Parameters known to `CONFIG GET` and `CONFIG SET`, along with whether they
+/// may be changed while the server runs.
+const PARAMETERS: &[(&str, bool)] = &[
+    ("port", false),
+    ("databases", false),
+    ("dbfilename", true),
+    ("appendonly", false),
+    ("appendfilename", false),
+    ("appendfsync", true),
+    ("requirepass", true),
+    ("unixsocket", false),
+    ("notify-keyspace-events", true),
+    ("maxmemory", true),
+    ("maxmemory-policy", true),
+    ("slowlog-log-slower-than", true),
+    ("slowlog-max-len", true),
+    ("save", true),
+    ("hash-max-listpack-entries", true),
+    ("hash-max-listpack-value", true),
+    ("list-max-listpack-size", true),
+    ("set-max-intset-entries", true),
+    ("zset-max-listpack-entries", true),
+    ("zset-max-listpack-value", true),
+];
+
+/// Reason given when `CONFIG SET` is passed an invalid number.
+const NOT_INTEGER: &str = "argument couldn't be parsed into an integer";
+
+impl ServerConfig {
+    /// Returns the parameters matching the glob-style `pattern` along with
+    /// their value, as reported by `CONFIG GET`.
+    pub(crate) fn get_parameters(&self, pattern: &str) -> Vec<(&'static str, String)> {
+        let pattern = pattern.to_lowercase();
+
+        PARAMETERS
+            .iter()
+            .filter(|(name, _)| glob::matches(pattern.as_bytes(), name.as_bytes()))
+            .map(|(name, _)| (*name, self.get_parameter(name)))
+            .collect()
+    }
+
+    /// Returns the value of `name`, which must be listed in `PARAMETERS`.
+    fn get_parameter(&self, name: &str) -> String {
+        match name {
+            "port" => self.port.to_string(),
+            "databases" => self.databases.to_string(),
+            "dbfilename" => self.dbfilename.display().to_string(),
+            "appendonly" => if self.appendonly { "yes" } else { "no" }.to_string(),
+            "appendfilename" => self.appendfilename.display().to_string(),
+            "appendfsync" => self.appendfsync.to_string(),
+            "requirepass" => self.requirepass.clone().unwrap_or_default(),
+            "unixsocket" => self
+                .unixsocket
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            "notify-keyspace-events" => self.notify_keyspace_events.to_string(),
+            "maxmemory" => self.maxmemory.to_string(),
+            "maxmemory-policy" => self.maxmemory_policy.to_string(),
+            "slowlog-log-slower-than" => self.slowlog_log_slower_than.to_string(),
+            "slowlog-max-len" => self.slowlog_max_len.to_string(),
+            "save" => self.save.to_string(),
+            "hash-max-listpack-entries" => self.hash_max_listpack_entries.to_string(),
+            "hash-max-listpack-value" => self.hash_max_listpack_value.to_string(),
+            "list-max-listpack-size" => self.list_max_listpack_size.to_string(),
+            "set-max-intset-entries" => self.set_max_intset_entries.to_string(),
+            "zset-max-listpack-entries" => self.zset_max_listpack_entries.to_string(),
+            "zset-max-listpack-value" => self.zset_max_listpack_value.to_string(),
+            _ => unreachable!("unknown parameter `{}`", name),
+        }
+    }
+
+    /// Set the parameter `name` to `value`, as done by `CONFIG SET`.
+    ///
+    /// On failure, the settings are left unchanged and the error to reply with
+    /// is returned.
+    pub(crate) fn set_parameter(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let name = name.to_lowercase();
+
+        let failed = |reason: &str| {
+            format!(
+                "ERR CONFIG SET failed (possibly related to argument '{}') - {}",
+                name, reason
+            )
+        };
+
+        match PARAMETERS.iter().find(|(known, _)| *known == name) {
+            Some((_, true)) => {}
+            Some((_, false)) => return Err(failed("can't set immutable config")),
+            None => {
+                return Err(format!(
+                    "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                    name
+                ))
+            }
+        }
+
+        match &name[..] {
+            "dbfilename" if value.is_empty() => return Err(failed("dbfilename can't be empty")),
+            "dbfilename" => self.dbfilename = PathBuf::from(value),
+            "appendfsync" => {
+                self.appendfsync = value.parse().map_err(|err: String| failed(&err))?
+            }
+            "requirepass" if value.is_empty() => self.requirepass = None,
+            "requirepass" => self.requirepass = Some(value.to_string()),
+            "notify-keyspace-events" => {
+                self.notify_keyspace_events = value.parse().map_err(|err: String| failed(&err))?
+            }
+            "maxmemory" => self.maxmemory = parse_memory(value).map_err(|err| failed(&err))?,
+            "maxmemory-policy" => {
+                self.maxmemory_policy = value.parse().map_err(|err: String| failed(&err))?
+            }
+            "slowlog-log-slower-than" => {
+                self.slowlog_log_slower_than = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            "slowlog-max-len" => {
+                self.slowlog_max_len = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            "save" => self.save = value.parse().map_err(|err: String| failed(&err))?,
+            "hash-max-listpack-entries" => {
+                self.hash_max_listpack_entries = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            "hash-max-listpack-value" => {
+                self.hash_max_listpack_value = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            "list-max-listpack-size" => {
+                self.list_max_listpack_size = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            "set-max-intset-entries" => {
+                self.set_max_intset_entries = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            "zset-max-listpack-entries" => {
+                self.zset_max_listpack_entries = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            "zset-max-listpack-value" => {
+                self.zset_max_listpack_value = value.parse().map_err(|_| failed(NOT_INTEGER))?
+            }
+            _ => unreachable!("unknown parameter `{}`", name),
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse an amount of memory, in bytes unless followed by a unit, as in
+/// `redis.conf`. `k`, `m` and `g` are powers of 1000, `kb`, `mb` and `gb`
+/// powers of 1024. Units are case insensitive.
+fn parse_memory(s: &str) -> Result<usize, String> {
+    let lower = s.to_lowercase();
+    let digits = lower.trim_end_matches(char::is_alphabetic);
+
+    let unit = match &lower[digits.len()..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid memory unit in `{}`", s)),
+    };
+
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(unit))
+        .ok_or_else(|| format!("invalid amount of memory `{}`", s))
+}
+
+impl FromStr for AppendFsync {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AppendFsync, String> {
+        match &s.to_lowercase()[..] {
+            "always" => Ok(AppendFsync::Always),
+            "everysec" => Ok(AppendFsync::EverySec),
+            "no" => Ok(AppendFsync::No),
+            _ => Err(format!(
+                "invalid appendfsync policy `{}`, expected always, everysec or no",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AppendFsync {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppendFsync::Always => "always".fmt(fmt),
+            AppendFsync::EverySec => "everysec".fmt(fmt),
+            AppendFsync::No => "no".fmt(fmt),
+        }
+    }
+}
+
+impl SavePoints {
+    /// Returns `true` if a snapshot is due, `elapsed` after the last save
+    /// during which `changes` writes were applied.
+    pub(crate) fn is_due(&self, elapsed: Duration, changes: u64) -> bool {
+        changes > 0
+            && self
+                .0
+                .iter()
+                .any(|&(seconds, min)| elapsed.as_secs() >= seconds && changes >= min)
+    }
+}
+
+impl FromStr for SavePoints {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SavePoints, String> {
+        let numbers = s
+            .split_whitespace()
+            .map(|number| number.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid save parameters `{}`", s))?;
+
+        if numbers.len() % 2 != 0 {
+            return Err(format!("invalid save parameters `{}`", s));
+        }
+
+        let points = numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+
+        Ok(SavePoints(points))
+    }
+}
+
+impl fmt::Display for SavePoints {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (seconds, changes)) in self.0.iter().enumerate() {
+            if i > 0 {
+                " ".fmt(fmt)?;
+            }
+
+            write!(fmt, "{} {}", seconds, changes)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl MaxMemoryPolicy {
+    /// Every policy, along with its name.
+    const NAMES: [(&'static str, MaxMemoryPolicy); 8] = [
+        ("noeviction", MaxMemoryPolicy::NoEviction),
+        ("allkeys-lru", MaxMemoryPolicy::AllKeysLru),
+        ("allkeys-lfu", MaxMemoryPolicy::AllKeysLfu),
+        ("allkeys-random", MaxMemoryPolicy::AllKeysRandom),
+        ("volatile-lru", MaxMemoryPolicy::VolatileLru),
+        ("volatile-lfu", MaxMemoryPolicy::VolatileLfu),
+        ("volatile-random", MaxMemoryPolicy::VolatileRandom),
+        ("volatile-ttl", MaxMemoryPolicy::VolatileTtl),
+    ];
+
+    /// Returns `true` if only keys with an expiration may be evicted.
+    pub(crate) fn is_volatile(self) -> bool {
+        matches!(
+            self,
+            MaxMemoryPolicy::VolatileLru
+                | MaxMemoryPolicy::VolatileLfu
+                | MaxMemoryPolicy::VolatileRandom
+                | MaxMemoryPolicy::VolatileTtl
+        )
+    }
+}
+
+impl FromStr for MaxMemoryPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MaxMemoryPolicy, String> {
+        let lower = s.to_lowercase();
+
+        MaxMemoryPolicy::NAMES
+            .iter()
+            .find(|(name, _)| *name == lower)
+            .map(|(_, policy)| *policy)
+            .ok_or_else(|| format!("invalid maxmemory policy `{}`", s))
+    }
+}
+
+impl fmt::Display for MaxMemoryPolicy {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let (name, _) = MaxMemoryPolicy::NAMES
+            .iter()
+            .find(|(_, policy)| policy == self)
+            .unwrap();
+
+        name.fmt(fmt)
+    }
+}
+
+impl KeyspaceEvents {
+    /// Publish to `__keyspace@<db>__:<key>` channels.
+    pub const KEYSPACE: KeyspaceEvents = KeyspaceEvents(1 << 0);
+
+    /// Publish to `__keyevent@<db>__:<event>` channels.
+    pub const KEYEVENT: KeyspaceEvents = KeyspaceEvents(1 << 1);
+
+    /// Commands that are not specific to a type, such as `EXPIRE`.
+    pub const GENERIC: KeyspaceEvents = KeyspaceEvents(1 << 2);
+
+    /// Commands operating on strings.
+    pub const STRING: KeyspaceEvents = KeyspaceEvents(1 << 3);
+
+    /// Commands operating on lists.
+    pub const LIST: KeyspaceEvents = KeyspaceEvents(1 << 4);
+
+    /// Commands operating on hashes.
+    pub const HASH: KeyspaceEvents = KeyspaceEvents(1 << 5);
+
+    /// Commands operating on sorted sets.
+    pub const ZSET: KeyspaceEvents = KeyspaceEvents(1 << 6);
+
+    /// Keys removed because they expired.
+    pub const EXPIRED: KeyspaceEvents = KeyspaceEvents(1 << 7);
+
+    /// Keys removed to free memory under `maxmemory`.
+    pub const EVICTED: KeyspaceEvents = KeyspaceEvents(1 << 8);
+
+    /// Commands operating on streams.
+    pub const STREAM: KeyspaceEvents = KeyspaceEvents(1 << 9);
+
+    /// Every event class, spelled `A`.
+    const ALL: KeyspaceEvents = KeyspaceEvents(0b11_1111_1100);
+
+    /// The character of each class, in the order they are displayed.
+    const CLASSES: [(char, KeyspaceEvents); 10] = [
+        ('g', KeyspaceEvents::GENERIC),
+        ('$', KeyspaceEvents::STRING),
+        ('l', KeyspaceEvents::LIST),
+        ('h', KeyspaceEvents::HASH),
+        ('z', KeyspaceEvents::ZSET),
+        ('t', KeyspaceEvents::STREAM),
+        ('x', KeyspaceEvents::EXPIRED),
+        ('e', KeyspaceEvents::EVICTED),
+        ('K', KeyspaceEvents::KEYSPACE),
+        ('E', KeyspaceEvents::KEYEVENT),
+    ];
+
+    /// Returns `true` if every class set in `other` is also set in `self`.
+    pub fn contains(self, other: KeyspaceEvents) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for KeyspaceEvents {
+    type Output = KeyspaceEvents;
+
+    fn bitor(self, other: KeyspaceEvents) -> KeyspaceEvents {
+        KeyspaceEvents(self.0 | other.0)
+    }
+}
+
+impl FromStr for KeyspaceEvents {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<KeyspaceEvents, String> {
+        let mut events = KeyspaceEvents::default();
+
+        for c in s.chars() {
+            let class = match c {
+                'A' => KeyspaceEvents::ALL,
+                c => KeyspaceEvents::CLASSES
+                    .iter()
+                    .find(|(flag, _)| *flag == c)
+                    .map(|(_, class)| *class)
+                    .ok_or_else(|| format!("invalid keyspace event class `{}`", c))?,
+            };
+
+            events = events | class;
+        }
+
+        Ok(events)
+    }
+}
+
+impl fmt::Display for KeyspaceEvents {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut remaining = *self;
+
+        if remaining.contains(KeyspaceEvents::ALL) {
+            "A".fmt(fmt)?;
+            remaining = KeyspaceEvents(remaining.0 & !KeyspaceEvents::ALL.0);
+        }
+
+        for (flag, class) in KeyspaceEvents::CLASSES {
+            if remaining.contains(class) {
+                flag.fmt(fmt)?;
+            }
+        }
+
+        Ok(())
+    }
+}
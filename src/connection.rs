@@ -1,47 +1,158 @@
-use crate::frame::{Error as FrameError, Frame};
+use crate::frame::{self, Checker, Frame};
 
 use bytes::{Buf, BytesMut};
+use std::fmt;
 use std::io::{self, Cursor};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 
 /// Send and receive `Frame` values from a remote peer.
 ///
 /// When implementing networking protocols, a message on that protocol is
 /// often composed of several smaller messages known as frames. The purpose of
-/// `Connection` is to read and write frames on the underlying `TcpStream`.
+/// `Connection` is to read and write frames on the underlying byte stream,
+/// either a `TcpStream` or a TLS stream wrapping one.
 ///
 /// To read frames, the `Connection` uses an internal buffer, which is filled
 /// up until there are enough bytes to create a full frame. Once this happens,
 /// the `Connection` creates the frame and returns it to the caller.
 ///
 /// When sending frames, the frame is first encoded into the write buffer.
-/// The contents of the write buffer are then written to the socket.
+/// The contents of the write buffer are then written to the socket with as
+/// few writes as possible.
 #[derive(Debug)]
 pub struct Connection {
-    // The `TcpStream`. It is decorated with a `BufWriter`, which provides write
-    // level buffering. The `BufWriter` implementation provided by Tokio is
-    // sufficient for our needs.
-    stream: BufWriter<TcpStream>,
+    // The underlying stream, usually a `TcpStream`.
+    stream: Box<dyn Stream>,
 
     // The buffer for reading frames.
     buffer: BytesMut,
+
+    // The buffer frames are encoded into before being written to `stream`.
+    write_buf: BytesMut,
+
+    // Tracks how much of the frame at the start of `buffer` has been checked,
+    // so the bytes already received are not scanned again after each read.
+    checker: Checker,
+
+    // The RESP protocol version used to encode frames. Connections start with
+    // RESP2 and may switch to RESP3 using `HELLO`.
+    protocol: u8,
+
+    // When set, frames passed to `write_frame` are collected here instead of
+    // being written to the socket. See `begin_capture`.
+    capture: Option<Vec<Frame>>,
+
+    // The user the peer is authenticated as. Only used by the server, where
+    // `None` means the peer has yet to authenticate.
+    user: Option<String>,
+
+    // The identifier of the peer in the registry of clients. Only used by the
+    // server, where every connection is registered.
+    client_id: u64,
+
+    // Whether commands may be sent inline, as a line of arguments rather than
+    // a RESP array. Only used by the server, see `accept_inline`.
+    inline: bool,
 }
 
+/// Inline commands longer than this are rejected, so a peer sending bytes
+/// without ever ending the line cannot grow the read buffer indefinitely.
+const MAX_INLINE_LEN: usize = 64 * 1024;
+
+/// A byte stream a `Connection` can be backed by.
+pub(crate) trait Stream: AsyncRead + AsyncWrite + Send + Unpin + fmt::Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin + fmt::Debug> Stream for T {}
+
 impl Connection {
     /// Create a new `Connection`, backed by `socket`. Read and write buffers
     /// are initialized.
     pub fn new(socket: TcpStream) -> Connection {
+        Connection::from_stream(socket)
+    }
+
+    /// Create a new `Connection`, backed by any byte stream, such as a TLS
+    /// stream.
+    pub(crate) fn from_stream(stream: impl Stream + 'static) -> Connection {
         Connection {
-            stream: BufWriter::new(socket),
+            stream: Box::new(stream),
             // Default to a 4KB read buffer. For the use case of mini redis,
             // this is fine. However, real applications will want to tune this
             // value to their specific use case. There is a high likelihood that
             // a larger read buffer will work better.
             buffer: BytesMut::with_capacity(4 * 1024),
+            write_buf: BytesMut::with_capacity(4 * 1024),
+            checker: Checker::default(),
+            protocol: 2,
+            capture: None,
+            user: None,
+            client_id: 0,
+            inline: false,
         }
     }
 
+    /// Returns the RESP protocol version used by the connection.
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    /// Set the RESP protocol version used to encode frames.
+    ///
+    /// This only affects how frames are written. Frames of both versions are
+    /// always accepted when reading.
+    pub fn set_protocol(&mut self, version: u8) {
+        self.protocol = version;
+    }
+
+    /// Returns the user the peer is authenticated as.
+    pub(crate) fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Set the user the peer is authenticated as.
+    pub(crate) fn set_user(&mut self, user: Option<String>) {
+        self.user = user;
+    }
+
+    /// Returns the identifier of the peer, as reported by `CLIENT ID`.
+    pub(crate) fn client_id(&self) -> u64 {
+        self.client_id
+    }
+
+    /// Set the identifier of the peer.
+    pub(crate) fn set_client_id(&mut self, id: u64) {
+        self.client_id = id;
+    }
+
+    /// Accept commands sent inline, such as `PING\r\n` typed in a telnet
+    /// session, in addition to RESP arrays.
+    ///
+    /// Inline commands are read as arrays of bulk strings. Only the server
+    /// enables this, as it is the only peer reading commands.
+    pub(crate) fn accept_inline(&mut self) {
+        self.inline = true;
+    }
+
+    /// Start collecting written frames instead of sending them.
+    ///
+    /// This lets the replies of several commands be gathered and sent as a
+    /// single frame, as `EXEC` does.
+    pub(crate) fn begin_capture(&mut self) {
+        self.capture = Some(vec![]);
+    }
+
+    /// Stop collecting written frames and return the frames written since
+    /// `begin_capture`.
+    pub(crate) fn end_capture(&mut self) -> Vec<Frame> {
+        self.capture.take().unwrap_or_default()
+    }
+
+    /// Returns `true` if written frames are being collected.
+    pub(crate) fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
     /// Read a single `Frame` value from the underlying stream.
     ///
     /// The function waits until it has retrieved enough data to parse a frame.
@@ -85,151 +196,148 @@ impl Connection {
     /// enough data has been buffered yet, `Ok(None)` is returned. If the
     /// buffered data does not represent a valid frame, `Err` is returned.
     fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
-        // Cursor is used to track the "current" location in the
-        // buffer. Cursor also implements `Buf` from the `bytes` crate
-        // which provides a number of helpful utilities for working
-        // with bytes.
-        let mut buf = Cursor::new(&self.buffer[..]);
+        // Commands are either RESP arrays or inline commands, which are told
+        // apart by their first byte.
+        if self.inline {
+            if let Some(frame) = self.parse_inline()? {
+                return Ok(Some(frame));
+            }
+
+            // The inline command has yet to be received in full.
+            if self.buffer.first().is_none_or(|&b| b != b'*') {
+                return Ok(None);
+            }
+        }
 
         // The first step is to check if enough data has been buffered to parse
         // a single frame. This step is usually much faster than doing a full
         // parse of the frame, and allows us to skip allocating data structures
         // to hold the frame data unless we know the full frame has been
         // received.
+        //
+        // The checker resumes where the previous call stopped, so a large
+        // frame arriving over many reads is only scanned once. It returns the
+        // number of bytes the frame occupies on the wire.
+        //
+        // If there is not enough data present in the read buffer, `None` is
+        // returned and we must wait for more data to be received from the
+        // socket. This is an expected runtime condition, not an error.
+        //
+        // If the data is not a valid frame, the connection is now in an
+        // invalid state. Returning `Err` from here will result in the
+        // connection being closed.
+        let len = match self.checker.check(&self.buffer[..])? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
 
-        match Frame::check(&mut buf) {
-            Ok(_) => {
-                // The `check` function will have advanced the cursor until the
-                // end of the frame. Since the cursor had position set to zero
-                // before `Frame::check` was called, we obtain the length of the
-                // frame by checking the cursor position.
-                let len = buf.position() as usize;
-
-                // Reset the position to zero before passing the cursor to
-                // `Frame::parse`.
-                buf.set_position(0);
-
-                // Parse the frame from the buffer. This allocates the necessary
-                // structures to represent the frame and returns the frame
-                // value.
-                //
-                // If the encoded frame representation is invalid, an error is
-                // returned. This should terminate the **current** connection
-                // but should not impact any other connected client.
-                let frame = Frame::parse(&mut buf)?;
-
-                // Discard the parsed data from the read buffer.
-                //
-                // When `advance` is called on the read buffer, all of the data
-                // up to `len` is discarded. The details of how this works is
-                // left to `BytesMut`. This is often done by moving an internal
-                // cursor, but it may be done by reallocating and copying data.
-                self.buffer.advance(len);
-
-                // Return the parsed frame to the caller.
-                Ok(Some(frame))
-            }
-            // There is not enough data present in the read buffer to parse a
-            // single frame. We must wait for more data to be received from the
-            // socket. Reading from the socket will be done in the statement
-            // after this `match`.
-            //
-            // We do not want to return `Err` from here as this "error" is an
-            // expected runtime condition.
-            Err(FrameError::Incomplete) => Ok(None),
-            // An error was encountered while parsing the frame. The connection
-            // is now in an invalid state. Returning `Err` from here will result
-            // in the connection being closed.
-            Err(e) => Err(e.into()),
-        }
+        // Cursor is used to track the "current" location in the
+        // buffer. Cursor also implements `Buf` from the `bytes` crate
+        // which provides a number of helpful utilities for working
+        // with bytes. It is limited to the bytes of the checked frame.
+        let mut buf = Cursor::new(&self.buffer[..len]);
+
+        // Parse the frame from the buffer. This allocates the necessary
+        // structures to represent the frame and returns the frame value.
+        //
+        // If the encoded frame representation is invalid, an error is
+        // returned. This should terminate the **current** connection but
+        // should not impact any other connected client.
+        let frame = Frame::parse(&mut buf)?;
+
+        // Discard the parsed data from the read buffer.
+        //
+        // When `advance` is called on the read buffer, all of the data up to
+        // `len` is discarded. The details of how this works is left to
+        // `BytesMut`. This is often done by moving an internal cursor, but it
+        // may be done by reallocating and copying data.
+        self.buffer.advance(len);
+
+        // Return the parsed frame to the caller.
+        Ok(Some(frame))
     }
 
-    /// Write a single `Frame` value to the underlying stream.
+    /// Tries to parse an inline command from the buffer: a line of arguments
+    /// separated by whitespace, ending with `\n` or `\r\n`. Blank lines are
+    /// discarded.
     ///
-    /// The `Frame` value is written to the socket using the various `write_*`
-    /// functions provided by `AsyncWrite`. Calling these functions directly on
-    /// a `TcpStream` is **not** advised, as this will result in a large number of
-    /// syscalls. However, it is fine to call these functions on a *buffered*
-    /// write stream. The data will be written to the buffer. Once the buffer is
-    /// full, it is flushed to the underlying socket.
-    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        // Arrays are encoded by encoding each entry. All other frame types are
-        // considered literals. For now, mini-redis is not able to encode
-        // recursive frame structures. See below for more details.
-        match frame {
-            Frame::Array(val) => {
-                // Encode the frame type prefix. For an array, it is `*`.
-                self.stream.write_u8(b'*').await?;
-
-                // Encode the length of the array.
-                self.write_decimal(val.len() as u64).await?;
-
-                // Iterate and encode each entry in the array.
-                for entry in &**val {
-                    self.write_value(entry).await?;
-                }
+    /// `Ok(None)` is returned if the buffer starts with a RESP array, or with
+    /// a line that has yet to be received in full.
+    fn parse_inline(&mut self) -> crate::Result<Option<Frame>> {
+        while let Some(&first) = self.buffer.first() {
+            if first == b'*' {
+                break;
             }
-            // The frame type is a literal. Encode the value directly.
-            _ => self.write_value(frame).await?,
-        }
 
-        // Ensure the encoded frame is written to the socket. The calls above
-        // are to the buffered stream and writes. Calling `flush` writes the
-        // remaining contents of the buffer to the socket.
-        self.stream.flush().await
-    }
+            let end = match self.buffer.iter().position(|&b| b == b'\n') {
+                Some(end) => end,
+                None if self.buffer.len() > MAX_INLINE_LEN => {
+                    let err = frame::Error::from("protocol error; too big inline request");
+                    return Err(err.into());
+                }
+                None => break,
+            };
 
-    /// Write a frame literal to the stream
-    async fn write_value(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            Frame::Simple(val) => {
-                self.stream.write_u8(b'+').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Error(val) => {
-                self.stream.write_u8(b'-').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Integer(val) => {
-                self.stream.write_u8(b':').await?;
-                self.write_decimal(*val).await?;
-            }
-            Frame::Null => {
-                self.stream.write_all(b"$-1\r\n").await?;
-            }
-            Frame::Bulk(val) => {
-                let len = val.len();
+            let line = self.buffer.split_to(end + 1);
+            let line = &line[..end];
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            let args = frame::split_inline(line)?;
 
-                self.stream.write_u8(b'$').await?;
-                self.write_decimal(len as u64).await?;
-                self.stream.write_all(val).await?;
-                self.stream.write_all(b"\r\n").await?;
+            if !args.is_empty() {
+                let args = args.into_iter().map(Frame::Bulk).collect();
+                return Ok(Some(Frame::Array(args)));
             }
-            // Encoding an `Array` from within a value cannot be done using a
-            // recursive strategy. In general, async fns do not support
-            // recursion. Mini-redis has not needed to encode nested arrays yet,
-            // so for now it is skipped.
-            Frame::Array(_val) => unreachable!(),
         }
 
-        Ok(())
+        Ok(None)
     }
 
-    /// Write a decimal frame to the stream
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
-        use std::io::Write;
+    /// Write a single `Frame` value to the underlying stream.
+    ///
+    /// The `Frame` value is encoded into the write buffer first, without
+    /// awaiting. Writing each part of the frame to a `TcpStream` directly is
+    /// **not** advised, as this would result in a large number of syscalls.
+    /// Instead, the whole buffer is then handed to the socket at once.
+    ///
+    /// Frame types introduced by RESP3 are downgraded to their closest RESP2
+    /// equivalent unless the connection negotiated RESP3.
+    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        self.buffer_frame(frame);
+        self.flush().await
+    }
 
-        // Convert the value to a string
-        let mut buf = [0u8; 20];
-        let mut buf = Cursor::new(&mut buf[..]);
-        write!(&mut buf, "{}", val)?;
+    /// Encode a `Frame` value into the write buffer, without writing it to
+    /// the underlying stream.
+    ///
+    /// Buffered frames are written by the next call to `flush` or
+    /// `write_frame`. Buffering several frames before flushing sends them
+    /// with as few writes as possible, which is how clients pipeline
+    /// commands.
+    pub fn buffer_frame(&mut self, frame: &Frame) {
+        if let Some(captured) = &mut self.capture {
+            captured.push(frame.clone());
+            return;
+        }
 
-        let pos = buf.position() as usize;
-        self.stream.write_all(&buf.get_ref()[..pos]).await?;
-        self.stream.write_all(b"\r\n").await?;
+        frame.write_to(&mut self.write_buf, self.protocol);
+    }
 
-        Ok(())
+    /// Write the buffered frames, then shut down the writing half of the
+    /// underlying stream.
+    ///
+    /// The peer reads every reply before reaching the end of the stream.
+    pub(crate) async fn shutdown(&mut self) -> io::Result<()> {
+        self.flush().await?;
+        self.stream.shutdown().await
+    }
+
+    /// Write the buffered frames to the underlying stream.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        // The written bytes are consumed from the buffer, which keeps its
+        // capacity for the next frame. If the future is dropped midway, the
+        // bytes that were not written yet are sent along with the next frame.
+        self.stream.write_all_buf(&mut self.write_buf).await?;
+        self.stream.flush().await
     }
 }
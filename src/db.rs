@@ -1,10 +1,66 @@
-use tokio::sync::{broadcast, Notify};
+use tokio::sync::{
+    broadcast, mpsc, oneshot, Mutex as AsyncMutex, Notify, OwnedMutexGuard, OwnedRwLockReadGuard,
+    OwnedRwLockWriteGuard, RwLock,
+};
+use tokio::task::JoinHandle;
 use tokio::time::{self, Duration, Instant};
 
+use crate::acl::{self, Acl, User};
+use crate::clients::{Clients, KillFilter};
+use crate::encoding::{self, Thresholds};
+use crate::hash::Hash;
+use crate::hotkeys::HotKeySketch;
+use crate::scan::ScanOrder;
+use crate::slowlog::{self, SlowLog};
+use crate::stats::Stats;
+use crate::stream::{Fields, NewId, Stream, StreamEntries, StreamId};
+use crate::zset::SortedSet;
+use crate::{
+    glob, AppendFsync, Frame, KeyspaceEvents, MaxMemoryPolicy, ServerConfig, ShutdownController,
+};
+
 use bytes::Bytes;
-use std::collections::{BTreeMap, HashMap};
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::ops::Bound;
 use std::sync::{Arc, Mutex};
-use tracing::debug;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fmt, str};
+use tracing::{debug, error};
+
+/// Number of writes held by the replication backlog. A replica falling
+/// further behind is disconnected, and performs a full resynchronization once
+/// it reconnects.
+const REPL_BACKLOG_LEN: usize = 16 * 1024;
+
+/// Number of keys of each database sampled to pick a key to evict.
+const EVICTION_SAMPLES: usize = 5;
+
+/// Number of elements of a collection sampled to estimate its size.
+const MEMORY_SAMPLES: usize = 5;
+
+/// Approximate memory used by an entry, besides its key and value, in bytes.
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Approximate memory used by each element of a collection, besides its data,
+/// in bytes.
+const ELEMENT_OVERHEAD: usize = 16;
+
+/// Access frequency counter of new entries, so they are not evicted before
+/// they get a chance to be accessed again.
+const LFU_INIT: u8 = 5;
+
+/// How slowly the access frequency counter grows. The higher, the more
+/// accesses are needed to increment it.
+const LFU_LOG_FACTOR: f64 = 10.0;
+
+/// Idle time after which the access frequency counter is decremented.
+const LFU_DECAY: Duration = Duration::from_secs(60);
+
+/// Longest sleep, about 30 years. Timers set further in the future are not
+/// supported by Tokio.
+const MAX_TIMER: Duration = Duration::from_secs(30 * 365 * 24 * 60 * 60);
 
 /// A wrapper around a `Db` instance. This exists to allow orderly cleanup
 /// of the `Db` by signalling the background purge task to shut down when
@@ -18,11 +74,15 @@ pub(crate) struct DbDropGuard {
 
 /// Server state shared across all connections.
 ///
-/// `Db` contains a `HashMap` storing the key/value data and all
-/// `broadcast::Sender` values for active pub/sub channels.
+/// `Db` contains a number of logical databases, each one a `HashMap` storing
+/// key/value data, and all `broadcast::Sender` values for active pub/sub
+/// channels.
 ///
 /// A `Db` instance is a handle to shared state. Cloning `Db` is shallow and
-/// only incurs an atomic ref count increment.
+/// only incurs an atomic ref count increment. Each handle is bound to one of
+/// the logical databases, the one key/value operations are applied to. A
+/// connection switches databases by replacing its handle with the one returned
+/// by `Db::select`.
 ///
 /// When a `Db` value is created, a background task is spawned. This task is
 /// used to expire values after the requested duration has elapsed. The task
@@ -33,6 +93,9 @@ pub(crate) struct Db {
     /// Handle to shared state. The background task will also have an
     /// `Arc<Shared>`.
     shared: Arc<Shared>,
+
+    /// Index of the logical database this handle operates on.
+    index: usize,
 }
 
 #[derive(Debug)]
@@ -55,18 +118,134 @@ struct Shared {
     /// task waits on this to be notified, then checks for expired values or the
     /// shutdown signal.
     background_task: Notify,
+
+    /// Makes transactions atomic. Commands hold a read guard while they run,
+    /// `EXEC` holds the write guard while it runs the queued commands.
+    ///
+    /// This is a Tokio lock, as opposed to `state`, since it is held across
+    /// `.await` points while replies are written. Guards are owned, so they do
+    /// not borrow the `Db` handle, which commands such as `SELECT` replace.
+    transactions: Arc<RwLock<()>>,
+
+    /// Held while a snapshot is saved, so saves do not write to the same
+    /// temporary file concurrently.
+    ///
+    /// This is a Tokio lock for the same reason as `transactions`: it is held
+    /// until the snapshot is written, across `.await` points.
+    saving: Arc<AsyncMutex<()>>,
+
+    /// Broadcasts a line describing each command received by the server to
+    /// the connections running `MONITOR`.
+    ///
+    /// This lives outside of `state`, so connections can check for monitors
+    /// without acquiring the lock.
+    monitors: broadcast::Sender<String>,
 }
 
 #[derive(Debug)]
 struct State {
-    /// The key-value data. We are not trying to do anything fancy so a
-    /// `std::collections::HashMap` works fine.
-    entries: HashMap<String, Entry>,
+    /// The logical databases, indexed by the number passed to `SELECT`.
+    databases: Vec<Keyspace>,
+
+    /// The current settings of the server.
+    config: ServerConfig,
 
     /// The pub/sub key-space. Redis uses a **separate** key space for key-value
     /// and pub/sub. `mini-redis` handles this by using a separate `HashMap`.
+    /// Pub/sub channels are not scoped to a logical database.
     pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
 
+    /// The channel patterns subscribed to with `PSUBSCRIBE`, each with its own
+    /// broadcast channel. Messages carry the name of the channel they were
+    /// published on.
+    pattern_sub: HashMap<String, broadcast::Sender<(String, Bytes)>>,
+
+    /// Identifier to use for the next expiration. Each expiration is associated
+    /// with a unique identifier. See `Keyspace::expirations` for why.
+    next_id: u64,
+
+    /// Clients blocked on an empty list or waiting for new entries of a
+    /// stream, by database index and key. Pushing to the list or adding to the
+    /// stream wakes them up.
+    ///
+    /// Each blocked client registers the same `Notify` under every key it
+    /// waits on. `Notify::notify_one` stores a permit when the client is not
+    /// currently waiting, so a push happening between the client's check and
+    /// its wait is not missed.
+    blocked: HashMap<(usize, String), Vec<Arc<Notify>>>,
+
+    /// Approximate access counts of keys, used to report the hottest keys.
+    hotkeys: HotKeySketch,
+
+    /// Counters reported by `INFO`. Connections update them as well, without
+    /// holding the lock.
+    stats: Arc<Stats>,
+
+    /// The connected clients. See `Db::register_client`.
+    clients: Clients,
+
+    /// The slowest commands executed recently.
+    slowlog: SlowLog,
+
+    /// The replication backlog, feeding the replicas the writes applied to the
+    /// key-value store, along with the logical database they apply to. See
+    /// `Db::subscribe_replication`.
+    ///
+    /// The channel is a ring buffer of `REPL_BACKLOG_LEN` writes, so replicas
+    /// that cannot keep up do not make the primary buffer writes without
+    /// bound.
+    replication: broadcast::Sender<(usize, Frame)>,
+
+    /// The receiver of writes of the append only file. See
+    /// `Db::subscribe_aof`. `None` when the append only file is disabled.
+    ///
+    /// The file must not miss any write, so the channel is unbounded.
+    aof: Option<mpsc::UnboundedSender<Write>>,
+
+    /// Notified when `BGREWRITEAOF` is received. `None` when the append only
+    /// file is disabled.
+    aof_rewrite: Option<Arc<Notify>>,
+
+    /// False once a write to the append only file failed, until one succeeds.
+    /// Writes are refused meanwhile, as they would not be persisted.
+    aof_write_ok: bool,
+
+    /// Identifies the data set of this server in the replication protocol.
+    replid: String,
+
+    /// Address of the primary this server replicates, along with the task
+    /// receiving its writes. `None` when this server is a primary.
+    primary: Option<(String, JoinHandle<()>)>,
+
+    /// Users and the commands they may run.
+    acl: Acl,
+
+    /// Stops the server the `Db` belongs to, when `SHUTDOWN` is received.
+    shutdown_controller: ShutdownController,
+
+    /// Whether to save a snapshot once the server stopped, as requested by
+    /// `SHUTDOWN`. `None` defers to `ServerConfig::save_on_shutdown`.
+    save_on_shutdown: Option<bool>,
+
+    /// Number of writes applied since the last snapshot was saved.
+    changes: u64,
+
+    /// When the last snapshot was saved, or the server started.
+    last_save: Instant,
+
+    /// True when the Db instance is shutting down. This happens when all `Db`
+    /// values drop. Setting this to `true` signals to the background task to
+    /// exit.
+    shutdown: bool,
+}
+
+/// A single logical database.
+#[derive(Debug, Default)]
+struct Keyspace {
+    /// The key-value data. We are not trying to do anything fancy so a
+    /// `std::collections::HashMap` works fine.
+    entries: HashMap<String, Entry>,
+
     /// Tracks key TTLs.
     ///
     /// A `BTreeMap` is used to maintain expirations sorted by when they expire.
@@ -79,14 +258,11 @@ struct State {
     /// to break these ties.
     expirations: BTreeMap<(Instant, u64), String>,
 
-    /// Identifier to use for the next expiration. Each expiration is associated
-    /// with a unique identifier. See above for why.
-    next_id: u64,
+    /// The keys, in the iteration order of `SCAN`.
+    scan_order: ScanOrder,
 
-    /// True when the Db instance is shutting down. This happens when all `Db`
-    /// values drop. Setting this to `true` signals to the background task to
-    /// exit.
-    shutdown: bool,
+    /// Sum of the memory used by the entries, in bytes.
+    used_memory: usize,
 }
 
 /// Entry in the key-value store
@@ -96,21 +272,179 @@ struct Entry {
     id: u64,
 
     /// Stored data
-    data: Bytes,
+    value: Value,
+
+    /// Changes every time the entry is modified. Used by `WATCH` to detect
+    /// modifications. Versions are taken from the same counter as `id`.
+    version: u64,
 
     /// Instant at which the entry expires and should be removed from the
     /// database.
     expires_at: Option<Instant>,
+
+    /// Instant at which the entry was last read or written. Used to evict the
+    /// least recently used keys.
+    accessed_at: Instant,
+
+    /// Logarithmic counter of the accesses to the entry, decremented as time
+    /// passes without access. Used to evict the least frequently used keys.
+    frequency: u8,
+
+    /// Approximate memory used by the entry, in bytes, as last estimated by
+    /// the `Keyspace` holding it.
+    memory: usize,
+
+    /// `false` once the value outgrew its compact encoding. It is not set
+    /// back when the value shrinks. See `encoding`.
+    compact: bool,
+}
+
+/// A value stored in the key-value store.
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    /// A plain string, as stored by `SET`.
+    String(Bytes),
+
+    /// A map of fields to values, as stored by `HSET`. A hash is never empty:
+    /// the key is removed along with its last field.
+    Hash(Hash),
+
+    /// A sequence of values, as stored by `LPUSH` and `RPUSH`. A list is never
+    /// empty: the key is removed along with its last element.
+    List(List),
+
+    /// Members ordered by score, as stored by `ZADD`. A sorted set is never
+    /// empty: the key is removed along with its last member.
+    SortedSet(SortedSet),
+
+    /// Entries ordered by identifier, as added by `XADD`. Entries cannot be
+    /// removed, so a stream is never empty either.
+    Stream(Stream),
+}
+
+/// Commands, each along with the logical database it applies to.
+pub(crate) type Commands = Vec<(usize, Frame)>;
+
+/// A write applied to the key-value store, in a form that can be applied
+/// again to rebuild the same data.
+///
+/// Writes are expressed as commands. Relative expirations are converted to
+/// absolute Unix timestamps, and blocking commands to their non-blocking
+/// equivalent, so replaying the commands later has the same effect.
+#[derive(Debug)]
+pub(crate) enum Write {
+    /// A command applied to the logical database `db`.
+    Command { db: usize, frame: Frame },
+
+    /// Commands rebuilding the whole content of the store, each along with the
+    /// logical database it applies to. Writes received afterwards apply on top
+    /// of it.
+    Snapshot(Commands),
+
+    /// A connection waiting for the writes received before this one to be
+    /// synced. Only sent to the append only file, see `Db::aof_synced`.
+    Sync(oneshot::Sender<()>),
+}
+
+/// One of the two ends of a list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum End {
+    Left,
+    Right,
+}
+
+/// Condition on the existence of the key for `SET` to take place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SetCondition {
+    /// Only set the key if it does not exist (`NX`).
+    NotExists,
+    /// Only set the key if it already exists (`XX`).
+    Exists,
+}
+
+/// Options of the `SET` command.
+#[derive(Debug, Default)]
+pub(crate) struct SetOptions {
+    /// When the key expires. `None` means the key does not expire.
+    pub(crate) expires_at: Option<Instant>,
+    /// Retain the time to live of the previous value, if any.
+    pub(crate) keep_ttl: bool,
+    /// Only set the key if this condition holds.
+    pub(crate) condition: Option<SetCondition>,
+    /// Return the previous value.
+    pub(crate) get: bool,
+}
+
+/// Elements of a list value.
+pub(crate) type List = VecDeque<Bytes>;
+
+/// A key along with its value, as stored in a snapshot.
+#[derive(Debug)]
+pub(crate) struct Record {
+    pub(crate) key: String,
+
+    pub(crate) value: Value,
+
+    /// Unix time at which the key expires, in milliseconds.
+    pub(crate) expires_at: Option<i64>,
+}
+
+/// An aggregate type stored in a `Value`.
+///
+/// Aggregates share their semantics regarding missing keys: reading a missing
+/// key behaves as reading an empty aggregate, writing creates it and the key is
+/// removed along with the last element.
+trait Collection: Default {
+    /// Class of the keyspace events published when the collection changes.
+    const EVENTS: KeyspaceEvents;
+
+    fn from_value(value: &Value) -> Option<&Self>;
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut Self>;
+
+    fn into_value(self) -> Value;
+
+    fn is_empty(&self) -> bool;
+}
+
+/// Error returned when an operation cannot be applied to a key.
+///
+/// The `Display` implementation is the error message sent to the client.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Error {
+    /// The key holds a value of a different type than the operation expects.
+    WrongType,
+
+    /// The value is not an integer or is out of range.
+    NotInteger,
+
+    /// The result of an increment or decrement does not fit in an `i64`.
+    Overflow,
+
+    /// The result of a floating point operation is not a number.
+    NaN,
+
+    /// The value is not a floating point number.
+    NotFloat,
+
+    /// The result of a floating point increment is not a number or infinite.
+    NotFinite,
+
+    /// The identifier of a new stream entry is not greater than the one of
+    /// the last entry.
+    StreamIdTooSmall,
 }
 
 impl DbDropGuard {
-    /// Create a new `DbHolder`, wrapping a `Db` instance. When this is dropped
-    /// the `Db`'s purge task will be shut down.
-    pub(crate) fn new() -> DbDropGuard {
-        DbDropGuard { db: Db::new() }
+    /// Create a new `DbHolder`, wrapping a `Db` instance configured with
+    /// `config`. When this is dropped the `Db`'s purge task will be shut down.
+    pub(crate) fn new(config: ServerConfig) -> DbDropGuard {
+        DbDropGuard {
+            db: Db::new(config),
+        }
     }
 
-    /// Get the shared database. Internally, this is an
+    /// Get the shared database, bound to database `0`. Internally, this is an
     /// `Arc`, so a clone only increments the ref count.
     pub(crate) fn db(&self) -> Db {
         self.db.clone()
@@ -125,24 +459,66 @@ impl Drop for DbDropGuard {
 }
 
 impl Db {
-    /// Create a new, empty, `Db` instance. Allocates shared state and spawns a
-    /// background task to manage key expiration.
-    pub(crate) fn new() -> Db {
+    /// Create a new, empty, `Db` instance with `config.databases` logical
+    /// databases. Allocates shared state and spawns a background task to
+    /// manage key expiration.
+    ///
+    /// The returned handle is bound to database `0`.
+    pub(crate) fn new(config: ServerConfig) -> Db {
         let shared = Arc::new(Shared {
             state: Mutex::new(State {
-                entries: HashMap::new(),
+                databases: (0..config.databases).map(|_| Keyspace::default()).collect(),
+                acl: Acl::new(config.requirepass.as_deref()),
+                config,
                 pub_sub: HashMap::new(),
-                expirations: BTreeMap::new(),
+                pattern_sub: HashMap::new(),
                 next_id: 0,
+                blocked: HashMap::new(),
+                hotkeys: HotKeySketch::new(),
+                stats: Arc::new(Stats::new()),
+                clients: Clients::new(),
+                slowlog: SlowLog::new(),
+                replication: broadcast::channel(REPL_BACKLOG_LEN).0,
+                aof: None,
+                aof_rewrite: None,
+                aof_write_ok: true,
+                replid: replid(),
+                primary: None,
+                shutdown_controller: ShutdownController::new(),
+                save_on_shutdown: None,
+                changes: 0,
+                last_save: Instant::now(),
                 shutdown: false,
             }),
             background_task: Notify::new(),
+            transactions: Arc::new(RwLock::new(())),
+            saving: Arc::new(AsyncMutex::new(())),
+            // As with pub/sub channels, lines are dropped for monitors
+            // lagging more than `1024` lines behind.
+            monitors: broadcast::channel(1024).0,
         });
 
         // Start the background task.
         tokio::spawn(purge_expired_tasks(shared.clone()));
 
-        Db { shared }
+        Db { shared, index: 0 }
+    }
+
+    /// Returns a handle to the same shared state, bound to the logical
+    /// database `index`.
+    ///
+    /// Returns `None` if there is no database with that index.
+    pub(crate) fn select(&self, index: usize) -> Option<Db> {
+        let state = self.shared.state.lock().unwrap();
+
+        if index >= state.databases.len() {
+            return None;
+        }
+
+        Some(Db {
+            shared: self.shared.clone(),
+            index,
+        })
     }
 
     /// Get the value associated with a key.
@@ -150,26 +526,108 @@ impl Db {
     /// Returns `None` if there is no value associated with the key. This may be
     /// due to never having assigned a value to the key or a previously assigned
     /// value expired.
-    pub(crate) fn get(&self, key: &str) -> Option<Bytes> {
+    /// Returns `Error::WrongType` if the key holds a value that is not a string.
+    pub(crate) fn get(&self, key: &str) -> Result<Option<Bytes>, Error> {
         // Acquire the lock, get the entry and clone the value.
         //
         // Because data is stored using `Bytes`, a clone here is a shallow
         // clone. Data is not copied.
-        let state = self.shared.state.lock().unwrap();
-        state.entries.get(key).map(|entry| entry.data.clone())
+        let mut state = self.shared.state.lock().unwrap();
+        state.record_access(self.index, key);
+
+        // The background task may not have purged the key yet. Expired keys
+        // are never returned.
+        state.remove_if_expired(self.index, key, Instant::now());
+        state.record_lookup(self.index, key);
+
+        match state.databases[self.index]
+            .entries
+            .get(key)
+            .map(|entry| &entry.value)
+        {
+            Some(Value::String(data)) => Ok(Some(data.clone())),
+            Some(_) => Err(Error::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the values associated with several keys at once.
+    ///
+    /// Keys that do not exist or hold a value that is not a string are
+    /// returned as `None`.
+    pub(crate) fn mget(&self, keys: &[String]) -> Vec<Option<Bytes>> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        keys.iter()
+            .map(|key| {
+                state.record_access(self.index, key);
+                state.remove_if_expired(self.index, key, now);
+                state.record_lookup(self.index, key);
+
+                match state.databases[self.index]
+                    .entries
+                    .get(key)
+                    .map(|entry| &entry.value)
+                {
+                    Some(Value::String(data)) => Some(data.clone()),
+                    _ => None,
+                }
+            })
+            .collect()
     }
 
-    /// Set the value associated with a key along with an optional expiration
-    /// Duration.
+    /// Set the value associated with a key, as directed by `options`.
     ///
-    /// If a value is already associated with the key, it is removed.
-    pub(crate) fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
+    /// If a value is already associated with the key, it is removed, along
+    /// with its expiration unless `options.keep_ttl` is set.
+    ///
+    /// Returns whether the value was set, which only fails to happen when
+    /// `options.condition` is not met. When `options.get` is set, the string
+    /// previously associated with the key is returned as well, and
+    /// `Error::WrongType` is returned without setting anything if the key holds
+    /// a value of another type.
+    pub(crate) fn set(
+        &self,
+        key: String,
+        value: Bytes,
+        options: SetOptions,
+    ) -> Result<(bool, Option<Bytes>), Error> {
         let mut state = self.shared.state.lock().unwrap();
+        state.record_access(self.index, &key);
+
+        // An expired key must not count as existing.
+        state.remove_if_expired(self.index, &key, Instant::now());
+
+        let (exists, previous, prev_expires_at) = {
+            let prev = state.databases[self.index].entries.get(&key);
+
+            let previous = match prev.map(|entry| &entry.value) {
+                Some(Value::String(data)) if options.get => Some(data.clone()),
+                Some(_) if options.get => return Err(Error::WrongType),
+                _ => None,
+            };
+
+            (
+                prev.is_some(),
+                previous,
+                prev.and_then(|entry| entry.expires_at),
+            )
+        };
+
+        let proceed = match options.condition {
+            Some(SetCondition::NotExists) => !exists,
+            Some(SetCondition::Exists) => exists,
+            None => true,
+        };
+
+        if !proceed {
+            return Ok((false, previous));
+        }
 
         // Get and increment the next insertion ID. Guarded by the lock, this
         // ensures a unique identifier is associated with each `set` operation.
-        let id = state.next_id;
-        state.next_id += 1;
+        let id = state.next_id();
 
         // If this `set` becomes the key that expires **next**, the background
         // task needs to be notified so it can update its state.
@@ -178,31 +636,40 @@ impl Db {
         // `set` routine.
         let mut notify = false;
 
-        let expires_at = expire.map(|duration| {
-            // `Instant` at which the key expires.
-            let when = Instant::now() + duration;
-
-            // Only notify the worker task if the newly inserted expiration is the
-            // **next** key to evict. In this case, the worker needs to be woken up
-            // to update its state.
-            notify = state
-                .next_expiration()
-                .map(|expiration| expiration > when)
-                .unwrap_or(true);
-
-            // Track the expiration.
-            state.expirations.insert((when, id), key.clone());
-            when
-        });
+        let expires_at = if options.keep_ttl {
+            prev_expires_at
+        } else {
+            options.expires_at.inspect(|&when| {
+                // Only notify the worker task if the newly inserted expiration
+                // is the **next** key to evict. In this case, the worker needs
+                // to be woken up to update its state.
+                notify = state
+                    .next_expiration()
+                    .map(|expiration| expiration > when)
+                    .unwrap_or(true);
+            })
+        };
+
+        // The expiration is propagated separately, as an absolute timestamp.
+        let mut write = command("SET", &key);
+        write.push_bulk(value.clone());
+        state.propagate(self.index, write);
+
+        if let Some(when) = expires_at {
+            state.propagate(self.index, pexpireat(&key, when));
+        }
+
+        let keyspace = &mut state.databases[self.index];
+
+        // Track the expiration.
+        if let Some(when) = expires_at {
+            keyspace.expirations.insert((when, id), key.clone());
+        }
 
         // Insert the entry into the `HashMap`.
-        let prev = state.entries.insert(
-            key,
-            Entry {
-                id,
-                data: value,
-                expires_at,
-            },
+        let prev = keyspace.insert(
+            key.clone(),
+            Entry::new(id, Value::String(value), expires_at),
         );
 
         // If there was a value previously associated with the key **and** it
@@ -211,10 +678,15 @@ impl Db {
         if let Some(prev) = prev {
             if let Some(when) = prev.expires_at {
                 // clear expiration
-                state.expirations.remove(&(when, prev.id));
+                keyspace.expirations.remove(&(when, prev.id));
             }
         }
 
+        state.notify(self.index, KeyspaceEvents::STRING, "set", &key);
+        if options.expires_at.is_some() && !options.keep_ttl {
+            state.notify(self.index, KeyspaceEvents::GENERIC, "expire", &key);
+        }
+
         // Release the mutex before notifying the background task. This helps
         // reduce contention by avoiding the background task waking up only to
         // be unable to acquire the mutex due to this function still holding it.
@@ -225,127 +697,2319 @@ impl Db {
             // its state to reflect a new expiration.
             self.shared.background_task.notify_one();
         }
+
+        Ok((true, previous))
     }
 
-    /// Returns a `Receiver` for the requested channel.
+    /// Set several keys at once, discarding their previous value and time to
+    /// live.
     ///
-    /// The returned `Receiver` is used to receive values broadcast by `PUBLISH`
-    /// commands.
-    pub(crate) fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
-        use std::collections::hash_map::Entry;
-
-        // Acquire the mutex
+    /// The keys are set atomically: no other operation observes some of them
+    /// set but not the others.
+    pub(crate) fn mset(&self, pairs: Vec<(String, Bytes)>) {
         let mut state = self.shared.state.lock().unwrap();
 
-        // If there is no entry for the requested channel, then create a new
-        // broadcast channel and associate it with the key. If one already
-        // exists, return an associated receiver.
-        match state.pub_sub.entry(key) {
-            Entry::Occupied(e) => e.get().subscribe(),
-            Entry::Vacant(e) => {
-                // No broadcast channel exists yet, so create one.
-                //
-                // The channel is created with a capacity of `1024` messages. A
-                // message is stored in the channel until **all** subscribers
-                // have seen it. This means that a slow subscriber could result
-                // in messages being held indefinitely.
-                //
-                // When the channel's capacity fills up, publishing will result
-                // in old messages being dropped. This prevents slow consumers
-                // from blocking the entire system.
-                let (tx, rx) = broadcast::channel(1024);
-                e.insert(tx);
-                rx
+        let mut write = Frame::array();
+        write.push_bulk(Bytes::from_static(b"MSET"));
+
+        for (key, value) in pairs {
+            state.record_access(self.index, &key);
+            let id = state.next_id();
+
+            write.push_bulk(Bytes::from(key.clone()));
+            write.push_bulk(value.clone());
+
+            let keyspace = &mut state.databases[self.index];
+            let prev = keyspace.insert(key.clone(), Entry::new(id, Value::String(value), None));
+
+            if let Some(prev) = prev {
+                if let Some(when) = prev.expires_at {
+                    keyspace.expirations.remove(&(when, prev.id));
+                }
             }
+
+            state.notify(self.index, KeyspaceEvents::STRING, "set", &key);
         }
+
+        state.propagate(self.index, write);
     }
 
-    /// Publish a message to the channel. Returns the number of subscribers
-    /// listening on the channel.
-    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
-        let state = self.shared.state.lock().unwrap();
+    /// Increment the integer stored at `key` by `delta`, and return the new
+    /// value. A missing key counts as `0`.
+    pub(crate) fn incr_by(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        self.update_string(key, "incrby", |current| {
+            let current = match current {
+                Some(value) => str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .ok_or(Error::NotInteger)?,
+                None => 0,
+            };
 
-        state
-            .pub_sub
-            .get(key)
-            // On a successful message send on the broadcast channel, the number
-            // of subscribers is returned. An error indicates there are no
-            // receivers, in which case, `0` should be returned.
-            .map(|tx| tx.send(value).unwrap_or(0))
-            // If there is no entry for the channel key, then there are no
-            // subscribers. In this case, return `0`.
-            .unwrap_or(0)
+            current.checked_add(delta).ok_or(Error::Overflow)
+        })
     }
 
-    /// Signals the purge background task to shut down. This is called by the
-    /// `DbShutdown`s `Drop` implementation.
-    fn shutdown_purge_task(&self) {
-        // The background task must be signaled to shut down. This is done by
-        // setting `State::shutdown` to `true` and signalling the task.
-        let mut state = self.shared.state.lock().unwrap();
-        state.shutdown = true;
+    /// Increment the floating point number stored at `key` by `delta`, and
+    /// return the new value. A missing key counts as `0`.
+    pub(crate) fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, Error> {
+        self.update_string(key, "incrbyfloat", |current| {
+            let current = match current {
+                Some(value) => str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .filter(|value| !value.is_nan())
+                    .ok_or(Error::NotFloat)?,
+                None => 0.0,
+            };
 
-        // Drop the lock before signalling the background task. This helps
-        // reduce lock contention by ensuring the background task doesn't
-        // wake up only to be unable to acquire the mutex.
-        drop(state);
-        self.shared.background_task.notify_one();
+            let value = current + delta;
+
+            if !value.is_finite() {
+                return Err(Error::NotFinite);
+            }
+
+            Ok(value)
+        })
     }
-}
 
-impl Shared {
-    /// Purge all expired keys and return the `Instant` at which the **next**
-    /// key will expire. The background task will sleep until this instant.
-    fn purge_expired_keys(&self) -> Option<Instant> {
-        let mut state = self.state.lock().unwrap();
+    /// Set the instant at which an existing key expires, replacing any
+    /// previous expiration.
+    ///
+    /// If `when` is not in the future, the key is deleted right away. Returns
+    /// `false` if there is no value associated with the key.
+    pub(crate) fn expire(&self, key: &str, when: Instant) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
 
-        if state.shutdown {
-            // The database is shutting down. All handles to the shared state
-            // have dropped. The background task should exit.
-            return None;
+        // As in `set`, the background task only needs to be woken up if this
+        // becomes the key that expires **next**.
+        let notify = state
+            .next_expiration()
+            .map(|expiration| expiration > when)
+            .unwrap_or(true);
+
+        let version = state.next_id();
+        state.remove_if_expired(self.index, key, now);
+        let keyspace = &mut state.databases[self.index];
+
+        if when <= now {
+            if keyspace.remove(key).is_none() {
+                return false;
+            }
+
+            state.notify(self.index, KeyspaceEvents::GENERIC, "del", key);
+            state.propagate(self.index, pexpireat(key, when));
+            return true;
         }
 
-        // This is needed to make the borrow checker happy. In short, `lock()`
-        // returns a `MutexGuard` and not a `&mut State`. The borrow checker is
-        // not able to see "through" the mutex guard and determine that it is
-        // safe to access both `state.expirations` and `state.entries` mutably,
-        // so we get a "real" mutable reference to `State` outside of the loop.
-        let state = &mut *state;
+        let entry = match keyspace.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
 
-        // Find all keys scheduled to expire **before** now.
-        let now = Instant::now();
+        if let Some(prev) = entry.expires_at.replace(when) {
+            keyspace.expirations.remove(&(prev, entry.id));
+        }
 
-        while let Some((&(when, id), key)) = state.expirations.iter().next() {
-            if when > now {
-                // Done purging, `when` is the instant at which the next key
-                // expires. The worker task will wait until this instant.
-                return Some(when);
-            }
+        entry.version = version;
 
-            // The key expired, remove it
-            state.entries.remove(key);
-            state.expirations.remove(&(when, id));
+        keyspace
+            .expirations
+            .insert((when, entry.id), key.to_string());
+
+        state.notify(self.index, KeyspaceEvents::GENERIC, "expire", key);
+        state.propagate(self.index, pexpireat(key, when));
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
         }
 
-        None
+        true
     }
 
-    /// Returns `true` if the database is shutting down
+    /// Remove the expiration of a key, making it persistent.
     ///
-    /// The `shutdown` flag is set when all `Db` values have dropped, indicating
-    /// that the shared state can no longer be accessed.
-    fn is_shutdown(&self) -> bool {
-        self.state.lock().unwrap().shutdown
-    }
-}
+    /// Returns `false` if there is no value associated with the key or the key
+    /// has no expiration.
+    pub(crate) fn persist(&self, key: &str) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        let version = state.next_id();
+        state.remove_if_expired(self.index, key, Instant::now());
+        let keyspace = &mut state.databases[self.index];
 
-impl State {
-    fn next_expiration(&self) -> Option<Instant> {
-        self.expirations
-            .keys()
-            .next()
-            .map(|expiration| expiration.0)
+        let entry = match keyspace.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        match entry.expires_at.take() {
+            Some(when) => {
+                keyspace.expirations.remove(&(when, entry.id));
+                entry.version = version;
+                state.notify(self.index, KeyspaceEvents::GENERIC, "persist", key);
+                state.propagate(self.index, command("PERSIST", key));
+                true
+            }
+            None => false,
+        }
     }
+
+    /// Remove keys, regardless of the type of their value. Returns the number
+    /// of keys that were removed.
+    pub(crate) fn del(&self, keys: &[String]) -> usize {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        let mut write = Frame::array();
+        write.push_bulk(Bytes::from_static(b"DEL"));
+        let mut removed = 0;
+
+        for key in keys {
+            state.remove_if_expired(self.index, key, now);
+
+            if state.databases[self.index].remove(key).is_some() {
+                removed += 1;
+                write.push_bulk(Bytes::from(key.clone()));
+                state.notify(self.index, KeyspaceEvents::GENERIC, "del", key);
+            }
+        }
+
+        if removed > 0 {
+            state.propagate(self.index, write);
+        }
+
+        removed
+    }
+
+    /// Returns the number of `keys` that exist. A key given several times is
+    /// counted as many times.
+    pub(crate) fn exists(&self, keys: &[String]) -> usize {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        keys.iter()
+            .filter(|key| {
+                state.remove_if_expired(self.index, key, now);
+                state.databases[self.index].entries.contains_key(*key)
+            })
+            .count()
+    }
+
+    /// Returns the current version of a key, or `None` if there is no value
+    /// associated with the key.
+    ///
+    /// The version changes whenever the key is modified. `WATCH` compares
+    /// versions to detect concurrent modifications.
+    pub(crate) fn version(&self, key: &str) -> Option<u64> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.remove_if_expired(self.index, key, Instant::now());
+        state.databases[self.index]
+            .entries
+            .get(key)
+            .map(|entry| entry.version)
+    }
+
+    /// Returns the name of the encoding of a key, as reported by
+    /// `OBJECT ENCODING`, or `None` if there is no value associated with the
+    /// key.
+    pub(crate) fn encoding(&self, key: &str) -> Option<&'static str> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.remove_if_expired(self.index, key, Instant::now());
+        state.databases[self.index]
+            .entries
+            .get(key)
+            .map(|entry| encoding::name(&entry.value, entry.compact))
+    }
+
+    /// Wait until no transaction is executing, and prevent transactions from
+    /// starting until the returned guard is dropped.
+    pub(crate) async fn lock_shared(&self) -> OwnedRwLockReadGuard<()> {
+        self.shared.transactions.clone().read_owned().await
+    }
+
+    /// Wait until no other command is executing, and prevent other commands
+    /// from starting until the returned guard is dropped.
+    pub(crate) async fn lock_exclusive(&self) -> OwnedRwLockWriteGuard<()> {
+        self.shared.transactions.clone().write_owned().await
+    }
+
+    /// Returns the time left before a key expires.
+    ///
+    /// Returns `None` if there is no value associated with the key and
+    /// `Some(None)` if the key exists but has no expiration.
+    pub(crate) fn ttl(&self, key: &str) -> Option<Option<Duration>> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        state.remove_if_expired(self.index, key, now);
+
+        state.databases[self.index].entries.get(key).map(|entry| {
+            entry
+                .expires_at
+                .map(|when| when.saturating_duration_since(now))
+        })
+    }
+
+    /// Set the given fields of the hash stored at `key`, creating the hash if
+    /// needed. Returns the number of fields that were added, as opposed to
+    /// updated.
+    pub(crate) fn hset(&self, key: &str, fields: Vec<(String, Bytes)>) -> Result<usize, Error> {
+        let mut write = command("HSET", key);
+        for (field, value) in &fields {
+            write.push_bulk(Bytes::from(field.clone()));
+            write.push_bulk(value.clone());
+        }
+
+        self.update(key, "hset", write, |hash: &mut Hash| {
+            let mut added = 0;
+
+            for (field, value) in fields {
+                if hash.insert(field, value).is_none() {
+                    added += 1;
+                }
+            }
+
+            Ok((added, true))
+        })
+    }
+
+    /// Get the value of a field of the hash stored at `key`.
+    pub(crate) fn hget(&self, key: &str, field: &str) -> Result<Option<Bytes>, Error> {
+        self.read(key, |hash: &Hash| hash.get(field).cloned())
+    }
+
+    /// Remove the given fields from the hash stored at `key`. Returns the
+    /// number of fields that were removed.
+    pub(crate) fn hdel(&self, key: &str, fields: &[String]) -> Result<usize, Error> {
+        let mut write = command("HDEL", key);
+        for field in fields {
+            write.push_bulk(Bytes::from(field.clone()));
+        }
+
+        self.update(key, "hdel", write, |hash: &mut Hash| {
+            let removed = fields
+                .iter()
+                .filter(|field| hash.remove(field).is_some())
+                .count();
+
+            Ok((removed, removed > 0))
+        })
+    }
+
+    /// Returns all fields and values of the hash stored at `key`.
+    pub(crate) fn hgetall(&self, key: &str) -> Result<Vec<(String, Bytes)>, Error> {
+        self.read(key, |hash: &Hash| {
+            hash.iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()
+        })
+    }
+
+    /// Returns `true` if the hash stored at `key` contains `field`.
+    pub(crate) fn hexists(&self, key: &str, field: &str) -> Result<bool, Error> {
+        self.read(key, |hash: &Hash| hash.contains_key(field))
+    }
+
+    /// Returns the number of fields of the hash stored at `key`.
+    pub(crate) fn hlen(&self, key: &str) -> Result<usize, Error> {
+        self.read(key, |hash: &Hash| hash.len())
+    }
+
+    /// Increment the integer stored in a field of the hash stored at `key` by
+    /// `delta`, and return the new value. A missing field counts as `0`.
+    pub(crate) fn hincrby(&self, key: &str, field: &str, delta: i64) -> Result<i64, Error> {
+        let mut write = command("HINCRBY", key);
+        write.push_bulk(Bytes::from(field.to_string()));
+        write.push_bulk(Bytes::from(delta.to_string()));
+
+        self.update(key, "hincrby", write, |hash: &mut Hash| {
+            let current = match hash.get(field) {
+                Some(value) => str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .ok_or(Error::NotInteger)?,
+                None => 0,
+            };
+
+            let value = current.checked_add(delta).ok_or(Error::Overflow)?;
+            hash.insert(field.to_string(), Bytes::from(value.to_string()));
+            Ok((value, true))
+        })
+    }
+
+    /// Push `values` to the given end of the list stored at `key`, one after
+    /// the other, creating the list if needed. Returns the length of the list
+    /// after the push.
+    ///
+    /// Clients blocked on the list are woken up.
+    pub(crate) fn push(&self, key: &str, values: Vec<Bytes>, end: End) -> Result<usize, Error> {
+        let mut write = command(end.push_command(), key);
+        for value in &values {
+            write.push_bulk(value.clone());
+        }
+
+        let event = end.push_command().to_lowercase();
+        let len = self.update(key, &event, write, |list: &mut List| {
+            for value in values {
+                match end {
+                    End::Left => list.push_front(value),
+                    End::Right => list.push_back(value),
+                }
+            }
+
+            Ok((list.len(), true))
+        })?;
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.wake(self.index, key);
+
+        Ok(len)
+    }
+
+    /// Remove and return up to `count` elements from the given end of the
+    /// list stored at `key`.
+    pub(crate) fn pop(&self, key: &str, end: End, count: usize) -> Result<Vec<Bytes>, Error> {
+        let mut write = command(end.pop_command(), key);
+        write.push_bulk(Bytes::from(count.to_string()));
+
+        let event = end.pop_command().to_lowercase();
+        self.update(key, &event, write, |list: &mut List| {
+            let count = count.min(list.len());
+
+            let popped: Vec<_> = match end {
+                End::Left => list.drain(..count).collect(),
+                End::Right => list.drain(list.len() - count..).rev().collect(),
+            };
+
+            Ok((popped, count > 0))
+        })
+    }
+
+    /// Returns the elements of the list stored at `key` between the `start`
+    /// and `stop` offsets, both inclusive. Negative offsets count from the end
+    /// of the list.
+    pub(crate) fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<Bytes>, Error> {
+        self.read(key, |list: &List| {
+            let len = list.len() as i64;
+
+            // Resolve negative offsets, then clamp to the bounds of the list.
+            let start = if start < 0 { len + start } else { start }.max(0);
+            let stop = if stop < 0 { len + stop } else { stop }.min(len - 1);
+
+            if start > stop {
+                return vec![];
+            }
+
+            list.range(start as usize..=stop as usize)
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// Returns the length of the list stored at `key`.
+    pub(crate) fn llen(&self, key: &str) -> Result<usize, Error> {
+        self.read(key, |list: &List| list.len())
+    }
+
+    /// Pop an element from the given end of the first non-empty list among
+    /// `keys`, and return it along with the key it was popped from.
+    ///
+    /// If all lists are empty, `waiter` is registered on every key and `None`
+    /// is returned. The caller should wait on `waiter` and try again. Once the
+    /// caller is done, it must call `unblock`.
+    pub(crate) fn blocking_pop(
+        &self,
+        keys: &[String],
+        end: End,
+        waiter: &Arc<Notify>,
+    ) -> Result<Option<(String, Bytes)>, Error> {
+        let mut state = self.shared.state.lock().unwrap();
+        let version = state.next_id();
+        let now = Instant::now();
+
+        for key in keys {
+            state.remove_if_expired(self.index, key, now);
+
+            let keyspace = &mut state.databases[self.index];
+            let entry = match keyspace.entries.get_mut(key) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let list = List::from_value_mut(&mut entry.value).ok_or(Error::WrongType)?;
+
+            let value = match end {
+                End::Left => list.pop_front(),
+                End::Right => list.pop_back(),
+            };
+
+            entry.version = version;
+
+            let empty = list.is_empty();
+            if empty {
+                keyspace.remove(key);
+            } else {
+                keyspace.resize(key);
+            }
+
+            if let Some(value) = value {
+                let event = end.pop_command().to_lowercase();
+                state.notify(self.index, KeyspaceEvents::LIST, &event, key);
+                if empty {
+                    state.notify(self.index, KeyspaceEvents::GENERIC, "del", key);
+                }
+
+                // Replaying a blocking pop must not block.
+                state.propagate(self.index, command(end.pop_command(), key));
+                return Ok(Some((key.clone(), value)));
+            }
+        }
+
+        // Checking the lists and registering happen under the same lock, so
+        // no push can slip in between.
+        state.block(self.index, keys, waiter);
+
+        Ok(None)
+    }
+
+    /// Add an entry to the stream stored at `key`, creating the stream if
+    /// needed. Returns the identifier of the entry.
+    ///
+    /// Clients waiting for new entries of the stream are woken up.
+    pub(crate) fn xadd(&self, key: &str, id: NewId, fields: Fields) -> Result<StreamId, Error> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.record_access(self.index, key);
+
+        let version = state.next_id();
+        let now = Instant::now();
+        state.remove_if_expired(self.index, key, now);
+
+        let keyspace = &mut state.databases[self.index];
+        let existed = keyspace.entries.contains_key(key);
+
+        let entry = keyspace.get_or_insert_with(key, || {
+            Entry::new(version, Stream::default().into_value(), None)
+        });
+
+        let stream = Stream::from_value_mut(&mut entry.value).ok_or(Error::WrongType)?;
+
+        let id = match stream.add(id, fields.clone(), unix_millis(now) as u64) {
+            Some(id) => id,
+            None => {
+                // A stream created only to be removed right away.
+                if !existed {
+                    keyspace.remove(key);
+                }
+
+                return Err(Error::StreamIdTooSmall);
+            }
+        };
+
+        entry.version = version;
+        keyspace.resize(key);
+
+        state.notify(self.index, KeyspaceEvents::STREAM, "xadd", key);
+
+        // The identifier is propagated, so replaying the command adds the
+        // same entry even if it was generated.
+        state.propagate(self.index, xadd_command(key, id, &fields));
+
+        // Unlike pops, reads do not consume entries: every waiting client
+        // gets them.
+        state.wake(self.index, key);
+
+        Ok(id)
+    }
+
+    /// Returns the number of entries of the stream stored at `key`.
+    pub(crate) fn xlen(&self, key: &str) -> Result<usize, Error> {
+        self.read(key, |stream: &Stream| stream.len())
+    }
+
+    /// Returns up to `count` entries of the stream stored at `key` with an
+    /// identifier between `start` and `end`, both inclusive.
+    pub(crate) fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+    ) -> Result<StreamEntries, Error> {
+        self.read(key, |stream: &Stream| stream.range(start, end, count))
+    }
+
+    /// Returns up to `count` entries of each stream in `streams` with an
+    /// identifier greater than the one given along with its key. Streams
+    /// without such entries are left out.
+    ///
+    /// Missing identifiers are set to the one of the last entry of their
+    /// stream, so only entries added afterwards are returned by later calls.
+    ///
+    /// If no stream has such entries and a `waiter` is given, it is registered
+    /// on every key. The caller should wait on `waiter` and try again. Once
+    /// the caller is done, it must call `unblock`.
+    pub(crate) fn xread(
+        &self,
+        streams: &mut [(String, Option<StreamId>)],
+        count: usize,
+        waiter: Option<&Arc<Notify>>,
+    ) -> Result<Vec<(String, StreamEntries)>, Error> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        let mut found = vec![];
+
+        for (key, id) in streams.iter_mut() {
+            state.remove_if_expired(self.index, key, now);
+
+            let stream = match state.databases[self.index].entries.get(key.as_str()) {
+                Some(entry) => Stream::from_value(&entry.value).ok_or(Error::WrongType)?,
+                None => {
+                    id.get_or_insert(StreamId::MIN);
+                    continue;
+                }
+            };
+
+            let after = *id.get_or_insert_with(|| stream.last_id());
+            let entries = stream.after(after, count);
+
+            if !entries.is_empty() {
+                found.push((key.clone(), entries));
+            }
+        }
+
+        // As for `blocking_pop`, checking the streams and registering happen
+        // under the same lock.
+        if found.is_empty() {
+            if let Some(waiter) = waiter {
+                let keys: Vec<String> = streams.iter().map(|(key, _)| key.clone()).collect();
+                state.block(self.index, &keys, waiter);
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Remove `waiter` from the clients blocked on `keys`.
+    pub(crate) fn unblock(&self, keys: &[String], waiter: &Arc<Notify>) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        for key in keys {
+            let key = (self.index, key.clone());
+
+            if let Some(waiters) = state.blocked.get_mut(&key) {
+                waiters.retain(|registered| !Arc::ptr_eq(registered, waiter));
+
+                if waiters.is_empty() {
+                    state.blocked.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Set the scores of members of the sorted set stored at `key`, creating
+    /// the sorted set if needed. Returns the number of members that were
+    /// added, as opposed to updated.
+    pub(crate) fn zadd(&self, key: &str, members: Vec<(f64, String)>) -> Result<usize, Error> {
+        let mut write = command("ZADD", key);
+        for (score, member) in &members {
+            write.push_bulk(Bytes::from(score.to_string()));
+            write.push_bulk(Bytes::from(member.clone()));
+        }
+
+        self.update(key, "zadd", write, |zset: &mut SortedSet| {
+            let added = members
+                .into_iter()
+                .map(|(score, member)| zset.insert(member, score))
+                .filter(|added| *added)
+                .count();
+
+            Ok((added, true))
+        })
+    }
+
+    /// Remove members from the sorted set stored at `key`. Returns the number
+    /// of members that were removed.
+    pub(crate) fn zrem(&self, key: &str, members: &[String]) -> Result<usize, Error> {
+        let mut write = command("ZREM", key);
+        for member in members {
+            write.push_bulk(Bytes::from(member.clone()));
+        }
+
+        self.update(key, "zrem", write, |zset: &mut SortedSet| {
+            let removed = members.iter().filter(|member| zset.remove(member)).count();
+            Ok((removed, removed > 0))
+        })
+    }
+
+    /// Returns the score of a member of the sorted set stored at `key`.
+    pub(crate) fn zscore(&self, key: &str, member: &str) -> Result<Option<f64>, Error> {
+        self.read(key, |zset: &SortedSet| zset.score(member))
+    }
+
+    /// Add `delta` to the score of a member of the sorted set stored at `key`
+    /// and return the new score. A missing member counts as `0`.
+    pub(crate) fn zincrby(&self, key: &str, member: &str, delta: f64) -> Result<f64, Error> {
+        let mut write = command("ZINCRBY", key);
+        write.push_bulk(Bytes::from(delta.to_string()));
+        write.push_bulk(Bytes::from(member.to_string()));
+
+        self.update(key, "zincr", write, |zset: &mut SortedSet| {
+            let score = zset.score(member).unwrap_or(0.0) + delta;
+
+            // Adding infinities of opposite signs
+            if score.is_nan() {
+                return Err(Error::NaN);
+            }
+
+            zset.insert(member.to_string(), score);
+            Ok((score, true))
+        })
+    }
+
+    /// Returns the members of the sorted set stored at `key` whose rank is
+    /// between `start` and `stop`, along with their scores.
+    pub(crate) fn zrange(
+        &self,
+        key: &str,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(String, f64)>, Error> {
+        self.read(key, |zset: &SortedSet| zset.range(start, stop))
+    }
+
+    /// Returns the members of the sorted set stored at `key` whose score is
+    /// between `min` and `max`, along with their scores. See
+    /// `SortedSet::range_by_score`.
+    pub(crate) fn zrangebyscore(
+        &self,
+        key: &str,
+        min: Bound<f64>,
+        max: Bound<f64>,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<(String, f64)>, Error> {
+        self.read(key, |zset: &SortedSet| {
+            zset.range_by_score(min, max, offset, count)
+        })
+    }
+
+    /// Returns the number of members of the sorted set stored at `key`.
+    pub(crate) fn zcard(&self, key: &str) -> Result<usize, Error> {
+        self.read(key, |zset: &SortedSet| zset.len())
+    }
+
+    /// Returns a page of the keys of the logical database this handle
+    /// operates on, along with the type of their value, and the cursor of the
+    /// next page. See `Keyspace::scan`.
+    pub(crate) fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(String, &'static str)>) {
+        let state = self.shared.state.lock().unwrap();
+        state.databases[self.index].scan(cursor, count, Instant::now())
+    }
+
+    /// Returns a page of the fields of the hash stored at `key`, along with
+    /// their value, and the cursor of the next page.
+    pub(crate) fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, Bytes)>), Error> {
+        self.read(key, |hash: &Hash| hash.scan(cursor, count))
+    }
+
+    /// Returns a page of the members of the sorted set stored at `key`, along
+    /// with their score, and the cursor of the next page.
+    pub(crate) fn zscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, f64)>), Error> {
+        self.read(key, |zset: &SortedSet| zset.scan(cursor, count))
+    }
+
+    /// Remove all keys from the logical database this handle operates on.
+    pub(crate) fn flush(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.databases[self.index] = Keyspace::default();
+
+        let mut write = Frame::array();
+        write.push_bulk(Bytes::from_static(b"FLUSHDB"));
+        state.propagate(self.index, write);
+    }
+
+    /// Swap the contents of the logical databases `a` and `b`.
+    ///
+    /// Handles bound to either database observe the other database's data
+    /// afterwards. Returns `false` if either index is out of range.
+    pub(crate) fn swap(&self, a: usize, b: usize) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if a >= state.databases.len() || b >= state.databases.len() {
+            return false;
+        }
+
+        // Expirations are tracked per database, so they move along with the
+        // data and the background task keeps purging the right keys.
+        state.databases.swap(a, b);
+
+        let mut write = Frame::array();
+        write.push_bulk(Bytes::from_static(b"SWAPDB"));
+        write.push_bulk(Bytes::from(a.to_string()));
+        write.push_bulk(Bytes::from(b.to_string()));
+        state.propagate(self.index, write);
+
+        true
+    }
+
+    /// Returns up to `count` of the most frequently accessed keys, hottest
+    /// first, along with an estimate of their accesses per second.
+    pub(crate) fn hot_keys(&self, count: usize) -> Vec<(String, f64)> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.hotkeys.hottest(count)
+    }
+
+    /// Returns a `Receiver` for the requested channel.
+    ///
+    /// The returned `Receiver` is used to receive values broadcast by `PUBLISH`
+    /// commands.
+    pub(crate) fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
+        use std::collections::hash_map::Entry;
+
+        // Acquire the mutex
+        let mut state = self.shared.state.lock().unwrap();
+
+        // If there is no entry for the requested channel, then create a new
+        // broadcast channel and associate it with the key. If one already
+        // exists, return an associated receiver.
+        match state.pub_sub.entry(key) {
+            Entry::Occupied(e) => e.get().subscribe(),
+            Entry::Vacant(e) => {
+                // No broadcast channel exists yet, so create one.
+                //
+                // The channel is created with a capacity of `1024` messages. A
+                // message is stored in the channel until **all** subscribers
+                // have seen it. This means that a slow subscriber could result
+                // in messages being held indefinitely.
+                //
+                // When the channel's capacity fills up, publishing will result
+                // in old messages being dropped. This prevents slow consumers
+                // from blocking the entire system.
+                let (tx, rx) = broadcast::channel(1024);
+                e.insert(tx);
+                rx
+            }
+        }
+    }
+
+    /// Returns a `Receiver` of the lines describing the commands received by
+    /// the server from now on, as streamed by `MONITOR`.
+    pub(crate) fn monitor(&self) -> broadcast::Receiver<String> {
+        self.shared.monitors.subscribe()
+    }
+
+    /// Describe `frame`, a command received by the client `id`, to the
+    /// connections running `MONITOR`. Does nothing if there is none.
+    ///
+    /// The line holds the time, the logical database of this handle, the
+    /// address of the client and the arguments of the command, quoted.
+    pub(crate) fn feed_monitors(&self, id: u64, frame: &Frame) {
+        if self.shared.monitors.receiver_count() == 0 {
+            return;
+        }
+
+        let addr = self.shared.state.lock().unwrap().clients.addr(id);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut line = format!(
+            "{}.{:06} [{} {}]",
+            now.as_secs(),
+            now.subsec_micros(),
+            self.index,
+            addr.unwrap_or_default()
+        );
+
+        let args = match frame {
+            Frame::Array(args) => &args[..],
+            frame => std::slice::from_ref(frame),
+        };
+
+        for (i, arg) in args.iter().enumerate() {
+            line.push(' ');
+
+            match arg {
+                // Passwords are not disclosed.
+                _ if acl::is_secret(args, i) => quote(&mut line, acl::REDACTED.as_bytes()),
+                Frame::Bulk(data) => quote(&mut line, data),
+                arg => quote(&mut line, arg.to_string().as_bytes()),
+            }
+        }
+
+        // Fails if every monitor disconnected meanwhile, which is fine.
+        let _ = self.shared.monitors.send(line);
+    }
+
+    /// Returns a `Receiver` for the requested channel pattern.
+    ///
+    /// The returned `Receiver` is used to receive values broadcast by
+    /// `PUBLISH` commands on the channels matching the pattern, along with the
+    /// name of the channel.
+    pub(crate) fn psubscribe(&self, pattern: String) -> broadcast::Receiver<(String, Bytes)> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        // As in `subscribe`, the broadcast channel is created on the first
+        // subscription to the pattern.
+        state
+            .pattern_sub
+            .entry(pattern)
+            .or_insert_with(|| broadcast::channel(1024).0)
+            .subscribe()
+    }
+
+    /// Publish a message to the channel. Returns the number of subscribers
+    /// listening on the channel.
+    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
+        let state = self.shared.state.lock().unwrap();
+        state.publish(key, value)
+    }
+
+    /// Returns the current settings of the server.
+    pub(crate) fn config(&self) -> ServerConfig {
+        self.shared.state.lock().unwrap().config.clone()
+    }
+
+    /// Returns the counters reported by `INFO`.
+    pub(crate) fn stats(&self) -> Arc<Stats> {
+        self.shared.state.lock().unwrap().stats.clone()
+    }
+
+    /// Register a client connected from `addr`, as listed by `CLIENT LIST`.
+    /// Returns the identifier of the client.
+    ///
+    /// The client stays registered until `unregister_client` is called.
+    pub(crate) fn register_client(&self, addr: String) -> u64 {
+        self.shared.state.lock().unwrap().clients.register(addr)
+    }
+
+    /// Set the task processing the connection of the client, so `CLIENT KILL`
+    /// can abort it.
+    pub(crate) fn set_client_task(&self, id: u64, task: JoinHandle<()>) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.clients.set_task(id, task);
+    }
+
+    pub(crate) fn unregister_client(&self, id: u64) {
+        self.shared.state.lock().unwrap().clients.unregister(id);
+    }
+
+    /// Record that the client received `command`, while bound to the logical
+    /// database of this handle.
+    pub(crate) fn record_command(&self, id: u64, command: &str) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.clients.record_command(id, self.index, command);
+    }
+
+    /// Returns the name of the client, set with `CLIENT SETNAME`.
+    pub(crate) fn client_name(&self, id: u64) -> Option<String> {
+        self.shared.state.lock().unwrap().clients.name(id)
+    }
+
+    pub(crate) fn set_client_name(&self, id: u64, name: String) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.clients.set_name(id, name);
+    }
+
+    /// Abort the connections of the clients matching `filter`. Returns the
+    /// number of clients killed.
+    pub(crate) fn kill_clients(&self, filter: &KillFilter) -> usize {
+        self.shared.state.lock().unwrap().clients.kill(filter)
+    }
+
+    /// Returns the description of every client, as listed by `CLIENT LIST`.
+    pub(crate) fn client_list(&self) -> String {
+        self.shared.state.lock().unwrap().clients.list()
+    }
+
+    /// Record a command of the client `id` in the slow log if it took longer
+    /// than `slowlog-log-slower-than` to execute. `args` are the arguments of
+    /// the command, as returned by `slowlog::arguments`.
+    pub(crate) fn log_if_slow(&self, id: u64, args: Vec<Bytes>, duration: Duration) {
+        let mut state = self.shared.state.lock().unwrap();
+        let threshold = state.config.slowlog_log_slower_than;
+
+        if threshold < 0 || duration.as_micros() < threshold as u128 {
+            return;
+        }
+
+        let addr = state.clients.addr(id).unwrap_or_default();
+        let name = state.clients.name(id).unwrap_or_default();
+        let max_len = state.config.slowlog_max_len;
+        state.slowlog.push(args, duration, addr, name, max_len);
+    }
+
+    /// Returns up to `count` entries of the slow log, newest first. All of
+    /// them if `count` is `None`.
+    pub(crate) fn slowlog(&self, count: Option<usize>) -> Vec<slowlog::Entry> {
+        let state = self.shared.state.lock().unwrap();
+        state.slowlog.entries(count).cloned().collect()
+    }
+
+    pub(crate) fn slowlog_len(&self) -> usize {
+        self.shared.state.lock().unwrap().slowlog.len()
+    }
+
+    pub(crate) fn reset_slowlog(&self) {
+        self.shared.state.lock().unwrap().slowlog.reset();
+    }
+
+    /// Returns the memory used by the data of every logical database, in
+    /// bytes, as estimated for `maxmemory`.
+    pub(crate) fn used_memory(&self) -> usize {
+        self.shared.state.lock().unwrap().used_memory()
+    }
+
+    /// Returns the number of keys of each logical database, along with the
+    /// number of those having an expiration.
+    pub(crate) fn key_counts(&self) -> Vec<(usize, usize)> {
+        let state = self.shared.state.lock().unwrap();
+
+        state
+            .databases
+            .iter()
+            .map(|keyspace| (keyspace.entries.len(), keyspace.expirations.len()))
+            .collect()
+    }
+
+    /// Change the settings of the server, as done by `CONFIG SET`. The
+    /// changes are observed from now on.
+    ///
+    /// Either every parameter is set, or none is and the error to reply with is
+    /// returned.
+    pub(crate) fn set_config(&self, parameters: &[(String, String)]) -> Result<(), String> {
+        let mut state = self.shared.state.lock().unwrap();
+        let mut config = state.config.clone();
+
+        for (name, value) in parameters {
+            config.set_parameter(name, value)?;
+        }
+
+        if config.requirepass != state.config.requirepass {
+            state.acl.set_requirepass(config.requirepass.as_deref());
+        }
+
+        state.config = config;
+        Ok(())
+    }
+
+    /// Evict keys as directed by `maxmemory-policy` until the data uses less
+    /// memory than `maxmemory`. This is called before running commands that
+    /// may store more data.
+    ///
+    /// Returns `false` if the data still uses too much memory, because the
+    /// policy forbids eviction or no key is eligible.
+    pub(crate) fn evict(&self) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        let maxmemory = state.config.maxmemory;
+        let policy = state.config.maxmemory_policy;
+        let now = Instant::now();
+
+        if maxmemory == 0 {
+            return true;
+        }
+
+        while state.used_memory() > maxmemory {
+            let (db, key) = match state.eviction_candidate(policy, now) {
+                Some(candidate) => candidate,
+                None => return false,
+            };
+
+            debug!(db, %key, "evicting key");
+            state.databases[db].remove(&key);
+            state.stats.key_evicted();
+            state.notify(db, KeyspaceEvents::EVICTED, "evicted", &key);
+            state.propagate(db, command("DEL", &key));
+        }
+
+        true
+    }
+
+    /// Returns a copy of the content of every logical database, indexed by
+    /// database, along with the number of writes it holds since the last save.
+    /// Once the copy is saved, the latter is passed to `saved`.
+    ///
+    /// Values are cloned. As data is stored using `Bytes`, this does not copy
+    /// strings, but the cost is still linear in the number of elements.
+    pub(crate) fn dump(&self) -> (Vec<Vec<Record>>, u64) {
+        let state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        let databases = state
+            .databases
+            .iter()
+            .map(|keyspace| {
+                keyspace
+                    .entries
+                    .iter()
+                    .filter(|(_, entry)| !entry.is_expired(now))
+                    .map(|(key, entry)| Record {
+                        key: key.clone(),
+                        value: entry.value.clone(),
+                        expires_at: entry.expires_at.map(unix_millis),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        (databases, state.changes)
+    }
+
+    /// Record that a snapshot was saved, holding `changes` writes as returned
+    /// by `dump`. Writes applied while it was saved remain to be saved.
+    pub(crate) fn saved(&self, changes: u64) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.changes = state.changes.saturating_sub(changes);
+        state.last_save = Instant::now();
+    }
+
+    /// Returns `true` if a snapshot is due according to
+    /// `ServerConfig::save`.
+    pub(crate) fn save_due(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .config
+            .save
+            .is_due(state.last_save.elapsed(), state.changes)
+    }
+
+    /// Store a key loaded from a snapshot in the logical database `index`,
+    /// replacing any previous value.
+    ///
+    /// Keys that already expired are skipped. Returns `false` if there is no
+    /// database with that index.
+    pub(crate) fn restore(&self, index: usize, record: Record) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if index >= state.databases.len() {
+            return false;
+        }
+
+        let now = Instant::now();
+        let expires_at = record.expires_at.and_then(expiration_at);
+
+        if expires_at.map(|when| when <= now).unwrap_or(false) {
+            return true;
+        }
+
+        let id = state.next_id();
+        let thresholds = Thresholds::new(&state.config);
+        let keyspace = &mut state.databases[index];
+        keyspace.remove(&record.key);
+
+        if let Some(when) = expires_at {
+            keyspace.expirations.insert((when, id), record.key.clone());
+        }
+
+        let mut entry = Entry::new(id, record.value, expires_at);
+        entry.compact = thresholds.fit(&entry.value);
+        keyspace.insert(record.key, entry);
+
+        drop(state);
+
+        if expires_at.is_some() {
+            self.shared.background_task.notify_one();
+        }
+
+        true
+    }
+
+    /// Prevent other snapshots from being saved until the returned guard is
+    /// dropped. Returns `None` if a snapshot is being saved already.
+    pub(crate) fn try_lock_save(&self) -> Option<OwnedMutexGuard<()>> {
+        self.shared.saving.clone().try_lock_owned().ok()
+    }
+
+    /// Wait until no snapshot is being saved, and prevent other snapshots
+    /// from being saved until the returned guard is dropped.
+    pub(crate) async fn lock_save(&self) -> OwnedMutexGuard<()> {
+        self.shared.saving.clone().lock_owned().await
+    }
+
+    /// Set the controller stopping the server, triggered by `SHUTDOWN`.
+    pub(crate) fn set_shutdown_controller(&self, controller: ShutdownController) {
+        self.shared.state.lock().unwrap().shutdown_controller = controller;
+    }
+
+    /// Stop the server. `save` overrides whether a snapshot is saved once it
+    /// stopped.
+    pub(crate) fn shutdown_server(&self, save: Option<bool>) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if save.is_some() {
+            state.save_on_shutdown = save;
+        }
+
+        state.shutdown_controller.trigger();
+    }
+
+    /// Returns `true` if a snapshot should be saved once the server stopped.
+    pub(crate) fn save_on_shutdown(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .save_on_shutdown
+            .unwrap_or(state.config.save_on_shutdown)
+    }
+
+    /// Returns the replication ID of this server.
+    pub(crate) fn replid(&self) -> String {
+        self.shared.state.lock().unwrap().replid.clone()
+    }
+
+    /// Returns the address of the primary this server replicates, or `None`
+    /// if this server is a primary.
+    pub(crate) fn primary(&self) -> Option<String> {
+        let state = self.shared.state.lock().unwrap();
+        state.primary.as_ref().map(|(addr, _)| addr.clone())
+    }
+
+    /// Set the primary this server replicates, along with the task receiving
+    /// its writes. The task replicating the previous primary, if any, is
+    /// aborted.
+    ///
+    /// Passing `None` turns this server into a primary.
+    pub(crate) fn set_primary(&self, primary: Option<(String, JoinHandle<()>)>) {
+        let mut state = self.shared.state.lock().unwrap();
+        let prev = std::mem::replace(&mut state.primary, primary);
+
+        if let Some((_, task)) = prev {
+            task.abort();
+        }
+    }
+
+    /// Returns the user new connections are logged in as, or `None` if they
+    /// need to authenticate first.
+    pub(crate) fn default_login(&self) -> Option<String> {
+        self.shared.state.lock().unwrap().acl.default_login()
+    }
+
+    /// Returns `true` if `password` authenticates `username`.
+    pub(crate) fn authenticate(&self, username: &str, password: &str) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.acl.authenticate(username, password)
+    }
+
+    /// Returns `true` if `username` may run `command`.
+    pub(crate) fn is_permitted(&self, username: &str, command: &str) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.acl.is_permitted(username, command)
+    }
+
+    /// Create or modify `username` by applying the ACL `rules`. On failure,
+    /// returns the invalid rule.
+    pub(crate) fn acl_set_user(&self, username: &str, rules: &[String]) -> Result<(), String> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.acl.set_user(username, rules)
+    }
+
+    /// Remove `username`. Returns `false` if there is no such user.
+    pub(crate) fn acl_del_user(&self, username: &str) -> bool {
+        self.shared.state.lock().unwrap().acl.del_user(username)
+    }
+
+    /// Returns a copy of the user named `username`.
+    pub(crate) fn acl_user(&self, username: &str) -> Option<User> {
+        let state = self.shared.state.lock().unwrap();
+        state.acl.user(username).cloned()
+    }
+
+    /// Returns a copy of every user along with its name, sorted by name.
+    pub(crate) fn acl_users(&self) -> Vec<(String, User)> {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .acl
+            .users()
+            .map(|(name, user)| (name.to_string(), user.clone()))
+            .collect()
+    }
+
+    /// Returns the commands rebuilding the current content of the store, each
+    /// along with the logical database it applies to, and a `Receiver` of the
+    /// writes applied from now on.
+    ///
+    /// The receiver reads from the replication backlog. Once it lags behind by
+    /// more than the backlog holds, it misses writes and reports
+    /// `RecvError::Lagged`.
+    pub(crate) fn subscribe_replication(&self) -> (Commands, broadcast::Receiver<(usize, Frame)>) {
+        let state = self.shared.state.lock().unwrap();
+        (state.snapshot(), state.replication.subscribe())
+    }
+
+    /// Returns a `Receiver` for the writes applied to the key-value store from
+    /// now on, for the append only file. It replaces the previous receiver,
+    /// and also receives the `Write::Sync` requests of `aof_synced`.
+    ///
+    /// If `snapshot` is set, the first value received is a `Write::Snapshot`
+    /// of the current content of the store.
+    pub(crate) fn subscribe_aof(&self, snapshot: bool) -> mpsc::UnboundedReceiver<Write> {
+        let mut state = self.shared.state.lock().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        if snapshot {
+            // The receiver is alive, sending cannot fail.
+            let _ = tx.send(Write::Snapshot(state.snapshot()));
+        }
+
+        state.aof = Some(tx);
+        rx
+    }
+
+    /// Returns a `Receiver` completing once the writes applied so far are
+    /// synced to the append only file. If the file cannot be written, the
+    /// sender is dropped instead.
+    ///
+    /// Returns `None` unless the append only file is enabled with
+    /// `appendfsync always`: writes are then acknowledged right away.
+    pub(crate) fn aof_synced(&self) -> Option<oneshot::Receiver<()>> {
+        let state = self.shared.state.lock().unwrap();
+
+        if state.config.appendfsync != AppendFsync::Always {
+            return None;
+        }
+
+        // If the writer stopped, the sender is dropped along with the request.
+        let (tx, rx) = oneshot::channel();
+        let _ = state.aof.as_ref()?.send(Write::Sync(tx));
+        Some(rx)
+    }
+
+    /// Returns `false` if the last write to the append only file failed.
+    pub(crate) fn aof_write_ok(&self) -> bool {
+        self.shared.state.lock().unwrap().aof_write_ok
+    }
+
+    /// Record whether the last write to the append only file succeeded.
+    pub(crate) fn set_aof_write_ok(&self, ok: bool) {
+        self.shared.state.lock().unwrap().aof_write_ok = ok;
+    }
+
+    /// Returns the `Notify` signalled when a rewrite of the append only file
+    /// is requested. Requests are only accepted once this has been called.
+    pub(crate) fn aof_rewrites(&self) -> Arc<Notify> {
+        let mut state = self.shared.state.lock().unwrap();
+        state
+            .aof_rewrite
+            .get_or_insert_with(Default::default)
+            .clone()
+    }
+
+    /// Request a rewrite of the append only file. Returns `false` if the
+    /// append only file is disabled.
+    pub(crate) fn rewrite_aof(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+
+        match &state.aof_rewrite {
+            Some(notify) => {
+                notify.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run `f` against the collection stored at `key`. A missing key is
+    /// treated as an empty collection.
+    fn read<C: Collection, T>(&self, key: &str, f: impl FnOnce(&C) -> T) -> Result<T, Error> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.record_access(self.index, key);
+
+        state.remove_if_expired(self.index, key, Instant::now());
+        state.record_lookup(self.index, key);
+
+        match state.databases[self.index].entries.get(key) {
+            Some(entry) => C::from_value(&entry.value).map(f).ok_or(Error::WrongType),
+            None => Ok(f(&C::default())),
+        }
+    }
+
+    /// Run `f` against the collection stored at `key`, creating the
+    /// collection if needed.
+    ///
+    /// `f` returns its result along with whether it modified the collection.
+    /// If the collection is empty once `f` returns, the key is removed. If `f`
+    /// modified the collection, `write` is propagated as the command
+    /// describing the change and `event` is published as a keyspace event.
+    fn update<C: Collection, T>(
+        &self,
+        key: &str,
+        event: &str,
+        write: Frame,
+        f: impl FnOnce(&mut C) -> Result<(T, bool), Error>,
+    ) -> Result<T, Error> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.record_access(self.index, key);
+
+        let id = state.next_id();
+        state.remove_if_expired(self.index, key, Instant::now());
+        let thresholds = Thresholds::new(&state.config);
+
+        let keyspace = &mut state.databases[self.index];
+        let existed = keyspace.entries.contains_key(key);
+
+        let entry =
+            keyspace.get_or_insert_with(key, || Entry::new(id, C::default().into_value(), None));
+
+        let collection = C::from_value_mut(&mut entry.value).ok_or(Error::WrongType)?;
+        let ret = f(collection);
+        let empty = collection.is_empty();
+        let modified = matches!(ret, Ok((_, true)));
+
+        if modified {
+            entry.version = id;
+        }
+
+        let converted = modified && entry.compact && !thresholds.fit(&entry.value);
+
+        if converted {
+            entry.compact = false;
+        }
+
+        if empty {
+            keyspace.remove(key);
+        } else {
+            keyspace.resize(key);
+        }
+
+        let (ret, _) = ret?;
+
+        if !modified {
+            return Ok(ret);
+        }
+
+        if converted {
+            state.stats.encoding_converted();
+        }
+
+        // A key created only to be removed right away was left untouched.
+        if existed || !empty {
+            state.notify(self.index, C::EVENTS, event, key);
+        }
+
+        if existed && empty {
+            state.notify(self.index, KeyspaceEvents::GENERIC, "del", key);
+        }
+
+        state.propagate(self.index, write);
+        Ok(ret)
+    }
+
+    /// Replace the string stored at `key` by the value `f` computes from it,
+    /// and return that value. `f` receives `None` if the key is missing.
+    ///
+    /// Unlike `set`, the expiration of the key is kept. The new value is
+    /// propagated as a `SET` followed by the expiration, so replaying it does
+    /// not depend on how the value was computed. `event` is published as a
+    /// keyspace event.
+    fn update_string<T: ToString>(
+        &self,
+        key: &str,
+        event: &str,
+        f: impl FnOnce(Option<&Bytes>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.record_access(self.index, key);
+
+        let id = state.next_id();
+        state.remove_if_expired(self.index, key, Instant::now());
+
+        let keyspace = &mut state.databases[self.index];
+
+        let current = match keyspace.entries.get(key).map(|entry| &entry.value) {
+            Some(Value::String(data)) => Some(data),
+            Some(_) => return Err(Error::WrongType),
+            None => None,
+        };
+
+        let ret = f(current)?;
+        let value = Bytes::from(ret.to_string());
+
+        let entry =
+            keyspace.get_or_insert_with(key, || Entry::new(id, Value::String(Bytes::new()), None));
+
+        entry.value = Value::String(value.clone());
+        entry.version = id;
+        let expires_at = entry.expires_at;
+        keyspace.resize(key);
+
+        state.notify(self.index, KeyspaceEvents::STRING, event, key);
+
+        let mut write = command("SET", key);
+        write.push_bulk(value);
+        state.propagate(self.index, write);
+
+        if let Some(when) = expires_at {
+            state.propagate(self.index, pexpireat(key, when));
+        }
+
+        Ok(ret)
+    }
+
+    /// Signals the purge background task to shut down. This is called by the
+    /// `DbShutdown`s `Drop` implementation.
+    fn shutdown_purge_task(&self) {
+        // The background task must be signaled to shut down. This is done by
+        // setting `State::shutdown` to `true` and signalling the task.
+        let mut state = self.shared.state.lock().unwrap();
+        state.shutdown = true;
+
+        // Stop replicating, the task holds a handle to the `Db`.
+        if let Some((_, task)) = state.primary.take() {
+            task.abort();
+        }
+
+        // Drop the lock before signalling the background task. This helps
+        // reduce lock contention by ensuring the background task doesn't
+        // wake up only to be unable to acquire the mutex.
+        drop(state);
+        self.shared.background_task.notify_one();
+    }
+}
+
+impl Shared {
+    /// Purge all expired keys and return the `Instant` at which the **next**
+    /// key will expire. The background task will sleep until this instant.
+    fn purge_expired_keys(&self) -> Option<Instant> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.shutdown {
+            // The database is shutting down. All handles to the shared state
+            // have dropped. The background task should exit.
+            return None;
+        }
+
+        // Find all keys scheduled to expire **before** now.
+        let now = Instant::now();
+
+        // Each database tracks its own expirations. The next instant the
+        // worker task needs to wake up at is the earliest across all of them.
+        let mut next = None;
+        let mut expired = vec![];
+
+        for db in 0..state.databases.len() {
+            if let Some(when) = state.databases[db].purge_expired_keys(now, &mut expired) {
+                next = Some(next.map_or(when, |next: Instant| next.min(when)));
+            }
+
+            for key in expired.drain(..) {
+                state.stats.key_expired();
+                state.notify(db, KeyspaceEvents::EXPIRED, "expired", &key);
+            }
+        }
+
+        next
+    }
+
+    /// Returns `true` if the database is shutting down
+    ///
+    /// The `shutdown` flag is set when all `Db` values have dropped, indicating
+    /// that the shared state can no longer be accessed.
+    fn is_shutdown(&self) -> bool {
+        self.state.lock().unwrap().shutdown
+    }
+}
+
+impl State {
+    /// Returns the commands rebuilding the current content of the store, each
+    /// along with the logical database it applies to.
+    fn snapshot(&self) -> Commands {
+        let now = Instant::now();
+        let mut commands = vec![];
+
+        for (index, keyspace) in self.databases.iter().enumerate() {
+            for frame in keyspace.dump(now) {
+                commands.push((index, frame));
+            }
+        }
+
+        commands
+    }
+
+    /// Send `frame` to the append only file and the replicas, as a command
+    /// applied to the logical database `db`.
+    fn propagate(&mut self, db: usize, frame: Frame) {
+        self.changes += 1;
+
+        if self.replication.receiver_count() > 0 {
+            // Only fails once every replica disconnected meanwhile.
+            let _ = self.replication.send((db, frame.clone()));
+        }
+
+        if let Some(tx) = &self.aof {
+            // Only fails once the writer stopped. The write is lost, so the
+            // following ones are refused.
+            if tx.send(Write::Command { db, frame }).is_err() && self.aof_write_ok {
+                error!("the append only file writer stopped, refusing writes");
+                self.aof_write_ok = false;
+            }
+        }
+    }
+
+    /// Publish a message to the channel. Returns the number of subscribers
+    /// listening on the channel, including the ones subscribed to a matching
+    /// pattern.
+    fn publish(&self, key: &str, value: Bytes) -> usize {
+        let mut receivers = self
+            .pub_sub
+            .get(key)
+            // On a successful message send on the broadcast channel, the number
+            // of subscribers is returned. An error indicates there are no
+            // receivers, in which case, `0` should be returned.
+            .map(|tx| tx.send(value.clone()).unwrap_or(0))
+            // If there is no entry for the channel key, then there are no
+            // subscribers. In this case, return `0`.
+            .unwrap_or(0);
+
+        // Every pattern has to be matched against the channel. A client
+        // subscribed to several matching patterns receives the message once
+        // per pattern.
+        for (pattern, tx) in &self.pattern_sub {
+            if glob::matches(pattern.as_bytes(), key.as_bytes()) {
+                receivers += tx.send((key.to_string(), value.clone())).unwrap_or(0);
+            }
+        }
+
+        receivers
+    }
+
+    /// Register `waiter` on `keys`, keys of the logical database `db`, so it
+    /// is notified once one of them is written to.
+    fn block(&mut self, db: usize, keys: &[String], waiter: &Arc<Notify>) {
+        for key in keys {
+            let waiters = self.blocked.entry((db, key.clone())).or_default();
+
+            if !waiters
+                .iter()
+                .any(|registered| Arc::ptr_eq(registered, waiter))
+            {
+                waiters.push(waiter.clone());
+            }
+        }
+    }
+
+    /// Wake up the clients blocked on `key`, a key of the logical database
+    /// `db`.
+    ///
+    /// All clients blocked on a list race to pop. The ones that lose register
+    /// again.
+    fn wake(&mut self, db: usize, key: &str) {
+        if let Some(waiters) = self.blocked.remove(&(db, key.to_string())) {
+            for waiter in waiters {
+                waiter.notify_one();
+            }
+        }
+    }
+
+    /// Publish a keyspace event about `key`, a key of the logical database
+    /// `db`, unless `class` is disabled by `notify-keyspace-events`.
+    fn notify(&self, db: usize, class: KeyspaceEvents, event: &str, key: &str) {
+        let events = self.config.notify_keyspace_events;
+
+        if !events.contains(class) {
+            return;
+        }
+
+        if events.contains(KeyspaceEvents::KEYSPACE) {
+            let channel = format!("__keyspace@{}__:{}", db, key);
+            self.publish(&channel, Bytes::from(event.to_string()));
+        }
+
+        if events.contains(KeyspaceEvents::KEYEVENT) {
+            let channel = format!("__keyevent@{}__:{}", db, event);
+            self.publish(&channel, Bytes::from(key.to_string()));
+        }
+    }
+
+    /// Record an access to `key`, a key of the logical database `db`, for
+    /// `HOTKEYS` and for eviction.
+    fn record_access(&mut self, db: usize, key: &str) {
+        self.hotkeys.record(key);
+
+        if let Some(entry) = self.databases[db].entries.get_mut(key) {
+            entry.touch(Instant::now());
+        }
+    }
+
+    /// Count a lookup of `key`, a key of the logical database `db`, as a hit
+    /// or a miss depending on whether it exists.
+    fn record_lookup(&self, db: usize, key: &str) {
+        let hit = self.databases[db].entries.contains_key(key);
+        self.stats.keyspace_lookup(hit);
+    }
+
+    /// Returns the memory used by the data of every logical database, in
+    /// bytes.
+    fn used_memory(&self) -> usize {
+        self.databases
+            .iter()
+            .map(|keyspace| keyspace.used_memory)
+            .sum()
+    }
+
+    /// Returns the key to evict next under `policy`, along with the logical
+    /// database holding it. The best key is picked among a sample of keys of
+    /// each database.
+    ///
+    /// Returns `None` if the policy forbids eviction or no key is eligible.
+    fn eviction_candidate(&self, policy: MaxMemoryPolicy, now: Instant) -> Option<(usize, String)> {
+        use MaxMemoryPolicy::*;
+
+        if policy == NoEviction {
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+
+        // The key with the highest score is evicted.
+        let mut best: Option<(u64, usize, &String)> = None;
+
+        for (db, keyspace) in self.databases.iter().enumerate() {
+            for (key, entry) in keyspace.sample(policy.is_volatile(), &mut rng) {
+                let score = match policy {
+                    AllKeysLru | VolatileLru => {
+                        now.saturating_duration_since(entry.accessed_at).as_millis() as u64
+                    }
+                    AllKeysLfu | VolatileLfu => u64::from(u8::MAX - entry.frequency(now)),
+                    AllKeysRandom | VolatileRandom => rng.gen(),
+                    VolatileTtl => match entry.expires_at {
+                        Some(when) => {
+                            u64::MAX - when.saturating_duration_since(now).as_millis() as u64
+                        }
+                        None => continue,
+                    },
+                    NoEviction => unreachable!(),
+                };
+
+                if best.map(|(highest, _, _)| score > highest).unwrap_or(true) {
+                    best = Some((score, db, key));
+                }
+            }
+        }
+
+        best.map(|(_, db, key)| (db, key.clone()))
+    }
+
+    /// Remove the key from the logical database `db` if it expired at or
+    /// before `now`, and publish the `expired` event.
+    fn remove_if_expired(&mut self, db: usize, key: &str, now: Instant) {
+        if self.databases[db].remove_if_expired(key, now) {
+            self.stats.key_expired();
+            self.notify(db, KeyspaceEvents::EXPIRED, "expired", key);
+        }
+    }
+
+    /// Get and increment the next entry identifier.
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Returns the instant at which the next key expires, across all
+    /// databases.
+    fn next_expiration(&self) -> Option<Instant> {
+        self.databases
+            .iter()
+            .filter_map(Keyspace::next_expiration)
+            .min()
+    }
+}
+
+impl Keyspace {
+    fn next_expiration(&self) -> Option<Instant> {
+        self.expirations
+            .keys()
+            .next()
+            .map(|expiration| expiration.0)
+    }
+
+    /// Insert an entry, returning the one previously associated with the key.
+    ///
+    /// The expiration of the previous entry is left to the caller.
+    fn insert(&mut self, key: String, mut entry: Entry) -> Option<Entry> {
+        if !self.entries.contains_key(&key) {
+            self.scan_order.insert(key.clone());
+        }
+
+        entry.memory = entry.memory_usage(&key);
+        self.used_memory += entry.memory;
+
+        let prev = self.entries.insert(key, entry);
+
+        if let Some(prev) = &prev {
+            self.used_memory -= prev.memory;
+        }
+
+        prev
+    }
+
+    /// Estimate again the memory used by the entry of `key`, after its value
+    /// was modified in place.
+    fn resize(&mut self, key: &str) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            let memory = entry.memory_usage(key);
+            self.used_memory = self.used_memory - entry.memory + memory;
+            entry.memory = memory;
+        }
+    }
+
+    /// Returns the entry associated with a key, inserting the one returned by
+    /// `f` if there is none.
+    fn get_or_insert_with(&mut self, key: &str, f: impl FnOnce() -> Entry) -> &mut Entry {
+        if !self.entries.contains_key(key) {
+            self.insert(key.to_string(), f());
+        }
+
+        self.entries.get_mut(key).unwrap()
+    }
+
+    /// Remove a key along with its expiration, if any.
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.used_memory -= entry.memory;
+
+        if let Some(when) = entry.expires_at {
+            self.expirations.remove(&(when, entry.id));
+        }
+
+        self.scan_order.remove(key);
+
+        Some(entry)
+    }
+
+    /// Returns the keys from position `cursor` on, in `SCAN` order, along
+    /// with the type of their value. About `count` keys are examined, but
+    /// keys that expired at or before `now` are skipped.
+    ///
+    /// Also returns the cursor to resume from, or `0` once every key has been
+    /// examined.
+    fn scan(&self, cursor: u64, count: usize, now: Instant) -> (u64, Vec<(String, &'static str)>) {
+        let (cursor, page) = self.scan_order.page(cursor, count);
+
+        let keys = page
+            .into_iter()
+            .filter_map(|key| match self.entries.get(key) {
+                Some(entry) if !entry.is_expired(now) => {
+                    Some((key.to_string(), entry.value.type_name()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        (cursor, keys)
+    }
+
+    /// Remove the key if it expired at or before `now`. Returns `true` if it
+    /// was removed.
+    ///
+    /// Expired keys are purged by the background task, but the task may lag
+    /// behind. Operations reading a key call this first so they never observe
+    /// an expired value.
+    fn remove_if_expired(&mut self, key: &str, now: Instant) -> bool {
+        let expired = self
+            .entries
+            .get(key)
+            .map(|entry| entry.is_expired(now))
+            .unwrap_or(false);
+
+        if expired {
+            self.remove(key);
+        }
+
+        expired
+    }
+
+    /// Returns up to `EVICTION_SAMPLES` keys picked at random, along with
+    /// their entry. If `volatile`, only keys with an expiration are picked.
+    ///
+    /// The same key may be picked more than once.
+    fn sample(&self, volatile: bool, rng: &mut impl Rng) -> Vec<(&String, &Entry)> {
+        let mut sample = vec![];
+
+        if volatile {
+            // Expirations are ordered by time, pick the first one after random
+            // instants.
+            let first = self.expirations.keys().next();
+            let last = self.expirations.keys().next_back();
+
+            if let (Some(&(first, _)), Some(&(last, _))) = (first, last) {
+                for _ in 0..EVICTION_SAMPLES {
+                    let at = first + (last - first).mul_f64(rng.gen());
+
+                    if let Some((_, key)) = self.expirations.range((at, 0)..).next() {
+                        sample.extend(self.entries.get_key_value(key));
+                    }
+                }
+            }
+        } else {
+            for _ in 0..EVICTION_SAMPLES {
+                for key in self.scan_order.sample(rng.gen()) {
+                    sample.extend(self.entries.get_key_value(key));
+                }
+            }
+        }
+
+        sample
+    }
+
+    /// Returns the commands rebuilding the keys that are not expired at `now`.
+    fn dump(&self, now: Instant) -> Vec<Frame> {
+        let mut frames = vec![];
+
+        for (key, entry) in &self.entries {
+            if entry.is_expired(now) {
+                continue;
+            }
+
+            let frame = match &entry.value {
+                Value::String(value) => {
+                    let mut frame = command("SET", key);
+                    frame.push_bulk(value.clone());
+                    frame
+                }
+                // Entries are added one at a time, keeping their identifier.
+                // The last one is pushed below.
+                Value::Stream(stream) => {
+                    let mut adds: Vec<Frame> = stream
+                        .iter()
+                        .map(|(id, fields)| xadd_command(key, *id, fields))
+                        .collect();
+
+                    let last = adds.pop().expect("streams are never empty");
+                    frames.extend(adds);
+                    last
+                }
+                Value::Hash(hash) => {
+                    let mut frame = command("HSET", key);
+                    for (field, value) in hash.iter() {
+                        frame.push_bulk(Bytes::from(field.clone()));
+                        frame.push_bulk(value.clone());
+                    }
+                    frame
+                }
+                Value::List(list) => {
+                    let mut frame = command("RPUSH", key);
+                    for value in list {
+                        frame.push_bulk(value.clone());
+                    }
+                    frame
+                }
+                Value::SortedSet(zset) => {
+                    let mut frame = command("ZADD", key);
+                    for (member, score) in zset.iter() {
+                        frame.push_bulk(Bytes::from(score.to_string()));
+                        frame.push_bulk(Bytes::from(member.to_string()));
+                    }
+                    frame
+                }
+            };
+
+            frames.push(frame);
+
+            if let Some(when) = entry.expires_at {
+                frames.push(pexpireat(key, when));
+            }
+        }
+
+        frames
+    }
+
+    /// Remove all keys that expired at or before `now` and return the
+    /// `Instant` at which the **next** key will expire. The removed keys are
+    /// pushed to `expired`.
+    fn purge_expired_keys(&mut self, now: Instant, expired: &mut Vec<String>) -> Option<Instant> {
+        while let Some((&(when, id), key)) = self.expirations.iter().next() {
+            if when > now {
+                // Done purging, `when` is the instant at which the next key
+                // expires.
+                return Some(when);
+            }
+
+            // The key expired, remove it
+            let key = key.clone();
+            self.remove(&key);
+            self.expirations.remove(&(when, id));
+            expired.push(key);
+        }
+
+        None
+    }
+}
+
+impl End {
+    /// Returns the name of the command pushing to this end.
+    fn push_command(self) -> &'static str {
+        match self {
+            End::Left => "LPUSH",
+            End::Right => "RPUSH",
+        }
+    }
+
+    /// Returns the name of the command popping from this end.
+    fn pop_command(self) -> &'static str {
+        match self {
+            End::Left => "LPOP",
+            End::Right => "RPOP",
+        }
+    }
+}
+
+impl Value {
+    /// Returns the name of the type of the value, as reported by `SCAN`.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::List(_) => "list",
+            Value::SortedSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+}
+
+impl Entry {
+    /// Create an entry holding `value`, accessed now.
+    fn new(id: u64, value: Value, expires_at: Option<Instant>) -> Entry {
+        Entry {
+            id,
+            value,
+            version: id,
+            expires_at,
+            accessed_at: Instant::now(),
+            frequency: LFU_INIT,
+            memory: 0,
+            compact: true,
+        }
+    }
+
+    /// Returns `true` if the entry expired at or before `now`.
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.map(|when| when <= now).unwrap_or(false)
+    }
+
+    /// Returns the access frequency counter, decayed by the time elapsed
+    /// since the last access.
+    fn frequency(&self, now: Instant) -> u8 {
+        let idle = now.saturating_duration_since(self.accessed_at);
+        let periods = idle.as_secs() / LFU_DECAY.as_secs();
+
+        self.frequency
+            .saturating_sub(periods.min(u64::from(u8::MAX)) as u8)
+    }
+
+    /// Record an access at `now`.
+    ///
+    /// As in Redis, the frequency counter is incremented with a probability
+    /// that decreases as the counter grows, so that it can count a large
+    /// number of accesses in a byte.
+    fn touch(&mut self, now: Instant) {
+        let frequency = self.frequency(now);
+        let p = 1.0 / (f64::from(frequency.saturating_sub(LFU_INIT)) * LFU_LOG_FACTOR + 1.0);
+
+        self.frequency = if frequency < u8::MAX && rand::thread_rng().gen::<f64>() < p {
+            frequency + 1
+        } else {
+            frequency
+        };
+        self.accessed_at = now;
+    }
+
+    /// Returns an estimate of the memory used by the entry stored under `key`,
+    /// in bytes.
+    fn memory_usage(&self, key: &str) -> usize {
+        ENTRY_OVERHEAD + key.len() + self.value.memory_usage()
+    }
+}
+
+impl Value {
+    /// Returns an estimate of the memory used by the value, in bytes.
+    ///
+    /// The size of a collection is extrapolated from a few of its elements,
+    /// so the estimate takes constant time.
+    fn memory_usage(&self) -> usize {
+        fn estimate<I: Iterator>(len: usize, items: I, size: impl Fn(I::Item) -> usize) -> usize {
+            let sizes: Vec<usize> = items.take(MEMORY_SAMPLES).map(size).collect();
+
+            match sizes.len() {
+                0 => 0,
+                sampled => len * (sizes.iter().sum::<usize>() / sampled + ELEMENT_OVERHEAD),
+            }
+        }
+
+        match self {
+            Value::String(data) => data.len(),
+            Value::Hash(hash) => estimate(hash.len(), hash.iter(), |(field, value)| {
+                field.len() + value.len()
+            }),
+            Value::List(list) => estimate(list.len(), list.iter(), |value| value.len()),
+            Value::SortedSet(zset) => {
+                estimate(zset.len(), zset.iter(), |(member, _)| member.len() + 8)
+            }
+            Value::Stream(stream) => estimate(stream.len(), stream.iter(), |(_, fields)| {
+                16 + fields
+                    .iter()
+                    .map(|(field, value)| field.len() + value.len())
+                    .sum::<usize>()
+            }),
+        }
+    }
+}
+
+impl Collection for Hash {
+    const EVENTS: KeyspaceEvents = KeyspaceEvents::HASH;
+
+    fn from_value(value: &Value) -> Option<&Hash> {
+        match value {
+            Value::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut Hash> {
+        match value {
+            Value::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Hash(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        Hash::is_empty(self)
+    }
+}
+
+impl Collection for List {
+    const EVENTS: KeyspaceEvents = KeyspaceEvents::LIST;
+
+    fn from_value(value: &Value) -> Option<&List> {
+        match value {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut List> {
+        match value {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::List(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        VecDeque::is_empty(self)
+    }
+}
+
+impl Collection for SortedSet {
+    const EVENTS: KeyspaceEvents = KeyspaceEvents::ZSET;
+
+    fn from_value(value: &Value) -> Option<&SortedSet> {
+        match value {
+            Value::SortedSet(zset) => Some(zset),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut SortedSet> {
+        match value {
+            Value::SortedSet(zset) => Some(zset),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::SortedSet(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        SortedSet::is_empty(self)
+    }
+}
+
+impl Collection for Stream {
+    const EVENTS: KeyspaceEvents = KeyspaceEvents::STREAM;
+
+    fn from_value(value: &Value) -> Option<&Stream> {
+        match value {
+            Value::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut Stream> {
+        match value {
+            Value::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Stream(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        Stream::is_empty(self)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::WrongType => {
+                "WRONGTYPE Operation against a key holding the wrong kind of value".fmt(fmt)
+            }
+            Error::NotInteger => "ERR value is not an integer or out of range".fmt(fmt),
+            Error::Overflow => "ERR increment or decrement would overflow".fmt(fmt),
+            Error::NaN => "ERR resulting score is not a number (NaN)".fmt(fmt),
+            Error::NotFloat => "ERR value is not a valid float".fmt(fmt),
+            Error::NotFinite => "ERR increment would produce NaN or Infinity".fmt(fmt),
+            Error::StreamIdTooSmall => {
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+                    .fmt(fmt)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Returns a `SELECT` command switching to the logical database `index`.
+///
+/// Writes are tagged with the database they apply to. Consumers writing them
+/// out as a stream of commands precede them with this command whenever the
+/// database changes.
+pub(crate) fn select_command(index: usize) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from_static(b"SELECT"));
+    frame.push_bulk(Bytes::from(index.to_string()));
+    frame
+}
+
+/// Returns a new random replication ID, made of 40 hexadecimal characters.
+fn replid() -> String {
+    let mut rng = rand::thread_rng();
+    (0..40)
+        .map(|_| std::char::from_digit(rng.gen_range(0..16), 16).unwrap())
+        .collect()
+}
+
+/// Returns a command frame made of the command name and the key it applies
+/// to. The remaining arguments are pushed by the caller.
+fn command(name: &'static str, key: &str) -> Frame {
+    let mut frame = Frame::array();
+    frame.push_bulk(Bytes::from_static(name.as_bytes()));
+    frame.push_bulk(Bytes::from(key.to_string()));
+    frame
+}
+
+/// Returns an `XADD` command adding the entry `id` holding `fields` to the
+/// stream stored at `key`.
+fn xadd_command(key: &str, id: StreamId, fields: &Fields) -> Frame {
+    let mut frame = command("XADD", key);
+    frame.push_bulk(Bytes::from(id.to_string()));
+
+    for (field, value) in fields {
+        frame.push_bulk(field.clone());
+        frame.push_bulk(value.clone());
+    }
+
+    frame
+}
+
+/// Append `data` to `line` between double quotes, escaping quotes,
+/// backslashes and bytes that are not printable ASCII, as `MONITOR` does.
+fn quote(line: &mut String, data: &[u8]) {
+    line.push('"');
+
+    for &byte in data {
+        match byte {
+            b'"' => line.push_str("\\\""),
+            b'\\' => line.push_str("\\\\"),
+            b'\n' => line.push_str("\\n"),
+            b'\r' => line.push_str("\\r"),
+            b'\t' => line.push_str("\\t"),
+            b' '..=b'~' => line.push(byte as char),
+            byte => line.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+
+    line.push('"');
+}
+
+/// Returns a `PEXPIREAT` command setting the expiration of `key` to `when`.
+fn pexpireat(key: &str, when: Instant) -> Frame {
+    let mut frame = command("PEXPIREAT", key);
+    frame.push_bulk(Bytes::from(unix_millis(when).to_string()));
+    frame
+}
+
+/// Returns the Unix time of `when`, in milliseconds.
+///
+/// Expirations are tracked with the monotonic clock. They are converted to a
+/// Unix timestamp using the system clock, saturating rather than overflowing.
+fn unix_millis(when: Instant) -> i64 {
+    let now = Instant::now();
+    let millis = |delay: Duration| i64::try_from(delay.as_millis()).unwrap_or(i64::MAX);
+
+    if when >= now {
+        unix_now().saturating_add(millis(when - now))
+    } else {
+        unix_now().saturating_sub(millis(now - when))
+    }
+}
+
+/// Returns the current Unix time, in milliseconds.
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Returns the expiration `millis` milliseconds from now, as given to
+/// `EXPIRE` or `SET EX`. A delay that is not positive expires right away.
+///
+/// Returns `None` if the expiration cannot be represented as a Unix time in
+/// milliseconds. Redis rejects such expirations as invalid.
+pub(crate) fn expiration_after(millis: i64) -> Option<Instant> {
+    unix_now().checked_add(millis)?;
+    Instant::now().checked_add(Duration::from_millis(millis.max(0) as u64))
+}
+
+/// Returns the expiration at the Unix time `timestamp`, in milliseconds, as
+/// given to `EXPIREAT` or `SET EXAT`. A timestamp in the past expires right
+/// away.
+///
+/// Returns `None` if the expiration cannot be represented.
+pub(crate) fn expiration_at(timestamp: i64) -> Option<Instant> {
+    let delay = timestamp.saturating_sub(unix_now()).max(0);
+    Instant::now().checked_add(Duration::from_millis(delay as u64))
+}
+
+/// Returns `when`, or `None` if it is more than `MAX_TIMER` from now. Such
+/// deadlines are never reached anyway.
+pub(crate) fn timer_deadline(when: Instant) -> Option<Instant> {
+    Instant::now()
+        .checked_add(MAX_TIMER)
+        .filter(|max| when < *max)
+        .map(|_| when)
 }
 
 /// Routine executed by the background task.
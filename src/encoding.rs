@@ -0,0 +1,108 @@
+//! Encodings reported by `OBJECT ENCODING`.
+//!
+//! Redis stores small hashes, lists and sorted sets in a compact
+//! representation, a listpack, and converts them to a larger one once they grow
+//! past the thresholds set by parameters such as `hash-max-listpack-entries`.
+//! Values are not converted back when they shrink.
+//!
+//! mini-redis stores values the same way whatever their size. It tracks the
+//! encoding Redis would use instead, so `OBJECT ENCODING` reports it and the
+//! conversions are counted by `INFO`.
+
+use crate::config::ServerConfig;
+use crate::db::Value;
+
+use bytes::Bytes;
+use std::str;
+
+/// Longest string stored with the `embstr` encoding.
+const EMBSTR_MAX_LEN: usize = 44;
+
+/// Size of a list in the `listpack` encoding when `list-max-listpack-size` is
+/// `-1`. Each step down doubles it.
+const LIST_MIN_SIZE: usize = 4096;
+
+/// Thresholds past which values are converted to their larger encoding, as
+/// set in the `ServerConfig`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Thresholds {
+    hash_entries: usize,
+    hash_value: usize,
+    list_size: i64,
+    zset_entries: usize,
+    zset_value: usize,
+}
+
+impl Thresholds {
+    pub(crate) fn new(config: &ServerConfig) -> Thresholds {
+        Thresholds {
+            hash_entries: config.hash_max_listpack_entries,
+            hash_value: config.hash_max_listpack_value,
+            list_size: config.list_max_listpack_size,
+            zset_entries: config.zset_max_listpack_entries,
+            zset_value: config.zset_max_listpack_value,
+        }
+    }
+
+    /// Returns `true` if `value` fits the compact encoding.
+    ///
+    /// This goes through the whole value, so it is only checked while the
+    /// value is compact, which bounds its size.
+    pub(crate) fn fit(&self, value: &Value) -> bool {
+        match value {
+            Value::Hash(hash) => {
+                hash.len() <= self.hash_entries
+                    && hash.iter().all(|(field, value)| {
+                        field.len() <= self.hash_value && value.len() <= self.hash_value
+                    })
+            }
+            Value::List(list) if self.list_size > 0 => list.len() as u64 <= self.list_size as u64,
+            Value::List(list) => {
+                let max = LIST_MIN_SIZE << ((-self.list_size).clamp(1, 5) - 1);
+                list.iter().map(Bytes::len).sum::<usize>() <= max
+            }
+            Value::SortedSet(zset) => {
+                zset.len() <= self.zset_entries
+                    && zset
+                        .iter()
+                        .all(|(member, _)| member.len() <= self.zset_value)
+            }
+            // There is no larger encoding to convert to.
+            Value::String(_) | Value::Stream(_) => true,
+        }
+    }
+}
+
+/// Returns the name of the encoding of `value`, as reported by
+/// `OBJECT ENCODING`. `compact` is `false` once the value outgrew the compact
+/// encoding.
+pub(crate) fn name(value: &Value, compact: bool) -> &'static str {
+    match value {
+        Value::String(value) => string(value),
+        Value::Hash(_) if compact => "listpack",
+        Value::Hash(_) => "hashtable",
+        Value::List(_) if compact => "listpack",
+        Value::List(_) => "quicklist",
+        Value::SortedSet(_) if compact => "listpack",
+        Value::SortedSet(_) => "skiplist",
+        Value::Stream(_) => "stream",
+    }
+}
+
+/// Returns the encoding of a string. Integers are stored as such, as long as
+/// they are written the way Redis formats them.
+fn string(value: &Bytes) -> &'static str {
+    let int = str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .map(|int| int.to_string().as_bytes() == &value[..])
+        .unwrap_or(false);
+
+    if int {
+        "int"
+    } else if value.len() <= EMBSTR_MAX_LEN {
+        "embstr"
+    } else {
+        "raw"
+    }
+}
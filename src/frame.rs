@@ -1,7 +1,7 @@
 //! Provides a type representing a Redis protocol frame as well as utilities for
 //! parsing frames from a byte array.
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes};
 use std::convert::TryInto;
 use std::fmt;
 use std::io::Cursor;
@@ -9,14 +9,32 @@ use std::num::TryFromIntError;
 use std::string::FromUtf8Error;
 
 /// A frame in the Redis protocol.
-#[derive(Clone, Debug)]
+///
+/// The first group of variants exists in both RESP2 and RESP3. The remaining
+/// ones were introduced by RESP3. When a connection speaks RESP2, they are
+/// encoded using the closest RESP2 type instead (see `Connection`).
+#[derive(Clone, Debug, PartialEq)]
 pub enum Frame {
     Simple(String),
     Error(String),
-    Integer(u64),
+    Integer(i64),
     Bulk(Bytes),
     Null,
     Array(Vec<Frame>),
+    /// An ordered sequence of key/value pairs.
+    Map(Vec<(Frame, Frame)>),
+    /// An unordered collection of unique frames.
+    Set(Vec<Frame>),
+    Double(f64),
+    Boolean(bool),
+    /// An integer outside of the range of `Integer`, kept as its decimal
+    /// representation.
+    BigNumber(String),
+    /// A string along with its three character format, such as `txt` or
+    /// `mkd`.
+    Verbatim(String, Bytes),
+    /// Out of band data pushed by the server, such as pub/sub messages.
+    Push(Vec<Frame>),
 }
 
 #[derive(Debug)]
@@ -53,7 +71,7 @@ impl Frame {
     /// # Panics
     ///
     /// panics if `self` is not an array
-    pub(crate) fn push_int(&mut self, value: u64) {
+    pub(crate) fn push_int(&mut self, value: i64) {
         match self {
             Frame::Array(vec) => {
                 vec.push(Frame::Integer(value));
@@ -64,45 +82,13 @@ impl Frame {
 
     /// Checks if an entire message can be decoded from `src`
     pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
-        match get_u8(src)? {
-            b'+' => {
-                get_line(src)?;
-                Ok(())
-            }
-            b'-' => {
-                get_line(src)?;
-                Ok(())
-            }
-            b':' => {
-                let _ = get_decimal(src)?;
-                Ok(())
-            }
-            b'$' => {
-                if b'-' == peek_u8(src)? {
-                    // Skip '-1\r\n'
-                    skip(src, 4)
-                } else {
-                    // Read the bulk string
-                    let len: usize = get_decimal(src)?.try_into()?;
-
-                    // skip that number of bytes + 2 (\r\n).
-                    skip(src, len + 2)
-                }
-            }
-            b'*' => {
-                let len = get_decimal(src)?;
-
-                for _ in 0..len {
-                    Frame::check(src)?;
-                }
-
-                Ok(())
-            }
-            _ => {
-                get_line(src)?;
-                Ok(())
+        if let Some(len) = check_header(src)? {
+            for _ in 0..len {
+                Frame::check(src)?;
             }
         }
+
+        Ok(())
     }
 
     /// The message has already been validated with `check`.
@@ -130,8 +116,8 @@ impl Frame {
             }
             b':' => {
                 skip(src, 1)?;
-                let len = get_decimal(src)?;
-                Ok(Frame::Integer(len))
+                let value = get_signed_decimal(src)?;
+                Ok(Frame::Integer(value))
             }
             b'$' => {
                 skip(src, 1)?;
@@ -161,15 +147,86 @@ impl Frame {
                 }
             }
             b'*' => {
+                skip(src, 1)?;
+                Ok(Frame::Array(parse_entries(src)?))
+            }
+            b'~' => {
+                skip(src, 1)?;
+                Ok(Frame::Set(parse_entries(src)?))
+            }
+            b'>' => {
+                skip(src, 1)?;
+                Ok(Frame::Push(parse_entries(src)?))
+            }
+            b'%' => {
                 skip(src, 1)?;
                 let len = get_decimal(src)?.try_into()?;
                 let mut out = Vec::with_capacity(len);
 
                 for _ in 0..len {
-                    out.push(Frame::parse(src)?);
+                    let key = Frame::parse(src)?;
+                    let value = Frame::parse(src)?;
+                    out.push((key, value));
+                }
+
+                Ok(Frame::Map(out))
+            }
+            b'_' => {
+                skip(src, 1)?;
+                if !get_line(src)?.is_empty() {
+                    return Err("protocol error; invalid frame format".into());
                 }
 
-                Ok(Frame::Array(out))
+                Ok(Frame::Null)
+            }
+            b',' => {
+                skip(src, 1)?;
+                let line = std::str::from_utf8(get_line(src)?)
+                    .map_err(|_| "protocol error; invalid frame format")?;
+
+                let value = line
+                    .parse::<f64>()
+                    .map_err(|_| "protocol error; invalid double")?;
+
+                Ok(Frame::Double(value))
+            }
+            b'#' => {
+                skip(src, 1)?;
+                match get_line(src)? {
+                    b"t" => Ok(Frame::Boolean(true)),
+                    b"f" => Ok(Frame::Boolean(false)),
+                    _ => Err("protocol error; invalid boolean".into()),
+                }
+            }
+            b'(' => {
+                skip(src, 1)?;
+                let line = get_line(src)?.to_vec();
+                let string = String::from_utf8(line)?;
+
+                Ok(Frame::BigNumber(string))
+            }
+            b'=' => {
+                skip(src, 1)?;
+                let len = get_decimal(src)?.try_into()?;
+                let n = len + 2;
+
+                if src.remaining() < n {
+                    return Err(Error::Incomplete);
+                }
+
+                // The payload starts with the format, followed by `:`.
+                let data = &src.chunk()[..len];
+
+                if len < 4 || data[3] != b':' {
+                    return Err("protocol error; invalid verbatim string".into());
+                }
+
+                let format = String::from_utf8(data[..3].to_vec())?;
+                let text = Bytes::copy_from_slice(&data[4..]);
+
+                skip(src, n)?;
+
+                Ok(Frame::Verbatim(format, text))
             }
             _ => {
                 // Read the line and convert it to `Vec<u8>`
@@ -183,6 +240,83 @@ impl Frame {
         }
     }
 
+    /// Encode the frame into `dst`, using the RESP `protocol` version.
+    ///
+    /// Frame types introduced by RESP3 are encoded as their closest RESP2
+    /// equivalent when `protocol` is `2`. Nothing is allocated, except for
+    /// formatting doubles.
+    pub fn write_to(&self, dst: &mut impl BufMut, protocol: u8) {
+        let resp3 = protocol >= 3;
+
+        match self {
+            Frame::Simple(val) => put_line(dst, b'+', val.as_bytes()),
+            Frame::Error(val) => put_line(dst, b'-', val.as_bytes()),
+            Frame::Integer(val) => {
+                dst.put_u8(b':');
+                put_decimal(dst, *val);
+            }
+            Frame::Null if resp3 => dst.put_slice(b"_\r\n"),
+            Frame::Null => dst.put_slice(b"$-1\r\n"),
+            Frame::Bulk(val) => put_bulk(dst, b'$', val),
+            Frame::Array(entries) => put_aggregate(dst, b'*', entries, protocol),
+            Frame::Set(entries) if resp3 => put_aggregate(dst, b'~', entries, protocol),
+            Frame::Push(entries) if resp3 => put_aggregate(dst, b'>', entries, protocol),
+            // RESP2 has no set or push type. Both are sent as arrays.
+            Frame::Set(entries) | Frame::Push(entries) => {
+                put_aggregate(dst, b'*', entries, protocol)
+            }
+            Frame::Map(entries) => {
+                // In RESP2, a map is sent as a flat array alternating between
+                // keys and values.
+                if resp3 {
+                    dst.put_u8(b'%');
+                    put_decimal(dst, entries.len() as i64);
+                } else {
+                    dst.put_u8(b'*');
+                    put_decimal(dst, entries.len() as i64 * 2);
+                }
+
+                for (key, value) in entries {
+                    key.write_to(dst, protocol);
+                    value.write_to(dst, protocol);
+                }
+            }
+            Frame::Double(val) => {
+                let val = if val.is_nan() {
+                    "nan".to_string()
+                } else {
+                    val.to_string()
+                };
+
+                if resp3 {
+                    put_line(dst, b',', val.as_bytes());
+                } else {
+                    put_bulk(dst, b'$', val.as_bytes());
+                }
+            }
+            Frame::Boolean(val) if resp3 => {
+                let val: &[u8] = if *val { b"#t\r\n" } else { b"#f\r\n" };
+                dst.put_slice(val);
+            }
+            Frame::Boolean(val) => {
+                dst.put_u8(b':');
+                put_decimal(dst, *val as i64);
+            }
+            Frame::BigNumber(val) if resp3 => put_line(dst, b'(', val.as_bytes()),
+            Frame::BigNumber(val) => put_bulk(dst, b'$', val.as_bytes()),
+            Frame::Verbatim(format, text) if resp3 => {
+                // The payload is the format and the text, separated by `:`.
+                dst.put_u8(b'=');
+                put_decimal(dst, (format.len() + 1 + text.len()) as i64);
+                dst.put_slice(format.as_bytes());
+                dst.put_u8(b':');
+                dst.put_slice(text);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Verbatim(_, text) => put_bulk(dst, b'$', text),
+        }
+    }
+
     /// Converts the frame to an "unexpected frame" error
     pub(crate) fn to_error(&self) -> crate::Error {
         format!("unexpected frame: {}", self).into()
@@ -194,6 +328,7 @@ impl PartialEq<&str> for Frame {
         match self {
             Frame::Simple(s) => s.eq(other),
             Frame::Bulk(s) => s.eq(other),
+            Frame::Verbatim(_, s) => s.eq(other),
             _ => false,
         }
     }
@@ -212,7 +347,7 @@ impl fmt::Display for Frame {
                 Err(_) => write!(fmt, "{:?}", msg),
             },
             Frame::Null => "(nil)".fmt(fmt),
-            Frame::Array(parts) => {
+            Frame::Array(parts) | Frame::Set(parts) | Frame::Push(parts) => {
                 for (i, part) in parts.iter().enumerate() {
                     if i > 0 {
                         write!(fmt, " ")?;
@@ -222,10 +357,199 @@ impl fmt::Display for Frame {
 
                 Ok(())
             }
+            Frame::Map(entries) => {
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(fmt, " ")?;
+                    }
+                    write!(fmt, "{} {}", key, value)?;
+                }
+
+                Ok(())
+            }
+            Frame::Double(num) => num.fmt(fmt),
+            Frame::Boolean(value) => value.fmt(fmt),
+            Frame::BigNumber(num) => num.fmt(fmt),
+            Frame::Verbatim(_, text) => match str::from_utf8(text) {
+                Ok(string) => string.fmt(fmt),
+                Err(_) => write!(fmt, "{:?}", text),
+            },
+        }
+    }
+}
+
+/// Incremental version of `Frame::check`.
+///
+/// `Frame::check` scans a frame from its first byte on every call, so a frame
+/// received over many reads is scanned again after each one. `Checker` keeps
+/// its progress between calls instead. Only the entry that was cut short is
+/// scanned again.
+#[derive(Debug, Default)]
+pub(crate) struct Checker {
+    // Number of bytes at the start of the buffer that have been checked.
+    pos: usize,
+
+    // Number of entries still expected by each aggregate frame being checked,
+    // the innermost one last.
+    pending: Vec<u64>,
+}
+
+impl Checker {
+    /// Checks if an entire frame can be decoded from the start of `src`.
+    ///
+    /// Until a frame is found, `src` must start with the bytes passed to the
+    /// previous calls. Once the frame is complete, its length on the wire is
+    /// returned and the checker is reset for the next frame.
+    pub(crate) fn check(&mut self, src: &[u8]) -> Result<Option<usize>, Error> {
+        let mut src = Cursor::new(src);
+        src.set_position(self.pos as u64);
+
+        loop {
+            match check_header(&mut src) {
+                // The entries of a non-empty aggregate are checked next.
+                Ok(Some(len)) if len > 0 => self.pending.push(len),
+                // A scalar or an empty aggregate. It completes an entry of
+                // the enclosing aggregate, which may complete it in turn.
+                Ok(_) => loop {
+                    match self.pending.last_mut() {
+                        Some(remaining) if *remaining > 1 => {
+                            *remaining -= 1;
+                            break;
+                        }
+                        Some(_) => {
+                            self.pending.pop();
+                        }
+                        None => {
+                            let len = src.position() as usize;
+                            *self = Checker::default();
+                            return Ok(Some(len));
+                        }
+                    }
+                },
+                Err(Error::Incomplete) => return Ok(None),
+                Err(err) => return Err(err),
+            }
+
+            self.pos = src.position() as usize;
         }
     }
 }
 
+/// Check the first line of a frame, along with the payload of scalar frames.
+///
+/// Returns the number of entries that follow for aggregate frames, and `None`
+/// for the others.
+fn check_header(src: &mut Cursor<&[u8]>) -> Result<Option<u64>, Error> {
+    match get_u8(src)? {
+        b':' => {
+            let _ = get_signed_decimal(src)?;
+        }
+        b'$' => {
+            if b'-' == peek_u8(src)? {
+                // Skip '-1\r\n'
+                skip(src, 4)?;
+            } else {
+                // Read the bulk string
+                let len: usize = get_decimal(src)?.try_into()?;
+
+                // skip that number of bytes + 2 (\r\n).
+                skip(src, len + 2)?;
+            }
+        }
+        b'=' => {
+            // Verbatim strings are encoded like bulk strings.
+            let len: usize = get_decimal(src)?.try_into()?;
+
+            // skip that number of bytes + 2 (\r\n).
+            skip(src, len + 2)?;
+        }
+        b'*' | b'~' | b'>' => return Ok(Some(get_decimal(src)?)),
+        b'%' => {
+            // Each entry is a key frame followed by a value frame.
+            let len = get_decimal(src)?;
+            let len = len
+                .checked_mul(2)
+                .ok_or("protocol error; invalid frame format")?;
+
+            return Ok(Some(len));
+        }
+        _ => {
+            // `+`, `-`, `_`, `,`, `#` and `(` frames are all single lines, as
+            // is the fallback simple string.
+            get_line(src)?;
+        }
+    }
+
+    Ok(None)
+}
+
+/// Parse the length-prefixed entries of an aggregate frame.
+fn parse_entries(src: &mut Cursor<&[u8]>) -> Result<Vec<Frame>, Error> {
+    let len = get_decimal(src)?.try_into()?;
+    let mut out = Vec::with_capacity(len);
+
+    for _ in 0..len {
+        out.push(Frame::parse(src)?);
+    }
+
+    Ok(out)
+}
+
+/// Write a single line frame
+fn put_line(dst: &mut impl BufMut, prefix: u8, val: &[u8]) {
+    dst.put_u8(prefix);
+    dst.put_slice(val);
+    dst.put_slice(b"\r\n");
+}
+
+/// Write a length-prefixed string
+fn put_bulk(dst: &mut impl BufMut, prefix: u8, val: &[u8]) {
+    dst.put_u8(prefix);
+    put_decimal(dst, val.len() as i64);
+    dst.put_slice(val);
+    dst.put_slice(b"\r\n");
+}
+
+/// Write an aggregate frame, encoding each entry in turn
+fn put_aggregate(dst: &mut impl BufMut, prefix: u8, entries: &[Frame], protocol: u8) {
+    dst.put_u8(prefix);
+    put_decimal(dst, entries.len() as i64);
+
+    for entry in entries {
+        entry.write_to(dst, protocol);
+    }
+}
+
+/// Write a new-line terminated decimal
+///
+/// The digits are produced right to left into a stack buffer, which avoids
+/// going through the `fmt` machinery.
+fn put_decimal(dst: &mut impl BufMut, val: i64) {
+    // `u64::MAX` has 20 digits.
+    let mut buf = [0u8; 20];
+    let mut pos = buf.len();
+
+    // Work on the magnitude so that `i64::MIN` does not overflow.
+    let mut n = val.unsigned_abs();
+
+    loop {
+        pos -= 1;
+        buf[pos] = b'0' + (n % 10) as u8;
+        n /= 10;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    if val < 0 {
+        dst.put_u8(b'-');
+    }
+
+    dst.put_slice(&buf[pos..]);
+    dst.put_slice(b"\r\n");
+}
+
 fn peek_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
     if !src.has_remaining() {
         return Err(Error::Incomplete);
@@ -260,12 +584,115 @@ fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
     atoi::<u64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
 }
 
+/// Read a new-line terminated decimal that may be negative
+fn get_signed_decimal(src: &mut Cursor<&[u8]>) -> Result<i64, Error> {
+    let line = get_line(src)?;
+
+    parse_signed(line).ok_or_else(|| "protocol error; invalid frame format".into())
+}
+
+/// Parse a decimal integer with an optional leading `-`.
+pub(crate) fn parse_signed(src: &[u8]) -> Option<i64> {
+    use atoi::atoi;
+
+    match src.split_first() {
+        Some((b'-', digits)) => atoi::<i64>(digits).map(|v| -v),
+        _ => atoi::<i64>(src),
+    }
+}
+
+/// Split an inline command into its arguments.
+///
+/// Arguments are separated by whitespace, and may be quoted the way
+/// `redis-cli` accepts them. Double quoted arguments support the `\n`, `\r`,
+/// `\t`, `\b`, `\a` and `\xHH` escapes, single quoted ones only `\'`.
+pub(crate) fn split_inline(line: &[u8]) -> Result<Vec<Bytes>, Error> {
+    const UNBALANCED: &str = "protocol error; unbalanced quotes in request";
+
+    let mut args = vec![];
+    let mut i = 0;
+
+    loop {
+        while line.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let quote = match line[i] {
+            b'"' | b'\'' => {
+                i += 1;
+                Some(line[i - 1])
+            }
+            _ => None,
+        };
+
+        let mut arg = vec![];
+
+        loop {
+            match (quote, line.get(i).copied()) {
+                (None, None) => break,
+                (None, Some(b)) if b.is_ascii_whitespace() => break,
+                (Some(_), None) => return Err(UNBALANCED.into()),
+                (Some(quote), Some(b)) if b == quote => {
+                    i += 1;
+
+                    // The closing quote must end the argument.
+                    if line.get(i).is_some_and(|b| !b.is_ascii_whitespace()) {
+                        return Err(UNBALANCED.into());
+                    }
+
+                    break;
+                }
+                (Some(b'"'), Some(b'\\')) if i + 1 < line.len() => {
+                    let (byte, len) = match line[i + 1] {
+                        b'x' => match line.get(i + 2..i + 4).and_then(parse_hex) {
+                            Some(byte) => (byte, 4),
+                            None => (b'x', 2),
+                        },
+                        b'n' => (b'\n', 2),
+                        b'r' => (b'\r', 2),
+                        b't' => (b'\t', 2),
+                        b'b' => (0x08, 2),
+                        b'a' => (0x07, 2),
+                        byte => (byte, 2),
+                    };
+
+                    arg.push(byte);
+                    i += len;
+                }
+                (Some(b'\''), Some(b'\\')) if line.get(i + 1) == Some(&b'\'') => {
+                    arg.push(b'\'');
+                    i += 2;
+                }
+                (_, Some(b)) => {
+                    arg.push(b);
+                    i += 1;
+                }
+            }
+        }
+
+        args.push(Bytes::from(arg));
+    }
+}
+
+/// Parse a byte written as two hexadecimal digits.
+fn parse_hex(src: &[u8]) -> Option<u8> {
+    if !src.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+
+    u8::from_str_radix(std::str::from_utf8(src).ok()?, 16).ok()
+}
+
 /// Find a line
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     // Scan the bytes directly
     let start = src.position() as usize;
     // Scan to the second to last byte
-    let end = src.get_ref().len() - 1;
+    let end = src.get_ref().len().saturating_sub(1);
 
     for i in start..end {
         if src.get_ref()[i] == b'\r' && src.get_ref()[i + 1] == b'\n' {
@@ -314,3 +741,63 @@ impl fmt::Display for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMES: &[&[u8]] = &[
+        b"+OK\r\n",
+        b"-ERR oops\r\n",
+        b":-42\r\n",
+        b"$5\r\nhello\r\n",
+        b"$-1\r\n",
+        b"=9\r\ntxt:hello\r\n",
+        b"*0\r\n",
+        b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n",
+        b"*2\r\n*1\r\n+a\r\n*0\r\n",
+        b"%1\r\n+key\r\n,1.5\r\n",
+        b">2\r\n+message\r\n~1\r\n#t\r\n",
+    ];
+
+    /// A frame cut short at any offset is incomplete. Once the rest arrives,
+    /// its length on the wire is returned.
+    #[test]
+    fn checker_frame_split_at_every_offset() {
+        for frame in FRAMES {
+            for split in 0..frame.len() {
+                let mut checker = Checker::default();
+                assert_eq!(None, checker.check(&frame[..split]).unwrap());
+                assert_eq!(Some(frame.len()), checker.check(frame).unwrap());
+            }
+        }
+    }
+
+    /// A frame received one byte at a time is checked by a single `Checker`,
+    /// resuming where the previous call stopped.
+    #[test]
+    fn checker_frame_received_byte_by_byte() {
+        for frame in FRAMES {
+            let mut checker = Checker::default();
+
+            for len in 0..frame.len() {
+                assert_eq!(None, checker.check(&frame[..len]).unwrap());
+            }
+
+            assert_eq!(Some(frame.len()), checker.check(frame).unwrap());
+        }
+    }
+
+    /// Bytes of the next frame following a complete frame are not part of it,
+    /// and the checker is reset for the next frame.
+    #[test]
+    fn checker_frame_followed_by_next_frame() {
+        let mut checker = Checker::default();
+        let src = b"*1\r\n$4\r\nPING\r\n*2\r\n$3\r\nGET\r\n";
+
+        assert_eq!(None, checker.check(&src[..10]).unwrap());
+        assert_eq!(Some(14), checker.check(src).unwrap());
+
+        assert_eq!(None, checker.check(&src[14..]).unwrap());
+    }
+}
@@ -0,0 +1,94 @@
+//! Glob-style pattern matching, as used by `SCAN ... MATCH`.
+//!
+//! The syntax is the one of Redis:
+//!
+//! * `?` matches any single byte.
+//! * `*` matches any sequence of bytes, including an empty one.
+//! * `[abc]` matches one of the listed bytes, `[^abc]` any byte but them, and
+//!   `[a-z]` any byte in the range.
+//! * `\` escapes the next byte, so `\*` matches a literal `*`.
+//!
+//! Matching is performed on bytes. Patterns and strings need not be valid
+//! UTF-8.
+
+/// Returns `true` if `string` matches `pattern` as a whole.
+pub(crate) fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    let mut p = 0;
+    let mut s = 0;
+
+    // Position in the pattern right after the last `*` seen, along with the
+    // position in the string it was last tried at. On a mismatch, the `*` is
+    // made to match one more byte and matching resumes from there.
+    let mut star = None;
+
+    while s < string.len() {
+        if p < pattern.len() {
+            if pattern[p] == b'*' {
+                p += 1;
+                star = Some((p, s));
+                continue;
+            }
+
+            if let Some(next) = match_one(pattern, p, string[s]) {
+                p = next;
+                s += 1;
+                continue;
+            }
+        }
+
+        match star {
+            Some((star_p, star_s)) => {
+                p = star_p;
+                s = star_s + 1;
+                star = Some((star_p, s));
+            }
+            None => return false,
+        }
+    }
+
+    // The string is consumed, only stars may remain in the pattern.
+    pattern[p..].iter().all(|&byte| byte == b'*')
+}
+
+/// Match the byte `c` against the pattern token starting at `p`, which is not
+/// a `*`.
+///
+/// Returns the position of the next token if it matches.
+fn match_one(pattern: &[u8], p: usize, c: u8) -> Option<usize> {
+    match pattern[p] {
+        b'?' => Some(p + 1),
+        b'\\' if p + 1 < pattern.len() => (pattern[p + 1] == c).then(|| p + 2),
+        b'[' => {
+            let mut i = p + 1;
+            let negate = pattern.get(i) == Some(&b'^');
+            if negate {
+                i += 1;
+            }
+
+            let mut matched = false;
+
+            // An unterminated class extends to the end of the pattern.
+            while i < pattern.len() && pattern[i] != b']' {
+                if pattern[i] == b'\\' && i + 1 < pattern.len() {
+                    matched |= pattern[i + 1] == c;
+                    i += 2;
+                } else if i + 2 < pattern.len() && pattern[i + 1] == b'-' {
+                    let (mut start, mut end) = (pattern[i], pattern[i + 2]);
+                    if start > end {
+                        std::mem::swap(&mut start, &mut end);
+                    }
+
+                    matched |= start <= c && c <= end;
+                    i += 3;
+                } else {
+                    matched |= pattern[i] == c;
+                    i += 1;
+                }
+            }
+
+            // Skip the closing `]`, if any.
+            (matched != negate).then(|| (i + 1).min(pattern.len()))
+        }
+        byte => (byte == c).then(|| p + 1),
+    }
+}
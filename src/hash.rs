@@ -0,0 +1,77 @@
+//! Hash value type.
+//!
+//! A hash maps fields to values. Two indexes are maintained: a `HashMap` to
+//! look up the value of a field, and a `ScanOrder` to page through the fields
+//! in the iteration order of `HSCAN`.
+
+use crate::scan::ScanOrder;
+
+use bytes::Bytes;
+use std::collections::hash_map::{self, HashMap};
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Hash {
+    /// Value of each field.
+    values: HashMap<String, Bytes>,
+
+    /// Fields in `HSCAN` order.
+    scan_order: ScanOrder,
+}
+
+impl Hash {
+    /// Returns the number of fields.
+    pub(crate) fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the hash has no fields.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the value of `field`.
+    pub(crate) fn get(&self, field: &str) -> Option<&Bytes> {
+        self.values.get(field)
+    }
+
+    /// Returns `true` if the hash contains `field`.
+    pub(crate) fn contains_key(&self, field: &str) -> bool {
+        self.values.contains_key(field)
+    }
+
+    /// Set the value of `field`, adding it if needed. Returns the previous
+    /// value, if any.
+    pub(crate) fn insert(&mut self, field: String, value: Bytes) -> Option<Bytes> {
+        if !self.values.contains_key(&field) {
+            self.scan_order.insert(field.clone());
+        }
+
+        self.values.insert(field, value)
+    }
+
+    /// Remove `field`. Returns its value, if it was a field.
+    pub(crate) fn remove(&mut self, field: &str) -> Option<Bytes> {
+        let value = self.values.remove(field)?;
+        self.scan_order.remove(field);
+        Some(value)
+    }
+
+    /// Returns an iterator over the fields and their values, in no particular
+    /// order.
+    pub(crate) fn iter(&self) -> hash_map::Iter<'_, String, Bytes> {
+        self.values.iter()
+    }
+
+    /// Returns a page of the fields along with their value, and the cursor of
+    /// the next page. See `ScanOrder::page`.
+    pub(crate) fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(String, Bytes)>) {
+        let (cursor, fields) = self.scan_order.page(cursor, count);
+
+        let page = fields
+            .into_iter()
+            .map(|field| (field.to_string(), self.values[field].clone()))
+            .collect();
+
+        (cursor, page)
+    }
+}
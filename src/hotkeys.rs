@@ -0,0 +1,148 @@
+//! Approximate tracking of the most frequently accessed keys.
+//!
+//! Counting every key exactly would require memory proportional to the size
+//! of the key space. Instead, accesses are recorded in a count-min sketch: a
+//! small, fixed-size matrix of counters that over-estimates, but never
+//! under-estimates, the number of accesses of any key. Alongside the sketch, a
+//! bounded set of candidate keys with the highest estimates is maintained. This
+//! is the set reported as "hot".
+//!
+//! Counters decay by halving every `DECAY_PERIOD`, so keys that stop being
+//! accessed eventually drop out of the hot set. The decay also gives the
+//! counters a known time scale, which is used to derive ops/sec estimates.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use tokio::time::{Duration, Instant};
+
+/// Number of rows (independent hash functions) in the sketch.
+const DEPTH: usize = 4;
+
+/// Number of counters per row.
+const WIDTH: usize = 1024;
+
+/// Maximum number of keys tracked as hot key candidates.
+const TOP_K: usize = 32;
+
+/// Counters are halved once per period.
+const DECAY_PERIOD: Duration = Duration::from_secs(10);
+
+/// Count-min sketch plus the current top-k candidates.
+#[derive(Debug)]
+pub(crate) struct HotKeySketch {
+    /// `DEPTH` rows of `WIDTH` counters, stored row after row.
+    counters: Vec<u32>,
+
+    /// Keys with the highest estimated counts, along with that estimate.
+    top: HashMap<String, u32>,
+
+    /// Instant of the last decay. Also the start of the current period.
+    last_decay: Instant,
+}
+
+impl HotKeySketch {
+    pub(crate) fn new() -> HotKeySketch {
+        HotKeySketch {
+            counters: vec![0; DEPTH * WIDTH],
+            top: HashMap::new(),
+            last_decay: Instant::now(),
+        }
+    }
+
+    /// Record a single access of `key`.
+    pub(crate) fn record(&mut self, key: &str) {
+        self.decay(Instant::now());
+
+        // Increment the key's counter in every row. The estimate is the
+        // smallest of the counters, as that one has the fewest collisions.
+        let mut estimate = u32::MAX;
+
+        for row in 0..DEPTH {
+            let counter = &mut self.counters[row * WIDTH + slot(key, row)];
+            *counter = counter.saturating_add(1);
+            estimate = estimate.min(*counter);
+        }
+
+        if let Some(count) = self.top.get_mut(key) {
+            *count = estimate;
+            return;
+        }
+
+        if self.top.len() < TOP_K {
+            self.top.insert(key.to_string(), estimate);
+            return;
+        }
+
+        // The candidate set is full. Replace the coldest candidate if the key
+        // is now estimated to be hotter than it.
+        let (coldest, min) = self
+            .top
+            .iter()
+            .min_by_key(|(_, count)| **count)
+            .map(|(key, count)| (key.clone(), *count))
+            .expect("top-k set is full");
+
+        if estimate > min {
+            self.top.remove(&coldest);
+            self.top.insert(key.to_string(), estimate);
+        }
+    }
+
+    /// Returns up to `count` of the hottest keys along with an estimate of
+    /// their accesses per second, hottest first.
+    pub(crate) fn hottest(&mut self, count: usize) -> Vec<(String, f64)> {
+        let now = Instant::now();
+        self.decay(now);
+
+        // Counters are halved once per period. For a key accessed at a steady
+        // rate `r`, a counter holds `r * DECAY_PERIOD` right after a decay and
+        // grows by `r` every second after that. Dividing by the same time span
+        // recovers the rate.
+        let span = (DECAY_PERIOD + (now - self.last_decay)).as_secs_f64();
+
+        let mut hottest: Vec<_> = self
+            .top
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(key, count)| (key.clone(), *count as f64 / span))
+            .collect();
+
+        hottest.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hottest.truncate(count);
+        hottest
+    }
+
+    /// Halve all counters once for every `DECAY_PERIOD` elapsed since the
+    /// last decay.
+    fn decay(&mut self, now: Instant) {
+        let periods = ((now - self.last_decay).as_nanos() / DECAY_PERIOD.as_nanos()) as u32;
+
+        if periods == 0 {
+            return;
+        }
+
+        // Halving `n` times is a right shift by `n`. After 32 halvings every
+        // counter is zero.
+        for counter in &mut self.counters {
+            *counter = counter.checked_shr(periods).unwrap_or(0);
+        }
+
+        for count in self.top.values_mut() {
+            *count = count.checked_shr(periods).unwrap_or(0);
+        }
+
+        self.top.retain(|_, count| *count > 0);
+        self.last_decay += DECAY_PERIOD * periods;
+    }
+}
+
+/// Returns the counter slot of `key` in the given row.
+fn slot(key: &str, row: usize) -> usize {
+    // Seeding the hasher with the row number gives an independent hash
+    // function per row.
+    let mut hasher = DefaultHasher::new();
+    row.hash(&mut hasher);
+    key.hash(&mut hasher);
+    (hasher.finish() % WIDTH as u64) as usize
+}
@@ -24,6 +24,10 @@
 //! * `frame`: represents a single Redis protocol frame. A frame is used as an
 //!   intermediate representation between a "command" and the byte
 //!   representation.
+//!
+//! * `shutdown`: graceful shutdown coordination. Embedders can trigger the
+//!   server's shutdown programmatically and register their own background
+//!   tasks as shutdown participants.
 
 pub mod blocking_client;
 pub mod client;
@@ -31,16 +35,51 @@ pub mod client;
 pub mod cmd;
 pub use cmd::Command;
 
+pub mod config;
+pub use config::{
+    AppendFsync, KeyspaceEvents, MaxMemoryPolicy, SavePoints, ServerConfig, TlsConfig,
+};
+
 mod connection;
 pub use connection::Connection;
 
 pub mod frame;
 pub use frame::Frame;
 
+mod acl;
+
+mod aof;
+
+mod clients;
+
 mod db;
 use db::Db;
 use db::DbDropGuard;
 
+mod encoding;
+
+mod glob;
+
+mod hash;
+
+mod hotkeys;
+
+mod scan;
+
+mod slowlog;
+
+mod stats;
+
+mod stream;
+
+mod zset;
+
+mod rdb;
+
+mod replay;
+
+mod replication;
+
 mod parse;
 use parse::{Parse, ParseError};
 
@@ -49,8 +88,11 @@ pub mod server;
 mod buffer;
 pub use buffer::{buffer, Buffer};
 
-mod shutdown;
-use shutdown::Shutdown;
+pub mod shutdown;
+pub use shutdown::{Shutdown, ShutdownController};
+
+mod tls;
+pub use tls::TlsOptions;
 
 /// Default port that a redis server listens on.
 ///
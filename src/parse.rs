@@ -1,6 +1,7 @@
-use crate::Frame;
+use crate::frame::{parse_signed, Frame};
 
 use bytes::Bytes;
+use std::convert::TryFrom;
 use std::{fmt, str, vec};
 
 /// Utility for parsing a command
@@ -17,14 +18,18 @@ pub(crate) struct Parse {
 
 /// Error encountered while parsing a frame.
 ///
-/// Only `EndOfStream` errors are handled at runtime. All other errors result in
-/// the connection being terminated.
+/// The server replies to a command that fails to parse with an error, and
+/// keeps the connection open. `EndOfStream` and `Trailing` are reported as a
+/// wrong number of arguments, all other errors as a syntax error.
 #[derive(Debug)]
 pub(crate) enum ParseError {
     /// Attempting to extract a value failed due to the frame being fully
     /// consumed.
     EndOfStream,
 
+    /// Entries remain once the command was parsed.
+    Trailing,
+
     /// All other errors
     Other(crate::Error),
 }
@@ -106,8 +111,9 @@ impl Parse {
         const MSG: &str = "protocol error; invalid number";
 
         match self.next()? {
-            // An integer frame type is already stored as an integer.
-            Frame::Integer(v) => Ok(v),
+            // An integer frame type is already stored as an integer. Negative
+            // values are not valid here.
+            Frame::Integer(v) => u64::try_from(v).map_err(|_| MSG.into()),
             // Simple and bulk frames must be parsed as integers. If the parsing
             // fails, an error is returned.
             Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or_else(|| MSG.into()),
@@ -116,12 +122,75 @@ impl Parse {
         }
     }
 
+    /// Return the next entry as an integer that may be negative.
+    ///
+    /// Accepts the same frame types as `next_int`.
+    pub(crate) fn next_signed_int(&mut self) -> Result<i64, ParseError> {
+        const MSG: &str = "protocol error; invalid number";
+
+        match self.next()? {
+            Frame::Integer(v) => Ok(v),
+            Frame::Simple(data) => parse_signed(data.as_bytes()).ok_or_else(|| MSG.into()),
+            Frame::Bulk(data) => parse_signed(&data).ok_or_else(|| MSG.into()),
+            frame => Err(format!("protocol error; expected int frame but got {:?}", frame).into()),
+        }
+    }
+
+    /// Return the next entry as a floating point number.
+    ///
+    /// Accepts the same frame types as `next_int`, as well as `inf`, `+inf`
+    /// and `-inf`. NaN is not a valid number.
+    pub(crate) fn next_float(&mut self) -> Result<f64, ParseError> {
+        const MSG: &str = "protocol error; invalid float";
+
+        let value = match self.next()? {
+            Frame::Integer(v) => v as f64,
+            Frame::Double(v) => v,
+            Frame::Simple(data) => data.parse::<f64>().map_err(|_| MSG)?,
+            Frame::Bulk(data) => str::from_utf8(&data)
+                .ok()
+                .and_then(|data| data.parse::<f64>().ok())
+                .ok_or(MSG)?,
+            frame => {
+                return Err(
+                    format!("protocol error; expected float frame but got {:?}", frame).into(),
+                )
+            }
+        };
+
+        if value.is_nan() {
+            return Err(MSG.into());
+        }
+
+        Ok(value)
+    }
+
+    /// Return all the remaining entries as strings, such as the keys of a
+    /// variadic command.
+    ///
+    /// At least one entry is required. `EndOfStream` is returned if there is
+    /// none.
+    pub(crate) fn next_strings(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut strings = vec![self.next_string()?];
+
+        while self.remaining() > 0 {
+            strings.push(self.next_string()?);
+        }
+
+        Ok(strings)
+    }
+
+    /// Returns the number of entries left to parse.
+    pub(crate) fn remaining(&self) -> usize {
+        self.parts.len()
+    }
+
     /// Ensure there are no more entries in the array
     pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
         if self.parts.next().is_none() {
             Ok(())
         } else {
-            Err("protocol error; expected end of frame, but there was more".into())
+            Err(ParseError::Trailing)
         }
     }
 }
@@ -142,6 +211,9 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ParseError::EndOfStream => "protocol error; unexpected end of stream".fmt(f),
+            ParseError::Trailing => {
+                "protocol error; expected end of frame, but there was more".fmt(f)
+            }
             ParseError::Other(err) => err.fmt(f),
         }
     }
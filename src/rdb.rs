@@ -0,0 +1,520 @@
+//! Point-in-time snapshots of the key-value store.
+//!
+//! Snapshots use a subset of the RDB format of Redis (version 9), so they can
+//! be inspected with the usual RDB tools. The file is laid out as follows:
+//!
+//! ```text
+//! "REDIS0009"
+//! AUX "mini-redis-ver" <version>
+//! for each non empty database:
+//!     SELECTDB <index>
+//!     RESIZEDB <number of keys> <number of keys with an expiration>
+//!     for each key:
+//!         [EXPIRETIME_MS <unix time in milliseconds, 8 bytes little endian>]
+//!         <value type> <key> <value>
+//! EOF <checksum, 8 bytes>
+//! ```
+//!
+//! Values are encoded as:
+//!
+//! * strings: type `0`, the string.
+//! * lists: type `1`, the number of elements followed by the elements.
+//! * hashes: type `4`, the number of fields followed by field, value pairs.
+//! * sorted sets: type `5`, the number of members followed by member, score
+//!   pairs. Scores are 8 byte little endian doubles.
+//! * streams: type `64`, the number of entries followed by the entries. Each
+//!   entry is its identifier, as two lengths, then the number of fields
+//!   followed by field, value pairs. Redis encodes streams as listpacks
+//!   instead, so snapshots holding streams can only be loaded by mini-redis.
+//!
+//! Strings are length prefixed. Snapshots are written without a checksum,
+//! which RDB readers accept as "checksum disabled". Compressed strings are not
+//! supported when loading.
+//!
+//! Besides `SAVE` and `BGSAVE`, a background task saves snapshots as directed
+//! by the `save` parameter. It checks once per second whether a save is due.
+
+use crate::db::{List, Record, Value};
+use crate::hash::Hash;
+use crate::stream::{NewId, Stream, StreamId};
+use crate::zset::SortedSet;
+use crate::{Db, Shutdown};
+
+use bytes::Bytes;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::OwnedMutexGuard;
+use tokio::task;
+use tokio::time::{self, Duration};
+use tracing::{error, info};
+
+/// How often the background task checks whether a snapshot is due.
+const SAVE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Opcodes and value types, as defined by the RDB format.
+const OPCODE_AUX: u8 = 0xfa;
+const OPCODE_RESIZEDB: u8 = 0xfb;
+const OPCODE_EXPIRETIME_MS: u8 = 0xfc;
+const OPCODE_EXPIRETIME: u8 = 0xfd;
+const OPCODE_SELECTDB: u8 = 0xfe;
+const OPCODE_EOF: u8 = 0xff;
+const OPCODE_IDLE: u8 = 0xf8;
+const OPCODE_FREQ: u8 = 0xf9;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+
+/// Specific to mini-redis, outside of the range of Redis value types.
+const TYPE_STREAM: u8 = 64;
+
+/// Write a snapshot of `databases`, indexed by database, to `path`.
+///
+/// The snapshot is written to a temporary file, which is then renamed over
+/// `path`. A crash in the middle of a save leaves the previous snapshot
+/// untouched.
+pub(crate) fn save(path: &Path, databases: &[Vec<Record>]) -> io::Result<()> {
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut dst = BufWriter::new(File::create(&tmp)?);
+
+    dst.write_all(b"REDIS0009")?;
+    dst.write_all(&[OPCODE_AUX])?;
+    write_string(&mut dst, b"mini-redis-ver")?;
+    write_string(&mut dst, env!("CARGO_PKG_VERSION").as_bytes())?;
+
+    for (index, records) in databases.iter().enumerate() {
+        if records.is_empty() {
+            continue;
+        }
+
+        let expires = records.iter().filter(|r| r.expires_at.is_some()).count();
+
+        dst.write_all(&[OPCODE_SELECTDB])?;
+        write_length(&mut dst, index as u64)?;
+        dst.write_all(&[OPCODE_RESIZEDB])?;
+        write_length(&mut dst, records.len() as u64)?;
+        write_length(&mut dst, expires as u64)?;
+
+        for record in records {
+            write_record(&mut dst, record)?;
+        }
+    }
+
+    dst.write_all(&[OPCODE_EOF])?;
+    dst.write_all(&[0; 8])?;
+
+    let file = dst.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path)
+}
+
+/// Copy the data stored in `db`, then return a future writing it to the
+/// snapshot file set by `ServerConfig::dbfilename`.
+///
+/// `guard` is obtained from `Db::lock_save` or `Db::try_lock_save`, and is
+/// released once the file is written. Encoding and writing the file is
+/// blocking, so it runs on the blocking thread pool.
+pub(crate) fn snapshot(
+    db: &Db,
+    guard: OwnedMutexGuard<()>,
+) -> impl Future<Output = crate::Result<()>> {
+    let (databases, changes) = db.dump();
+    let path = db.config().dbfilename;
+    let db = db.clone();
+
+    async move {
+        task::spawn_blocking(move || save(&path, &databases)).await??;
+        db.saved(changes);
+        drop(guard);
+        Ok(())
+    }
+}
+
+/// Save a snapshot of the data as of now in the background. Returns `false`
+/// if a snapshot is already being saved.
+pub(crate) fn bgsave(db: &Db) -> bool {
+    let guard = match db.try_lock_save() {
+        Some(guard) => guard,
+        None => return false,
+    };
+
+    // The data is copied before spawning, so the snapshot reflects the data
+    // as of now.
+    let snapshot = snapshot(db, guard);
+
+    tokio::spawn(async move {
+        match snapshot.await {
+            Ok(()) => info!("background saving terminated with success"),
+            Err(err) => error!(cause = %err, "background saving failed"),
+        }
+    });
+
+    true
+}
+
+/// Save a snapshot of the data, once the snapshot being saved in the
+/// background, if any, is written.
+pub(crate) async fn save_now(db: &Db) -> crate::Result<()> {
+    let guard = db.lock_save().await;
+    snapshot(db, guard).await
+}
+
+/// Start the task saving snapshots in the background as directed by
+/// `ServerConfig::save`. The task stops once the server shuts down.
+pub(crate) fn start(db: &Db, mut shutdown: Shutdown) {
+    let db = db.clone();
+
+    tokio::spawn(async move {
+        let mut interval = time::interval(SAVE_CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.recv() => return,
+            }
+
+            if db.save_due() && bgsave(&db) {
+                info!("save point reached, saving");
+            }
+        }
+    });
+}
+
+/// Store the keys of the snapshot at `path` in `db`.
+///
+/// A missing file is treated as an empty snapshot. This is called on startup,
+/// before connections are accepted.
+pub(crate) fn restore(db: &Db, path: &Path) -> crate::Result<()> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let records = load(&data)?;
+    let count = records.len();
+
+    for (index, record) in records {
+        if !db.restore(index, record) {
+            return Err(format!("invalid snapshot; database {} out of range", index).into());
+        }
+    }
+
+    info!(keys = count, path = %path.display(), "loaded snapshot");
+    Ok(())
+}
+
+/// Decode a snapshot. Returns the keys along with the index of the database
+/// they belong to.
+fn load(data: &[u8]) -> crate::Result<Vec<(usize, Record)>> {
+    let mut src = Reader { data, pos: 0 };
+
+    let header = src.bytes(9)?;
+    if &header[..5] != b"REDIS" {
+        return Err("invalid snapshot; missing header".into());
+    }
+
+    let mut records = vec![];
+    let mut index = 0;
+    let mut expires_at = None;
+
+    loop {
+        match src.u8()? {
+            OPCODE_AUX => {
+                src.string()?;
+                src.string()?;
+            }
+            OPCODE_RESIZEDB => {
+                src.length()?;
+                src.length()?;
+            }
+            OPCODE_EXPIRETIME_MS => {
+                expires_at = Some(src.u64_le()? as i64);
+            }
+            OPCODE_EXPIRETIME => {
+                let secs = src.bytes(4)?;
+                let secs = u32::from_le_bytes([secs[0], secs[1], secs[2], secs[3]]);
+                expires_at = Some(secs as i64 * 1000);
+            }
+            OPCODE_SELECTDB => {
+                index = src.length()? as usize;
+            }
+            OPCODE_IDLE => {
+                src.length()?;
+            }
+            OPCODE_FREQ => {
+                src.u8()?;
+            }
+            // The checksum is not verified.
+            OPCODE_EOF => break,
+            kind => {
+                let key = src.utf8()?;
+                let value = src.value(kind)?;
+
+                records.push((
+                    index,
+                    Record {
+                        key,
+                        value,
+                        expires_at: expires_at.take(),
+                    },
+                ));
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+fn write_record(dst: &mut impl Write, record: &Record) -> io::Result<()> {
+    if let Some(when) = record.expires_at {
+        dst.write_all(&[OPCODE_EXPIRETIME_MS])?;
+        dst.write_all(&(when as u64).to_le_bytes())?;
+    }
+
+    match &record.value {
+        Value::String(value) => {
+            dst.write_all(&[TYPE_STRING])?;
+            write_string(dst, record.key.as_bytes())?;
+            write_string(dst, value)?;
+        }
+        Value::List(list) => {
+            dst.write_all(&[TYPE_LIST])?;
+            write_string(dst, record.key.as_bytes())?;
+            write_length(dst, list.len() as u64)?;
+
+            for value in list {
+                write_string(dst, value)?;
+            }
+        }
+        Value::Hash(hash) => {
+            dst.write_all(&[TYPE_HASH])?;
+            write_string(dst, record.key.as_bytes())?;
+            write_length(dst, hash.len() as u64)?;
+
+            for (field, value) in hash.iter() {
+                write_string(dst, field.as_bytes())?;
+                write_string(dst, value)?;
+            }
+        }
+        Value::SortedSet(zset) => {
+            dst.write_all(&[TYPE_ZSET_2])?;
+            write_string(dst, record.key.as_bytes())?;
+            write_length(dst, zset.len() as u64)?;
+
+            for (member, score) in zset.iter() {
+                write_string(dst, member.as_bytes())?;
+                dst.write_all(&score.to_le_bytes())?;
+            }
+        }
+        Value::Stream(stream) => {
+            dst.write_all(&[TYPE_STREAM])?;
+            write_string(dst, record.key.as_bytes())?;
+            write_length(dst, stream.len() as u64)?;
+
+            for (id, fields) in stream.iter() {
+                write_length(dst, id.ms)?;
+                write_length(dst, id.seq)?;
+                write_length(dst, fields.len() as u64)?;
+
+                for (field, value) in fields {
+                    write_string(dst, field)?;
+                    write_string(dst, value)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a length, using the smallest of the RDB length encodings.
+fn write_length(dst: &mut impl Write, len: u64) -> io::Result<()> {
+    if len < 1 << 6 {
+        dst.write_all(&[len as u8])
+    } else if len < 1 << 14 {
+        dst.write_all(&[0x40 | (len >> 8) as u8, len as u8])
+    } else if len <= u32::MAX as u64 {
+        dst.write_all(&[0x80])?;
+        dst.write_all(&(len as u32).to_be_bytes())
+    } else {
+        dst.write_all(&[0x81])?;
+        dst.write_all(&len.to_be_bytes())
+    }
+}
+
+/// Write a length prefixed string.
+fn write_string(dst: &mut impl Write, val: &[u8]) -> io::Result<()> {
+    write_length(dst, val.len() as u64)?;
+    dst.write_all(val)
+}
+
+/// Cursor over the content of a snapshot file.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> crate::Result<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return Err("invalid snapshot; unexpected end of file".into());
+        }
+
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> crate::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u64_le(&mut self) -> crate::Result<u64> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Read a length. Fails on the special string encodings.
+    fn length(&mut self) -> crate::Result<u64> {
+        match self.encoded_length()? {
+            Length::Len(len) => Ok(len),
+            Length::Special(_) => Err("invalid snapshot; unexpected string encoding".into()),
+        }
+    }
+
+    fn encoded_length(&mut self) -> crate::Result<Length> {
+        let first = self.u8()?;
+
+        let len = match first >> 6 {
+            0 => (first & 0x3f) as u64,
+            1 => ((first & 0x3f) as u64) << 8 | self.u8()? as u64,
+            2 if first == 0x80 => {
+                let mut buf = [0; 4];
+                buf.copy_from_slice(self.bytes(4)?);
+                u32::from_be_bytes(buf) as u64
+            }
+            2 if first == 0x81 => {
+                let mut buf = [0; 8];
+                buf.copy_from_slice(self.bytes(8)?);
+                u64::from_be_bytes(buf)
+            }
+            2 => return Err("invalid snapshot; invalid length encoding".into()),
+            _ => return Ok(Length::Special(first & 0x3f)),
+        };
+
+        Ok(Length::Len(len))
+    }
+
+    /// Read a string, decoding integer encoded strings.
+    fn string(&mut self) -> crate::Result<Bytes> {
+        let len = match self.encoded_length()? {
+            Length::Len(len) => len as usize,
+            Length::Special(0) => return Ok(Bytes::from((self.u8()? as i8).to_string())),
+            Length::Special(1) => {
+                let buf = self.bytes(2)?;
+                let val = i16::from_le_bytes([buf[0], buf[1]]);
+                return Ok(Bytes::from(val.to_string()));
+            }
+            Length::Special(2) => {
+                let buf = self.bytes(4)?;
+                let val = i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+                return Ok(Bytes::from(val.to_string()));
+            }
+            Length::Special(_) => {
+                return Err("invalid snapshot; compressed strings are not supported".into())
+            }
+        };
+
+        Ok(Bytes::copy_from_slice(self.bytes(len)?))
+    }
+
+    fn utf8(&mut self) -> crate::Result<String> {
+        let val = self.string()?;
+        String::from_utf8(val.to_vec()).map_err(|_| "invalid snapshot; invalid string".into())
+    }
+
+    /// Read a value of type `kind`.
+    fn value(&mut self, kind: u8) -> crate::Result<Value> {
+        match kind {
+            TYPE_STRING => Ok(Value::String(self.string()?)),
+            TYPE_LIST => {
+                let len = self.length()?;
+                let mut list = List::new();
+
+                for _ in 0..len {
+                    list.push_back(self.string()?);
+                }
+
+                Ok(Value::List(list))
+            }
+            TYPE_HASH => {
+                let len = self.length()?;
+                let mut hash = Hash::default();
+
+                for _ in 0..len {
+                    let field = self.utf8()?;
+                    hash.insert(field, self.string()?);
+                }
+
+                Ok(Value::Hash(hash))
+            }
+            TYPE_ZSET_2 => {
+                let len = self.length()?;
+                let mut zset = SortedSet::default();
+
+                for _ in 0..len {
+                    let member = self.utf8()?;
+                    let score = f64::from_bits(self.u64_le()?);
+
+                    if score.is_nan() {
+                        return Err("invalid snapshot; invalid score".into());
+                    }
+
+                    zset.insert(member, score);
+                }
+
+                Ok(Value::SortedSet(zset))
+            }
+            TYPE_STREAM => {
+                let len = self.length()?;
+                let mut stream = Stream::default();
+
+                for _ in 0..len {
+                    let id = StreamId {
+                        ms: self.length()?,
+                        seq: self.length()?,
+                    };
+
+                    let mut fields = vec![];
+                    for _ in 0..self.length()? {
+                        fields.push((self.string()?, self.string()?));
+                    }
+
+                    // Entries are stored in order.
+                    if stream.add(NewId::Explicit(id), fields, 0).is_none() {
+                        return Err("invalid snapshot; unordered stream entries".into());
+                    }
+                }
+
+                Ok(Value::Stream(stream))
+            }
+            kind => Err(format!("invalid snapshot; unsupported value type {}", kind).into()),
+        }
+    }
+}
+
+/// A decoded length, or the format of a specially encoded string.
+enum Length {
+    Len(u64),
+    Special(u8),
+}
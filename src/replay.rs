@@ -0,0 +1,63 @@
+//! Applies commands that do not come from a client connection, such as the
+//! ones read from the append only file or received from a primary.
+
+use crate::{Command, Connection, Db, Frame, Shutdown, ShutdownController};
+
+/// Executes command frames against a `Db`, discarding the replies.
+#[derive(Debug)]
+pub(crate) struct Replay {
+    /// Handle the commands are applied to. Commands such as `SELECT` rebind
+    /// it.
+    db: Db,
+
+    /// Connection the replies are written to. It always captures the frames,
+    /// so the stream is never used.
+    dst: Connection,
+
+    /// None of the replayed commands wait on the shutdown signal, but
+    /// executing a command requires a handle.
+    shutdown: Shutdown,
+
+    /// Keeps `shutdown` from observing a signal.
+    _controller: ShutdownController,
+}
+
+impl Replay {
+    /// Create a `Replay` applying commands to `db`, starting with database
+    /// `0`.
+    pub(crate) fn new(db: &Db) -> Replay {
+        let (stream, _) = tokio::io::duplex(1);
+        let controller = ShutdownController::new();
+
+        Replay {
+            db: db.select(0).unwrap(),
+            dst: Connection::from_stream(stream),
+            shutdown: controller.subscribe(),
+            _controller: controller,
+        }
+    }
+
+    /// Execute the command `frame`.
+    ///
+    /// Returns `Err` if the frame is not a valid command or the command
+    /// replied with an error.
+    pub(crate) async fn apply(&mut self, frame: Frame) -> crate::Result<()> {
+        let cmd = Command::from_frame(frame)?;
+        let name = cmd.get_name().to_string();
+
+        // Like client commands, keep transactions from running meanwhile.
+        let _guard = self.db.lock_shared().await;
+
+        self.dst.begin_capture();
+        cmd.execute(&mut self.db, &mut self.dst, &mut self.shutdown)
+            .await?;
+
+        for reply in self.dst.end_capture() {
+            if let Frame::Error(msg) = reply {
+                return Err(format!("failed to apply `{}`: {}", name, msg).into());
+            }
+        }
+
+        Ok(())
+    }
+}
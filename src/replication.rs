@@ -0,0 +1,148 @@
+//! Primary/replica replication.
+//!
+//! A replica connects to its primary and sends `PSYNC ? -1`. The primary
+//! replies with `+FULLRESYNC <replid> <offset>`, then streams the commands
+//! rebuilding its data, followed by every write applied from then on. Writes
+//! are taken from `Db::subscribe_replication`, the same commands the append
+//! only file is built from, so the replica applies exactly what the primary
+//! applied.
+//!
+//! Each replica is fed by the task handling its connection on the primary.
+//! Writes waiting to be sent are held by the replication backlog, a ring
+//! buffer shared by the replicas. A replica falling behind by more than the
+//! backlog holds is disconnected, rather than buffering writes without bound.
+//!
+//! Partial resynchronization is not supported: whenever the link breaks, the
+//! replica reconnects and performs a full resynchronization.
+
+use crate::db::select_command;
+use crate::replay::Replay;
+use crate::{Connection, Db, Frame, Shutdown};
+
+use bytes::Bytes;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration};
+use tracing::{info, warn};
+
+/// Delay before reconnecting to the primary after the link broke.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Start replicating the primary at `host:port` into `db`, in a background
+/// task.
+///
+/// The task runs until it is aborted, reconnecting whenever the link breaks.
+pub(crate) fn start(db: &Db, host: String, port: u16) -> JoinHandle<()> {
+    let db = db.clone();
+
+    tokio::spawn(async move {
+        loop {
+            match sync(&db, &host, port).await {
+                Ok(()) => info!("connection to primary closed"),
+                Err(err) => warn!(cause = %err, "replication from primary failed"),
+            }
+
+            time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+/// Perform a full resynchronization with the primary, then apply the writes
+/// it sends until the connection is closed.
+async fn sync(db: &Db, host: &str, port: u16) -> crate::Result<()> {
+    let socket = TcpStream::connect((host, port)).await?;
+    let mut primary = Connection::new(socket);
+
+    let mut psync = Frame::array();
+    psync.push_bulk(Bytes::from_static(b"PSYNC"));
+    psync.push_bulk(Bytes::from_static(b"?"));
+    psync.push_bulk(Bytes::from_static(b"-1"));
+    primary.write_frame(&psync).await?;
+
+    match primary.read_frame().await? {
+        Some(Frame::Simple(reply)) if reply.starts_with("FULLRESYNC") => {
+            info!(%host, port, "full resynchronization with primary");
+        }
+        Some(frame) => return Err(format!("unexpected reply to PSYNC: {}", frame).into()),
+        None => return Ok(()),
+    }
+
+    // The data is replaced by the one of the primary.
+    for index in 0..db.config().databases {
+        if let Some(db) = db.select(index) {
+            db.flush();
+        }
+    }
+
+    let mut replay = Replay::new(db);
+
+    while let Some(frame) = primary.read_frame().await? {
+        replay.apply(frame).await?;
+    }
+
+    Ok(())
+}
+
+/// Feed a replica connected on `dst`: send the commands rebuilding the data,
+/// then every write applied from now on.
+///
+/// Returns once the replica disconnects or the server shuts down, or with an
+/// error once the replica falls behind the replication backlog.
+pub(crate) async fn feed(
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> crate::Result<()> {
+    let (snapshot, mut writes) = db.subscribe_replication();
+
+    let response = Frame::Simple(format!("FULLRESYNC {} 0", db.replid()));
+    dst.write_frame(&response).await?;
+
+    // Logical database the commands sent last apply to.
+    let mut selected = None;
+
+    for (db, frame) in &snapshot {
+        send(dst, &mut selected, *db, frame).await?;
+    }
+
+    loop {
+        tokio::select! {
+            res = writes.recv() => match res {
+                Ok((db, frame)) => send(dst, &mut selected, db, &frame).await?,
+                // The replica can no longer apply the same writes as the
+                // primary. The error closes the connection: the replica
+                // reconnects and performs a full resynchronization.
+                Err(RecvError::Lagged(missed)) => {
+                    return Err(format!("replica missed {} writes", missed).into());
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            res = dst.read_frame() => {
+                // Replicas do not send anything besides acknowledgements,
+                // which are ignored.
+                if res?.is_none() {
+                    return Ok(());
+                }
+            }
+            _ = shutdown.recv() => return Ok(()),
+        }
+    }
+}
+
+/// Send the command `frame` applying to the logical database `db`, preceded
+/// by a `SELECT` if needed.
+async fn send(
+    dst: &mut Connection,
+    selected: &mut Option<usize>,
+    db: usize,
+    frame: &Frame,
+) -> crate::Result<()> {
+    if *selected != Some(db) {
+        dst.write_frame(&select_command(db)).await?;
+        *selected = Some(db);
+    }
+
+    dst.write_frame(frame).await?;
+    Ok(())
+}
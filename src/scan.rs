@@ -0,0 +1,87 @@
+//! Iteration order of the `SCAN` family of commands.
+//!
+//! Keys, and the items of hashes and sorted sets, are returned in the order of
+//! their position: a hash of their name, which does not depend on the other
+//! names. The cursor returned to the client is the position to resume from,
+//! so names added or removed meanwhile do not move the names yet to be
+//! returned.
+//!
+//! `ScanOrder` keeps the names ordered by position, so a page is found
+//! without going through every name.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+/// Names ordered by their position. Names sharing a position are returned
+/// together, as the cursor cannot point in between them.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ScanOrder {
+    positions: BTreeMap<u64, Vec<String>>,
+}
+
+impl ScanOrder {
+    /// Add `name`, which must not be present already.
+    pub(crate) fn insert(&mut self, name: String) {
+        self.positions
+            .entry(position(&name))
+            .or_default()
+            .push(name);
+    }
+
+    /// Remove `name`, if present.
+    pub(crate) fn remove(&mut self, name: &str) {
+        let position = position(name);
+
+        if let Some(names) = self.positions.get_mut(&position) {
+            names.retain(|other| other != name);
+
+            if names.is_empty() {
+                self.positions.remove(&position);
+            }
+        }
+    }
+
+    /// Returns the names from position `cursor` on, stopping after about
+    /// `count` names.
+    ///
+    /// Also returns the cursor to resume from, or `0` once every name has been
+    /// returned.
+    pub(crate) fn page(&self, cursor: u64, count: usize) -> (u64, Vec<&str>) {
+        let mut page = vec![];
+
+        for (&position, names) in self.positions.range(cursor..) {
+            if page.len() >= count {
+                return (position, page);
+            }
+
+            page.extend(names.iter().map(String::as_str));
+        }
+
+        (0, page)
+    }
+
+    /// Returns the names sharing the first position at or after `position`,
+    /// wrapping around to the first one.
+    ///
+    /// Positions are hashes, so the names following a random position are
+    /// random names.
+    pub(crate) fn sample(&self, position: u64) -> &[String] {
+        self.positions
+            .range(position..)
+            .next()
+            .or_else(|| self.positions.iter().next())
+            .map(|(_, names)| &names[..])
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the position of `name` in the iteration order.
+///
+/// The hasher is created with fixed keys, so positions are stable for the
+/// lifetime of the process and cursors remain valid across calls.
+fn position(name: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish()
+}
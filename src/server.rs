@@ -3,18 +3,28 @@
 //! Provides an async `run` function that listens for inbound connections,
 //! spawning a task per connection.
 
-use crate::{Command, Connection, Db, DbDropGuard, Shutdown};
-
+use crate::cmd::Transaction;
+use crate::shutdown::{Shutdown, ShutdownController};
+use crate::stats::Stats;
+use crate::{aof, frame, rdb, slowlog, tls};
+use crate::{Command, Connection, Db, DbDropGuard, Frame, ParseError, ServerConfig};
+
+use bytes::Bytes;
+use std::fmt;
 use std::future::Future;
+use std::io;
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, Semaphore};
-use tokio::time::{self, Duration};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Semaphore;
+use tokio::task;
+use tokio::time::{self, Duration, Instant};
+use tokio_rustls::TlsAcceptor;
 use tracing::{debug, error, info, instrument};
 
 /// Server listener state. Created in the `run` call. It includes a `run` method
 /// which performs the TCP listening and initialization of per-connection state.
-#[derive(Debug)]
 struct Listener {
     /// Shared database handle.
     ///
@@ -28,6 +38,15 @@ struct Listener {
     /// TCP listener supplied by the `run` caller.
     listener: TcpListener,
 
+    /// Unix domain socket listener, bound when `ServerConfig::unixsocket` is
+    /// set. Connections are accepted from both listeners.
+    #[cfg(unix)]
+    unix: Option<UnixListener>,
+
+    /// Performs the TLS handshake on accepted sockets. `None` when the server
+    /// is not configured for TLS.
+    tls: Option<TlsAcceptor>,
+
     /// Limit the max number of connections.
     ///
     /// A `Semaphore` is used to limit the max number of connections. Before
@@ -38,30 +57,15 @@ struct Listener {
     /// to the semaphore.
     limit_connections: Arc<Semaphore>,
 
-    /// Broadcasts a shutdown signal to all active connections.
-    ///
-    /// The initial `shutdown` trigger is provided by the `run` caller. The
-    /// server is responsible for gracefully shutting down active connections.
-    /// When a connection task is spawned, it is passed a broadcast receiver
-    /// handle. When a graceful shutdown is initiated, a `()` value is sent via
-    /// the broadcast::Sender. Each active connection receives it, reaches a
-    /// safe terminal state, and completes the task.
-    notify_shutdown: broadcast::Sender<()>,
-
-    /// Used as part of the graceful shutdown process to wait for client
-    /// connections to complete processing.
+    /// Coordinates the graceful shutdown of all active connections.
     ///
-    /// Tokio channels are closed once all `Sender` handles go out of scope.
-    /// When a channel is closed, the receiver receives `None`. This is
-    /// leveraged to detect all connection handlers completing. When a
-    /// connection handler is initialized, it is assigned a clone of
-    /// `shutdown_complete_tx`. When the listener shuts down, it drops the
-    /// sender held by this `shutdown_complete_tx` field. Once all handler tasks
-    /// complete, all clones of the `Sender` are also dropped. This results in
-    /// `shutdown_complete_rx.recv()` completing with `None`. At this point, it
-    /// is safe to exit the server process.
-    shutdown_complete_rx: mpsc::Receiver<()>,
-    shutdown_complete_tx: mpsc::Sender<()>,
+    /// When a connection task is spawned, it subscribes to the controller and
+    /// is handed a `Shutdown`. When a graceful shutdown is initiated, each
+    /// active connection is notified, reaches a safe terminal state, and
+    /// completes the task. Dropping the `Shutdown` handle marks the
+    /// connection as complete, which the controller uses to detect that all
+    /// handlers have finished.
+    shutdown_controller: ShutdownController,
 }
 
 /// Per-connection handler. Reads requests from `connection` and applies the
@@ -73,6 +77,9 @@ struct Handler {
     /// When a command is received from `connection`, it is applied with `db`.
     /// The implementation of the command is in the `cmd` module. Each command
     /// will need to interact with `db` in order to complete the work.
+    ///
+    /// The handle is bound to the logical database selected by the
+    /// connection. `SELECT` replaces it with a handle bound to another one.
     db: Db,
 
     /// The TCP connection decorated with the redis protocol encoder / decoder
@@ -92,10 +99,62 @@ struct Handler {
     /// received from `shutdown`. In the latter case, any in-flight work being
     /// processed for the peer is continued until it reaches a safe state, at
     /// which point the connection is terminated.
+    ///
+    /// The handle is dropped together with the `Handler`, which signals to
+    /// the `ShutdownController` that this connection has completed.
     shutdown: Shutdown,
 
-    /// Not used directly. Instead, when `Handler` is dropped...?
-    _shutdown_complete: mpsc::Sender<()>,
+    /// Commands queued by `MULTI` and keys watched by `WATCH`.
+    transaction: Transaction,
+
+    /// Counters reported by `INFO`. The connection is counted as open until
+    /// the `Handler` drops.
+    stats: Arc<Stats>,
+}
+
+/// A socket accepted by one of the listeners.
+#[derive(Debug)]
+enum Socket {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Socket {
+    /// Returns the address of the peer, as listed by `CLIENT LIST`. Peers of
+    /// the unix socket are identified by the path of the socket.
+    fn peer_addr(&self) -> String {
+        match self {
+            Socket::Tcp(socket) => socket
+                .peer_addr()
+                .map(|addr| addr.to_string())
+                .unwrap_or_default(),
+            #[cfg(unix)]
+            Socket::Unix(socket) => socket
+                .local_addr()
+                .ok()
+                .and_then(|addr| {
+                    addr.as_pathname()
+                        .map(|path| format!("{}:0", path.display()))
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Keeps a client registered while its connection task runs. The client is
+/// unregistered when the task completes or is aborted by `CLIENT KILL`, as
+/// both drop the registration.
+#[derive(Debug)]
+struct Registration {
+    db: Db,
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.db.unregister_client(self.id);
+    }
 }
 
 /// Maximum number of concurrent connections the redis server will accept.
@@ -122,29 +181,14 @@ const MAX_CONNECTIONS: usize = 250;
 /// `tokio::signal::ctrl_c()` can be used as the `shutdown` argument. This will
 /// listen for a SIGINT signal.
 pub async fn run(listener: TcpListener, shutdown: impl Future) {
-    // When the provided `shutdown` future completes, we must send a shutdown
-    // message to all active connections. We use a broadcast channel for this
-    // purpose. The call below ignores the receiver of the broadcast pair, and when
-    // a receiver is needed, the subscribe() method on the sender is used to create
-    // one.
-    let (notify_shutdown, _) = broadcast::channel(1);
-    let (shutdown_complete_tx, shutdown_complete_rx) = mpsc::channel(1);
+    let controller = ShutdownController::new();
 
-    // Initialize the listener state
-    let mut server = Listener {
-        listener,
-        db_holder: DbDropGuard::new(),
-        limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
-        notify_shutdown,
-        shutdown_complete_tx,
-        shutdown_complete_rx,
-    };
+    // Run the server with a controller of its own. When the provided
+    // `shutdown` future completes, the controller is triggered and the server
+    // is given the chance to complete its graceful shutdown.
+    let server = run_with_controller(listener, controller.clone());
+    tokio::pin!(server);
 
-    // Concurrently run the server and listen for the `shutdown` signal. The
-    // server task runs until an error is encountered, so under normal
-    // circumstances, this `select!` statement runs until the `shutdown` signal
-    // is received.
-    //
     // `select!` statements are written in the form of:
     //
     // ```
@@ -159,6 +203,141 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
     // asynchronous Rust. See the API docs for more details:
     //
     // https://docs.rs/tokio/*/tokio/macro.select.html
+    tokio::select! {
+        _ = &mut server => return,
+        _ = shutdown => {
+            // The shutdown signal has been received.
+            info!("shutting down");
+            controller.trigger();
+        }
+    }
+
+    server.await
+}
+
+/// Run the mini-redis server until `controller` is triggered.
+///
+/// This is the same as [`run`] except that shutdown is driven by a
+/// [`ShutdownController`]. This allows embedders to trigger shutdown
+/// programmatically and to register their own background tasks as shutdown
+/// participants. Once triggered, the function returns after all participants,
+/// including the ones registered by the embedder, have completed.
+pub async fn run_with_controller(listener: TcpListener, controller: ShutdownController) {
+    if let Err(err) = run_with_config(listener, ServerConfig::default(), controller).await {
+        error!(cause = %err, "server failed");
+    }
+}
+
+/// Run the mini-redis server with the given `config` until `controller` is
+/// triggered.
+///
+/// See [`run_with_controller`] for how shutdown is coordinated.
+///
+/// # Errors
+///
+/// Returns `Err` if the server cannot start, such as when the TLS certificate
+/// or the persisted data cannot be loaded, or if the snapshot requested on
+/// shutdown cannot be saved.
+pub async fn run_with_config(
+    listener: TcpListener,
+    mut config: ServerConfig,
+    controller: ShutdownController,
+) -> crate::Result<()> {
+    // `SELECT 0` must always be valid.
+    if config.databases == 0 {
+        return Err("there must be at least one database".into());
+    }
+
+    // Load the certificate before anything else, so a misconfigured server
+    // fails right away.
+    let tls = match config.tls.as_ref().map(tls::acceptor).transpose() {
+        Ok(tls) => tls,
+        Err(err) => return Err(format!("failed to load the TLS certificate: {}", err).into()),
+    };
+
+    // Bind the unix socket, replacing the file left behind by a previous run.
+    #[cfg(unix)]
+    let unix = match &config.unixsocket {
+        Some(path) => {
+            let _ = std::fs::remove_file(path);
+
+            match UnixListener::bind(path) {
+                Ok(listener) => Some(listener),
+                Err(err) => return Err(format!("failed to bind the unix socket: {}", err).into()),
+            }
+        }
+        None => None,
+    };
+
+    #[cfg(not(unix))]
+    if config.unixsocket.is_some() {
+        return Err("unix sockets are not supported on this platform".into());
+    }
+
+    // Reported by `CONFIG GET port`.
+    config.port = match listener.local_addr() {
+        Ok(addr) => addr.port(),
+        Err(err) => return Err(format!("failed to read the listener address: {}", err).into()),
+    };
+
+    let db_holder = DbDropGuard::new(config.clone());
+
+    // Lets `SHUTDOWN` stop the server.
+    let db = db_holder.db();
+    db.set_shutdown_controller(controller.clone());
+
+    // Rebuild the data from the append only file before accepting any
+    // connection, then log every write from now on. The writer task is a
+    // shutdown participant, so pending writes are flushed before returning.
+    //
+    // The append only file is more up to date than the snapshot, so the
+    // snapshot is only loaded when the append only file is disabled.
+    if config.appendonly {
+        let db = db_holder.db();
+        let path = &config.appendfilename;
+
+        if let Err(err) = aof::load(&db, path).await {
+            return Err(format!("failed to load the append only file: {}", err).into());
+        }
+
+        let shutdown = controller.subscribe();
+        if let Err(err) = aof::start(&db, path, shutdown).await {
+            return Err(format!("failed to open the append only file: {}", err).into());
+        }
+    } else {
+        let db = db_holder.db();
+        let path = config.dbfilename.clone();
+
+        // Decoding the snapshot is blocking.
+        let res = task::spawn_blocking(move || rdb::restore(&db, &path)).await;
+
+        if let Err(err) = res.unwrap_or_else(|err| Err(err.into())) {
+            return Err(format!("failed to load the snapshot: {}", err).into());
+        }
+    }
+
+    // Save snapshots as directed by the save points.
+    rdb::start(&db, controller.subscribe());
+
+    // Initialize the listener state
+    let mut server = Listener {
+        listener,
+        #[cfg(unix)]
+        unix,
+        tls,
+        db_holder,
+        limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+        shutdown_controller: controller.clone(),
+    };
+
+    // The listener itself is a participant as well. It stops accepting new
+    // connections once the signal is received.
+    let mut shutdown = controller.subscribe();
+
+    // Concurrently run the server and listen for the shutdown signal. The
+    // server task runs until an error is encountered, so under normal
+    // circumstances, this `select!` statement runs until the shutdown signal
+    // is received.
     tokio::select! {
         res = server.run() => {
             // If an error is received here, accepting connections from the TCP
@@ -171,33 +350,42 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
                 error!(cause = %err, "failed to accept");
             }
         }
-        _ = shutdown => {
-            // The shutdown signal has been received.
-            info!("shutting down");
+        _ = shutdown.recv() => {
+            info!("shutdown signal received");
+        }
+    }
+
+    // Make sure every participant is notified. This is a no-op if shutdown
+    // was already triggered, but is needed when the listener failed on its
+    // own.
+    controller.trigger();
+
+    // Drop the listener's own handles so that the wait below does not depend
+    // on them. This also stops accepting connections.
+    drop(shutdown);
+    drop(server);
+
+    // Clean up the unix socket file now that nothing listens on it anymore.
+    if let Some(path) = &config.unixsocket {
+        let _ = std::fs::remove_file(path);
+    }
+
+    // Wait for all active connections and any other registered participants
+    // to finish processing. Each of them holds a `Shutdown` handle; once all
+    // of them are dropped the controller reports completion.
+    controller.wait_complete().await;
+
+    // No connection is left to modify the data, so the snapshot holds every
+    // write acknowledged to a client.
+    if db.save_on_shutdown() {
+        if let Err(err) = rdb::save_now(&db).await {
+            return Err(format!("failed to save the snapshot: {}", err).into());
         }
+
+        info!("saved the snapshot before exiting");
     }
 
-    // Extract the `shutdown_complete` receiver and transmitter
-    // explicitly drop `shutdown_transmitter`. This is important, as the
-    // `.await` below would otherwise never complete.
-    let Listener {
-        mut shutdown_complete_rx,
-        shutdown_complete_tx,
-        notify_shutdown,
-        ..
-    } = server;
-
-    // When `notify_shutdown` is dropped, all tasks which have `subscribe`d will
-    // receive the shutdown signal and can exit
-    drop(notify_shutdown);
-    // Drop final `Sender` so the `Receiver` below can complete
-    drop(shutdown_complete_tx);
-
-    // Wait for all active connections to finish processing. As the `Sender`
-    // handle held by the listener has been dropped above, the only remaining
-    // `Sender` instances are held by connection handler tasks. When those drop,
-    // the `mpsc` channel will close and `recv()` will return `None`.
-    let _ = shutdown_complete_rx.recv().await;
+    Ok(())
 }
 
 impl Listener {
@@ -240,26 +428,69 @@ impl Listener {
             // error here is non-recoverable.
             let socket = self.accept().await?;
 
-            // Create the necessary per-connection handler state.
-            let mut handler = Handler {
-                // Get a handle to the shared database.
-                db: self.db_holder.db(),
-
-                // Initialize the connection state. This allocates read/write
-                // buffers to perform redis protocol frame parsing.
-                connection: Connection::new(socket),
-
-                // Receive shutdown notifications.
-                shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
+            let tls = self.tls.clone();
+            let db = self.db_holder.db();
+            let mut shutdown = self.shutdown_controller.subscribe();
 
-                // Notifies the receiver half once all clones are
-                // dropped.
-                _shutdown_complete: self.shutdown_complete_tx.clone(),
+            // The client is registered before its task is spawned, so the
+            // task can always unregister it.
+            let registration = Registration {
+                id: db.register_client(socket.peer_addr()),
+                db: db.clone(),
             };
+            let id = registration.id;
 
             // Spawn a new task to process the connections. Tokio tasks are like
             // asynchronous green threads and are executed concurrently.
-            tokio::spawn(async move {
+            let task = tokio::spawn(async move {
+                let registration = registration;
+
+                // The TLS handshake is performed by the connection task, so a
+                // slow peer does not hold up accepting other connections. Unix
+                // socket connections are local and never encrypted.
+                let connection = match (socket, tls) {
+                    (Socket::Tcp(socket), Some(acceptor)) => {
+                        let res = tokio::select! {
+                            res = acceptor.accept(socket) => res,
+                            _ = shutdown.recv() => return,
+                        };
+
+                        match res {
+                            Ok(stream) => Connection::from_stream(stream),
+                            Err(err) => {
+                                debug!(cause = %err, "TLS handshake failed");
+                                return;
+                            }
+                        }
+                    }
+                    // Initialize the connection state. This allocates read/write
+                    // buffers to perform redis protocol frame parsing.
+                    (Socket::Tcp(socket), None) => Connection::new(socket),
+                    #[cfg(unix)]
+                    (Socket::Unix(socket), _) => Connection::from_stream(socket),
+                };
+
+                let stats = db.stats();
+                stats.connection_opened();
+
+                // Create the necessary per-connection handler state.
+                let mut handler = Handler {
+                    db,
+                    connection,
+                    shutdown,
+
+                    // Connections start outside of a transaction.
+                    transaction: Transaction::default(),
+                    stats,
+                };
+
+                // Connections are logged in as the default user, unless it
+                // requires a password.
+                let user = handler.db.default_login();
+                handler.connection.set_user(user);
+                handler.connection.set_client_id(registration.id);
+                handler.connection.accept_inline();
+
                 // Process the connection. If an error is encountered, log it.
                 if let Err(err) = handler.run().await {
                     error!(cause = ?err, "connection error");
@@ -268,6 +499,9 @@ impl Listener {
                 // This returns the permit back to the semaphore.
                 drop(permit);
             });
+
+            // Lets `CLIENT KILL` abort the task.
+            self.db_holder.db().set_client_task(id, task);
         }
     }
 
@@ -278,15 +512,15 @@ impl Listener {
     /// After the second failure, the task waits for 2 seconds. Each subsequent
     /// failure doubles the wait time. If accepting fails on the 6th try after
     /// waiting for 64 seconds, then this function returns with an error.
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
+    async fn accept(&mut self) -> crate::Result<Socket> {
         let mut backoff = 1;
 
         // Try to accept a few times
         loop {
             // Perform the accept operation. If a socket is successfully
             // accepted, return it. Otherwise, save the error.
-            match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+            match self.accept_any().await {
+                Ok(socket) => return Ok(socket),
                 Err(err) => {
                     if backoff > 64 {
                         // Accept has failed too many times. Return the error.
@@ -302,6 +536,36 @@ impl Listener {
             backoff *= 2;
         }
     }
+
+    /// Wait for an inbound connection on any of the listeners.
+    async fn accept_any(&self) -> io::Result<Socket> {
+        #[cfg(unix)]
+        {
+            if let Some(unix) = &self.unix {
+                return tokio::select! {
+                    res = self.listener.accept() => res.map(|(socket, _)| Socket::Tcp(socket)),
+                    res = unix.accept() => res.map(|(socket, _)| Socket::Unix(socket)),
+                };
+            }
+        }
+
+        let (socket, _) = self.listener.accept().await?;
+        Ok(Socket::Tcp(socket))
+    }
+}
+
+// `TlsAcceptor` does not implement `Debug`, so only whether TLS is enabled is
+// shown.
+impl fmt::Debug for Listener {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Listener")
+            .field("db_holder", &self.db_holder)
+            .field("listener", &self.listener)
+            .field("tls", &self.tls.is_some())
+            .field("limit_connections", &self.limit_connections)
+            .field("shutdown_controller", &self.shutdown_controller)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Handler {
@@ -324,8 +588,8 @@ impl Handler {
         while !self.shutdown.is_shutdown() {
             // While reading a request frame, also listen for the shutdown
             // signal.
-            let maybe_frame = tokio::select! {
-                res = self.connection.read_frame() => res?,
+            let res = tokio::select! {
+                res = self.connection.read_frame() => res,
                 _ = self.shutdown.recv() => {
                     // If a shutdown signal is received, return from `run`.
                     // This will result in the task terminating.
@@ -336,15 +600,37 @@ impl Handler {
             // If `None` is returned from `read_frame()` then the peer closed
             // the socket. There is no further work to do and the task can be
             // terminated.
-            let frame = match maybe_frame {
-                Some(frame) => frame,
-                None => return Ok(()),
+            let frame = match res {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return Ok(()),
+                // Malformed frames are reported to the peer. Other errors,
+                // such as I/O errors, leave no peer to report them to.
+                Err(err) if err.is::<frame::Error>() => return self.reject(err).await,
+                Err(err) => return Err(err),
             };
 
+            // Connections running `MONITOR` see every command received,
+            // before it is parsed.
+            self.db.feed_monitors(self.connection.client_id(), &frame);
+
+            // The arguments are kept for the slow log, which needs them once
+            // the command executed.
+            let args = slowlog::arguments(&frame);
+
             // Convert the redis frame into a command struct. This returns an
             // error if the frame is not a valid redis command or it is an
             // unsupported command.
-            let cmd = Command::from_frame(frame)?;
+            let cmd = match Command::from_frame(frame) {
+                Ok(cmd) => cmd,
+                // The connection is kept open, so the replies to the commands
+                // pipelined after this one still line up.
+                Err(err) => {
+                    self.refuse(&args, err).await?;
+                    continue;
+                }
+            };
+            self.db
+                .record_command(self.connection.client_id(), cmd.get_name());
 
             // Logs the `cmd` object. The syntax here is a shorthand provided by
             // the `tracing` crate. It can be thought of as similar to:
@@ -364,10 +650,85 @@ impl Handler {
             // command to write response frames directly to the connection. In
             // the case of pub/sub, multiple frames may be send back to the
             // peer.
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown)
-                .await?;
+            let blocking = cmd.is_blocking();
+            let started_at = Instant::now();
+
+            cmd.apply(
+                &mut self.db,
+                &mut self.connection,
+                &mut self.shutdown,
+                &mut self.transaction,
+            )
+            .await?;
+
+            // As in Redis, a command is counted once it ran, so `INFO` does
+            // not count itself.
+            self.stats.command_processed();
+
+            if !blocking {
+                self.db
+                    .log_if_slow(self.connection.client_id(), args, started_at.elapsed());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reply to a request that could not be parsed, then close the
+    /// connection.
+    ///
+    /// Once a request is malformed, the following bytes cannot be trusted to
+    /// start a new request, so the connection is not processed any further.
+    async fn reject(&mut self, err: crate::Error) -> crate::Result<()> {
+        debug!(cause = %err, "protocol error");
+
+        let err = err.to_string();
+        let reason = err.strip_prefix("protocol error; ").unwrap_or(&err);
+        let response = Frame::Error(format!("ERR Protocol error: {}", reason));
+
+        self.connection.write_frame(&response).await?;
+        self.connection.shutdown().await?;
+
+        Ok(())
+    }
+
+    /// Reply to a command that could not be parsed, such as a command missing
+    /// arguments. `args` are the arguments of the command, starting with its
+    /// name.
+    ///
+    /// As in Redis, the active transaction is aborted: `EXEC` then discards
+    /// it.
+    async fn refuse(&mut self, args: &[Bytes], err: crate::Error) -> crate::Result<()> {
+        debug!(cause = %err, "invalid command");
+
+        let response = match err.downcast_ref::<ParseError>() {
+            Some(ParseError::EndOfStream | ParseError::Trailing) => {
+                let name = args
+                    .first()
+                    .map(|name| String::from_utf8_lossy(name).to_lowercase())
+                    .unwrap_or_default();
+
+                Frame::Error(format!(
+                    "ERR wrong number of arguments for '{}' command",
+                    name
+                ))
+            }
+            _ => Frame::Error("ERR syntax error".to_string()),
+        };
+
+        if self.transaction.is_active() {
+            self.transaction.abort();
         }
 
+        debug!(?response);
+        self.connection.write_frame(&response).await?;
+
         Ok(())
     }
 }
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        self.stats.connection_closed();
+    }
+}
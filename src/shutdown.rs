@@ -1,26 +1,135 @@
-use tokio::sync::broadcast;
+//! Graceful shutdown coordination.
+//!
+//! A [`ShutdownController`] owns the shutdown signal. Any number of
+//! participants (the server's connection handlers, but also background tasks
+//! spawned by an embedding application) subscribe to it and receive a
+//! [`Shutdown`] handle. Once the signal is triggered, every participant is
+//! notified and is expected to reach a safe state and drop its handle. The
+//! controller can then wait until all participants have completed.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Owns the shutdown signal and tracks the participants listening for it.
+///
+/// `ShutdownController` is a handle to shared state. Cloning it is shallow and
+/// all clones control the same signal. This allows, for example, one clone to
+/// be passed to the server while another is kept around to trigger shutdown
+/// programmatically.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mini_redis::server;
+/// use mini_redis::shutdown::ShutdownController;
+/// use tokio::net::TcpListener;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let listener = TcpListener::bind("127.0.0.1:6379").await.unwrap();
+///     let controller = ShutdownController::new();
+///
+///     // A background task of the embedding application.
+///     let mut shutdown = controller.subscribe();
+///     tokio::spawn(async move {
+///         shutdown.recv().await;
+///         // Flush state, then drop `shutdown` to signal completion.
+///     });
+///
+///     let server = tokio::spawn(server::run_with_controller(listener, controller.clone()));
+///
+///     // Some time later, stop the server and wait for every participant.
+///     controller.shutdown().await;
+///     server.await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShutdownController {
+    /// The send half of the shutdown signal. The value flips to `true` once
+    /// shutdown is triggered.
+    ///
+    /// Each `Shutdown` handle holds a receiver of this channel. The channel
+    /// reports itself as closed once all receivers have dropped, which is used
+    /// to detect that all participants have completed.
+    notify: Arc<watch::Sender<bool>>,
+}
 
 /// Listens for the server shutdown signal.
 ///
-/// Shutdown is signalled using a `broadcast::Receiver`. Only a single value is
-/// ever sent. Once a value has been sent via the broadcast channel, the server
-/// should shutdown.
+/// Shutdown is signalled by a `ShutdownController`. Once triggered, the signal
+/// is never reset, so the participant should reach a safe state and exit.
 ///
 /// The `Shutdown` struct listens for the signal and tracks that the signal has
 /// been received. Callers may query for whether the shutdown signal has been
 /// received or not.
+///
+/// Holding a `Shutdown` also registers the holder as a participant of the
+/// shutdown process: `ShutdownController::wait_complete` does not return until
+/// the handle is dropped.
 #[derive(Debug)]
-pub(crate) struct Shutdown {
+pub struct Shutdown {
     /// `true` if the shutdown signal has been received
     shutdown: bool,
 
     /// The receive half of the channel used to listen for shutdown.
-    notify: broadcast::Receiver<()>,
+    notify: watch::Receiver<bool>,
+}
+
+impl ShutdownController {
+    /// Create a new `ShutdownController` with no participants.
+    pub fn new() -> ShutdownController {
+        // The initial receiver is dropped right away. Participants obtain
+        // their own receiver through `subscribe`.
+        let (notify, _) = watch::channel(false);
+
+        ShutdownController {
+            notify: Arc::new(notify),
+        }
+    }
+
+    /// Register a new participant and return its `Shutdown` handle.
+    ///
+    /// If shutdown has already been triggered, the returned handle observes
+    /// the signal immediately.
+    pub fn subscribe(&self) -> Shutdown {
+        Shutdown::new(self.notify.subscribe())
+    }
+
+    /// Signal all participants to shut down.
+    ///
+    /// This does not wait for the participants to complete; see
+    /// `wait_complete` or `shutdown`.
+    pub fn trigger(&self) {
+        self.notify.send_replace(true);
+    }
+
+    /// Returns `true` if shutdown has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        *self.notify.borrow()
+    }
+
+    /// Wait until every `Shutdown` handle obtained from this controller has
+    /// been dropped.
+    pub async fn wait_complete(&self) {
+        self.notify.closed().await;
+    }
+
+    /// Signal all participants to shut down, then wait for them to complete.
+    pub async fn shutdown(&self) {
+        self.trigger();
+        self.wait_complete().await;
+    }
+}
+
+impl Default for ShutdownController {
+    fn default() -> ShutdownController {
+        ShutdownController::new()
+    }
 }
 
 impl Shutdown {
-    /// Create a new `Shutdown` backed by the given `broadcast::Receiver`.
-    pub(crate) fn new(notify: broadcast::Receiver<()>) -> Shutdown {
+    /// Create a new `Shutdown` backed by the given `watch::Receiver`.
+    pub(crate) fn new(notify: watch::Receiver<bool>) -> Shutdown {
         Shutdown {
             shutdown: false,
             notify,
@@ -28,20 +137,26 @@ impl Shutdown {
     }
 
     /// Returns `true` if the shutdown signal has been received.
-    pub(crate) fn is_shutdown(&self) -> bool {
+    pub fn is_shutdown(&self) -> bool {
         self.shutdown
     }
 
     /// Receive the shutdown notice, waiting if necessary.
-    pub(crate) async fn recv(&mut self) {
+    pub async fn recv(&mut self) {
         // If the shutdown signal has already been received, then return
         // immediately.
         if self.shutdown {
             return;
         }
 
-        // Cannot receive a "lag error" as only one value is ever sent.
-        let _ = self.notify.recv().await;
+        // Wait for the value to flip to `true`. If all controllers are dropped
+        // the signal can never be triggered anymore, which is treated as a
+        // shutdown as well.
+        while !*self.notify.borrow_and_update() {
+            if self.notify.changed().await.is_err() {
+                break;
+            }
+        }
 
         // Remember that the signal has been received.
         self.shutdown = true;
@@ -0,0 +1,139 @@
+//! The slow log, recording the commands that took the longest to execute.
+//!
+//! Commands taking longer than `slowlog-log-slower-than` are recorded, along
+//! with their arguments and the client running them. The log is bounded by
+//! `slowlog-max-len`: the oldest entries are dropped first.
+
+use crate::{acl, Frame};
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Maximum number of arguments of a command kept in an entry.
+const MAX_ARGS: usize = 32;
+
+/// Maximum length of an argument kept in an entry, in bytes.
+const MAX_ARG_LEN: usize = 128;
+
+/// The entries of the slow log, newest first.
+#[derive(Debug)]
+pub(crate) struct SlowLog {
+    /// Identifier of the next entry. Identifiers are never reused, even
+    /// after a reset.
+    next_id: u64,
+
+    entries: VecDeque<Entry>,
+}
+
+/// A command recorded in the slow log.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub(crate) id: u64,
+
+    /// Unix time at which the command was recorded, in seconds.
+    pub(crate) timestamp: u64,
+
+    /// How long the command took to execute.
+    pub(crate) duration: Duration,
+
+    /// The arguments of the command, starting with its name. See
+    /// `arguments`.
+    pub(crate) args: Vec<Bytes>,
+
+    /// Address of the client that ran the command.
+    pub(crate) addr: String,
+
+    /// Name of the client that ran the command, empty if it has none.
+    pub(crate) name: String,
+}
+
+impl SlowLog {
+    pub(crate) fn new() -> SlowLog {
+        SlowLog {
+            next_id: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Record a command, dropping the oldest entries beyond `max_len`.
+    pub(crate) fn push(
+        &mut self,
+        args: Vec<Bytes>,
+        duration: Duration,
+        addr: String,
+        name: String,
+        max_len: usize,
+    ) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+
+        self.entries.push_front(Entry {
+            id: self.next_id,
+            timestamp,
+            duration,
+            args,
+            addr,
+            name,
+        });
+        self.next_id += 1;
+
+        self.entries.truncate(max_len);
+    }
+
+    /// Returns up to `count` entries, newest first. All of them if `count` is
+    /// `None`.
+    pub(crate) fn entries(&self, count: Option<usize>) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().take(count.unwrap_or(usize::MAX))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Returns the arguments of the command `frame`, as recorded in the slow log.
+///
+/// Only the first arguments are kept, and long arguments are cut short. This
+/// bounds the memory used by the slow log. What was left out is mentioned in
+/// place of the last argument, or at the end of the argument. Passwords are
+/// not disclosed.
+pub(crate) fn arguments(frame: &Frame) -> Vec<Bytes> {
+    let parts = match frame {
+        Frame::Array(parts) => &parts[..],
+        frame => std::slice::from_ref(frame),
+    };
+
+    let mut args = vec![];
+
+    for (i, part) in parts.iter().enumerate() {
+        if i == MAX_ARGS - 1 && parts.len() > MAX_ARGS {
+            let more = parts.len() - i;
+            args.push(Bytes::from(format!("... ({} more arguments)", more)));
+            break;
+        }
+
+        let arg = match part {
+            _ if acl::is_secret(parts, i) => Bytes::from_static(acl::REDACTED.as_bytes()),
+            Frame::Bulk(data) => data.clone(),
+            part => Bytes::from(part.to_string()),
+        };
+
+        if arg.len() > MAX_ARG_LEN {
+            let mut cut = arg[..MAX_ARG_LEN].to_vec();
+            let more = arg.len() - MAX_ARG_LEN;
+            cut.extend_from_slice(format!("... ({} more bytes)", more).as_bytes());
+            args.push(Bytes::from(cut));
+        } else {
+            args.push(arg);
+        }
+    }
+
+    args
+}
@@ -0,0 +1,125 @@
+//! Statistics of the server, as reported by `INFO`.
+//!
+//! Counters are atomics, so connections update them without holding the state
+//! lock of the `Db`. They count events since the server started.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::time::{Duration, Instant};
+
+/// Counters shared by the whole server.
+#[derive(Debug)]
+pub(crate) struct Stats {
+    /// When the server started.
+    started_at: Instant,
+
+    /// Number of connections currently open.
+    connected_clients: AtomicU64,
+
+    /// Number of connections accepted.
+    total_connections_received: AtomicU64,
+
+    /// Number of commands received, including the ones that failed.
+    total_commands_processed: AtomicU64,
+
+    /// Number of successful lookups of keys.
+    keyspace_hits: AtomicU64,
+
+    /// Number of lookups of keys that do not exist.
+    keyspace_misses: AtomicU64,
+
+    /// Number of keys removed because they expired.
+    expired_keys: AtomicU64,
+
+    /// Number of keys evicted under `maxmemory`.
+    evicted_keys: AtomicU64,
+
+    /// Number of values converted from their compact encoding.
+    encoding_conversions: AtomicU64,
+}
+
+impl Stats {
+    /// Create the counters of a server starting now.
+    pub(crate) fn new() -> Stats {
+        Stats {
+            started_at: Instant::now(),
+            connected_clients: AtomicU64::new(0),
+            total_connections_received: AtomicU64::new(0),
+            total_commands_processed: AtomicU64::new(0),
+            keyspace_hits: AtomicU64::new(0),
+            keyspace_misses: AtomicU64::new(0),
+            expired_keys: AtomicU64::new(0),
+            evicted_keys: AtomicU64::new(0),
+            encoding_conversions: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns how long the server has been running.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub(crate) fn connection_opened(&self) {
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+        self.total_connections_received
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn connection_closed(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn command_processed(&self) {
+        self.total_commands_processed
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a lookup of a key, which exists if `hit`.
+    pub(crate) fn keyspace_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.keyspace_hits
+        } else {
+            &self.keyspace_misses
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn key_expired(&self) {
+        self.expired_keys.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn key_evicted(&self) {
+        self.evicted_keys.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn encoding_converted(&self) {
+        self.encoding_conversions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of connections currently open.
+    pub(crate) fn connected_clients(&self) -> u64 {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    /// Returns the name and value of every counter of events, in the order
+    /// `INFO` lists them.
+    pub(crate) fn counters(&self) -> [(&'static str, u64); 7] {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        [
+            (
+                "total_connections_received",
+                load(&self.total_connections_received),
+            ),
+            (
+                "total_commands_processed",
+                load(&self.total_commands_processed),
+            ),
+            ("keyspace_hits", load(&self.keyspace_hits)),
+            ("keyspace_misses", load(&self.keyspace_misses)),
+            ("expired_keys", load(&self.expired_keys)),
+            ("evicted_keys", load(&self.evicted_keys)),
+            ("encoding_conversions", load(&self.encoding_conversions)),
+        ]
+    }
+}
@@ -0,0 +1,166 @@
+//! Stream value type.
+//!
+//! A stream is an append only log of entries, each holding field-value pairs.
+//! Entries are identified by `<ms>-<seq>`: the Unix time in milliseconds at
+//! which the entry was added, and a sequence number telling apart entries
+//! added within the same millisecond. Identifiers only ever grow, so entries
+//! are kept in a `BTreeMap` ordered by identifier, and ranges of entries are
+//! found in logarithmic time.
+
+use bytes::Bytes;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound;
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Stream {
+    entries: BTreeMap<StreamId, Fields>,
+
+    /// Identifier of the last entry added. New entries must have a greater
+    /// identifier.
+    last_id: StreamId,
+}
+
+/// Identifier of a stream entry, ordered by time then sequence number.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct StreamId {
+    pub(crate) ms: u64,
+    pub(crate) seq: u64,
+}
+
+/// Identifier requested for a new entry, as given to `XADD`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum NewId {
+    /// Generate the identifier from the current time, `*`.
+    Auto,
+
+    /// Use the given time, generating the sequence number, `<ms>-*`.
+    Ms(u64),
+
+    /// Use exactly the given identifier.
+    Explicit(StreamId),
+}
+
+/// Fields of a stream entry, in the order they were given.
+pub(crate) type Fields = Vec<(Bytes, Bytes)>;
+
+/// Entries of a stream along with their identifier, in order.
+pub(crate) type StreamEntries = Vec<(StreamId, Fields)>;
+
+impl Stream {
+    /// Returns the number of entries.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the stream has no entries.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the identifier of the last entry added, `0-0` if none was.
+    pub(crate) fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// Add an entry, `now` being the current Unix time in milliseconds.
+    /// Returns the identifier of the entry.
+    ///
+    /// Returns `None` if the identifier would not be greater than the one of
+    /// the last entry.
+    pub(crate) fn add(&mut self, id: NewId, fields: Fields, now: u64) -> Option<StreamId> {
+        let last = self.last_id;
+
+        let id = match id {
+            // The clock may go backwards, identifiers may not.
+            NewId::Auto if now > last.ms => StreamId { ms: now, seq: 0 },
+            NewId::Auto => last.next()?,
+            NewId::Ms(ms) if ms == last.ms => StreamId {
+                ms,
+                seq: last.seq.checked_add(1)?,
+            },
+            NewId::Ms(ms) => StreamId { ms, seq: 0 },
+            NewId::Explicit(id) => id,
+        };
+
+        if id <= last {
+            return None;
+        }
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        Some(id)
+    }
+
+    /// Returns up to `count` entries with an identifier between `start` and
+    /// `end`, both inclusive.
+    pub(crate) fn range(&self, start: StreamId, end: StreamId, count: usize) -> StreamEntries {
+        if start > end {
+            return vec![];
+        }
+
+        take(self.entries.range(start..=end), count)
+    }
+
+    /// Returns up to `count` entries with an identifier greater than `id`.
+    pub(crate) fn after(&self, id: StreamId, count: usize) -> StreamEntries {
+        let entries = self.entries.range((Bound::Excluded(id), Bound::Unbounded));
+        take(entries, count)
+    }
+
+    /// Returns an iterator over the entries, in order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&StreamId, &Fields)> {
+        self.entries.iter()
+    }
+}
+
+impl StreamId {
+    pub(crate) const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+
+    pub(crate) const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    /// Parse an identifier, `<ms>-<seq>`. The sequence number may be omitted,
+    /// in which case it is `default_seq`.
+    pub(crate) fn parse(src: &str, default_seq: u64) -> Option<StreamId> {
+        let (ms, seq) = match src.split_once('-') {
+            Some((ms, seq)) => (ms, seq.parse().ok()?),
+            None => (src, default_seq),
+        };
+
+        Some(StreamId {
+            ms: ms.parse().ok()?,
+            seq,
+        })
+    }
+
+    /// Returns the smallest identifier greater than this one.
+    fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => Some(StreamId {
+                ms: self.ms.checked_add(1)?,
+                seq: 0,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// Returns copies of the first `count` entries of `entries`.
+fn take<'a>(
+    entries: impl Iterator<Item = (&'a StreamId, &'a Fields)>,
+    count: usize,
+) -> StreamEntries {
+    entries
+        .take(count)
+        .map(|(id, fields)| (*id, fields.clone()))
+        .collect()
+}
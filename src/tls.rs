@@ -0,0 +1,117 @@
+//! TLS support for server and client connections.
+//!
+//! The server presents the certificate configured by `ServerConfig::tls`.
+//! Clients verify it against the certificate authorities of the operating
+//! system, or against the ones passed in `TlsOptions::ca_file`. Client
+//! certificates are not supported.
+
+use crate::config::TlsConfig;
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::{self, Certificate, PrivateKey, RootCertStore, ServerName};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// Options for establishing a TLS connection to a server.
+#[derive(Debug, Clone)]
+pub struct TlsOptions {
+    /// Name the server certificate must be valid for. It is also sent to the
+    /// server using SNI.
+    pub server_name: String,
+
+    /// Path of a PEM file holding the certificate authorities to trust. When
+    /// `None`, the certificate authorities of the operating system are
+    /// trusted.
+    pub ca_file: Option<PathBuf>,
+}
+
+impl TlsOptions {
+    /// Create options connecting to the server named `server_name`, trusting
+    /// the certificate authorities of the operating system.
+    pub fn new(server_name: impl ToString) -> TlsOptions {
+        TlsOptions {
+            server_name: server_name.to_string(),
+            ca_file: None,
+        }
+    }
+}
+
+/// Build the acceptor performing the server side of the TLS handshake.
+pub(crate) fn acceptor(config: &TlsConfig) -> crate::Result<TlsAcceptor> {
+    let certs = load_certs(&config.cert_file)?;
+    let key = load_key(&config.key_file)?;
+
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Perform the client side of the TLS handshake over `socket`.
+pub(crate) async fn connect(
+    socket: TcpStream,
+    options: &TlsOptions,
+) -> crate::Result<TlsStream<TcpStream>> {
+    let mut roots = RootCertStore::empty();
+
+    match &options.ca_file {
+        Some(path) => {
+            for cert in load_certs(path)? {
+                roots.add(&cert)?;
+            }
+        }
+        None => {
+            for cert in rustls_native_certs::load_native_certs()? {
+                // Some systems ship certificates rustls cannot parse. They are
+                // skipped rather than failing every connection.
+                let _ = roots.add(&Certificate(cert.0));
+            }
+        }
+    }
+
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let server_name = ServerName::try_from(&options.server_name[..])
+        .map_err(|_| format!("invalid server name `{}`", options.server_name))?;
+
+    let connector = TlsConnector::from(Arc::new(config));
+    Ok(connector.connect(server_name, socket).await?)
+}
+
+/// Read the certificates stored in the PEM file at `path`.
+fn load_certs(path: &Path) -> crate::Result<Vec<Certificate>> {
+    let mut src = BufReader::new(File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut src)?;
+
+    if certs.is_empty() {
+        return Err(format!("no certificate found in `{}`", path.display()).into());
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// Read the first private key stored in the PEM file at `path`.
+fn load_key(path: &Path) -> crate::Result<PrivateKey> {
+    let mut src = BufReader::new(File::open(path)?);
+
+    for item in rustls_pemfile::read_all(&mut src)? {
+        match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+
+    Err(format!("no private key found in `{}`", path.display()).into())
+}
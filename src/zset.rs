@@ -0,0 +1,174 @@
+//! Sorted set value type.
+//!
+//! A sorted set maps members to scores and keeps its members ordered by
+//! score, then by member for equal scores. Three indexes are maintained: a
+//! `HashMap` to look up the score of a member, a `BTreeSet` of
+//! `(score, member)` pairs to iterate members in order, and a `ScanOrder` to
+//! page through the members in the iteration order of `ZSCAN`.
+//!
+//! Ranges by rank walk the ordered index, so they are linear in the offset.
+//! That is good enough for the workloads mini-redis targets.
+
+use crate::scan::ScanOrder;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SortedSet {
+    /// Score of each member.
+    scores: HashMap<String, f64>,
+
+    /// Members ordered by score.
+    ordered: BTreeSet<(Score, String)>,
+
+    /// Members in `ZSCAN` order.
+    scan_order: ScanOrder,
+}
+
+/// A score, ordered using `f64::total_cmp`.
+///
+/// Scores are never NaN, so this orders scores numerically, except for `-0.0`
+/// which is placed right before `0.0`.
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl SortedSet {
+    /// Returns the number of members.
+    pub(crate) fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Returns `true` if the set has no members.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Returns the score of `member`.
+    pub(crate) fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Set the score of `member`, adding it if needed. Returns `true` if the
+    /// member was added.
+    ///
+    /// `score` must not be NaN.
+    pub(crate) fn insert(&mut self, member: String, score: f64) -> bool {
+        debug_assert!(!score.is_nan());
+
+        let prev = self.scores.insert(member.clone(), score);
+
+        match prev {
+            Some(prev) => {
+                self.ordered.remove(&(Score(prev), member.clone()));
+            }
+            None => self.scan_order.insert(member.clone()),
+        }
+
+        self.ordered.insert((Score(score), member));
+        prev.is_none()
+    }
+
+    /// Remove `member`. Returns `true` if it was a member.
+    pub(crate) fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.ordered.remove(&(Score(score), member.to_string()));
+                self.scan_order.remove(member);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns an iterator over the members and their scores, in order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.ordered
+            .iter()
+            .map(|(score, member)| (member.as_str(), score.0))
+    }
+
+    /// Returns a page of the members along with their score, and the cursor
+    /// of the next page. See `ScanOrder::page`.
+    pub(crate) fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(String, f64)>) {
+        let (cursor, members) = self.scan_order.page(cursor, count);
+
+        let page = members
+            .into_iter()
+            .map(|member| (member.to_string(), self.scores[member]))
+            .collect();
+
+        (cursor, page)
+    }
+
+    /// Returns the members whose rank is between `start` and `stop`, both
+    /// inclusive, along with their scores. Negative ranks count from the
+    /// highest score.
+    pub(crate) fn range(&self, start: i64, stop: i64) -> Vec<(String, f64)> {
+        let len = self.len() as i64;
+
+        // Resolve negative ranks, then clamp to the bounds of the set.
+        let start = if start < 0 { len + start } else { start }.max(0);
+        let stop = if stop < 0 { len + stop } else { stop }.min(len - 1);
+
+        if start > stop {
+            return vec![];
+        }
+
+        self.ordered
+            .iter()
+            .skip(start as usize)
+            .take((stop - start + 1) as usize)
+            .map(|(score, member)| (member.clone(), score.0))
+            .collect()
+    }
+
+    /// Returns the members whose score is between `min` and `max`, in order,
+    /// along with their scores. The first `offset` matching members are
+    /// skipped and at most `count` members are returned.
+    pub(crate) fn range_by_score(
+        &self,
+        min: Bound<f64>,
+        max: Bound<f64>,
+        offset: usize,
+        count: usize,
+    ) -> Vec<(String, f64)> {
+        self.ordered
+            .iter()
+            .skip_while(|(score, _)| match min {
+                Bound::Included(min) => score.0 < min,
+                Bound::Excluded(min) => score.0 <= min,
+                Bound::Unbounded => false,
+            })
+            .take_while(|(score, _)| match max {
+                Bound::Included(max) => score.0 <= max,
+                Bound::Excluded(max) => score.0 < max,
+                Bound::Unbounded => true,
+            })
+            .skip(offset)
+            .take(count)
+            .map(|(score, member)| (member.clone(), score.0))
+            .collect()
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Score) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
@@ -0,0 +1,54 @@
+use mini_redis::server;
+use std::net::SocketAddr;
+use std::process::Output;
+use tokio::net::TcpListener;
+use tokio::process::Command;
+
+/// The command line arguments are accepted, and the usage is printed.
+#[tokio::test]
+async fn help() {
+    let output = cli(&["--help"]).await;
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--hotkeys"));
+}
+
+/// Commands are issued to the server and their response is printed.
+#[tokio::test]
+async fn set_get_and_hotkeys() {
+    let addr = start_server().await;
+    let port = addr.port().to_string();
+
+    let output = cli(&["--port", &port, "set", "hello", "world"]).await;
+    assert!(output.status.success());
+    assert_eq!("OK\n", String::from_utf8_lossy(&output.stdout));
+
+    let output = cli(&["--port", &port, "get", "hello"]).await;
+    assert!(output.status.success());
+    assert_eq!("\"world\"\n", String::from_utf8_lossy(&output.stdout));
+
+    let output = cli(&["--port", &port, "--hotkeys"]).await;
+    assert!(output.status.success());
+
+    // A command cannot be issued along with `--hotkeys`.
+    let output = cli(&["--port", &port, "--hotkeys", "get", "hello"]).await;
+    assert!(!output.status.success());
+}
+
+/// Run the CLI with `args` until it exits.
+async fn cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mini-redis-cli"))
+        .args(args)
+        .output()
+        .await
+        .unwrap()
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    addr
+}
@@ -1,7 +1,12 @@
-use mini_redis::{client, server};
+// The tests predate the `clone_on_copy` lint and are kept as written.
+#![allow(clippy::clone_on_copy)]
+
+use mini_redis::{client, server, Frame, ServerConfig, ShutdownController, TlsConfig, TlsOptions};
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
+use tokio::time;
 
 /// A PING PONG test without message provided.
 /// It should return "PONG".
@@ -88,6 +93,27 @@ async fn receive_message_multiple_subscribed_channels() {
     assert_eq!(b"howdy?", &message2.content[..])
 }
 
+/// A subscriber that negotiated RESP3 receives messages as push frames.
+#[tokio::test]
+async fn receive_message_resp3() {
+    let (addr, _) = start_server().await;
+
+    let mut client = client::connect(addr).await.unwrap();
+    let info = client.hello(3).await.unwrap();
+    assert!(matches!(info, Frame::Map(_)));
+
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    tokio::spawn(async move {
+        let mut client = client::connect(addr).await.unwrap();
+        client.publish("hello", "world".into()).await.unwrap()
+    });
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"world", &message.content[..])
+}
+
 /// test that a client accurately removes its own subscribed chanel list
 /// when unsubscribing to all subscribed channels by submitting an empty vec
 #[tokio::test]
@@ -104,6 +130,377 @@ async fn unsubscribes_from_channels() {
     assert_eq!(subscriber.get_subscribed().len(), 0);
 }
 
+/// A pattern subscription receives the messages of every matching channel,
+/// along with the pattern they matched.
+#[tokio::test]
+async fn receive_message_subscribed_pattern() {
+    let (addr, _) = start_server().await;
+
+    let client = client::connect(addr).await.unwrap();
+    let mut subscriber = client.psubscribe(vec!["news.*".into()]).await.unwrap();
+    subscriber.subscribe(&["weather".into()]).await.unwrap();
+
+    let mut publisher = client::connect(addr).await.unwrap();
+    publisher.publish("sports", "ignored".into()).await.unwrap();
+    publisher.publish("news.tech", "rust".into()).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("news.tech", &message.channel);
+    assert_eq!(Some("news.*"), message.pattern.as_deref());
+    assert_eq!(b"rust", &message.content[..]);
+
+    publisher.publish("weather", "sunny".into()).await.unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("weather", &message.channel);
+    assert_eq!(None, message.pattern);
+
+    subscriber.punsubscribe(&[]).await.unwrap();
+    assert!(subscriber.get_subscribed_patterns().is_empty());
+    assert_eq!(["weather"], subscriber.get_subscribed());
+}
+
+/// Keys accessed more often are reported first by `HOTKEYS`.
+#[tokio::test]
+async fn hotkeys_reports_most_accessed_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("cold", "value".into()).await.unwrap();
+    client.set("hot", "value".into()).await.unwrap();
+    for _ in 0..10 {
+        client.get("hot").await.unwrap();
+    }
+
+    let hotkeys = client.hotkeys(10).await.unwrap();
+    let keys: Vec<_> = hotkeys.iter().map(|(key, _)| &key[..]).collect();
+    assert_eq!(vec!["hot", "cold"], keys);
+    assert!(hotkeys[0].1 > hotkeys[1].1);
+
+    let hotkeys = client.hotkeys(1).await.unwrap();
+    assert_eq!(1, hotkeys.len());
+}
+
+/// Keys set in one logical database are not visible from another one.
+#[tokio::test]
+async fn select_isolates_databases() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+
+    client.select(1).await.unwrap();
+    assert!(client.get("hello").await.unwrap().is_none());
+
+    client.select(0).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+
+    assert!(client.select(16).await.is_err());
+}
+
+/// Pipelined commands are sent at once and their replies returned in order.
+#[tokio::test]
+async fn pipeline() {
+    let (addr, _) = start_server().await;
+    let mut client = client::connect(addr).await.unwrap();
+
+    let mut pipeline = client.pipeline();
+    for i in 0..100 {
+        pipeline.set(&format!("key:{}", i), i.to_string().into());
+    }
+    pipeline
+        .get("key:42")
+        .get("missing")
+        .command(Frame::Array(vec![
+            Frame::Bulk("INCR".into()),
+            Frame::Bulk("key:42".into()),
+        ]))
+        .command(Frame::Array(vec![Frame::Bulk("FOO".into())]));
+    assert_eq!(104, pipeline.len());
+
+    let replies = pipeline.execute().await.unwrap();
+    assert_eq!(104, replies.len());
+    assert!(replies[..100]
+        .iter()
+        .all(|reply| *reply == Frame::Simple("OK".to_string())));
+    assert_eq!(Frame::Bulk("42".into()), replies[100]);
+    assert_eq!(Frame::Null, replies[101]);
+    assert_eq!(Frame::Integer(43), replies[102]);
+    assert!(matches!(replies[103], Frame::Error(_)));
+
+    // The pipeline is emptied once executed.
+    assert!(pipeline.is_empty());
+
+    // The client is still usable afterwards.
+    let value = client.get("key:99").await.unwrap().unwrap();
+    assert_eq!(b"99", &value[..]);
+}
+
+/// Connections of a pool are reused, limited to the size of the pool, and
+/// re-established once broken.
+#[tokio::test]
+async fn pool_reuses_and_reconnects() {
+    let (addr, _) = start_server().await;
+    let pool = client::Pool::new(addr, 2);
+
+    let mut first = pool.get().await.unwrap();
+    let mut second = pool.get().await.unwrap();
+    let first_id = client_id(&mut first).await;
+    assert_ne!(first_id, client_id(&mut second).await);
+
+    // Both connections are checked out, so a third task has to wait.
+    let waiting = pool.clone();
+    let mut third = tokio::spawn(async move { client_id(&mut waiting.get().await.unwrap()).await });
+    assert!(time::timeout(Duration::from_millis(50), &mut third)
+        .await
+        .is_err());
+
+    // The returned connection is handed over to the waiting task.
+    drop(first);
+    assert_eq!(first_id, third.await.unwrap());
+
+    // The idle connection is killed, so it is replaced by a new one.
+    let second_id = client_id(&mut second).await;
+    drop(second);
+    let mut killer = client::connect(addr).await.unwrap();
+    for id in &[first_id, second_id] {
+        killer
+            .pipeline()
+            .command(command(&["CLIENT", "KILL", "ID", &id.to_string()]))
+            .execute()
+            .await
+            .unwrap();
+    }
+
+    let mut client = pool.get().await.unwrap();
+    assert!(client_id(&mut client).await > second_id);
+
+    client.set("hello", "world".into()).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+}
+
+/// Connections whose state was changed, or whose request was cancelled before
+/// its reply was read, are closed rather than returned to the pool.
+#[tokio::test]
+async fn pool_discards_modified_connections() {
+    let (addr, _) = start_server().await;
+    let pool = client::Pool::new(addr, 1);
+
+    let mut client = pool.get().await.unwrap();
+    let first_id = client_id(&mut client).await;
+    drop(client);
+
+    // Connections only used to issue commands are reused.
+    let mut client = pool.get().await.unwrap();
+    assert_eq!(first_id, client_id(&mut client).await);
+    client.select(1).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    drop(client);
+
+    // The next task uses the default database of a new connection.
+    let mut client = pool.get().await.unwrap();
+    let second_id = client_id(&mut client).await;
+    assert_ne!(first_id, second_id);
+    assert!(client.get("hello").await.unwrap().is_none());
+
+    // The reply to the cancelled BLPOP would be read by the next command.
+    let mut pipeline = client.pipeline();
+    pipeline.command(command(&["BLPOP", "list", "0"]));
+    assert!(time::timeout(Duration::from_millis(50), pipeline.execute())
+        .await
+        .is_err());
+    drop(client);
+
+    let mut client = pool.get().await.unwrap();
+    assert_ne!(second_id, client_id(&mut client).await);
+
+    // Starting a transaction in a pipeline changes the state as well.
+    let third_id = client_id(&mut client).await;
+    client
+        .pipeline()
+        .command(command(&["MULTI"]))
+        .execute()
+        .await
+        .unwrap();
+    drop(client);
+
+    let mut client = pool.get().await.unwrap();
+    assert_ne!(third_id, client_id(&mut client).await);
+}
+
+/// The connections of a pool are established according to its options, and
+/// authenticated once established.
+#[cfg(unix)]
+#[tokio::test]
+async fn pool_options() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-pool.sock", std::process::id()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = ServerConfig {
+        requirepass: Some("secret".to_string()),
+        unixsocket: Some(path.clone()),
+        ..ServerConfig::default()
+    };
+    let controller = ShutdownController::new();
+    let server = tokio::spawn(server::run_with_config(
+        listener,
+        config,
+        controller.clone(),
+    ));
+
+    // The password is not shown when the options are logged.
+    let options = client::PoolOptions {
+        unixsocket: Some(path.clone()),
+        password: Some("secret".to_string()),
+        ..client::PoolOptions::default()
+    };
+    assert!(!format!("{:?}", options).contains("secret"));
+
+    // The socket is bound by the server task. Retry until it is ready.
+    let pool = client::Pool::with_options(addr, 1, options);
+    let mut client = loop {
+        match pool.get().await {
+            Ok(client) => break client,
+            Err(_) => time::sleep(Duration::from_millis(10)).await,
+        }
+    };
+    client.set("hello", "world".into()).await.unwrap();
+    let id = client_id(&mut client).await;
+    drop(client);
+
+    // Authenticating does not prevent the connection from being reused.
+    let mut client = pool.get().await.unwrap();
+    assert_eq!(id, client_id(&mut client).await);
+    drop(client);
+
+    // A wrong password fails without retrying.
+    let options = client::PoolOptions {
+        password: Some("wrong".to_string()),
+        ..client::PoolOptions::default()
+    };
+    let pool = client::Pool::with_options(addr, 1, options);
+    assert!(pool.get().await.is_err());
+
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+}
+
+/// Commands are exchanged over TLS when the server is given a certificate. The
+/// client verifies it against the certificate authority it is told to trust.
+#[tokio::test]
+async fn tls_connection() {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+    let dir = std::env::temp_dir();
+    let cert_file = dir.join(format!("mini-redis-{}-cert.pem", std::process::id()));
+    let key_file = dir.join(format!("mini-redis-{}-key.pem", std::process::id()));
+    std::fs::write(&cert_file, cert.serialize_pem().unwrap()).unwrap();
+    std::fs::write(&key_file, cert.serialize_private_key_pem()).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = ServerConfig {
+        tls: Some(TlsConfig {
+            cert_file: cert_file.clone(),
+            key_file: key_file.clone(),
+        }),
+        ..ServerConfig::default()
+    };
+    let controller = ShutdownController::new();
+    tokio::spawn(server::run_with_config(
+        listener,
+        config,
+        controller.clone(),
+    ));
+
+    let mut options = TlsOptions::new("localhost");
+    options.ca_file = Some(cert_file.clone());
+
+    let mut client = client::connect_tls(addr, options).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+
+    // The certificate is not valid for another name.
+    let mut options = TlsOptions::new("example.com");
+    options.ca_file = Some(cert_file.clone());
+    assert!(client::connect_tls(addr, options).await.is_err());
+
+    controller.shutdown().await;
+    std::fs::remove_file(&cert_file).unwrap();
+    std::fs::remove_file(&key_file).unwrap();
+}
+
+/// The server accepts connections on a unix domain socket alongside the TCP
+/// listener. Both share the same data.
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_connection() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}.sock", std::process::id()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let config = ServerConfig {
+        unixsocket: Some(path.clone()),
+        ..ServerConfig::default()
+    };
+    let controller = ShutdownController::new();
+    let server = tokio::spawn(server::run_with_config(
+        listener,
+        config,
+        controller.clone(),
+    ));
+
+    // The socket is bound by the server task. Retry until it is ready.
+    let mut client = loop {
+        match client::connect_unix(&path).await {
+            Ok(client) => break client,
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+        }
+    };
+    client.set("hello", "world".into()).await.unwrap();
+
+    let mut tcp = client::connect(addr).await.unwrap();
+    let value = tcp.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    // The socket file is removed on shutdown.
+    assert!(!path.exists());
+}
+
+/// Returns the identifier of the connection of `client` on the server.
+async fn client_id(client: &mut client::Client) -> i64 {
+    let mut replies = client
+        .pipeline()
+        .command(command(&["CLIENT", "ID"]))
+        .execute()
+        .await
+        .unwrap();
+
+    match replies.pop() {
+        Some(Frame::Integer(id)) => id,
+        reply => panic!("expected integer frame, got {:?}", reply),
+    }
+}
+
+/// Returns the frame of a command, an array of bulk strings.
+fn command(args: &[&str]) -> Frame {
+    Frame::Array(
+        args.iter()
+            .map(|arg| Frame::Bulk(arg.to_string().into()))
+            .collect(),
+    )
+}
+
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
@@ -1,8 +1,14 @@
-use mini_redis::server;
+use mini_redis::{
+    server, AppendFsync, Command, Connection, Frame, MaxMemoryPolicy, ServerConfig,
+    ShutdownController,
+};
+
+use bytes::Bytes;
 
 use std::net::SocketAddr;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
 use tokio::time::{self, Duration};
 
 /// A basic "hello world" style test. A server instance is started in a
@@ -119,282 +125,3129 @@ async fn key_value_timeout() {
     assert_eq!(b"$-1\r\n", &response);
 }
 
+/// EXPIRE sets a timeout on an existing key, TTL reports it and PERSIST
+/// removes it again.
+///
+/// Time is not paused here. With paused time, the runtime automatically
+/// advances the clock to the purge task's deadline while waiting on the
+/// socket, which would expire the key between commands.
 #[tokio::test]
-async fn pub_sub() {
+async fn expire_ttl_and_persist() {
     let addr = start_server().await;
 
-    let mut publisher = TcpStream::connect(addr).await.unwrap();
+    let mut stream = TcpStream::connect(addr).await.unwrap();
 
-    // Publish a message, there are no subscribers yet so the server will
-    // return `0`.
-    publisher
-        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+    // Setting a timeout on a missing key does nothing
+    stream
+        .write_all(b"*3\r\n$6\r\nEXPIRE\r\n$5\r\nhello\r\n$2\r\n10\r\n")
         .await
         .unwrap();
-
     let mut response = [0; 4];
-    publisher.read_exact(&mut response).await.unwrap();
+    stream.read_exact(&mut response).await.unwrap();
     assert_eq!(b":0\r\n", &response);
 
-    // Create a subscriber. This subscriber will only subscribe to the `hello`
-    // channel.
-    let mut sub1 = TcpStream::connect(addr).await.unwrap();
-    sub1.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$5\r\nhello\r\n")
         .await
         .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":-2\r\n", &response);
 
-    // Read the subscribe response
-    let mut response = [0; 34];
-    sub1.read_exact(&mut response).await.unwrap();
-    assert_eq!(
-        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
-        &response[..]
-    );
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
 
-    // Publish a message, there now is a subscriber
-    publisher
-        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+    // The key has no timeout yet
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$5\r\nhello\r\n")
         .await
         .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":-1\r\n", &response);
 
+    stream
+        .write_all(b"*3\r\n$6\r\nEXPIRE\r\n$5\r\nhello\r\n$2\r\n10\r\n")
+        .await
+        .unwrap();
     let mut response = [0; 4];
-    publisher.read_exact(&mut response).await.unwrap();
+    stream.read_exact(&mut response).await.unwrap();
     assert_eq!(b":1\r\n", &response);
 
-    // The first subscriber received the message
-    let mut response = [0; 39];
-    sub1.read_exact(&mut response).await.unwrap();
-    assert_eq!(
-        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nworld\r\n"[..],
-        &response[..]
-    );
+    // The remaining time is rounded to the nearest second
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":10\r\n", &response);
 
-    // Create a second subscriber
-    //
-    // This subscriber will be subscribed to both `hello` and `foo`
-    let mut sub2 = TcpStream::connect(addr).await.unwrap();
-    sub2.write_all(b"*3\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n$3\r\nfoo\r\n")
+    // Shorten the timeout
+    stream
+        .write_all(b"*3\r\n$7\r\nPEXPIRE\r\n$5\r\nhello\r\n$2\r\n50\r\n")
         .await
         .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
 
-    // Read the subscribe response
-    let mut response = [0; 34];
-    sub2.read_exact(&mut response).await.unwrap();
-    assert_eq!(
-        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
-        &response[..]
-    );
-    let mut response = [0; 32];
-    sub2.read_exact(&mut response).await.unwrap();
-    assert_eq!(
-        &b"*3\r\n$9\r\nsubscribe\r\n$3\r\nfoo\r\n:2\r\n"[..],
-        &response[..]
-    );
+    // Remove the timeout
+    stream
+        .write_all(b"*2\r\n$7\r\nPERSIST\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
 
-    // Publish another message on `hello`, there are two subscribers
-    publisher
-        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\njazzy\r\n")
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$5\r\nhello\r\n")
         .await
         .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":-1\r\n", &response);
 
-    let mut response = [0; 4];
-    publisher.read_exact(&mut response).await.unwrap();
-    assert_eq!(b":2\r\n", &response);
+    // The key does not expire anymore
+    time::sleep(Duration::from_millis(100)).await;
 
-    // Publish a message on `foo`, there is only one subscriber
-    publisher
-        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
         .await
         .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
 
+    // A negative timeout deletes the key right away
+    stream
+        .write_all(b"*3\r\n$7\r\nPEXPIRE\r\n$5\r\nhello\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
     let mut response = [0; 4];
-    publisher.read_exact(&mut response).await.unwrap();
+    stream.read_exact(&mut response).await.unwrap();
     assert_eq!(b":1\r\n", &response);
 
-    // The first subscriber received the message
-    let mut response = [0; 39];
-    sub1.read_exact(&mut response).await.unwrap();
-    assert_eq!(
-        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\njazzy\r\n"[..],
-        &response[..]
-    );
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+/// Expirations too far in the future to be represented are rejected, and
+/// leave the key as it was.
+#[tokio::test]
+async fn expire_time_out_of_range() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    request(&mut connection, &["SET", "key", "value"]).await;
+
+    for (args, name) in [
+        (&["EXPIRE", "key", "9223372036854775807"][..], "expire"),
+        (&["EXPIRE", "key", "9223372036854775"], "expire"),
+        (&["PEXPIRE", "key", "9223372036854775807"], "pexpire"),
+        (&["EXPIREAT", "key", "9223372036854775807"], "expireat"),
+        (&["SET", "key", "other", "EX", "9223372036854775807"], "set"),
+        (
+            &["SET", "key", "other", "PX", "18446744073709551615"],
+            "set",
+        ),
+        (
+            &["SET", "key", "other", "EXAT", "18446744073709551615"],
+            "set",
+        ),
+        (
+            &["SET", "key", "other", "PXAT", "18446744073709551615"],
+            "set",
+        ),
+    ] {
+        assert_eq!(
+            Frame::Error(format!("ERR invalid expire time in '{}'", name)),
+            request(&mut connection, args).await
+        );
+    }
 
-    // The second subscriber received the message
-    let mut response = [0; 39];
-    sub2.read_exact(&mut response).await.unwrap();
     assert_eq!(
-        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\njazzy\r\n"[..],
-        &response[..]
+        Frame::Bulk(Bytes::from("value")),
+        request(&mut connection, &["GET", "key"]).await
     );
-
-    // The first subscriber **did not** receive the second message
-    let mut response = [0; 1];
-    time::timeout(Duration::from_millis(100), sub1.read(&mut response))
-        .await
-        .unwrap_err();
-
-    // The second subscriber **did** receive the message
-    let mut response = [0; 35];
-    sub2.read_exact(&mut response).await.unwrap();
     assert_eq!(
-        &b"*3\r\n$7\r\nmessage\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"[..],
-        &response[..]
+        Frame::Integer(-1),
+        request(&mut connection, &["TTL", "key"]).await
     );
 }
 
+/// Multi-key commands operate on every key they are given.
 #[tokio::test]
-async fn manage_subscription() {
+async fn multi_key_commands() {
     let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
 
-    let mut publisher = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut connection, &["MSET", "a", "1", "b", "2", "c", "3"]).await
+    );
+    request(&mut connection, &["HSET", "hash", "field", "value"]).await;
 
-    // Create a subscriber
-    let mut sub = TcpStream::connect(addr).await.unwrap();
-    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
-        .await
-        .unwrap();
+    // Missing keys and values that are not strings are returned as nil.
+    assert_eq!(
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from("1")),
+            Frame::Null,
+            Frame::Bulk(Bytes::from("3")),
+            Frame::Null,
+        ]),
+        request(&mut connection, &["MGET", "a", "missing", "c", "hash"]).await
+    );
 
-    // Read the subscribe response
-    let mut response = [0; 34];
-    sub.read_exact(&mut response).await.unwrap();
+    // Keys given several times are counted several times.
     assert_eq!(
-        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
-        &response[..]
+        Frame::Integer(3),
+        request(&mut connection, &["EXISTS", "a", "a", "missing", "hash"]).await
     );
 
-    // Update subscription to add `foo`
-    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$3\r\nfoo\r\n")
-        .await
-        .unwrap();
+    assert_eq!(
+        Frame::Integer(2),
+        request(&mut connection, &["DEL", "a", "missing", "hash"]).await
+    );
+    assert_eq!(
+        Frame::Integer(1),
+        request(&mut connection, &["UNLINK", "a", "b"]).await
+    );
+    assert_eq!(
+        Frame::Integer(1),
+        request(&mut connection, &["EXISTS", "a", "b", "c"]).await
+    );
 
-    let mut response = [0; 32];
-    sub.read_exact(&mut response).await.unwrap();
+    // MSET discards the time to live.
+    request(&mut connection, &["SET", "c", "value", "EX", "100"]).await;
+    request(&mut connection, &["MSET", "c", "4"]).await;
     assert_eq!(
-        &b"*3\r\n$9\r\nsubscribe\r\n$3\r\nfoo\r\n:2\r\n"[..],
-        &response[..]
+        Frame::Integer(-1),
+        request(&mut connection, &["TTL", "c"]).await
     );
+}
 
-    // Update subscription to remove `hello`
-    sub.write_all(b"*2\r\n$11\r\nUNSUBSCRIBE\r\n$5\r\nhello\r\n")
-        .await
-        .unwrap();
+/// `SET` options control whether the key is written, what its expiration is
+/// and whether the previous value is returned.
+#[tokio::test]
+async fn set_options() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
 
-    let mut response = [0; 37];
-    sub.read_exact(&mut response).await.unwrap();
+    let ok = Frame::Simple("OK".to_string());
+
+    // NX only sets missing keys, XX only existing ones.
     assert_eq!(
-        &b"*3\r\n$11\r\nunsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
-        &response[..]
+        Frame::Null,
+        request(&mut connection, &["SET", "hello", "world", "XX"]).await
     );
-
-    // Publish a message to `hello` and then a message to `foo`
-    publisher
-        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+    assert_eq!(
+        ok,
+        request(&mut connection, &["SET", "hello", "world", "NX"]).await
+    );
+    assert_eq!(
+        Frame::Null,
+        request(&mut connection, &["SET", "hello", "there", "NX"]).await
+    );
+    assert_eq!(
+        ok,
+        request(
+            &mut connection,
+            &["SET", "hello", "there", "XX", "EX", "100"]
+        )
         .await
-        .unwrap();
-    let mut response = [0; 4];
-    publisher.read_exact(&mut response).await.unwrap();
-    assert_eq!(b":0\r\n", &response);
+    );
 
-    publisher
-        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+    // KEEPTTL retains the expiration, GET returns the previous value.
+    assert_eq!(
+        Frame::Bulk(Bytes::from("there")),
+        request(
+            &mut connection,
+            &["SET", "hello", "again", "KEEPTTL", "GET"]
+        )
         .await
-        .unwrap();
-    let mut response = [0; 4];
-    publisher.read_exact(&mut response).await.unwrap();
-    assert_eq!(b":1\r\n", &response);
+    );
+    match request(&mut connection, &["TTL", "hello"]).await {
+        Frame::Integer(ttl) => assert!(ttl > 0),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
 
-    // Receive the message
-    // The second subscriber **did** receive the message
-    let mut response = [0; 35];
-    sub.read_exact(&mut response).await.unwrap();
+    // A plain SET discards it.
+    request(&mut connection, &["SET", "hello", "world"]).await;
     assert_eq!(
-        &b"*3\r\n$7\r\nmessage\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"[..],
-        &response[..]
+        Frame::Integer(-1),
+        request(&mut connection, &["TTL", "hello"]).await
     );
 
-    // No more messages
-    let mut response = [0; 1];
-    time::timeout(Duration::from_millis(100), sub.read(&mut response))
-        .await
-        .unwrap_err();
+    // GET is answered even if the condition prevents the write.
+    assert_eq!(
+        Frame::Bulk(Bytes::from("world")),
+        request(&mut connection, &["SET", "hello", "ignored", "NX", "GET"]).await
+    );
+    assert_eq!(
+        Frame::Null,
+        request(&mut connection, &["SET", "missing", "value", "GET"]).await
+    );
 
-    // Unsubscribe from all channels
-    sub.write_all(b"*1\r\n$11\r\nunsubscribe\r\n")
-        .await
-        .unwrap();
+    // Absolute expirations in the past expire the key right away.
+    assert_eq!(
+        ok,
+        request(&mut connection, &["SET", "old", "value", "PXAT", "1"]).await
+    );
+    assert_eq!(Frame::Null, request(&mut connection, &["GET", "old"]).await);
 
-    let mut response = [0; 35];
-    sub.read_exact(&mut response).await.unwrap();
+    request(&mut connection, &["HSET", "hash", "field", "value"]).await;
+    let response = request(&mut connection, &["SET", "hash", "value", "GET"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+
+    // Conflicting options are a syntax error. The connection stays open.
     assert_eq!(
-        &b"*3\r\n$11\r\nunsubscribe\r\n$3\r\nfoo\r\n:0\r\n"[..],
-        &response[..]
+        Frame::Error("ERR syntax error".to_string()),
+        request(
+            &mut connection,
+            &["SET", "hello", "world", "EX", "1", "KEEPTTL"]
+        )
+        .await
+    );
+    assert_eq!(
+        Frame::Error("ERR wrong number of arguments for 'set' command".to_string()),
+        request(&mut connection, &["SET", "hello"]).await
+    );
+    assert_eq!(
+        ok,
+        request(&mut connection, &["SET", "hello", "world"]).await
     );
 }
 
-// In this case we test that server Responds with an Error message if a client
-// sends an unknown command
+/// Counters are incremented atomically, keep their expiration and reject
+/// values that are not numbers.
 #[tokio::test]
-async fn send_error_unknown_command() {
+async fn numeric_commands() {
     let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
 
-    // Establish a connection to the server
-    let mut stream = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(
+        Frame::Integer(1),
+        request(&mut connection, &["INCR", "counter"]).await
+    );
+    assert_eq!(
+        Frame::Integer(11),
+        request(&mut connection, &["INCRBY", "counter", "10"]).await
+    );
+    assert_eq!(
+        Frame::Integer(10),
+        request(&mut connection, &["DECR", "counter"]).await
+    );
+    assert_eq!(
+        Frame::Integer(-5),
+        request(&mut connection, &["DECRBY", "counter", "15"]).await
+    );
 
-    // Get a key, data is missing
-    stream
-        .write_all(b"*2\r\n$3\r\nFOO\r\n$5\r\nhello\r\n")
+    request(&mut connection, &["SET", "float", "10.5", "EX", "100"]).await;
+    assert_eq!(
+        Frame::Bulk(Bytes::from("10.75")),
+        request(&mut connection, &["INCRBYFLOAT", "float", "0.25"]).await
+    );
+    assert_eq!(
+        Frame::Bulk(Bytes::from("5")),
+        request(&mut connection, &["INCRBYFLOAT", "float", "-5.75"]).await
+    );
+
+    // The expiration is kept.
+    match request(&mut connection, &["TTL", "float"]).await {
+        Frame::Integer(ttl) => assert!(ttl > 0),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    request(&mut connection, &["SET", "max", &i64::MAX.to_string()]).await;
+    assert_eq!(
+        Frame::Error("ERR increment or decrement would overflow".to_string()),
+        request(&mut connection, &["INCR", "max"]).await
+    );
+
+    request(&mut connection, &["SET", "name", "bob"]).await;
+    assert_eq!(
+        Frame::Error("ERR value is not an integer or out of range".to_string()),
+        request(&mut connection, &["INCR", "name"]).await
+    );
+    assert_eq!(
+        Frame::Error("ERR value is not a valid float".to_string()),
+        request(&mut connection, &["INCRBYFLOAT", "name", "1"]).await
+    );
+
+    request(&mut connection, &["HSET", "hash", "field", "1"]).await;
+    let response = request(&mut connection, &["INCR", "hash"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+}
+
+#[tokio::test]
+async fn hash_commands() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Create a hash with two fields
+    stream
+        .write_all(
+            b"*6\r\n$4\r\nHSET\r\n$4\r\nuser\r\n$4\r\nname\r\n$3\r\nbob\r\n\
+              $6\r\nvisits\r\n$1\r\n1\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$4\r\nHGET\r\n$4\r\nuser\r\n$4\r\nname\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\nbob\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$7\r\nHINCRBY\r\n$4\r\nuser\r\n$6\r\nvisits\r\n$2\r\n41\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":42\r\n", &response);
+
+    // A field holding a string cannot be incremented
+    stream
+        .write_all(b"*4\r\n$7\r\nHINCRBY\r\n$4\r\nuser\r\n$4\r\nname\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 46];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-ERR value is not an integer or out of range\r\n"[..],
+        &response[..]
+    );
+
+    stream
+        .write_all(b"*3\r\n$4\r\nHDEL\r\n$4\r\nuser\r\n$4\r\nname\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$7\r\nHEXISTS\r\n$4\r\nuser\r\n$4\r\nname\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nHLEN\r\n$4\r\nuser\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$7\r\nHGETALL\r\n$4\r\nuser\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 24];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$6\r\nvisits\r\n$2\r\n42\r\n", &response);
+
+    // String commands cannot be applied to a hash
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$4\r\nuser\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 68];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"[..],
+        &response[..]
+    );
+
+    // Removing the last field removes the key
+    stream
+        .write_all(b"*3\r\n$4\r\nHDEL\r\n$4\r\nuser\r\n$6\r\nvisits\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$4\r\nuser\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+#[tokio::test]
+async fn list_commands() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // LPUSH inserts values one after the other, so `b` ends up first
+    stream
+        .write_all(b"*4\r\n$5\r\nLPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\nc\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":3\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$6\r\nLRANGE\r\n$4\r\nlist\r\n$1\r\n0\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 25];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*3\r\n$1\r\nb\r\n$1\r\na\r\n$1\r\nc\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nRPOP\r\n$4\r\nlist\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$1\r\nc\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$4\r\nLPOP\r\n$4\r\nlist\r\n$2\r\n10\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 18];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$1\r\nb\r\n$1\r\na\r\n", &response);
+
+    // Popping the last element removes the key
+    stream
+        .write_all(b"*2\r\n$4\r\nLLEN\r\n$4\r\nlist\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nLPOP\r\n$4\r\nlist\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+#[tokio::test]
+async fn blpop_waits_for_push() {
+    let addr = start_server().await;
+
+    let mut blocked = TcpStream::connect(addr).await.unwrap();
+    let mut pusher = TcpStream::connect(addr).await.unwrap();
+
+    // Nothing is pushed before the timeout elapses
+    blocked
+        .write_all(b"*3\r\n$5\r\nBLPOP\r\n$4\r\nlist\r\n$4\r\n0.05\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    blocked.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    // Block indefinitely on two lists
+    blocked
+        .write_all(b"*4\r\n$5\r\nBLPOP\r\n$5\r\nother\r\n$4\r\nlist\r\n$1\r\n0\r\n")
+        .await
+        .unwrap();
+
+    // Make sure the client is blocked before pushing
+    time::sleep(Duration::from_millis(50)).await;
+
+    pusher
+        .write_all(b"*3\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    pusher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    let mut response = [0; 25];
+    blocked.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$4\r\nlist\r\n$5\r\nhello\r\n", &response);
+
+    // The element was handed to the blocked client
+    pusher
+        .write_all(b"*2\r\n$4\r\nLLEN\r\n$4\r\nlist\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    pusher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+}
+
+/// A timeout too large to be represented is rejected instead of blocking.
+#[tokio::test]
+async fn blpop_timeout_out_of_range() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(
+        Frame::Error("ERR timeout is out of range".to_string()),
+        request(&mut connection, &["BLPOP", "list", "1e300"]).await
+    );
+    assert_eq!(
+        Frame::Simple("PONG".to_string()),
+        request(&mut connection, &["PING"]).await
+    );
+}
+
+#[tokio::test]
+async fn sorted_set_commands() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(
+            b"*8\r\n$4\r\nZADD\r\n$5\r\nboard\r\n$1\r\n3\r\n$5\r\ncarol\r\n\
+              $1\r\n1\r\n$5\r\nalice\r\n$1\r\n2\r\n$3\r\nbob\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":3\r\n", &response);
+
+    // Move alice to the top of the board
+    stream
+        .write_all(b"*4\r\n$7\r\nZINCRBY\r\n$5\r\nboard\r\n$3\r\n2.5\r\n$5\r\nalice\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\n3.5\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nZSCORE\r\n$5\r\nboard\r\n$3\r\nbob\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$1\r\n2\r\n", &response);
+
+    stream
+        .write_all(
+            b"*5\r\n$6\r\nZRANGE\r\n$5\r\nboard\r\n$1\r\n0\r\n$2\r\n-1\r\n\
+              $10\r\nWITHSCORES\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = [0; 58];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*6\r\n$3\r\nbob\r\n$1\r\n2\r\n$5\r\ncarol\r\n$1\r\n3\r\n$5\r\nalice\r\n$3\r\n3.5\r\n"[..],
+        &response[..]
+    );
+
+    // Scores strictly above 2
+    stream
+        .write_all(b"*4\r\n$13\r\nZRANGEBYSCORE\r\n$5\r\nboard\r\n$2\r\n(2\r\n$4\r\n+inf\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 26];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$5\r\ncarol\r\n$5\r\nalice\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$4\r\nZREM\r\n$5\r\nboard\r\n$3\r\nbob\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$5\r\nZCARD\r\n$5\r\nboard\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+}
+
+/// Entries are added to streams and read back, and `XREAD BLOCK` waits for
+/// new entries.
+#[tokio::test]
+async fn stream_commands() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut reader = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(
+        Frame::Bulk("1-1".into()),
+        request(&mut connection, &["XADD", "events", "1-1", "kind", "login"]).await
+    );
+    assert_eq!(
+        Frame::Bulk("1-2".into()),
+        request(
+            &mut connection,
+            &["XADD", "events", "1-*", "kind", "logout"]
+        )
+        .await
+    );
+
+    // Identifiers only ever grow.
+    assert_eq!(
+        Frame::Error(
+            "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+                .to_string()
+        ),
+        request(&mut connection, &["XADD", "events", "1-2", "kind", "login"]).await
+    );
+
+    // Generated identifiers are based on the current time.
+    match request(&mut connection, &["XADD", "events", "*", "kind", "login"]).await {
+        Frame::Bulk(id) => {
+            let id = std::str::from_utf8(&id).unwrap();
+            let ms: u64 = id.split('-').next().unwrap().parse().unwrap();
+            assert!(ms > 1, "{}", id);
+        }
+        frame => panic!("expected bulk frame, got {:?}", frame),
+    }
+
+    assert_eq!(
+        Frame::Integer(3),
+        request(&mut connection, &["XLEN", "events"]).await
+    );
+
+    let first_two = Frame::Array(vec![
+        Frame::Array(vec![
+            Frame::Bulk("1-1".into()),
+            bulk_array(&["kind", "login"]),
+        ]),
+        Frame::Array(vec![
+            Frame::Bulk("1-2".into()),
+            bulk_array(&["kind", "logout"]),
+        ]),
+    ]);
+
+    assert_eq!(
+        first_two,
+        request(
+            &mut connection,
+            &["XRANGE", "events", "-", "+", "COUNT", "2"]
+        )
+        .await
+    );
+
+    // Without a sequence number, the end bound includes every entry of the
+    // millisecond.
+    assert_eq!(
+        first_two,
+        request(&mut connection, &["XRANGE", "events", "1", "1"]).await
+    );
+
+    assert_eq!(
+        Frame::Array(vec![Frame::Array(vec![
+            Frame::Bulk("events".into()),
+            Frame::Array(vec![Frame::Array(vec![
+                Frame::Bulk("1-2".into()),
+                bulk_array(&["kind", "logout"]),
+            ])]),
+        ])]),
+        request(
+            &mut connection,
+            &["XREAD", "COUNT", "1", "STREAMS", "events", "1-1"]
+        )
+        .await
+    );
+
+    // Nothing is added before the timeout elapses.
+    assert_eq!(
+        Frame::Null,
+        request(
+            &mut reader,
+            &["XREAD", "BLOCK", "50", "STREAMS", "events", "$"]
+        )
+        .await
+    );
+
+    // Block indefinitely on two streams.
+    reader
+        .write_frame(&bulk_array(&[
+            "XREAD", "BLOCK", "0", "STREAMS", "events", "audit", "$", "$",
+        ]))
+        .await
+        .unwrap();
+
+    // Make sure the client is blocked before adding.
+    time::sleep(Duration::from_millis(50)).await;
+
+    request(&mut connection, &["XADD", "audit", "5-0", "user", "alice"]).await;
+
+    assert_eq!(
+        Frame::Array(vec![Frame::Array(vec![
+            Frame::Bulk("audit".into()),
+            Frame::Array(vec![Frame::Array(vec![
+                Frame::Bulk("5-0".into()),
+                bulk_array(&["user", "alice"]),
+            ])]),
+        ])]),
+        reader.read_frame().await.unwrap().unwrap()
+    );
+
+    // A timeout too large to be represented blocks indefinitely too.
+    reader
+        .write_frame(&bulk_array(&[
+            "XREAD",
+            "BLOCK",
+            "18446744073709551615",
+            "STREAMS",
+            "audit",
+            "$",
+        ]))
+        .await
+        .unwrap();
+
+    time::sleep(Duration::from_millis(50)).await;
+
+    request(&mut connection, &["XADD", "audit", "6-0", "user", "bob"]).await;
+
+    assert_eq!(
+        Frame::Array(vec![Frame::Array(vec![
+            Frame::Bulk("audit".into()),
+            Frame::Array(vec![Frame::Array(vec![
+                Frame::Bulk("6-0".into()),
+                bulk_array(&["user", "bob"]),
+            ])]),
+        ])]),
+        reader.read_frame().await.unwrap().unwrap()
+    );
+
+    request(&mut connection, &["SET", "plain", "value"]).await;
+    assert_eq!(
+        Frame::Error(
+            "WRONGTYPE Operation against a key holding the wrong kind of value".to_string()
+        ),
+        request(&mut connection, &["XADD", "plain", "*", "kind", "login"]).await
+    );
+}
+
+#[tokio::test]
+async fn multi_exec() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Commands are queued, not executed
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+QUEUED\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+QUEUED\r\n", &response);
+
+    // All replies are sent at once
+    stream.write_all(b"*1\r\n$4\r\nEXEC\r\n").await.unwrap();
+    let mut response = [0; 20];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n+OK\r\n$5\r\nworld\r\n", &response);
+
+    stream.write_all(b"*1\r\n$4\r\nEXEC\r\n").await.unwrap();
+    let mut response = [0; 25];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-ERR EXEC without MULTI\r\n", &response);
+
+    // Discarded commands are not executed
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$3\r\nbye\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+QUEUED\r\n", &response);
+
+    stream.write_all(b"*1\r\n$7\r\nDISCARD\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+}
+
+#[tokio::test]
+async fn watch_aborts_exec_on_concurrent_write() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut other = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$5\r\nWATCH\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Another connection modifies the watched key
+    other
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    other.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream.write_all(b"*1\r\n$5\r\nMULTI\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$3\r\nbye\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+QUEUED\r\n", &response);
+
+    // The transaction is not executed
+    stream.write_all(b"*1\r\n$4\r\nEXEC\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+}
+
+/// Commands leaving the watched keys as they were do not abort `EXEC`.
+#[tokio::test]
+async fn watch_ignores_commands_changing_nothing() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut other = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    request(&mut connection, &["HSET", "hash", "field", "value"]).await;
+    request(&mut connection, &["RPUSH", "list", "a"]).await;
+    request(&mut connection, &["WATCH", "hash", "list", "missing"]).await;
+
+    assert_eq!(
+        Frame::Integer(0),
+        request(&mut other, &["HDEL", "hash", "other"]).await
+    );
+    request(&mut other, &["LPOP", "list", "0"]).await;
+    assert_eq!(Frame::Null, request(&mut other, &["LPOP", "missing"]).await);
+
+    request(&mut connection, &["MULTI"]).await;
+    request(&mut connection, &["PING"]).await;
+    assert_eq!(
+        Frame::Array(vec![Frame::Simple("PONG".to_string())]),
+        request(&mut connection, &["EXEC"]).await
+    );
+}
+
+/// Writes are logged to the append only file and replayed when the server
+/// restarts. `BGREWRITEAOF` compacts the file without losing data.
+#[tokio::test]
+async fn append_only_file() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}.aof", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let config = ServerConfig {
+        appendonly: true,
+        appendfilename: path.clone(),
+        appendfsync: AppendFsync::Always,
+        ..ServerConfig::default()
+    };
+
+    let (addr, controller, server) = start_server_with_config(config.clone()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Under `always`, writes are on disk once acknowledged
+    let data = std::fs::read(&path).unwrap();
+    assert!(data.ends_with(b"$5\r\nhello\r\n$5\r\nworld\r\n"));
+
+    stream
+        .write_all(b"*4\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nLPOP\r\n$4\r\nlist\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$1\r\na\r\n", &response);
+
+    // Writes to another database, with an expiration
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*5\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n$2\r\nEX\r\n$3\r\n100\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    drop(stream);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    // The data is back after a restart
+    let (addr, controller, server) = start_server_with_config(config.clone()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$6\r\nLRANGE\r\n$4\r\nlist\r\n$1\r\n0\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*1\r\n$1\r\nb\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 6];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":100\r\n", &response);
+
+    // Compact the file, then keep writing on top of it
+    stream
+        .write_all(b"*1\r\n$12\r\nBGREWRITEAOF\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 48];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"+Background append only file rewriting started\r\n"[..],
+        &response[..]
+    );
+
+    // The rewritten file rebuilds the list without popping
+    while std::fs::read(&path)
+        .unwrap()
+        .windows(4)
+        .any(|w| w == b"LPOP")
+    {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nbaz\r\n$3\r\nqux\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    drop(stream);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    let (addr, controller, server) = start_server_with_config(config).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*4\r\n$6\r\nLRANGE\r\n$4\r\nlist\r\n$1\r\n0\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*1\r\n$1\r\nb\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nbaz\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\nqux\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\nbar\r\n", &response);
+
+    drop(stream);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Writes of concurrent connections are appended in batches. None is lost or
+/// written twice.
+#[tokio::test]
+async fn append_only_file_group_commit() {
+    let path = std::env::temp_dir().join(format!("mini-redis-group-{}.aof", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let config = ServerConfig {
+        appendonly: true,
+        appendfilename: path.clone(),
+        appendfsync: AppendFsync::Always,
+        ..ServerConfig::default()
+    };
+
+    let (addr, controller, server) = start_server_with_config(config.clone()).await;
+
+    let clients: Vec<_> = (0..8)
+        .map(|client| {
+            tokio::spawn(async move {
+                let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+                for _ in 0..25 {
+                    request(&mut connection, &["INCR", &format!("counter{}", client)]).await;
+                }
+            })
+        })
+        .collect();
+
+    for client in clients {
+        client.await.unwrap();
+    }
+
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    let (addr, controller, server) = start_server_with_config(config).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for client in 0..8 {
+        assert_eq!(
+            Frame::Bulk("25".into()),
+            request(&mut connection, &["GET", &format!("counter{}", client)]).await
+        );
+    }
+
+    drop(connection);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// A snapshot is saved in the background once a save point is reached.
+#[tokio::test]
+async fn save_points() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}-save.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let config = ServerConfig {
+        dbfilename: path.clone(),
+        save: "1 2".parse().unwrap(),
+        ..ServerConfig::default()
+    };
+
+    let (addr, controller, server) = start_server_with_config(config.clone()).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // A single write is not enough.
+    request(&mut connection, &["SET", "hello", "world"]).await;
+    time::sleep(Duration::from_millis(1500)).await;
+    assert!(!path.exists());
+
+    request(&mut connection, &["SET", "foo", "bar"]).await;
+    for _ in 0..50 {
+        if path.exists() {
+            break;
+        }
+        time::sleep(Duration::from_millis(100)).await;
+    }
+
+    drop(connection);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    // The snapshot is loaded on restart.
+    let (addr, controller, server) = start_server_with_config(config).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    assert_eq!(
+        Frame::Bulk("bar".into()),
+        request(&mut connection, &["GET", "foo"]).await
+    );
+
+    drop(connection);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// `SAVE` and `BGSAVE` write a snapshot of every database, which is loaded
+/// when the server restarts.
+#[tokio::test]
+async fn snapshot() {
+    let path = std::env::temp_dir().join(format!("mini-redis-{}.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let config = ServerConfig {
+        dbfilename: path.clone(),
+        ..ServerConfig::default()
+    };
+
+    let (addr, controller, server) = start_server_with_config(config.clone()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$4\r\nHSET\r\n$4\r\nhash\r\n$5\r\nfield\r\n$5\r\nvalue\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$4\r\nZADD\r\n$4\r\nzset\r\n$3\r\n1.5\r\n$6\r\nmember\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*5\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n$2\r\nEX\r\n$3\r\n100\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream.write_all(b"*1\r\n$4\r\nSAVE\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    drop(stream);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    // The snapshot is loaded on restart
+    let (addr, controller, server) = start_server_with_config(config.clone()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$4\r\nHGET\r\n$4\r\nhash\r\n$5\r\nfield\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nvalue\r\n", &response);
+
+    stream
+        .write_all(b"*4\r\n$6\r\nLRANGE\r\n$4\r\nlist\r\n$1\r\n0\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 18];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$1\r\na\r\n$1\r\nb\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nZSCORE\r\n$4\r\nzset\r\n$6\r\nmember\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\n1.5\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nTTL\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 6];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":100\r\n", &response);
+
+    // Save again in the background
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nbaz\r\n$3\r\nqux\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    std::fs::remove_file(&path).unwrap();
+
+    stream.write_all(b"*1\r\n$6\r\nBGSAVE\r\n").await.unwrap();
+    let mut response = [0; 28];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+Background saving started\r\n", &response);
+
+    // The snapshot is renamed into place once complete
+    while !path.exists() {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    drop(stream);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    let (addr, controller, server) = start_server_with_config(config).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nbaz\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\nqux\r\n", &response);
+
+    drop(stream);
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn replication() {
+    let primary_addr = start_server().await;
+    let replica_addr = start_server().await;
+
+    let mut primary = TcpStream::connect(primary_addr).await.unwrap();
+    let mut replica = TcpStream::connect(replica_addr).await.unwrap();
+
+    // Written before the replica connects, sent with the full resync
+    primary
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    primary.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    let port = primary_addr.port().to_string();
+    let cmd = format!(
+        "*3\r\n$9\r\nREPLICAOF\r\n$9\r\n127.0.0.1\r\n${}\r\n{}\r\n",
+        port.len(),
+        port
+    );
+    replica.write_all(cmd.as_bytes()).await.unwrap();
+    let mut response = [0; 5];
+    replica.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    wait_for_key(
+        &mut replica,
+        b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n",
+        b"$5\r\nworld\r\n",
+    )
+    .await;
+
+    // Written once the replica is in sync, propagated as it happens
+    primary
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    primary.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    wait_for_key(
+        &mut replica,
+        b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n",
+        b"$3\r\nbar\r\n",
+    )
+    .await;
+
+    // Replicas are read only
+    replica
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbaz\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 56];
+    replica.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-READONLY You can't write against a read only replica.\r\n"[..],
+        &response[..]
+    );
+
+    // `HELLO` reports the role of the server
+    assert_eq!("replica", hello_role(replica_addr).await);
+    assert_eq!("master", hello_role(primary_addr).await);
+
+    // Like other errors, this fails the transaction
+    replica
+        .write_all(b"MULTI\r\nSET foo baz\r\nEXEC\r\n")
+        .await
+        .unwrap();
+    let expected = &b"+OK\r\n\
+        -READONLY You can't write against a read only replica.\r\n\
+        -EXECABORT Transaction discarded because of previous errors.\r\n"[..];
+    let mut response = vec![0; expected.len()];
+    replica.read_exact(&mut response).await.unwrap();
+    assert_eq!(expected, &response[..]);
+
+    replica
+        .write_all(b"*3\r\n$9\r\nREPLICAOF\r\n$2\r\nNO\r\n$3\r\nONE\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    replica.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Writable again, and the replicated data is kept
+    replica
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbaz\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    replica.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    replica
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    replica.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+}
+
+/// A replica that stops reading falls behind the replication backlog. Its
+/// connection is closed once it reads again, rather than the primary
+/// buffering every write meanwhile.
+#[tokio::test]
+async fn replication_backlog() {
+    let addr = start_server().await;
+
+    let mut replica = TcpStream::connect(addr).await.unwrap();
+    replica
+        .write_all(b"*3\r\n$5\r\nPSYNC\r\n$1\r\n?\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+
+    // Far more writes than the backlog and the socket buffers hold.
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let value = "x".repeat(1024);
+    let set = bulk_array(&["SET", "key", &value]);
+
+    for _ in 0..40 {
+        for _ in 0..1000 {
+            connection.buffer_frame(&set);
+        }
+        connection.flush().await.unwrap();
+
+        for _ in 0..1000 {
+            let reply = connection.read_frame().await.unwrap().unwrap();
+            assert_eq!(Frame::Simple("OK".to_string()), reply);
+        }
+    }
+
+    let mut received = vec![];
+    time::timeout(Duration::from_secs(10), replica.read_to_end(&mut received))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(received.starts_with(b"+FULLRESYNC"));
+}
+
+#[tokio::test]
+async fn auth_and_acl() {
+    let config = ServerConfig {
+        requirepass: Some("secret".to_string()),
+        ..ServerConfig::default()
+    };
+
+    let (addr, _, _) = start_server_with_config(config).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Commands are refused until the connection authenticates
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 34];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(&b"-NOAUTH Authentication required.\r\n"[..], &response[..]);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nAUTH\r\n$5\r\nwrong\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 64];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-WRONGPASS invalid username-password pair or user is disabled.\r\n"[..],
+        &response[..]
+    );
+
+    stream
+        .write_all(b"*2\r\n$4\r\nAUTH\r\n$6\r\nsecret\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Create a user that may only read
+    stream
+        .write_all(b"*6\r\n$3\r\nACL\r\n$7\r\nSETUSER\r\n$5\r\nalice\r\n$2\r\non\r\n$4\r\n>pwd\r\n$4\r\n+get\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nACL\r\n$4\r\nLIST\r\n")
+        .await
+        .unwrap();
+    // Only the SHA-256 hashes of the passwords are shown
+    let mut response = [0; 207];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        concat!(
+            "*2\r\n$96\r\nuser alice on ",
+            "#a1159e9df3670d549d04524532629f5477ceb7deec9b45e47e8c009506ecb2c8",
+            " ~* &* -@all +get\r\n$93\r\nuser default on ",
+            "#2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b",
+            " ~* &* +@all\r\n"
+        )
+        .as_bytes(),
+        &response[..]
+    );
+
+    stream
+        .write_all(b"*3\r\n$4\r\nAUTH\r\n$5\r\nalice\r\n$3\r\npwd\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 64];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-NOPERM User alice has no permissions to run the 'set' command\r\n"[..],
+        &response[..]
+    );
+
+    // Passwords may be given by their SHA-256 hash
+    let mut connection = Connection::new(stream);
+    let ok = Frame::Simple("OK".to_string());
+    let wrongpass =
+        Frame::Error("WRONGPASS invalid username-password pair or user is disabled.".to_string());
+
+    assert_eq!(
+        ok,
+        request(&mut connection, &["AUTH", "default", "secret"]).await
+    );
+    assert_eq!(
+        ok,
+        request(
+            &mut connection,
+            &[
+                "ACL",
+                "SETUSER",
+                "alice",
+                "!A1159E9DF3670D549D04524532629F5477CEB7DEEC9B45E47E8C009506ECB2C8",
+                "#11507a0e2f5e69d5dfa40a62a1bd7b6ee57e6bcd85c67c9b8431b36fff21c437",
+            ]
+        )
+        .await
+    );
+    assert_eq!(
+        wrongpass,
+        request(&mut connection, &["AUTH", "alice", "pwd"]).await
+    );
+    assert_eq!(
+        ok,
+        request(&mut connection, &["AUTH", "alice", "new"]).await
+    );
+
+    // Aliases and variants of a command have their own permission
+    assert_eq!(
+        ok,
+        request(&mut connection, &["AUTH", "default", "secret"]).await
+    );
+    assert_eq!(
+        ok,
+        request(
+            &mut connection,
+            &["ACL", "SETUSER", "alice", "+expire", "+ttl"]
+        )
+        .await
+    );
+    assert_eq!(
+        ok,
+        request(&mut connection, &["AUTH", "alice", "new"]).await
+    );
+    assert_eq!(
+        Frame::Integer(0),
+        request(&mut connection, &["EXPIRE", "hello", "10"]).await
+    );
+    assert_eq!(
+        Frame::Error(
+            "NOPERM User alice has no permissions to run the 'pexpire' command".to_string()
+        ),
+        request(&mut connection, &["PEXPIRE", "hello", "10"]).await
+    );
+    assert_eq!(
+        Frame::Integer(-2),
+        request(&mut connection, &["TTL", "hello"]).await
+    );
+    assert_eq!(
+        Frame::Error("NOPERM User alice has no permissions to run the 'pttl' command".to_string()),
+        request(&mut connection, &["PTTL", "hello"]).await
+    );
+
+    // `HELLO` may authenticate the connection
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    match request(&mut connection, &["HELLO", "3"]).await {
+        Frame::Error(err) => assert!(err.starts_with("NOAUTH "), "{}", err),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+    assert_eq!(
+        wrongpass,
+        request(&mut connection, &["HELLO", "3", "AUTH", "alice", "pwd"]).await
+    );
+    assert!(matches!(
+        request(&mut connection, &["HELLO", "3", "AUTH", "alice", "new"]).await,
+        Frame::Map(_)
+    ));
+    assert_eq!(
+        Frame::Integer(-2),
+        request(&mut connection, &["TTL", "hello"]).await
+    );
+}
+
+#[test]
+fn debug_redacts_passwords() {
+    let commands = [
+        &["AUTH", "alice", "pwd"][..],
+        &["ACL", "SETUSER", "alice", "on", ">pwd", "<pwd", "+get"][..],
+        &["HELLO", "3", "AUTH", "alice", "pwd"][..],
+    ];
+
+    for args in commands {
+        let command = Command::from_frame(bulk_array(args)).unwrap();
+        let debug = format!("{:?}", command);
+        assert!(debug.contains("alice"), "{}", debug);
+        assert!(!debug.contains("pwd"), "{}", debug);
+    }
+}
+
+#[tokio::test]
+async fn pub_sub() {
+    let addr = start_server().await;
+
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+    // Publish a message, there are no subscribers yet so the server will
+    // return `0`.
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    // Create a subscriber. This subscriber will only subscribe to the `hello`
+    // channel.
+    let mut sub1 = TcpStream::connect(addr).await.unwrap();
+    sub1.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    // Read the subscribe response
+    let mut response = [0; 34];
+    sub1.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    // Publish a message, there now is a subscriber
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    // The first subscriber received the message
+    let mut response = [0; 39];
+    sub1.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\nworld\r\n"[..],
+        &response[..]
+    );
+
+    // Create a second subscriber
+    //
+    // This subscriber will be subscribed to both `hello` and `foo`
+    let mut sub2 = TcpStream::connect(addr).await.unwrap();
+    sub2.write_all(b"*3\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    // Read the subscribe response
+    let mut response = [0; 34];
+    sub2.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+    let mut response = [0; 32];
+    sub2.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$3\r\nfoo\r\n:2\r\n"[..],
+        &response[..]
+    );
+
+    // Publish another message on `hello`, there are two subscribers
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\njazzy\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    // Publish a message on `foo`, there is only one subscriber
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    // The first subscriber received the message
+    let mut response = [0; 39];
+    sub1.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\njazzy\r\n"[..],
+        &response[..]
+    );
+
+    // The second subscriber received the message
+    let mut response = [0; 39];
+    sub2.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$7\r\nmessage\r\n$5\r\nhello\r\n$5\r\njazzy\r\n"[..],
+        &response[..]
+    );
+
+    // The first subscriber **did not** receive the second message
+    let mut response = [0; 1];
+    time::timeout(Duration::from_millis(100), sub1.read(&mut response))
+        .await
+        .unwrap_err();
+
+    // The second subscriber **did** receive the message
+    let mut response = [0; 35];
+    sub2.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$7\r\nmessage\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"[..],
+        &response[..]
+    );
+}
+
+#[tokio::test]
+async fn manage_subscription() {
+    let addr = start_server().await;
+
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+    // Create a subscriber
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    // Read the subscribe response
+    let mut response = [0; 34];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    // Update subscription to add `foo`
+    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 32];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$3\r\nfoo\r\n:2\r\n"[..],
+        &response[..]
+    );
+
+    // Update subscription to remove `hello`
+    sub.write_all(b"*2\r\n$11\r\nUNSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 37];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$11\r\nunsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    // Publish a message to `hello` and then a message to `foo`
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    // Receive the message
+    // The second subscriber **did** receive the message
+    let mut response = [0; 35];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$7\r\nmessage\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"[..],
+        &response[..]
+    );
+
+    // No more messages
+    let mut response = [0; 1];
+    time::timeout(Duration::from_millis(100), sub.read(&mut response))
+        .await
+        .unwrap_err();
+
+    // Unsubscribe from all channels
+    sub.write_all(b"*1\r\n$11\r\nunsubscribe\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 35];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$11\r\nunsubscribe\r\n$3\r\nfoo\r\n:0\r\n"[..],
+        &response[..]
+    );
+}
+
+// In this case we test that server Responds with an Error message if a client
+// sends an unknown command
+#[tokio::test]
+async fn send_error_unknown_command() {
+    let addr = start_server().await;
+
+    // Establish a connection to the server
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Get a key, data is missing
+    stream
+        .write_all(b"*2\r\n$3\r\nFOO\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 28];
+
+    stream.read_exact(&mut response).await.unwrap();
+
+    assert_eq!(b"-ERR unknown command \'foo\'\r\n", &response);
+}
+
+/// Commands may be sent inline, as a line of arguments, which is what typing
+/// in a telnet session does. Malformed requests are answered with an error
+/// before the connection is closed.
+#[tokio::test]
+async fn inline_commands_and_protocol_errors() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Blank lines are skipped, quoted arguments may hold spaces and escapes,
+    // and inline commands mix with RESP arrays.
+    stream
+        .write_all(b"PING\r\n\r\nSET key \"hello\\x21 world\"\n*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 31];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"+PONG\r\n+OK\r\n$12\r\nhello! world\r\n"[..],
+        &response[..]
+    );
+
+    stream.write_all(b"GET 'it\\'s'\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    // Unbalanced quotes are a protocol error.
+    stream.write_all(b"SET key \"value\r\n").await.unwrap();
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    assert_eq!(
+        &b"-ERR Protocol error: unbalanced quotes in request\r\n"[..],
+        &response[..]
+    );
+
+    // So are invalid RESP frames.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1\r\n$x\r\n").await.unwrap();
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response).await.unwrap();
+    assert!(response.starts_with(b"-ERR Protocol error: "));
+
+    // Commands that fail to parse are answered, and the replies to the
+    // commands pipelined after them still line up.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"MULTI\r\nGET\r\nGET key extra\r\nSET key value\r\nEXEC\r\nPING\r\n")
+        .await
+        .unwrap();
+
+    let expected = &b"+OK\r\n\
+        -ERR wrong number of arguments for 'get' command\r\n\
+        -ERR wrong number of arguments for 'get' command\r\n\
+        +QUEUED\r\n\
+        -EXECABORT Transaction discarded because of previous errors.\r\n\
+        +PONG\r\n"[..];
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(expected, &response[..]);
+}
+
+// In this case we test that server Responds with an Error message if a client
+// sends an GET or SET command after a SUBSCRIBE
+#[tokio::test]
+async fn send_error_get_set_after_subscribe() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // send SUBSCRIBE command
+    stream
+        .write_all(b"*2\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 34];
+
+    stream.read_exact(&mut response).await.unwrap();
+
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 28];
+
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-ERR unknown command \'set\'\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 28];
+
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-ERR unknown command \'get\'\r\n", &response);
+}
+
+/// A server without any database refuses to start, as `SELECT 0` must be
+/// valid.
+#[tokio::test]
+async fn no_databases() {
+    let config = ServerConfig {
+        databases: 0,
+        ..ServerConfig::default()
+    };
+
+    let (_, _, server) = start_server_with_config(config).await;
+    assert!(server.await.unwrap().is_err());
+}
+
+/// SWAPDB exchanges the contents of two databases and FLUSHDB only clears the
+/// selected one.
+#[tokio::test]
+async fn swapdb_and_flushdb() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Set a key in database 0, then swap it into database 1
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nSWAPDB\r\n$1\r\n0\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // The key is gone from database 0
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    // Flushing database 0 does not affect database 1
+    stream.write_all(b"*1\r\n$7\r\nFLUSHDB\r\n").await.unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nworld\r\n", &response);
+
+    // Selecting a database out of range is an error
+    stream
+        .write_all(b"*2\r\n$6\r\nSELECT\r\n$2\r\n16\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 31];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-ERR DB index is out of range\r\n", &response);
+}
+
+/// After negotiating RESP3 with HELLO, replies use the RESP3 encoding.
+/// Iterating with `SCAN` returns every key exactly once, however small the
+/// pages are.
+#[tokio::test]
+async fn scan_commands() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for i in 0..25 {
+        let key = format!("key:{}", i);
+        assert_eq!(
+            Frame::Simple("OK".to_string()),
+            request(&mut connection, &["SET", &key, "value"]).await
+        );
+    }
+
+    assert_eq!(
+        Frame::Integer(2),
+        request(&mut connection, &["HSET", "hash", "a", "1", "b", "2"]).await
+    );
+
+    let mut keys = scan_all(&mut connection, &["SCAN", "{}", "COUNT", "4"]).await;
+    keys.sort();
+
+    let mut expected: Vec<_> = (0..25).map(|i| format!("key:{}", i)).collect();
+    expected.push("hash".to_string());
+    expected.sort();
+    assert_eq!(expected, keys);
+
+    let mut keys = scan_all(&mut connection, &["SCAN", "{}", "MATCH", "key:1*"]).await;
+    keys.sort();
+    let mut expected: Vec<_> = (10..20).map(|i| format!("key:{}", i)).collect();
+    expected.insert(0, "key:1".to_string());
+    assert_eq!(expected, keys);
+
+    let keys = scan_all(&mut connection, &["SCAN", "{}", "TYPE", "hash"]).await;
+    assert_eq!(vec!["hash"], keys);
+
+    let mut fields = scan_all(&mut connection, &["HSCAN", "hash", "{}", "COUNT", "1"]).await;
+    fields.sort();
+    assert_eq!(vec!["1", "2", "a", "b"], fields);
+
+    // Removed fields and members are no longer returned.
+    assert_eq!(
+        Frame::Integer(1),
+        request(&mut connection, &["HDEL", "hash", "a"]).await
+    );
+    let fields = scan_all(&mut connection, &["HSCAN", "hash", "{}"]).await;
+    assert_eq!(vec!["b", "2"], fields);
+
+    assert_eq!(
+        Frame::Integer(3),
+        request(
+            &mut connection,
+            &["ZADD", "zset", "1", "a", "2", "b", "3", "c"]
+        )
         .await
-        .unwrap();
+    );
+    assert_eq!(
+        Frame::Integer(1),
+        request(&mut connection, &["ZREM", "zset", "b"]).await
+    );
+    let mut members = scan_all(&mut connection, &["ZSCAN", "zset", "{}", "COUNT", "1"]).await;
+    members.sort();
+    assert_eq!(vec!["1", "3", "a", "c"], members);
 
-    let mut response = [0; 28];
+    // A key of another type is an error.
+    let response = request(&mut connection, &["HSCAN", "key:1", "0"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+}
 
-    stream.read_exact(&mut response).await.unwrap();
+/// `CONFIG GET` matches parameters with glob-style patterns, and `CONFIG SET`
+/// changes them at runtime, all at once or not at all.
+#[tokio::test]
+async fn config_get_and_set() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
 
-    assert_eq!(b"-ERR unknown command \'foo\'\r\n", &response);
+    assert_eq!(
+        bulk_array(&[
+            "appendonly",
+            "no",
+            "appendfilename",
+            "appendonly.aof",
+            "appendfsync",
+            "everysec",
+        ]),
+        request(&mut connection, &["CONFIG", "GET", "APPEND*"]).await
+    );
+    assert_eq!(
+        bulk_array(&["databases", "16", "dbfilename", "dump.rdb"]),
+        request(&mut connection, &["CONFIG", "GET", "d*", "databases"]).await
+    );
+
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(
+            &mut connection,
+            &[
+                "CONFIG",
+                "SET",
+                "appendfsync",
+                "always",
+                "dbfilename",
+                "x.rdb"
+            ]
+        )
+        .await
+    );
+    assert_eq!(
+        bulk_array(&["appendfsync", "always"]),
+        request(&mut connection, &["CONFIG", "GET", "appendfsync"]).await
+    );
+
+    // Nothing is changed if one of the parameters cannot be set.
+    for args in [
+        &["CONFIG", "SET", "appendfsync", "no", "databases", "4"][..],
+        &[
+            "CONFIG",
+            "SET",
+            "appendfsync",
+            "no",
+            "appendfsync",
+            "sometimes",
+        ][..],
+        &["CONFIG", "SET", "appendfsync", "no", "unknown", "value"][..],
+    ] {
+        let response = request(&mut connection, args).await;
+        assert!(matches!(response, Frame::Error(err) if err.starts_with("ERR ")));
+    }
+    assert_eq!(
+        bulk_array(&["appendfsync", "always"]),
+        request(&mut connection, &["CONFIG", "GET", "appendfsync"]).await
+    );
+
+    // The port is the one of the listener, and cannot be changed.
+    let port = addr.port().to_string();
+    assert_eq!(
+        bulk_array(&["port", &port]),
+        request(&mut connection, &["CONFIG", "GET", "port"]).await
+    );
+    let response = request(&mut connection, &["CONFIG", "SET", "port", "1234"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.contains("immutable")));
+
+    // Save points are pairs of numbers.
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut connection, &["CONFIG", "SET", "save", "900 1  300 10"]).await
+    );
+    assert_eq!(
+        bulk_array(&["save", "900 1 300 10"]),
+        request(&mut connection, &["CONFIG", "GET", "save"]).await
+    );
+    let response = request(&mut connection, &["CONFIG", "SET", "save", "900"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut connection, &["CONFIG", "SET", "save", ""]).await
+    );
+
+    // A password is required from new connections once set.
+    request(&mut connection, &["CONFIG", "SET", "requirepass", "secret"]).await;
+
+    let mut other = Connection::new(TcpStream::connect(addr).await.unwrap());
+    assert_eq!(
+        Frame::Error("NOAUTH Authentication required.".to_string()),
+        request(&mut other, &["GET", "foo"]).await
+    );
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut other, &["AUTH", "secret"]).await
+    );
 }
 
-// In this case we test that server Responds with an Error message if a client
-// sends an GET or SET command after a SUBSCRIBE
+/// Once `maxmemory` is reached, keys are evicted as directed by the policy, or
+/// writes are refused.
 #[tokio::test]
-async fn send_error_get_set_after_subscribe() {
+async fn maxmemory_eviction() {
+    let config = ServerConfig {
+        maxmemory: 2000,
+        maxmemory_policy: MaxMemoryPolicy::AllKeysLru,
+        ..ServerConfig::default()
+    };
+
+    let (addr, _controller, _server) = start_server_with_config(config).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let value = "x".repeat(100);
+    for i in 0..50 {
+        let key = format!("key:{}", i);
+        assert_eq!(
+            Frame::Simple("OK".to_string()),
+            request(&mut connection, &["SET", &key, &value]).await
+        );
+    }
+
+    // Keys are evicted before a write, so the last one is kept.
+    let keys = scan_all(&mut connection, &["SCAN", "{}"]).await;
+    assert!(!keys.is_empty() && keys.len() < 50);
+    assert!(keys.contains(&"key:49".to_string()));
+
+    // Only keys with an expiration may be evicted.
+    request(
+        &mut connection,
+        &[
+            "CONFIG",
+            "SET",
+            "maxmemory-policy",
+            "volatile-ttl",
+            "maxmemory",
+            "1kb",
+        ],
+    )
+    .await;
+    assert_eq!(
+        Frame::Error("OOM command not allowed when used memory > 'maxmemory'.".to_string()),
+        request(&mut connection, &["SET", "key:50", &value]).await
+    );
+
+    // Commands freeing memory are still allowed.
+    assert_eq!(
+        Frame::Integer(1),
+        request(&mut connection, &["DEL", "key:49"]).await
+    );
+    assert_eq!(
+        bulk_array(&["maxmemory", "1024", "maxmemory-policy", "volatile-ttl"]),
+        request(&mut connection, &["CONFIG", "GET", "maxmemory*"]).await
+    );
+}
+
+/// `INFO` reports the requested sections, with counters updated as commands
+/// run.
+#[tokio::test]
+async fn info() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    request(&mut connection, &["SET", "foo", "bar"]).await;
+    request(&mut connection, &["SET", "baz", "qux", "EX", "100"]).await;
+    request(&mut connection, &["GET", "foo"]).await;
+    request(&mut connection, &["GET", "missing"]).await;
+
+    let info = match request(&mut connection, &["INFO"]).await {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("expected bulk frame, got {:?}", frame),
+    };
+
+    for header in &[
+        "# Server",
+        "# Clients",
+        "# Memory",
+        "# Persistence",
+        "# Stats",
+        "# Replication",
+        "# Keyspace",
+        "# Hotkeys",
+    ] {
+        assert!(info.contains(header), "missing {} in {:?}", header, info);
+    }
+
+    for line in &[
+        "connected_clients:1\r\n",
+        "total_connections_received:1\r\n",
+        "total_commands_processed:4\r\n",
+        "keyspace_hits:1\r\n",
+        "keyspace_misses:1\r\n",
+        "role:master\r\n",
+        "aof_last_write_status:ok\r\n",
+        "db0:keys=2,expires=1\r\n",
+    ] {
+        assert!(info.contains(line), "missing {:?} in {:?}", line, info);
+    }
+
+    // Only the requested sections are returned.
+    let info = match request(&mut connection, &["INFO", "CLIENTS"]).await {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("expected bulk frame, got {:?}", frame),
+    };
+    assert_eq!("# Clients\r\nconnected_clients:1\r\n", info);
+
+    // The hottest keys, as reported by `HOTKEYS`.
+    let info = match request(&mut connection, &["INFO", "HOTKEYS"]).await {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("expected bulk frame, got {:?}", frame),
+    };
+    assert!(
+        info.starts_with("# Hotkeys\r\nhotkey0:key=foo,ops_per_sec="),
+        "{:?}",
+        info
+    );
+}
+
+/// Connections are listed by `CLIENT LIST` and can be closed by `CLIENT KILL`.
+#[tokio::test]
+async fn client_commands() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let other_addr = socket.local_addr().unwrap().to_string();
+    let mut other = Connection::new(socket);
+
+    let id = match request(&mut connection, &["CLIENT", "ID"]).await {
+        Frame::Integer(id) => id,
+        frame => panic!("expected integer frame, got {:?}", frame),
+    };
+    let other_id = match request(&mut other, &["CLIENT", "ID"]).await {
+        Frame::Integer(id) => id,
+        frame => panic!("expected integer frame, got {:?}", frame),
+    };
+    assert_ne!(id, other_id);
+
+    assert_eq!(
+        Frame::Null,
+        request(&mut other, &["CLIENT", "GETNAME"]).await
+    );
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut other, &["CLIENT", "SETNAME", "worker"]).await
+    );
+    assert_eq!(
+        Frame::Bulk("worker".into()),
+        request(&mut other, &["CLIENT", "GETNAME"]).await
+    );
+    assert!(matches!(
+        request(&mut other, &["CLIENT", "SETNAME", "two words"]).await,
+        Frame::Error(_)
+    ));
+
+    let list = match request(&mut connection, &["CLIENT", "LIST"]).await {
+        Frame::Bulk(list) => String::from_utf8(list.to_vec()).unwrap(),
+        frame => panic!("expected bulk frame, got {:?}", frame),
+    };
+    assert_eq!(2, list.lines().count());
+    assert!(list.contains(&format!("id={} addr={} name=worker ", other_id, other_addr)));
+    assert!(list.contains(&format!("id={} ", id)));
+    assert!(list.contains("cmd=client"));
+
+    // The connection running the command is skipped by default.
+    assert_eq!(
+        Frame::Integer(0),
+        request(&mut connection, &["CLIENT", "KILL", "ID", &id.to_string()]).await
+    );
+    assert_eq!(
+        Frame::Integer(1),
+        request(
+            &mut connection,
+            &["CLIENT", "KILL", "ID", &other_id.to_string()]
+        )
+        .await
+    );
+    assert!(other.read_frame().await.unwrap().is_none());
+
+    // The killed connection is no longer listed.
+    let list = match request(&mut connection, &["CLIENT", "LIST"]).await {
+        Frame::Bulk(list) => String::from_utf8(list.to_vec()).unwrap(),
+        frame => panic!("expected bulk frame, got {:?}", frame),
+    };
+    assert_eq!(1, list.lines().count());
+
+    // The old form kills by address.
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let third_addr = socket.local_addr().unwrap().to_string();
+    let mut third = Connection::new(socket);
+    request(&mut third, &["PING"]).await;
+
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut connection, &["CLIENT", "KILL", &third_addr]).await
+    );
+    assert!(third.read_frame().await.unwrap().is_none());
+    assert_eq!(
+        Frame::Error("ERR No such client".to_string()),
+        request(&mut connection, &["CLIENT", "KILL", &third_addr]).await
+    );
+}
+
+/// `MONITOR` streams the commands received from other connections.
+#[tokio::test]
+async fn monitor() {
+    let addr = start_server().await;
+    let mut monitor = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let client_addr = socket.local_addr().unwrap().to_string();
+    let mut connection = Connection::new(socket);
+
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut monitor, &["MONITOR"]).await
+    );
+
+    request(&mut connection, &["SET", "foo", "bar \"baz\""]).await;
+    request(&mut connection, &["SELECT", "1"]).await;
+    request(&mut connection, &["GET", "foo"]).await;
+    request(&mut connection, &["AUTH", "secret"]).await;
+    request(&mut connection, &["HELLO", "2", "AUTH", "alice", "secret"]).await;
+    request(&mut connection, &["ACL", "SETUSER", "alice", ">secret"]).await;
+
+    let expected = [
+        format!(r#"[0 {}] "SET" "foo" "bar \"baz\"""#, client_addr),
+        format!(r#"[0 {}] "SELECT" "1""#, client_addr),
+        format!(r#"[1 {}] "GET" "foo""#, client_addr),
+        format!(r#"[1 {}] "AUTH" "(redacted)""#, client_addr),
+        format!(
+            r#"[1 {}] "HELLO" "2" "AUTH" "alice" "(redacted)""#,
+            client_addr
+        ),
+        format!(
+            r#"[1 {}] "ACL" "SETUSER" "alice" "(redacted)""#,
+            client_addr
+        ),
+    ];
+
+    for expected in &expected {
+        match monitor.read_frame().await.unwrap().unwrap() {
+            Frame::Simple(line) => {
+                // The line starts with the time, in seconds and microseconds.
+                let (time, rest) = line.split_at(line.find(' ').unwrap());
+                assert!(time.parse::<f64>().is_ok(), "{:?}", line);
+                assert_eq!(expected, &rest[1..]);
+            }
+            frame => panic!("expected simple frame, got {:?}", frame),
+        }
+    }
+}
+
+/// Commands slower than `slowlog-log-slower-than` are recorded in the slow
+/// log, up to `slowlog-max-len` entries.
+#[tokio::test]
+async fn slowlog() {
+    let addr = start_server().await;
+    let socket = TcpStream::connect(addr).await.unwrap();
+    let client_addr = socket.local_addr().unwrap().to_string();
+    let mut connection = Connection::new(socket);
+
+    // Every command is recorded, including this one.
+    request(
+        &mut connection,
+        &["CONFIG", "SET", "slowlog-log-slower-than", "0"],
+    )
+    .await;
+    let value = "x".repeat(200);
+    request(&mut connection, &["SET", "foo", &value]).await;
+    assert_eq!(
+        Frame::Integer(2),
+        request(&mut connection, &["SLOWLOG", "LEN"]).await
+    );
+
+    // Entries are returned newest first. Long arguments are cut short.
+    let entries = match request(&mut connection, &["SLOWLOG", "GET", "2"]).await {
+        Frame::Array(entries) => entries,
+        frame => panic!("expected array frame, got {:?}", frame),
+    };
+    assert_eq!(2, entries.len());
+
+    let expected_args = [
+        bulk_array(&["SLOWLOG", "LEN"]),
+        bulk_array(&[
+            "SET",
+            "foo",
+            &format!("{}... (72 more bytes)", &value[..128]),
+        ]),
+    ];
+
+    for (entry, (id, args)) in entries.iter().zip([2, 1].iter().zip(&expected_args)) {
+        let fields = match entry {
+            Frame::Array(fields) => fields,
+            frame => panic!("expected array frame, got {:?}", frame),
+        };
+
+        assert_eq!(6, fields.len());
+        assert_eq!(Frame::Integer(*id), fields[0]);
+        assert_eq!(*args, fields[3]);
+        assert_eq!(Frame::Bulk(client_addr.clone().into()), fields[4]);
+        assert_eq!(Frame::Bulk("".into()), fields[5]);
+    }
+
+    // Old entries are dropped beyond the maximum length.
+    request(&mut connection, &["CONFIG", "SET", "slowlog-max-len", "2"]).await;
+    assert_eq!(
+        Frame::Integer(2),
+        request(&mut connection, &["SLOWLOG", "LEN"]).await
+    );
+
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(&mut connection, &["SLOWLOG", "RESET"]).await
+    );
+
+    // Passwords are not disclosed.
+    request(
+        &mut connection,
+        &["CONFIG", "SET", "slowlog-max-len", "128"],
+    )
+    .await;
+    request(&mut connection, &["AUTH", "secret"]).await;
+    request(&mut connection, &["HELLO", "2", "AUTH", "alice", "secret"]).await;
+    request(
+        &mut connection,
+        &["ACL", "SETUSER", "alice", "on", ">secret"],
+    )
+    .await;
+
+    let entries = match request(&mut connection, &["SLOWLOG", "GET", "3"]).await {
+        Frame::Array(entries) => entries,
+        frame => panic!("expected array frame, got {:?}", frame),
+    };
+
+    let expected_args = [
+        bulk_array(&["ACL", "SETUSER", "alice", "on", "(redacted)"]),
+        bulk_array(&["HELLO", "2", "AUTH", "alice", "(redacted)"]),
+        bulk_array(&["AUTH", "(redacted)"]),
+    ];
+    assert_eq!(3, entries.len());
+
+    for (entry, args) in entries.iter().zip(&expected_args) {
+        match entry {
+            Frame::Array(fields) => assert_eq!(*args, fields[3]),
+            frame => panic!("expected array frame, got {:?}", frame),
+        }
+    }
+
+    // A negative threshold disables the slow log.
+    request(
+        &mut connection,
+        &["CONFIG", "SET", "slowlog-log-slower-than", "-1"],
+    )
+    .await;
+    request(&mut connection, &["SET", "foo", "bar"]).await;
+    assert_eq!(
+        Frame::Integer(6),
+        request(&mut connection, &["SLOWLOG", "LEN"]).await
+    );
+}
+
+/// `OBJECT ENCODING` reports the encoding of a value, which changes once the
+/// value grows past the thresholds set with `CONFIG SET`.
+#[tokio::test]
+async fn object_encoding() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let long = "x".repeat(100);
+    request(&mut connection, &["SET", "int", "12345"]).await;
+    request(&mut connection, &["SET", "padded", "012345"]).await;
+    request(&mut connection, &["SET", "raw", &long]).await;
+    request(&mut connection, &["HSET", "hash", "a", "1", "b", "2"]).await;
+    request(&mut connection, &["RPUSH", "list", "a", "b"]).await;
+    request(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    request(&mut connection, &["XADD", "stream", "*", "a", "1"]).await;
+
+    for (key, encoding) in &[
+        ("int", "int"),
+        ("padded", "embstr"),
+        ("raw", "raw"),
+        ("hash", "listpack"),
+        ("list", "listpack"),
+        ("zset", "listpack"),
+        ("stream", "stream"),
+    ] {
+        assert_eq!(
+            Frame::Bulk(Bytes::from(*encoding)),
+            request(&mut connection, &["OBJECT", "ENCODING", key]).await,
+            "encoding of {}",
+            key
+        );
+    }
+
+    assert_eq!(
+        Frame::Null,
+        request(&mut connection, &["OBJECT", "ENCODING", "missing"]).await
+    );
+    assert_eq!(
+        Frame::Error("ERR unknown subcommand 'foo'. Try OBJECT HELP.".to_string()),
+        request(&mut connection, &["OBJECT", "foo"]).await
+    );
+
+    // Thresholds apply from the next write on.
+    request(
+        &mut connection,
+        &["CONFIG", "SET", "hash-max-listpack-entries", "2"],
+    )
+    .await;
+    request(
+        &mut connection,
+        &["CONFIG", "SET", "list-max-listpack-size", "2"],
+    )
+    .await;
+    assert_eq!(
+        bulk_array(&["hash-max-listpack-entries", "2"]),
+        request(
+            &mut connection,
+            &["CONFIG", "GET", "hash-max-listpack-entries"]
+        )
+        .await
+    );
+    assert_eq!(
+        Frame::Bulk(Bytes::from("listpack")),
+        request(&mut connection, &["OBJECT", "ENCODING", "hash"]).await
+    );
+
+    request(&mut connection, &["HSET", "hash", "c", "3"]).await;
+    request(&mut connection, &["RPUSH", "list", "c"]).await;
+    request(&mut connection, &["ZADD", "zset", "2", &long]).await;
+
+    for (key, encoding) in &[
+        ("hash", "hashtable"),
+        ("list", "quicklist"),
+        ("zset", "skiplist"),
+    ] {
+        assert_eq!(
+            Frame::Bulk(Bytes::from(*encoding)),
+            request(&mut connection, &["OBJECT", "ENCODING", key]).await,
+            "encoding of {}",
+            key
+        );
+    }
+
+    // Values are not converted back when they shrink.
+    request(&mut connection, &["HDEL", "hash", "c"]).await;
+    assert_eq!(
+        Frame::Bulk(Bytes::from("hashtable")),
+        request(&mut connection, &["OBJECT", "ENCODING", "hash"]).await
+    );
+
+    let info = match request(&mut connection, &["INFO", "stats"]).await {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("expected bulk frame, got {:?}", frame),
+    };
+    assert!(
+        info.contains("encoding_conversions:3\r\n"),
+        "missing conversions in {:?}",
+        info
+    );
+}
+
+/// Keyspace events are published once enabled with `CONFIG SET`.
+#[tokio::test]
+async fn keyspace_notifications() {
+    let addr = start_server().await;
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    request(
+        &mut subscriber,
+        &["SUBSCRIBE", "__keyspace@0__:foo", "__keyevent@0__:expired"],
+    )
+    .await;
+    subscriber.read_frame().await.unwrap().unwrap();
+
+    // Disabled by default.
+    request(&mut connection, &["SET", "foo", "bar"]).await;
+
+    assert_eq!(
+        Frame::Simple("OK".to_string()),
+        request(
+            &mut connection,
+            &["CONFIG", "SET", "notify-keyspace-events", "KEA"]
+        )
+        .await
+    );
+    assert_eq!(
+        bulk_array(&["notify-keyspace-events", "AKE"]),
+        request(
+            &mut connection,
+            &["CONFIG", "GET", "notify-keyspace-events"]
+        )
+        .await
+    );
+
+    request(&mut connection, &["SET", "foo", "bar", "PX", "100"]).await;
+
+    let expected = [
+        ("__keyspace@0__:foo", "set"),
+        ("__keyspace@0__:foo", "expire"),
+        ("__keyspace@0__:foo", "expired"),
+        ("__keyevent@0__:expired", "foo"),
+    ];
+
+    // Messages of different channels may be delivered in any order.
+    let mut received = vec![];
+    for _ in 0..expected.len() {
+        received.push(subscriber.read_frame().await.unwrap().unwrap());
+    }
+
+    for (channel, message) in expected {
+        let message = bulk_array(&["message", channel, message]);
+        assert!(received.contains(&message), "{:?}", received);
+    }
+
+    let response = request(
+        &mut connection,
+        &["CONFIG", "SET", "notify-keyspace-events", "Q"],
+    )
+    .await;
+    assert!(matches!(response, Frame::Error(_)));
+}
+
+#[tokio::test]
+async fn hello_switches_to_resp3() {
     let addr = start_server().await;
 
     let mut stream = TcpStream::connect(addr).await.unwrap();
 
-    // send SUBSCRIBE command
+    // Unsupported protocol versions are rejected
     stream
-        .write_all(b"*2\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n")
+        .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n4\r\n")
         .await
         .unwrap();
-
-    let mut response = [0; 34];
-
+    let mut response = [0; 39];
     stream.read_exact(&mut response).await.unwrap();
-
     assert_eq!(
-        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &b"-NOPROTO unsupported protocol version\r\n"[..],
         &response[..]
     );
 
     stream
-        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n")
         .await
         .unwrap();
 
-    let mut response = [0; 28];
+    // The server information is sent as a map with 6 entries
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"%6\r\n", &response);
 
+    let mut response = [0; 23];
     stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-ERR unknown command \'set\'\r\n", &response);
+    assert_eq!(b"$6\r\nserver\r\n$5\r\nredis\r\n", &response);
+
+    // Skip the rest of the map: version, proto, mode, role and modules.
+    let version = env!("CARGO_PKG_VERSION");
+    let rest = format!(
+        "$7\r\nversion\r\n${}\r\n{}\r\n$5\r\nproto\r\n:3\r\n\
+         $4\r\nmode\r\n$10\r\nstandalone\r\n$4\r\nrole\r\n$6\r\nmaster\r\n\
+         $7\r\nmodules\r\n*0\r\n",
+        version.len(),
+        version
+    );
+    let mut response = vec![0; rest.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(rest.as_bytes(), &response[..]);
 
+    // A missing key is now a RESP3 null
     stream
         .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
         .await
         .unwrap();
+    let mut response = [0; 3];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"_\r\n", &response);
+}
 
-    let mut response = [0; 28];
+/// Frames may be split across any number of reads. A command sent one byte
+/// at a time, and pipelined commands whose boundaries fall in the middle of
+/// a read, are all parsed correctly.
+#[tokio::test]
+async fn partial_frames_across_reads() {
+    let addr = start_server().await;
+
+    // Establish a connection to the server
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.set_nodelay(true).unwrap();
+
+    // Send an array of bulk strings one byte at a time, pausing after each
+    // byte so that it is received by a separate read.
+    for byte in b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n" {
+        stream.write_all(&[*byte]).await.unwrap();
+        time::sleep(Duration::from_millis(1)).await;
+    }
 
+    let mut response = [0; 5];
     stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-ERR unknown command \'get\'\r\n", &response);
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Pipeline a large value followed by a read of it, sent in chunks that
+    // do not line up with the frames.
+    let value = vec![b'x'; 100_000];
+    let mut pipeline = b"*3\r\n$3\r\nSET\r\n$5\r\nlarge\r\n$100000\r\n".to_vec();
+    pipeline.extend_from_slice(&value);
+    pipeline.extend_from_slice(b"\r\n*2\r\n$3\r\nGET\r\n$5\r\nlarge\r\n");
+
+    for chunk in pipeline.chunks(4093) {
+        stream.write_all(chunk).await.unwrap();
+        time::sleep(Duration::from_millis(1)).await;
+    }
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // `$100000\r\n`, the value, then `\r\n`.
+    let mut response = vec![0; 9 + 100_000 + 2];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$100000\r\n", &response[..9]);
+    assert_eq!(&value[..], &response[9..100_009]);
+    assert_eq!(b"\r\n", &response[100_009..]);
+}
+
+/// Shutdown is triggered programmatically through a `ShutdownController`. The
+/// server must close its connections and wait for every registered
+/// participant, including ones that are not part of the server, to complete.
+#[tokio::test]
+async fn shutdown_controller_waits_for_participants() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let controller = ShutdownController::new();
+    let server = tokio::spawn(server::run_with_controller(listener, controller.clone()));
+
+    // An external participant that needs some time to wrap up.
+    let mut shutdown = controller.subscribe();
+    let (done_tx, mut done_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        shutdown.recv().await;
+        time::sleep(Duration::from_millis(50)).await;
+        done_tx.send(()).unwrap();
+    });
+
+    // Establish a connection to the server
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    controller.shutdown().await;
+
+    // The participant completed before `shutdown` returned.
+    done_rx.try_recv().unwrap();
+    server.await.unwrap();
+
+    // The connection has been closed by the server.
+    assert_eq!(0, stream.read(&mut response).await.unwrap());
+}
+
+/// `SHUTDOWN` stops the server once every connection finished its current
+/// command. `SAVE` saves a snapshot before exiting, `NOSAVE` does not.
+#[tokio::test]
+async fn shutdown_command() {
+    let path = std::env::temp_dir().join(format!("mini-redis-shutdown-{}.rdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let config = ServerConfig {
+        dbfilename: path.clone(),
+        ..ServerConfig::default()
+    };
+
+    let ok = Frame::Simple("OK".to_string());
+
+    let (addr, _controller, server) = start_server_with_config(config.clone()).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut other = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(
+        ok,
+        request(&mut connection, &["SET", "hello", "world"]).await
+    );
+
+    // No reply is sent, every connection is closed.
+    connection
+        .write_frame(&bulk_array(&["SHUTDOWN", "NOSAVE"]))
+        .await
+        .unwrap();
+    assert!(connection.read_frame().await.unwrap().is_none());
+    assert!(other.read_frame().await.unwrap().is_none());
+
+    server.await.unwrap().unwrap();
+    assert!(!path.exists());
+
+    let (addr, _controller, server) = start_server_with_config(config.clone()).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(
+        ok,
+        request(&mut connection, &["SET", "hello", "world"]).await
+    );
+
+    // The snapshot is saved once the background save in progress is.
+    assert_eq!(
+        Frame::Simple("Background saving started".to_string()),
+        request(&mut connection, &["BGSAVE"]).await
+    );
+
+    connection
+        .write_frame(&bulk_array(&["SHUTDOWN", "SAVE"]))
+        .await
+        .unwrap();
+    assert!(connection.read_frame().await.unwrap().is_none());
+    server.await.unwrap().unwrap();
+
+    // The snapshot is loaded on the next start.
+    let (addr, controller, server) = start_server_with_config(config).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(
+        Frame::Bulk("world".into()),
+        request(&mut connection, &["GET", "hello"]).await
+    );
+
+    controller.shutdown().await;
+    server.await.unwrap().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+
+    // The server keeps running if the snapshot cannot be saved.
+    let config = ServerConfig {
+        dbfilename: path.join("missing").join("dump.rdb"),
+        save_on_shutdown: true,
+        ..ServerConfig::default()
+    };
+
+    let (addr, controller, server) = start_server_with_config(config).await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    assert_eq!(
+        Frame::Error("ERR Errors trying to SHUTDOWN. Check logs.".to_string()),
+        request(&mut connection, &["SHUTDOWN"]).await
+    );
+    assert_eq!(
+        Frame::Simple("PONG".to_string()),
+        request(&mut connection, &["PING"]).await
+    );
+
+    // Stopped otherwise, the server reports the failure.
+    drop(connection);
+    controller.shutdown().await;
+    assert!(server.await.unwrap().is_err());
 }
 
 async fn start_server() -> SocketAddr {
@@ -405,3 +3258,116 @@ async fn start_server() -> SocketAddr {
 
     addr
 }
+
+async fn start_server_with_config(
+    config: ServerConfig,
+) -> (
+    SocketAddr,
+    ShutdownController,
+    JoinHandle<mini_redis::Result<()>>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let controller = ShutdownController::new();
+    let server = tokio::spawn(server::run_with_config(
+        listener,
+        config,
+        controller.clone(),
+    ));
+
+    (addr, controller, server)
+}
+
+/// Send `cmd` until the reply is `expected`, as long as the key is missing.
+async fn wait_for_key(stream: &mut TcpStream, cmd: &[u8], expected: &[u8]) {
+    for _ in 0..100 {
+        stream.write_all(cmd).await.unwrap();
+
+        let mut response = [0; 5];
+        stream.read_exact(&mut response).await.unwrap();
+        if &response == b"$-1\r\n" {
+            time::sleep(Duration::from_millis(20)).await;
+            continue;
+        }
+
+        let mut rest = vec![0; expected.len() - 5];
+        stream.read_exact(&mut rest).await.unwrap();
+        assert_eq!(expected, &[&response[..], &rest[..]].concat()[..]);
+        return;
+    }
+
+    panic!("key was not replicated");
+}
+
+/// Send a command made of `args` and return the response.
+async fn request(connection: &mut Connection, args: &[&str]) -> Frame {
+    connection.write_frame(&bulk_array(args)).await.unwrap();
+    connection.read_frame().await.unwrap().unwrap()
+}
+
+/// Returns an array frame of bulk strings.
+fn bulk_array(items: &[&str]) -> Frame {
+    Frame::Array(
+        items
+            .iter()
+            .map(|item| Frame::Bulk(Bytes::from(item.to_string())))
+            .collect(),
+    )
+}
+
+/// Returns the role reported by `HELLO`.
+async fn hello_role(addr: SocketAddr) -> String {
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // Without RESP3, the map is returned as an array of fields and values.
+    let fields = match request(&mut connection, &["HELLO"]).await {
+        Frame::Array(fields) => fields,
+        frame => panic!("unexpected frame {:?}", frame),
+    };
+
+    match fields
+        .chunks(2)
+        .find(|pair| pair[0] == Frame::Bulk(Bytes::from("role")))
+    {
+        Some([_, Frame::Bulk(role)]) => String::from_utf8(role.to_vec()).unwrap(),
+        pair => panic!("unexpected role {:?}", pair),
+    }
+}
+
+/// Run a command of the `SCAN` family until the iteration is complete, and
+/// return the items of every page. `{}` in `args` is replaced by the cursor.
+async fn scan_all(connection: &mut Connection, args: &[&str]) -> Vec<String> {
+    let mut cursor = "0".to_string();
+    let mut items = vec![];
+
+    loop {
+        let args: Vec<_> = args
+            .iter()
+            .map(|&arg| if arg == "{}" { &cursor[..] } else { arg })
+            .collect();
+
+        let mut page = match request(connection, &args).await {
+            Frame::Array(page) => page.into_iter(),
+            frame => panic!("unexpected frame: {:?}", frame),
+        };
+
+        match (page.next(), page.next()) {
+            (Some(Frame::Bulk(next)), Some(Frame::Array(page))) => {
+                cursor = String::from_utf8(next.to_vec()).unwrap();
+
+                for item in page {
+                    match item {
+                        Frame::Bulk(item) => items.push(String::from_utf8(item.to_vec()).unwrap()),
+                        frame => panic!("unexpected frame: {:?}", frame),
+                    }
+                }
+            }
+            frame => panic!("unexpected frame: {:?}", frame),
+        }
+
+        if cursor == "0" {
+            return items;
+        }
+    }
+}